@@ -49,7 +49,7 @@ module "dynamodb" {
   table_name                    = "${local.prefix}-table"
   billing_mode                  = "PAY_PER_REQUEST"
   enable_point_in_time_recovery = true
-  enable_ttl                    = false
+  enable_ttl                    = true # reaps expires_at (idempotency keys, compacted state)
 
   tags = local.tags
 }
@@ -6,10 +6,11 @@ resource "aws_apigatewayv2_api" "eventledger" {
   description   = "EventLedger REST API"
 
   cors_configuration {
-    allow_origins = var.cors_allow_origins
-    allow_methods = ["GET", "POST", "DELETE", "OPTIONS"]
-    allow_headers = ["Content-Type", "Authorization", "X-Api-Key"]
-    max_age       = 300
+    allow_origins  = var.cors_allow_origins
+    allow_methods  = ["GET", "POST", "PATCH", "DELETE", "OPTIONS"]
+    allow_headers  = ["Content-Type", "Authorization", "X-Api-Key", "If-Match", "If-None-Match", "Idempotency-Key"]
+    expose_headers = ["ETag"]
+    max_age        = 300
   }
 
   tags = var.tags
@@ -75,6 +76,12 @@ resource "aws_apigatewayv2_integration" "poll" {
 }
 
 # Routes - Admin (streams management)
+resource "aws_apigatewayv2_route" "capabilities" {
+  api_id    = aws_apigatewayv2_api.eventledger.id
+  route_key = "GET /capabilities"
+  target    = "integrations/${aws_apigatewayv2_integration.admin.id}"
+}
+
 resource "aws_apigatewayv2_route" "create_stream" {
   api_id    = aws_apigatewayv2_api.eventledger.id
   route_key = "POST /streams"
@@ -87,6 +94,12 @@ resource "aws_apigatewayv2_route" "list_streams" {
   target    = "integrations/${aws_apigatewayv2_integration.admin.id}"
 }
 
+resource "aws_apigatewayv2_route" "partition_preview" {
+  api_id    = aws_apigatewayv2_api.eventledger.id
+  route_key = "POST /streams/partition-preview"
+  target    = "integrations/${aws_apigatewayv2_integration.admin.id}"
+}
+
 resource "aws_apigatewayv2_route" "get_stream" {
   api_id    = aws_apigatewayv2_api.eventledger.id
   route_key = "GET /streams/{stream_id}"
@@ -99,6 +112,97 @@ resource "aws_apigatewayv2_route" "delete_stream" {
   target    = "integrations/${aws_apigatewayv2_integration.admin.id}"
 }
 
+resource "aws_apigatewayv2_route" "update_stream" {
+  api_id    = aws_apigatewayv2_api.eventledger.id
+  route_key = "PATCH /streams/{stream_id}"
+  target    = "integrations/${aws_apigatewayv2_integration.admin.id}"
+}
+
+resource "aws_apigatewayv2_route" "partition_for" {
+  api_id    = aws_apigatewayv2_api.eventledger.id
+  route_key = "GET /streams/{stream_id}/partition-for"
+  target    = "integrations/${aws_apigatewayv2_integration.admin.id}"
+}
+
+resource "aws_apigatewayv2_route" "stream_stats" {
+  api_id    = aws_apigatewayv2_api.eventledger.id
+  route_key = "GET /streams/{stream_id}/stats"
+  target    = "integrations/${aws_apigatewayv2_integration.admin.id}"
+}
+
+resource "aws_apigatewayv2_route" "partition_events" {
+  api_id    = aws_apigatewayv2_api.eventledger.id
+  route_key = "GET /streams/{stream_id}/partitions/{partition}/events"
+  target    = "integrations/${aws_apigatewayv2_integration.admin.id}"
+}
+
+resource "aws_apigatewayv2_route" "batch_get_events" {
+  api_id    = aws_apigatewayv2_api.eventledger.id
+  route_key = "POST /streams/{stream_id}/partitions/{partition}/events:batchGet"
+  target    = "integrations/${aws_apigatewayv2_integration.admin.id}"
+}
+
+resource "aws_apigatewayv2_route" "redact_event" {
+  api_id    = aws_apigatewayv2_api.eventledger.id
+  route_key = "DELETE /streams/{stream_id}/partitions/{partition}/events/{sequence}"
+  target    = "integrations/${aws_apigatewayv2_integration.admin.id}"
+}
+
+resource "aws_apigatewayv2_route" "list_compacted" {
+  api_id    = aws_apigatewayv2_api.eventledger.id
+  route_key = "GET /streams/{stream_id}/compacted"
+  target    = "integrations/${aws_apigatewayv2_integration.admin.id}"
+}
+
+resource "aws_apigatewayv2_route" "compacted_changelog" {
+  api_id    = aws_apigatewayv2_api.eventledger.id
+  route_key = "GET /streams/{stream_id}/compacted/changelog"
+  target    = "integrations/${aws_apigatewayv2_integration.admin.id}"
+}
+
+resource "aws_apigatewayv2_route" "compact_stream" {
+  api_id    = aws_apigatewayv2_api.eventledger.id
+  route_key = "POST /streams/{stream_id}/compact"
+  target    = "integrations/${aws_apigatewayv2_integration.admin.id}"
+}
+
+resource "aws_apigatewayv2_route" "repartition_stream" {
+  api_id    = aws_apigatewayv2_api.eventledger.id
+  route_key = "POST /streams/{stream_id}/repartition"
+  target    = "integrations/${aws_apigatewayv2_integration.admin.id}"
+}
+
+resource "aws_apigatewayv2_route" "snapshot" {
+  api_id    = aws_apigatewayv2_api.eventledger.id
+  route_key = "GET /streams/{stream_id}/snapshot"
+  target    = "integrations/${aws_apigatewayv2_integration.admin.id}"
+}
+
+resource "aws_apigatewayv2_route" "list_dlq" {
+  api_id    = aws_apigatewayv2_api.eventledger.id
+  route_key = "GET /streams/{stream_id}/dlq"
+  target    = "integrations/${aws_apigatewayv2_integration.admin.id}"
+}
+
+# Routes - Aliases
+resource "aws_apigatewayv2_route" "put_alias" {
+  api_id    = aws_apigatewayv2_api.eventledger.id
+  route_key = "PUT /aliases/{alias}"
+  target    = "integrations/${aws_apigatewayv2_integration.admin.id}"
+}
+
+resource "aws_apigatewayv2_route" "get_alias" {
+  api_id    = aws_apigatewayv2_api.eventledger.id
+  route_key = "GET /aliases/{alias}"
+  target    = "integrations/${aws_apigatewayv2_integration.admin.id}"
+}
+
+resource "aws_apigatewayv2_route" "delete_alias" {
+  api_id    = aws_apigatewayv2_api.eventledger.id
+  route_key = "DELETE /aliases/{alias}"
+  target    = "integrations/${aws_apigatewayv2_integration.admin.id}"
+}
+
 # Routes - Subscriptions
 resource "aws_apigatewayv2_route" "create_subscription" {
   api_id    = aws_apigatewayv2_api.eventledger.id
@@ -106,6 +210,30 @@ resource "aws_apigatewayv2_route" "create_subscription" {
   target    = "integrations/${aws_apigatewayv2_integration.admin.id}"
 }
 
+resource "aws_apigatewayv2_route" "seek_all_subscriptions" {
+  api_id    = aws_apigatewayv2_api.eventledger.id
+  route_key = "POST /streams/{stream_id}/subscriptions/seek-all"
+  target    = "integrations/${aws_apigatewayv2_integration.admin.id}"
+}
+
+resource "aws_apigatewayv2_route" "list_subscriptions" {
+  api_id    = aws_apigatewayv2_api.eventledger.id
+  route_key = "GET /streams/{stream_id}/subscriptions"
+  target    = "integrations/${aws_apigatewayv2_integration.admin.id}"
+}
+
+resource "aws_apigatewayv2_route" "seek_subscription" {
+  api_id    = aws_apigatewayv2_api.eventledger.id
+  route_key = "POST /streams/{stream_id}/subscriptions/{subscription_id}/seek"
+  target    = "integrations/${aws_apigatewayv2_integration.admin.id}"
+}
+
+resource "aws_apigatewayv2_route" "subscription_lag" {
+  api_id    = aws_apigatewayv2_api.eventledger.id
+  route_key = "GET /streams/{stream_id}/subscriptions/{subscription_id}/lag"
+  target    = "integrations/${aws_apigatewayv2_integration.admin.id}"
+}
+
 resource "aws_apigatewayv2_route" "delete_subscription" {
   api_id    = aws_apigatewayv2_api.eventledger.id
   route_key = "DELETE /streams/{stream_id}/subscriptions/{subscription_id}"
@@ -119,6 +247,12 @@ resource "aws_apigatewayv2_route" "publish_events" {
   target    = "integrations/${aws_apigatewayv2_integration.publish.id}"
 }
 
+resource "aws_apigatewayv2_route" "publish_batch" {
+  api_id    = aws_apigatewayv2_api.eventledger.id
+  route_key = "POST /publish-batch"
+  target    = "integrations/${aws_apigatewayv2_integration.publish.id}"
+}
+
 # Routes - Poll and Commit
 resource "aws_apigatewayv2_route" "poll" {
   api_id    = aws_apigatewayv2_api.eventledger.id
@@ -132,6 +266,30 @@ resource "aws_apigatewayv2_route" "commit" {
   target    = "integrations/${aws_apigatewayv2_integration.poll.id}"
 }
 
+resource "aws_apigatewayv2_route" "commit_poll" {
+  api_id    = aws_apigatewayv2_api.eventledger.id
+  route_key = "POST /streams/{stream_id}/subscriptions/{subscription_id}/commit-poll"
+  target    = "integrations/${aws_apigatewayv2_integration.poll.id}"
+}
+
+resource "aws_apigatewayv2_route" "offsets" {
+  api_id    = aws_apigatewayv2_api.eventledger.id
+  route_key = "GET /streams/{stream_id}/subscriptions/{subscription_id}/offsets"
+  target    = "integrations/${aws_apigatewayv2_integration.poll.id}"
+}
+
+resource "aws_apigatewayv2_route" "commit_batch" {
+  api_id    = aws_apigatewayv2_api.eventledger.id
+  route_key = "POST /streams/{stream_id}/commit-batch"
+  target    = "integrations/${aws_apigatewayv2_integration.poll.id}"
+}
+
+resource "aws_apigatewayv2_route" "tail" {
+  api_id    = aws_apigatewayv2_api.eventledger.id
+  route_key = "GET /streams/{stream_id}/tail"
+  target    = "integrations/${aws_apigatewayv2_integration.poll.id}"
+}
+
 # Lambda permissions for API Gateway
 resource "aws_lambda_permission" "admin" {
   statement_id  = "AllowAPIGatewayInvoke"
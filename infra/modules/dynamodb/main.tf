@@ -22,6 +22,42 @@ resource "aws_dynamodb_table" "eventledger" {
     type = "S"
   }
 
+  # Sparse index over stream metadata: only META items carry streams_pk
+  attribute {
+    name = "streams_pk"
+    type = "S"
+  }
+
+  attribute {
+    name = "stream_id"
+    type = "S"
+  }
+
+  global_secondary_index {
+    name            = "streams-index"
+    hash_key        = "streams_pk"
+    range_key       = "stream_id"
+    projection_type = "ALL"
+    read_capacity   = var.billing_mode == "PROVISIONED" ? var.read_capacity : null
+    write_capacity  = var.billing_mode == "PROVISIONED" ? var.write_capacity : null
+  }
+
+  # Sparse index over compacted state by event time: only compacted items
+  # carry timestamp_ns
+  attribute {
+    name = "timestamp_ns"
+    type = "N"
+  }
+
+  global_secondary_index {
+    name            = "compacted-time-index"
+    hash_key        = "PK"
+    range_key       = "timestamp_ns"
+    projection_type = "ALL"
+    read_capacity   = var.billing_mode == "PROVISIONED" ? var.read_capacity : null
+    write_capacity  = var.billing_mode == "PROVISIONED" ? var.write_capacity : null
+  }
+
   # Enable DynamoDB Streams for compaction
   stream_enabled   = true
   stream_view_type = "NEW_IMAGE"
@@ -184,8 +184,10 @@ resource "aws_lambda_function" "compactor" {
 
   environment {
     variables = {
-      EVENTLEDGER_TABLE = var.dynamodb_table_name
-      RUST_LOG          = var.log_level
+      EVENTLEDGER_TABLE                 = var.dynamodb_table_name
+      EVENTLEDGER_DLQ_MAX_ATTEMPTS      = var.dlq_max_attempts
+      EVENTLEDGER_COMPACTOR_CONCURRENCY = var.compactor_concurrency
+      RUST_LOG                          = var.log_level
     }
   }
 
@@ -199,6 +201,9 @@ resource "aws_lambda_event_source_mapping" "compactor_stream" {
   starting_position = "LATEST"
   batch_size        = 100
 
+  # Retry only the records the compactor reports in batchItemFailures
+  function_response_types = ["ReportBatchItemFailures"]
+
   filter_criteria {
     filter {
       pattern = jsonencode({
@@ -51,6 +51,18 @@ variable "log_level" {
   default     = "info"
 }
 
+variable "dlq_max_attempts" {
+  description = "Failed compaction attempts before a stream record is dead-lettered"
+  type        = number
+  default     = 3
+}
+
+variable "compactor_concurrency" {
+  description = "Keys the compactor updates concurrently within a batch"
+  type        = number
+  default     = 10
+}
+
 variable "log_retention_days" {
   description = "CloudWatch log retention in days"
   type        = number
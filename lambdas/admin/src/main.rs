@@ -1,25 +1,84 @@
 //! EventLedger Admin Lambda
 //!
 //! Handles stream and subscription management:
+//! - GET /capabilities - Server limits, defaults and supported features
 //! - POST /streams - Create stream
-//! - GET /streams - List streams
-//! - GET /streams/{stream_id} - Get stream
-//! - DELETE /streams/{stream_id} - Delete stream
+//! - GET /streams - List streams a page at a time (`?limit=&start_key=`; filter with
+//!   `?prefix=` and `?tag=key:value`)
+//! - POST /streams/partition-preview - Preview how keys spread over N partitions
+//! - GET /streams/{stream_id} - Get stream (with `ETag`)
+//! - PATCH /streams/{stream_id} - Update stream config (requires `If-Match`)
+//! - DELETE /streams/{stream_id} - Delete stream (`?force=true` if it has subscriptions)
+//! - GET /streams/{stream_id}/partition-for?key=... - Preview partition for a key
+//! - GET /streams/{stream_id}/stats - Stream size statistics
+//! - GET /streams/{stream_id}/partitions/{partition}/events - Inspect a partition (debug)
+//! - POST /streams/{stream_id}/partitions/{partition}/events:batchGet - Fetch events by sequence
+//! - DELETE /streams/{stream_id}/partitions/{partition}/events/{sequence} - Redact an event
+//! - GET /streams/{stream_id}/compacted - List compacted state (latest event per key)
+//! - GET /streams/{stream_id}/compacted/changelog - Tail compacted updates in order
+//! - POST /streams/{stream_id}/compact - Backfill compacted state from existing events
+//! - POST /streams/{stream_id}/repartition - Copy events into a stream with a new partition count
+//! - GET /streams/{stream_id}/snapshot - Compacted state plus a tail cursor to continue from
+//! - GET /streams/{stream_id}/dlq - Records the compactor dead-lettered
+//! - GET /streams/{stream_id}/subscriptions - List subscriptions (`?stale_after=` hours idle)
 //! - POST /streams/{stream_id}/subscriptions - Create subscription
+//! - POST /streams/{stream_id}/subscriptions/seek-all - Reposition every subscription
+//! - POST /streams/{stream_id}/subscriptions/{subscription_id}/seek - Reposition a subscription
+//! - GET /streams/{stream_id}/subscriptions/{subscription_id}/lag - Events behind per partition
 //! - DELETE /streams/{stream_id}/subscriptions/{subscription_id} - Delete subscription
+//! - PUT /aliases/{alias} - Point an alias at a stream
+//! - GET /aliases/{alias} - Get the stream an alias points at
+//! - DELETE /aliases/{alias} - Delete an alias
 
 use aws_config::BehaviorVersion;
+use chrono::Utc;
+use eventledger_core::response::{error_response, missing_path_param, recover};
 use eventledger_core::{
-    CreateStreamRequest, CreateSubscriptionRequest, DynamoClient, Error, ErrorResponse, Stream,
-    Subscription,
+    body, sort_by_order_key, BatchGetEventsRequest, Capabilities, CompactedEvent,
+    CompactionBackfillRequest, CompactionChangelogResponse, CreateStreamRequest,
+    CreateSubscriptionRequest, CursorState, DeadLetter, DynamoClient, Error, ErrorResponse, Event,
+    PartitionOffset, PartitionPreviewRequest, Partitioner, PutAliasRequest, RepartitionRequest,
+    SeekAllRequest, SeekRequest, SnapshotResponse, StartFrom, Stream, Subscription, TagFilter,
+    UpdateStreamRequest, TABLE_OVERRIDE_HEADER, TAIL_SUBSCRIPTION_ID,
 };
 use lambda_http::{run, service_fn, Body, Error as LambdaError, Request, RequestExt, Response};
-use serde::Serialize;
-use tracing::{error, info};
+use serde::{de::DeserializeOwned, Serialize};
+use std::time::Duration;
+use tracing::info;
+
+/// Keys accepted by a single partition preview
+const MAX_PREVIEW_KEYS: usize = 10_000;
+
+/// Compacted keys returned when `limit` is not given
+const DEFAULT_COMPACTED_LIMIT: usize = 100;
+
+/// Streams listed per page when `limit` is not given
+const DEFAULT_STREAMS_LIMIT: usize = 100;
+
+/// Leaves headroom under the 30s Lambda timeout; an incomplete backfill resumes
+/// from the offsets it returns
+const BACKFILL_TIME_BUDGET: Duration = Duration::from_secs(20);
+
+/// As for backfills; an incomplete copy resumes from the offsets it returns
+const REPARTITION_TIME_BUDGET: Duration = Duration::from_secs(20);
 
 #[derive(Serialize)]
 struct ListStreamsResponse {
     streams: Vec<Stream>,
+    /// Pass as `start_key` to fetch the next page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_start_key: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ListSubscriptionsResponse {
+    subscriptions: Vec<Subscription>,
+}
+
+#[derive(Serialize)]
+struct SeekResponse {
+    subscription_id: String,
+    offsets: Vec<PartitionOffset>,
 }
 
 #[derive(Serialize)]
@@ -27,69 +86,722 @@ struct DeleteResponse {
     success: bool,
 }
 
-async fn handler(event: Request) -> Result<Response<Body>, LambdaError> {
+#[derive(Serialize)]
+struct PartitionEventsResponse {
+    events: Vec<Event>,
+}
+
+#[derive(Serialize)]
+struct ListCompactedResponse {
+    events: Vec<CompactedEvent>,
+    /// Pass as `start_key` to fetch the next page (key order only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_start_key: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ListDlqResponse {
+    records: Vec<DeadLetter>,
+}
+
+#[derive(Serialize)]
+struct PartitionForResponse {
+    key: String,
+    partition: u32,
+}
+
+async fn handler(
+    base_client: &DynamoClient,
+    event: Request,
+) -> Result<Response<Body>, LambdaError> {
     let method = event.method().as_str();
     let path = event.uri().path().to_string();
 
     info!(method = %method, path = %path, "Processing admin request");
 
-    // Initialize AWS clients
-    let config = aws_config::load_defaults(BehaviorVersion::latest()).await;
-    let dynamo_client = aws_sdk_dynamodb::Client::new(&config);
-    let client = DynamoClient::new(dynamo_client);
+    let table_override = event
+        .headers()
+        .get(TABLE_OVERRIDE_HEADER)
+        .and_then(|v| v.to_str().ok());
+    let client = base_client.for_request(table_override);
 
     // Extract path parameters if present
     let path_params = event.path_parameters();
     let stream_id = path_params.first("stream_id").map(|s| s.to_string());
-    let subscription_id = path_params.first("subscription_id").map(|s| s.to_string());
 
     // Route based on method and path
     match (method, path.as_str()) {
+        // GET /capabilities - What clients may rely on from this deployment
+        ("GET", "/capabilities") => json_response(200, &Capabilities::current()),
+
         // POST /streams - Create stream
         ("POST", "/streams") => {
-            let body = event.body();
-            let body_str = std::str::from_utf8(body).map_err(|_| "Invalid UTF-8 in body")?;
-            let req: CreateStreamRequest = serde_json::from_str(body_str)?;
+            let req: CreateStreamRequest = match parse_body(event.body()) {
+                Ok(req) => req,
+                Err(e) => return error_response(e),
+            };
 
             match client.create_stream(&req).await {
-                Ok(stream) => json_response(201, &stream),
+                Ok(stream) => stream_response(201, &stream),
+                Err(e) => error_response(e),
+            }
+        }
+
+        // POST /streams/partition-preview - Key distribution for a candidate partition count
+        ("POST", "/streams/partition-preview") => {
+            let req: PartitionPreviewRequest = match parse_body(event.body()) {
+                Ok(req) => req,
+                Err(e) => return error_response(e),
+            };
+
+            if req.partition_count == 0 {
+                return error_response(Error::Validation(
+                    "partition_count must be at least 1".to_string(),
+                ));
+            }
+            if req.keys.len() > MAX_PREVIEW_KEYS {
+                return error_response(Error::Validation(format!(
+                    "At most {} keys can be previewed at once",
+                    MAX_PREVIEW_KEYS
+                )));
+            }
+
+            json_response(
+                200,
+                &Partitioner::new(req.partition_count).distribution(&req.keys),
+            )
+        }
+
+        // GET /streams?limit=&start_key= - List a page of streams, or only those
+        // named by ?ids=a,b,c; ?prefix= keeps only IDs starting with it and each
+        // ?tag=key:value only streams carrying that tag (so a page can come back
+        // short of `limit`)
+        ("GET", "/streams") => {
+            let query_params = event.query_string_parameters();
+            let prefix = query_params.first("prefix").filter(|p| !p.is_empty());
+            let tag_filters: Vec<TagFilter> = match query_params
+                .all("tag")
+                .unwrap_or_default()
+                .into_iter()
+                .map(str::parse)
+                .collect()
+            {
+                Ok(filters) => filters,
+                Err(e) => return error_response(e),
+            };
+            let limit = match query_params.first("limit").map(|s| s.parse::<usize>()) {
+                None => DEFAULT_STREAMS_LIMIT,
+                Some(Ok(limit)) if limit > 0 => limit,
+                Some(_) => {
+                    return error_response(Error::Validation(
+                        "limit must be a positive integer".to_string(),
+                    ))
+                }
+            };
+            let start_key = query_params.first("start_key");
+            let result = match query_params.first("ids") {
+                Some(ids) => {
+                    let ids: Vec<String> = ids
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|id| !id.is_empty())
+                        .map(String::from)
+                        .collect();
+                    client
+                        .batch_get_streams(&ids)
+                        .await
+                        .map(|streams| (streams, None))
+                }
+                None => client.list_streams_page(prefix, start_key, limit).await,
+            };
+            match result {
+                Ok((mut streams, next_start_key)) => {
+                    streams.retain(|stream| {
+                        prefix.is_none_or(|p| stream.stream_id.starts_with(p))
+                            && tag_filters.iter().all(|f| stream.has_tag(f))
+                    });
+                    json_response(
+                        200,
+                        &ListStreamsResponse {
+                            streams,
+                            next_start_key,
+                        },
+                    )
+                }
+                Err(e) => error_response(e),
+            }
+        }
+
+        // GET /streams/{stream_id}/partition-for?key=... - Preview key routing
+        ("GET", p) if p.starts_with("/streams/") && p.ends_with("/partition-for") => {
+            let stream_id = stream_id.ok_or_else(|| missing_path_param("stream_id"))?;
+
+            let query_params = event.query_string_parameters();
+            let key = match query_params.first("key") {
+                Some(key) => key.to_string(),
+                None => {
+                    return error_response(Error::Validation(
+                        "key query parameter is required".to_string(),
+                    ))
+                }
+            };
+
+            match client
+                .get_stream(&stream_id)
+                .await
+                .and_then(|stream| stream.partitioner())
+            {
+                Ok(partitioner) => {
+                    let partition = partitioner.partition(&key);
+                    json_response(200, &PartitionForResponse { key, partition })
+                }
+                Err(e) => error_response(e),
+            }
+        }
+
+        // GET /streams/{stream_id}/dlq - Records the compactor gave up on
+        ("GET", p) if p.starts_with("/streams/") && p.ends_with("/dlq") => {
+            let stream_id = stream_id.ok_or_else(|| missing_path_param("stream_id"))?;
+
+            if let Err(e) = check_stream_exists(&client, &stream_id).await {
+                return error_response(e);
+            }
+
+            match client.list_dlq(&stream_id).await {
+                Ok(records) => json_response(200, &ListDlqResponse { records }),
+                Err(e) => error_response(e),
+            }
+        }
+
+        // GET /streams/{stream_id}/stats - Stream size statistics
+        ("GET", p) if p.starts_with("/streams/") && p.ends_with("/stats") => {
+            let stream_id = stream_id.ok_or_else(|| missing_path_param("stream_id"))?;
+
+            match client.stream_stats(&stream_id).await {
+                Ok(stats) => json_response(200, &stats),
                 Err(e) => error_response(e),
             }
         }
 
-        // GET /streams - List streams
-        ("GET", "/streams") => match client.list_streams().await {
-            Ok(streams) => json_response(200, &ListStreamsResponse { streams }),
-            Err(e) => error_response(e),
-        },
+        // GET /streams/{stream_id}/partitions/{partition}/events?order=asc|desc&from=&limit=
+        //     &order_by=sequence|order_key
+        ("GET", p)
+            if p.starts_with("/streams/")
+                && p.contains("/partitions/")
+                && p.ends_with("/events") =>
+        {
+            let stream_id = stream_id.ok_or_else(|| missing_path_param("stream_id"))?;
+            let partition: u32 = match path_params.first("partition").and_then(|p| p.parse().ok()) {
+                Some(partition) => partition,
+                None => {
+                    return error_response(Error::Validation(
+                        "partition must be a non-negative integer".to_string(),
+                    ))
+                }
+            };
+
+            let query_params = event.query_string_parameters();
+            let scan_forward = match query_params.first("order") {
+                None | Some("asc") => true,
+                Some("desc") => false,
+                Some(other) => {
+                    return error_response(Error::Validation(format!(
+                        "Invalid order '{}': expected 'asc' or 'desc'",
+                        other
+                    )))
+                }
+            };
+            // The page is still read by sequence; order_key only sorts within it
+            let by_order_key = match query_params.first("order_by") {
+                None | Some("sequence") => false,
+                Some("order_key") => true,
+                Some(other) => {
+                    return error_response(Error::Validation(format!(
+                        "Invalid order_by '{}': expected 'sequence' or 'order_key'",
+                        other
+                    )))
+                }
+            };
+            let from_offset: u64 = query_params
+                .first("from")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0);
+            let limit = match query_params.first("limit").map(|s| s.parse::<u32>()) {
+                None => 100,
+                Some(Ok(limit)) if limit > 0 => limit,
+                Some(_) => {
+                    return error_response(Error::Validation(
+                        "limit must be a positive integer".to_string(),
+                    ))
+                }
+            };
+
+            let stream = match client.get_stream(&stream_id).await {
+                Ok(stream) => stream,
+                Err(e) => return error_response(e),
+            };
+            if partition >= stream.partition_count {
+                return error_response(Error::Validation(format!(
+                    "Partition {} out of range (stream has {})",
+                    partition, stream.partition_count
+                )));
+            }
+
+            match client
+                .read_events(&stream_id, partition, from_offset, limit, scan_forward)
+                .await
+            {
+                Ok(mut events) => {
+                    if by_order_key {
+                        sort_by_order_key(&mut events, !scan_forward);
+                    }
+                    json_response(200, &PartitionEventsResponse { events })
+                }
+                Err(e) => error_response(e),
+            }
+        }
+
+        // GET /streams/{stream_id}/compacted?sort=key|updated_at&order=asc|desc&limit=&start_key=
+        //     &since_sequence=
+        ("GET", p) if p.starts_with("/streams/") && p.ends_with("/compacted") => {
+            let stream_id = stream_id.ok_or_else(|| missing_path_param("stream_id"))?;
+
+            let query_params = event.query_string_parameters();
+            let by_updated_at = match query_params.first("sort") {
+                None | Some("key") => false,
+                Some("updated_at") => true,
+                Some(other) => {
+                    return error_response(Error::Validation(format!(
+                        "Invalid sort '{}': expected 'key' or 'updated_at'",
+                        other
+                    )))
+                }
+            };
+            let descending = match query_params.first("order") {
+                None | Some("asc") => false,
+                Some("desc") => true,
+                Some(other) => {
+                    return error_response(Error::Validation(format!(
+                        "Invalid order '{}': expected 'asc' or 'desc'",
+                        other
+                    )))
+                }
+            };
+            let limit = match query_params.first("limit").map(|s| s.parse::<usize>()) {
+                None => DEFAULT_COMPACTED_LIMIT,
+                Some(Ok(limit)) if limit > 0 => limit,
+                Some(_) => {
+                    return error_response(Error::Validation(
+                        "limit must be a positive integer".to_string(),
+                    ))
+                }
+            };
+
+            // Not a key condition: every compacted item is still read
+            let since_sequence = match query_params.first("since_sequence") {
+                None => None,
+                Some(since) => match since.parse::<u64>() {
+                    Ok(since) => Some(since),
+                    Err(_) => {
+                        return error_response(Error::Validation(
+                            "since_sequence must be a non-negative integer".to_string(),
+                        ))
+                    }
+                },
+            };
+
+            let start_key = query_params.first("start_key");
+
+            if let Err(e) = check_stream_exists(&client, &stream_id).await {
+                return error_response(e);
+            }
+
+            let page = if by_updated_at {
+                client
+                    .list_compacted_page_by_timestamp(
+                        &stream_id,
+                        start_key,
+                        limit,
+                        descending,
+                        since_sequence,
+                    )
+                    .await
+            } else {
+                client
+                    .list_compacted_page(&stream_id, start_key, limit, descending, since_sequence)
+                    .await
+            };
+            match page {
+                Ok((events, next_start_key)) => json_response(
+                    200,
+                    &ListCompactedResponse {
+                        events,
+                        next_start_key,
+                    },
+                ),
+                Err(e) => error_response(e),
+            }
+        }
+
+        // GET /streams/{stream_id}/compacted/changelog?cursor=&limit= - Every compacted
+        // update in the order it was applied, for CDC-style materialized views
+        ("GET", p) if p.starts_with("/streams/") && p.ends_with("/compacted/changelog") => {
+            let stream_id = stream_id.ok_or_else(|| missing_path_param("stream_id"))?;
+
+            let query_params = event.query_string_parameters();
+            let after = match query_params.first("cursor").map(|s| s.parse::<u64>()) {
+                None => 0,
+                Some(Ok(position)) => position,
+                Some(Err(_)) => {
+                    return error_response(Error::InvalidCursor {
+                        message: "Changelog cursors are returned by this endpoint".to_string(),
+                        details: None,
+                    })
+                }
+            };
+            let limit = match query_params.first("limit").map(|s| s.parse::<u32>()) {
+                None => DEFAULT_COMPACTED_LIMIT as u32,
+                Some(Ok(limit)) if limit > 0 => limit,
+                Some(_) => {
+                    return error_response(Error::Validation(
+                        "limit must be a positive integer".to_string(),
+                    ))
+                }
+            };
+
+            if let Err(e) = check_stream_exists(&client, &stream_id).await {
+                return error_response(e);
+            }
+
+            match client
+                .read_compaction_changelog(&stream_id, after, limit)
+                .await
+            {
+                Ok(changes) => {
+                    // With nothing new the same cursor is handed back
+                    let cursor = changes.last().map_or(after, |c| c.position).to_string();
+                    json_response(200, &CompactionChangelogResponse { changes, cursor })
+                }
+                Err(e) => error_response(e),
+            }
+        }
+
+        // GET /streams/{stream_id}/snapshot - Compacted state and the tail cursor
+        // it is consistent with, for bootstrapping read models
+        ("GET", p) if p.starts_with("/streams/") && p.ends_with("/snapshot") => {
+            let stream_id = stream_id.ok_or_else(|| missing_path_param("stream_id"))?;
+
+            let (compacted, offsets) = match client.compacted_snapshot(&stream_id).await {
+                Ok(snapshot) => snapshot,
+                Err(e) => return error_response(e),
+            };
+            let cursor = CursorState {
+                stream_id,
+                subscription_id: TAIL_SUBSCRIPTION_ID.to_string(),
+                offsets,
+                remaining_per_partition: Vec::new(),
+                committed_at: None,
+            }
+            .encode()?;
+
+            json_response(200, &SnapshotResponse { compacted, cursor })
+        }
+
+        // POST /streams/{stream_id}/compact - Backfill compacted state
+        ("POST", p) if p.starts_with("/streams/") && p.ends_with("/compact") => {
+            let stream_id = stream_id.ok_or_else(|| missing_path_param("stream_id"))?;
+
+            // The body is optional; without one the backfill starts from the beginning
+            let req: CompactionBackfillRequest = if event.body().is_empty() {
+                CompactionBackfillRequest::default()
+            } else {
+                match parse_body(event.body()) {
+                    Ok(req) => req,
+                    Err(e) => return error_response(e),
+                }
+            };
+
+            match client
+                .backfill_compacted(&stream_id, &req, BACKFILL_TIME_BUDGET)
+                .await
+            {
+                Ok(result) => json_response(200, &result),
+                Err(e) => error_response(e),
+            }
+        }
+
+        // POST /streams/{stream_id}/repartition - Copy into a stream with a new partition count
+        ("POST", p) if p.starts_with("/streams/") && p.ends_with("/repartition") => {
+            let stream_id = stream_id.ok_or_else(|| missing_path_param("stream_id"))?;
+            let req: RepartitionRequest = match parse_body(event.body()) {
+                Ok(req) => req,
+                Err(e) => return error_response(e),
+            };
+
+            match client
+                .repartition_stream(&stream_id, &req, REPARTITION_TIME_BUDGET)
+                .await
+            {
+                Ok(result) => {
+                    info!(
+                        stream_id = %stream_id,
+                        target_stream_id = %result.target_stream_id,
+                        events_copied = result.events_copied,
+                        complete = result.complete,
+                        "Repartitioned stream"
+                    );
+                    json_response(200, &result)
+                }
+                Err(e) => error_response(e),
+            }
+        }
 
         // GET /streams/{stream_id} - Get stream
         ("GET", p) if p.starts_with("/streams/") && !p.contains("/subscriptions") => {
-            let stream_id = stream_id.ok_or_else(|| "Missing stream_id")?;
+            let stream_id = stream_id.ok_or_else(|| missing_path_param("stream_id"))?;
 
             match client.get_stream(&stream_id).await {
-                Ok(stream) => json_response(200, &stream),
+                Ok(stream) => stream_response(200, &stream),
                 Err(e) => error_response(e),
             }
         }
 
-        // DELETE /streams/{stream_id} - Delete stream
+        // PATCH /streams/{stream_id} - Update stream config
+        ("PATCH", p) if p.starts_with("/streams/") && !p.contains("/subscriptions") => {
+            let stream_id = stream_id.ok_or_else(|| missing_path_param("stream_id"))?;
+
+            let if_match = event
+                .headers()
+                .get("If-Match")
+                .and_then(|v| v.to_str().ok());
+            let expected_version = match parse_if_match(if_match) {
+                Ok(version) => version,
+                Err(e) => return error_response(e),
+            };
+
+            let req: UpdateStreamRequest = match parse_body(event.body()) {
+                Ok(req) => req,
+                Err(e) => return error_response(e),
+            };
+
+            match client
+                .update_stream(&stream_id, expected_version, &req)
+                .await
+            {
+                Ok(stream) => stream_response(200, &stream),
+                Err(e) => error_response(e),
+            }
+        }
+
+        // POST /streams/{stream_id}/partitions/{partition}/events:batchGet - Fetch specific
+        // events, reporting the sequences that don't exist
+        ("POST", p)
+            if p.starts_with("/streams/")
+                && p.contains("/partitions/")
+                && p.ends_with("/events:batchGet") =>
+        {
+            let stream_id = stream_id.ok_or_else(|| missing_path_param("stream_id"))?;
+            let partition: u32 = match path_params.first("partition").and_then(|p| p.parse().ok()) {
+                Some(partition) => partition,
+                None => {
+                    return error_response(Error::Validation(
+                        "partition must be a non-negative integer".to_string(),
+                    ))
+                }
+            };
+
+            let req: BatchGetEventsRequest = match parse_body(event.body()) {
+                Ok(req) => req,
+                Err(e) => return error_response(e),
+            };
+            if let Err(e) = req.validate() {
+                return error_response(e);
+            }
+
+            match client
+                .batch_get_events(&stream_id, partition, &req.sequences)
+                .await
+            {
+                Ok(response) => json_response(200, &response),
+                Err(e) => error_response(e),
+            }
+        }
+
+        // DELETE /streams/{stream_id}/partitions/{partition}/events/{sequence} - Redact an
+        // event's payload in place (ahead of stream deletion, which matches any DELETE path)
+        ("DELETE", p)
+            if p.starts_with("/streams/")
+                && p.contains("/partitions/")
+                && p.contains("/events/") =>
+        {
+            let stream_id = stream_id.ok_or_else(|| missing_path_param("stream_id"))?;
+            let partition: u32 = match path_params.first("partition").and_then(|p| p.parse().ok()) {
+                Some(partition) => partition,
+                None => {
+                    return error_response(Error::Validation(
+                        "partition must be a non-negative integer".to_string(),
+                    ))
+                }
+            };
+            let sequence: u64 = match path_params.first("sequence").and_then(|s| s.parse().ok()) {
+                Some(sequence) if sequence > 0 => sequence,
+                _ => {
+                    return error_response(Error::Validation(
+                        "sequence must be a positive integer".to_string(),
+                    ))
+                }
+            };
+
+            info!(stream_id = %stream_id, partition, sequence, "Redacting event");
+            match client.redact_event(&stream_id, partition, sequence).await {
+                Ok(event) => json_response(200, &event),
+                Err(e) => error_response(e),
+            }
+        }
+
+        // DELETE /streams/{stream_id} - Delete stream (?force=true if it has subscriptions)
         ("DELETE", p) if p.starts_with("/streams/") && !p.contains("/subscriptions") => {
-            let stream_id = stream_id.ok_or_else(|| "Missing stream_id")?;
+            let stream_id = stream_id.ok_or_else(|| missing_path_param("stream_id"))?;
+
+            let force = match event.query_string_parameters().first("force") {
+                None | Some("false") => false,
+                Some("true") => true,
+                Some(other) => {
+                    return error_response(Error::Validation(format!(
+                        "Invalid force '{}': expected 'true' or 'false'",
+                        other
+                    )))
+                }
+            };
 
-            match client.delete_stream(&stream_id).await {
+            match client.delete_stream(&stream_id, force).await {
                 Ok(_) => json_response(200, &DeleteResponse { success: true }),
                 Err(e) => error_response(e),
             }
         }
 
+        // POST /streams/{stream_id}/subscriptions/seek-all - Reposition every subscription
+        ("POST", p) if p.starts_with("/streams/") && p.ends_with("/subscriptions/seek-all") => {
+            let stream_id = stream_id.ok_or_else(|| missing_path_param("stream_id"))?;
+
+            let req: SeekAllRequest = match parse_body(event.body()) {
+                Ok(req) => req,
+                Err(e) => return error_response(e),
+            };
+            if let Err(e) = req.validate() {
+                return error_response(e);
+            }
+
+            match client.seek_all_subscriptions(&stream_id, &req.to).await {
+                Ok(response) => {
+                    info!(
+                        stream_id = %stream_id,
+                        repositioned = response.repositioned,
+                        failed = response.failed.len(),
+                        "Repositioned subscriptions"
+                    );
+                    json_response(200, &response)
+                }
+                Err(e) => error_response(e),
+            }
+        }
+
+        // POST /streams/{stream_id}/subscriptions/{subscription_id}/seek - Reposition a subscription
+        ("POST", p)
+            if p.starts_with("/streams/")
+                && p.contains("/subscriptions/")
+                && p.ends_with("/seek") =>
+        {
+            let stream_id = stream_id.ok_or_else(|| missing_path_param("stream_id"))?;
+            let subscription_id = path_params
+                .first("subscription_id")
+                .ok_or_else(|| missing_path_param("subscription_id"))?
+                .to_string();
+
+            let req: SeekRequest = match parse_body(event.body()) {
+                Ok(req) => req,
+                Err(e) => return error_response(e),
+            };
+
+            match client
+                .seek_subscription(&stream_id, &subscription_id, &req.to)
+                .await
+            {
+                Ok(offsets) => json_response(
+                    200,
+                    &SeekResponse {
+                        subscription_id,
+                        offsets,
+                    },
+                ),
+                Err(e) => error_response(e),
+            }
+        }
+
+        // GET /streams/{stream_id}/subscriptions/{subscription_id}/lag - Events behind per partition
+        ("GET", p)
+            if p.starts_with("/streams/")
+                && p.contains("/subscriptions/")
+                && p.ends_with("/lag") =>
+        {
+            let stream_id = stream_id.ok_or_else(|| missing_path_param("stream_id"))?;
+            let subscription_id = path_params
+                .first("subscription_id")
+                .ok_or_else(|| missing_path_param("subscription_id"))?;
+
+            match client.subscription_lag(&stream_id, subscription_id).await {
+                Ok(lag) => json_response(200, &lag),
+                Err(e) => error_response(e),
+            }
+        }
+
+        // GET /streams/{stream_id}/subscriptions - List subscriptions; ?stale_after=<hours>
+        // keeps only those that haven't polled for that long (abandoned consumers)
+        ("GET", p) if p.starts_with("/streams/") && p.ends_with("/subscriptions") => {
+            let stream_id = stream_id.ok_or_else(|| missing_path_param("stream_id"))?;
+
+            let stale_after = match event.query_string_parameters().first("stale_after") {
+                None => None,
+                Some(hours) => match hours.parse::<u32>() {
+                    Ok(hours) if hours > 0 => Some(chrono::Duration::hours(hours.into())),
+                    _ => {
+                        return error_response(Error::Validation(
+                            "stale_after must be a positive number of hours".to_string(),
+                        ))
+                    }
+                },
+            };
+
+            // Distinguish a missing stream from one with no subscriptions
+            if let Err(e) = check_stream_exists(&client, &stream_id).await {
+                return error_response(e);
+            }
+
+            match client.list_subscriptions(&stream_id).await {
+                Ok(mut subscriptions) => {
+                    if let Some(stale_after) = stale_after {
+                        let cutoff = Utc::now() - stale_after;
+                        subscriptions.retain(|sub| sub.is_stale(cutoff));
+                    }
+                    json_response(200, &ListSubscriptionsResponse { subscriptions })
+                }
+                Err(e) => error_response(e),
+            }
+        }
+
         // POST /streams/{stream_id}/subscriptions - Create subscription
-        ("POST", p) if p.contains("/subscriptions") && !p.ends_with("/poll") && !p.ends_with("/commit") => {
-            let stream_id = stream_id.ok_or_else(|| "Missing stream_id")?;
+        ("POST", p)
+            if p.contains("/subscriptions")
+                && !p.ends_with("/poll")
+                && !p.ends_with("/commit")
+                && !p.ends_with("/commit-poll") =>
+        {
+            let stream_id = stream_id.ok_or_else(|| missing_path_param("stream_id"))?;
 
-            let body = event.body();
-            let body_str = std::str::from_utf8(body).map_err(|_| "Invalid UTF-8 in body")?;
-            let req: CreateSubscriptionRequest = serde_json::from_str(body_str)?;
+            let req = match parse_subscription_request(event.body()) {
+                Ok(req) => req,
+                Err(e) => return error_response(e),
+            };
 
             match client.create_subscription(&stream_id, &req).await {
                 Ok(sub) => json_response(201, &sub),
@@ -99,8 +811,58 @@ async fn handler(event: Request) -> Result<Response<Body>, LambdaError> {
 
         // DELETE /streams/{stream_id}/subscriptions/{subscription_id}
         ("DELETE", p) if p.contains("/subscriptions/") => {
-            // For MVP, we'll just return success (subscription deletion not fully implemented)
-            json_response(200, &DeleteResponse { success: true })
+            let stream_id = stream_id.ok_or_else(|| missing_path_param("stream_id"))?;
+            let subscription_id = path_params
+                .first("subscription_id")
+                .ok_or_else(|| missing_path_param("subscription_id"))?;
+
+            match client
+                .delete_subscription(&stream_id, subscription_id)
+                .await
+            {
+                Ok(_) => json_response(200, &DeleteResponse { success: true }),
+                Err(e) => error_response(e),
+            }
+        }
+
+        // PUT /aliases/{alias} - Point an alias at a stream
+        ("PUT", p) if p.starts_with("/aliases/") => {
+            let alias = path_params
+                .first("alias")
+                .ok_or_else(|| missing_path_param("alias"))?;
+            let req: PutAliasRequest = match parse_body(event.body()) {
+                Ok(req) => req,
+                Err(e) => return error_response(e),
+            };
+
+            match client.put_alias(alias, &req.stream_id, None).await {
+                Ok(alias) => json_response(200, &alias),
+                Err(e) => error_response(e),
+            }
+        }
+
+        // GET /aliases/{alias} - Get the stream an alias points at
+        ("GET", p) if p.starts_with("/aliases/") => {
+            let alias = path_params
+                .first("alias")
+                .ok_or_else(|| missing_path_param("alias"))?;
+
+            match client.get_alias(alias).await {
+                Ok(alias) => json_response(200, &alias),
+                Err(e) => error_response(e),
+            }
+        }
+
+        // DELETE /aliases/{alias} - Delete an alias
+        ("DELETE", p) if p.starts_with("/aliases/") => {
+            let alias = path_params
+                .first("alias")
+                .ok_or_else(|| missing_path_param("alias"))?;
+
+            match client.delete_alias(alias).await {
+                Ok(_) => json_response(200, &DeleteResponse { success: true }),
+                Err(e) => error_response(e),
+            }
         }
 
         // Not found
@@ -114,6 +876,29 @@ async fn handler(event: Request) -> Result<Response<Body>, LambdaError> {
     }
 }
 
+fn parse_body<T: DeserializeOwned>(body: &[u8]) -> Result<T, Error> {
+    body::parse_json(body::body_str(body::non_empty(body)?)?)
+}
+
+/// Parse a create-subscription body, reporting an unknown `start_from` with
+/// the accepted values rather than a bare serde error
+fn parse_subscription_request(body: &[u8]) -> Result<CreateSubscriptionRequest, Error> {
+    let value: serde_json::Value = parse_body(body)?;
+    if let Some(start_from) = value.get("start_from").and_then(|v| v.as_str()) {
+        start_from.parse::<StartFrom>()?;
+    }
+    serde_json::from_value(value).map_err(|e| body::invalid_json(&e))
+}
+
+/// 404 unless the stream exists, for routes that don't need its metadata
+async fn check_stream_exists(client: &DynamoClient, stream_id: &str) -> Result<(), Error> {
+    if client.head_stream(stream_id).await? {
+        Ok(())
+    } else {
+        Err(Error::StreamNotFound(stream_id.to_string()))
+    }
+}
+
 fn json_response<T: Serialize>(status: u16, body: &T) -> Result<Response<Body>, LambdaError> {
     Ok(Response::builder()
         .status(status)
@@ -121,14 +906,28 @@ fn json_response<T: Serialize>(status: u16, body: &T) -> Result<Response<Body>,
         .body(Body::from(serde_json::to_string(body)?))?)
 }
 
-fn error_response(e: Error) -> Result<Response<Body>, LambdaError> {
-    error!(error = %e, "Request failed");
-    let status = e.status_code();
-    let body = ErrorResponse::new(e.code(), e.to_string());
+/// Stream body with its version as the `ETag`
+fn stream_response(status: u16, stream: &Stream) -> Result<Response<Body>, LambdaError> {
     Ok(Response::builder()
         .status(status)
         .header("Content-Type", "application/json")
-        .body(Body::from(serde_json::to_string(&body)?))?)
+        .header("ETag", format!("\"{}\"", stream.version))
+        .body(Body::from(serde_json::to_string(stream)?))?)
+}
+
+/// Stream version from an `If-Match` header, e.g. `"3"`
+fn parse_if_match(header: Option<&str>) -> Result<u64, Error> {
+    let header = header.ok_or_else(|| {
+        Error::PreconditionRequired(
+            "If-Match header with the stream's ETag is required".to_string(),
+        )
+    })?;
+    header.trim().trim_matches('"').parse().map_err(|_| {
+        Error::Validation(format!(
+            "Invalid If-Match '{}': expected a stream ETag",
+            header
+        ))
+    })
 }
 
 #[tokio::main]
@@ -139,5 +938,201 @@ async fn main() -> Result<(), LambdaError> {
         .without_time()
         .init();
 
-    run(service_fn(handler)).await
+    // Initialize AWS clients once and reuse them across invocations
+    let config = aws_config::load_defaults(BehaviorVersion::latest()).await;
+    let client = DynamoClient::from_config(&config);
+    let client = &client;
+
+    run(service_fn(move |event| async move {
+        recover(handler(client, event).await)
+    }))
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eventledger_core::test_util::offline_client;
+    use eventledger_core::{MAX_BATCH_GET_EVENTS, MAX_PUBLISH_BATCH};
+    use std::collections::HashMap;
+
+    fn request(method: &str, uri: &str) -> Request {
+        lambda_http::http::Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::from("{}"))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_missing_path_params_are_bad_requests() {
+        let client = offline_client();
+
+        let response = recover(handler(&client, request("GET", "/streams/orders/stats")).await);
+        let response = response.expect("should respond rather than fail the invocation");
+        assert_eq!(response.status(), 400);
+
+        let event =
+            request("GET", "/streams/orders/subscriptions/billing/lag").with_path_parameters(
+                HashMap::from([("stream_id".to_string(), "orders".to_string())]),
+            );
+        let response = recover(handler(&client, event).await).unwrap();
+        assert_eq!(response.status(), 400);
+    }
+
+    #[tokio::test]
+    async fn test_empty_create_bodies_are_bad_requests() {
+        let subscriptions = HashMap::from([("stream_id".to_string(), "orders".to_string())]);
+        let requests = [
+            request("POST", "/streams"),
+            request("POST", "/streams/orders/subscriptions").with_path_parameters(subscriptions),
+        ];
+        for event in requests {
+            let (parts, _) = event.into_parts();
+            let event = Request::from_parts(parts, Body::Empty);
+            let response = handler(&offline_client(), event).await.unwrap();
+            assert_eq!(response.status(), 400);
+
+            let body: ErrorResponse = serde_json::from_slice(response.body()).unwrap();
+            assert_eq!(body.error, "validation_error");
+            assert_eq!(body.message, "Validation error: Request body is empty");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_malformed_tag_filter_is_a_bad_request() {
+        let event = request("GET", "/streams")
+            .with_query_string_parameters(HashMap::from([("tag".to_string(), "team".to_string())]));
+        let response = handler(&offline_client(), event).await.unwrap();
+        assert_eq!(response.status(), 400);
+    }
+
+    #[tokio::test]
+    async fn test_malformed_stale_after_is_a_bad_request() {
+        for hours in ["0", "-1", "a day"] {
+            let event = request("GET", "/streams/orders/subscriptions")
+                .with_path_parameters(HashMap::from([(
+                    "stream_id".to_string(),
+                    "orders".to_string(),
+                )]))
+                .with_query_string_parameters(HashMap::from([(
+                    "stale_after".to_string(),
+                    hours.to_string(),
+                )]));
+            let response = handler(&offline_client(), event).await.unwrap();
+            assert_eq!(response.status(), 400, "{}", hours);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_capabilities_report_limits() {
+        let response = handler(&offline_client(), request("GET", "/capabilities"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+
+        let capabilities: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(
+            capabilities["limits"]["max_publish_batch"],
+            MAX_PUBLISH_BATCH
+        );
+        assert_eq!(capabilities["features"]["compaction"], true);
+    }
+
+    #[tokio::test]
+    async fn test_redact_validates_coordinates_before_touching_the_stream() {
+        for (partition, sequence) in [("0", "abc"), ("0", "0"), ("-1", "5")] {
+            let path = format!(
+                "/streams/orders/partitions/{}/events/{}",
+                partition, sequence
+            );
+            let event = request("DELETE", &path).with_path_parameters(HashMap::from([
+                ("stream_id".to_string(), "orders".to_string()),
+                ("partition".to_string(), partition.to_string()),
+                ("sequence".to_string(), sequence.to_string()),
+            ]));
+            let response = handler(&offline_client(), event).await.unwrap();
+            assert_eq!(response.status(), 400, "{}", path);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_changelog_rejects_a_foreign_cursor() {
+        let event = request("GET", "/streams/orders/compacted/changelog")
+            .with_path_parameters(HashMap::from([(
+                "stream_id".to_string(),
+                "orders".to_string(),
+            )]))
+            .with_query_string_parameters(HashMap::from([(
+                "cursor".to_string(),
+                "eyJzIjoxfQ".to_string(),
+            )]));
+        let response = handler(&offline_client(), event).await.unwrap();
+        assert_eq!(response.status(), 400);
+
+        let body: ErrorResponse = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body.error, "invalid_cursor");
+    }
+
+    #[tokio::test]
+    async fn test_batch_get_events_validates_sequences_before_reading() {
+        let too_many: Vec<u64> = (1..=MAX_BATCH_GET_EVENTS as u64 + 1).collect();
+        for sequences in [vec![], too_many] {
+            let body = serde_json::json!({ "sequences": sequences }).to_string();
+            let (parts, _) = request("POST", "/streams/orders/partitions/0/events:batchGet")
+                .with_path_parameters(HashMap::from([
+                    ("stream_id".to_string(), "orders".to_string()),
+                    ("partition".to_string(), "0".to_string()),
+                ]))
+                .into_parts();
+            let event = Request::from_parts(parts, Body::from(body));
+            let response = handler(&offline_client(), event).await.unwrap();
+            assert_eq!(response.status(), 400);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_repartition_into_itself_is_a_bad_request() {
+        let body = serde_json::json!({ "target_stream_id": "orders", "partition_count": 4 });
+        let (parts, _) = request("POST", "/streams/orders/repartition")
+            .with_path_parameters(HashMap::from([(
+                "stream_id".to_string(),
+                "orders".to_string(),
+            )]))
+            .into_parts();
+        let event = Request::from_parts(parts, Body::from(body.to_string()));
+        let response = handler(&offline_client(), event).await.unwrap();
+        assert_eq!(response.status(), 400);
+
+        let body: ErrorResponse = serde_json::from_slice(response.body()).unwrap();
+        assert!(
+            body.message.contains("target_stream_id"),
+            "{}",
+            body.message
+        );
+    }
+
+    #[tokio::test]
+    async fn test_alias_that_cant_be_a_key_is_a_bad_request() {
+        let body = serde_json::json!({ "stream_id": "orders" });
+        let (parts, _) = request("PUT", "/aliases/orders%23live")
+            .with_path_parameters(HashMap::from([(
+                "alias".to_string(),
+                "orders#live".to_string(),
+            )]))
+            .into_parts();
+        let event = Request::from_parts(parts, Body::from(body.to_string()));
+        let response = handler(&offline_client(), event).await.unwrap();
+        assert_eq!(response.status(), 400);
+
+        let body: ErrorResponse = serde_json::from_slice(response.body()).unwrap();
+        assert!(body.message.contains("'#'"), "{}", body.message);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_route_is_not_found() {
+        let response = recover(handler(&offline_client(), request("GET", "/nowhere")).await);
+        let response = response.unwrap();
+        assert_eq!(response.status(), 404);
+    }
 }
@@ -2,13 +2,32 @@
 //!
 //! Triggered by DynamoDB Streams to maintain compacted state.
 //! For each new event, updates the compacted table with the latest value per key.
+//! Events on streams created with `compaction: false` are skipped.
+//!
+//! Records that fail are reported back as `batchItemFailures` so the event
+//! source mapping retries them instead of treating the whole batch as done.
+//! After `EVENTLEDGER_DLQ_MAX_ATTEMPTS` failures a record that can't be
+//! compacted (e.g. it doesn't parse) is moved to its stream's dead-letter
+//! queue and no longer retried. DynamoDB failures and throttling don't count
+//! toward that; those records are retried until they succeed.
+//!
+//! Up to `EVENTLEDGER_COMPACTOR_CONCURRENCY` keys are updated at once; records
+//! for the same key are still applied one at a time, in batch order.
 
 use aws_config::BehaviorVersion;
 use aws_lambda_events::event::dynamodb::{Event, EventRecord};
+use aws_lambda_events::event::streams::{DynamoDbBatchItemFailure, DynamoDbEventResponse};
 use serde_dynamo::AttributeValue;
 use chrono::Utc;
-use eventledger_core::{CompactedEvent, DynamoClient};
+use eventledger_core::{
+    compactor_concurrency, dlq_max_attempts, event_id, CompactedEvent, DynamoClient,
+    Error as CoreError,
+};
+use futures::stream::{self, StreamExt};
 use lambda_runtime::{run, service_fn, Error as LambdaError, LambdaEvent};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
 use tracing::{error, info, warn};
 
 /// Extract string value from AttributeValue
@@ -27,8 +46,199 @@ fn get_number_str(av: &AttributeValue) -> Option<&str> {
     }
 }
 
+/// Stream a record belongs to, from its `stream_id` or else its `STREAM#{id}#P{n}` key
+fn record_stream_id(record: &EventRecord) -> Option<String> {
+    let new_image = &record.change.new_image;
+    if let Some(stream_id) = new_image.get("stream_id").and_then(get_string) {
+        return Some(stream_id.to_string());
+    }
+    new_image
+        .get("PK")
+        .and_then(get_string)
+        .and_then(|pk| pk.strip_prefix("STREAM#"))
+        .and_then(|rest| rest.rsplit_once("#P"))
+        .map(|(stream_id, _)| stream_id.to_string())
+}
+
+/// Why a record failed to compact
+#[derive(Debug)]
+enum RecordError {
+    /// The record itself can't be compacted, e.g. it doesn't parse; retrying
+    /// gives the same result, so it counts toward dead-lettering
+    Poison(String),
+    /// DynamoDB failed or throttled; the record is retried and never
+    /// dead-lettered for it
+    Transient(String),
+}
+
+impl RecordError {
+    /// Classify an error from the store, with `context` saying what failed
+    fn from_core(context: &str, e: CoreError) -> Self {
+        let message = format!("{}: {}", context, e);
+        match e {
+            CoreError::Validation(_)
+            | CoreError::Serialization(_)
+            | CoreError::DynamoSerialization(_) => RecordError::Poison(message),
+            _ => RecordError::Transient(message),
+        }
+    }
+}
+
+impl From<&str> for RecordError {
+    fn from(message: &str) -> Self {
+        RecordError::Poison(message.to_string())
+    }
+}
+
+impl std::fmt::Display for RecordError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecordError::Poison(message) | RecordError::Transient(message) => f.write_str(message),
+        }
+    }
+}
+
+/// Count a failure against a record, dead-lettering it once it has used up its
+/// attempts. Returns true if the record should still be retried.
+async fn should_retry(
+    client: &DynamoClient,
+    record: &EventRecord,
+    error: &str,
+    max_attempts: u32,
+) -> bool {
+    let (Some(stream_id), Some(record_id)) = (
+        record_stream_id(record),
+        record.change.sequence_number.as_deref(),
+    ) else {
+        warn!("Cannot dead-letter a record without a stream or sequence number");
+        return true;
+    };
+
+    let raw = match serde_json::to_value(&record.change.new_image) {
+        Ok(raw) => raw,
+        Err(e) => {
+            error!(error = %e, "Failed to serialize record for the dead-letter queue");
+            return true;
+        }
+    };
+
+    match client
+        .dead_letter_on_failure(&stream_id, record_id, &raw, error, max_attempts)
+        .await
+    {
+        Ok(true) => {
+            warn!(stream_id = %stream_id, record_id = %record_id, "Moved record to dead-letter queue");
+            false
+        }
+        Ok(false) => true,
+        Err(e) => {
+            error!(error = %e, "Failed to count record failure");
+            true
+        }
+    }
+}
+
+/// How a stream's events are compacted
+#[derive(Debug, Clone, Copy)]
+struct CompactionConfig {
+    /// The stream's `compaction` flag
+    enabled: bool,
+    /// The stream's `compacted_ttl_hours`
+    ttl_hours: Option<u32>,
+}
+
+/// A stream's [`CompactionConfig`], looked up once per batch
+///
+/// Keys processed concurrently may both miss the cache and look it up; that
+/// costs a read, not correctness.
+async fn compaction_config(
+    client: &DynamoClient,
+    stream_id: &str,
+    cache: &Mutex<HashMap<String, CompactionConfig>>,
+) -> Result<CompactionConfig, RecordError> {
+    if let Some(config) = cache.lock().unwrap().get(stream_id) {
+        return Ok(*config);
+    }
+    let config = match client.get_stream(stream_id).await {
+        Ok(stream) => CompactionConfig {
+            enabled: stream.compaction,
+            ttl_hours: stream.compacted_ttl_hours,
+        },
+        // The stream was deleted after the event was written; nothing to expire by
+        Err(CoreError::StreamNotFound(_)) => CompactionConfig {
+            enabled: true,
+            ttl_hours: None,
+        },
+        Err(e) => return Err(RecordError::from_core("Failed to get stream", e)),
+    };
+    cache.lock().unwrap().insert(stream_id.to_string(), config);
+    Ok(config)
+}
+
+/// Indexes of `records` grouped by the compacted state they update, each
+/// group in batch order
+///
+/// Records that can't be tied to a key get a group of their own; they either
+/// aren't events or fail to parse, so they update nothing.
+fn group_by_key(records: &[EventRecord]) -> Vec<Vec<usize>> {
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut by_key: HashMap<(String, String), usize> = HashMap::new();
+    for (index, record) in records.iter().enumerate() {
+        let key = record.change.new_image.get("key").and_then(get_string);
+        match record_stream_id(record).zip(key) {
+            Some((stream_id, key)) => {
+                let group = *by_key
+                    .entry((stream_id, key.to_string()))
+                    .or_insert_with(|| {
+                        groups.push(Vec::new());
+                        groups.len() - 1
+                    });
+                groups[group].push(index);
+            }
+            None => groups.push(vec![index]),
+        }
+    }
+    groups
+}
+
+/// Run `f` on every record, working on up to `concurrency` keys at once
+///
+/// Records for the same key run one after another in batch order, so a key's
+/// compacted state is never written out of order. Results come back in batch
+/// order.
+async fn for_each_by_key<'a, T, F, Fut>(
+    records: &'a [EventRecord],
+    concurrency: usize,
+    f: F,
+) -> Vec<T>
+where
+    F: Fn(&'a EventRecord) -> Fut,
+    Fut: Future<Output = T>,
+{
+    let f = &f;
+    let groups: Vec<Vec<(usize, T)>> = stream::iter(group_by_key(records))
+        .map(|group| async move {
+            let mut results = Vec::with_capacity(group.len());
+            for index in group {
+                results.push((index, f(&records[index]).await));
+            }
+            results
+        })
+        .buffer_unordered(concurrency.max(1))
+        .collect()
+        .await;
+
+    let mut results: Vec<(usize, T)> = groups.into_iter().flatten().collect();
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
 /// Process a single DynamoDB Stream record
-async fn process_record(client: &DynamoClient, record: &EventRecord) -> Result<(), String> {
+async fn process_record(
+    client: &DynamoClient,
+    record: &EventRecord,
+    config_cache: &Mutex<HashMap<String, CompactionConfig>>,
+) -> Result<(), RecordError> {
     // Only process INSERT and MODIFY events
     let event_name = record.event_name.as_str();
     if event_name != "INSERT" && event_name != "MODIFY" {
@@ -85,6 +295,27 @@ async fn process_record(client: &DynamoClient, record: &EventRecord) -> Result<(
         .and_then(|n| n.parse().ok())
         .ok_or("Missing or invalid partition")?;
 
+    let config = compaction_config(client, &stream_id, config_cache).await?;
+    if !config.enabled {
+        return Ok(());
+    }
+
+    // Events are only modified by a redaction; the INSERT before it applied
+    // the event, so this just redacts what that wrote
+    if event_name == "MODIFY" {
+        let data: serde_json::Value = new_image
+            .get("data")
+            .map(|v| serde_dynamo::from_attribute_value(v.clone()))
+            .transpose()
+            .map_err(|e| RecordError::Poison(format!("Invalid data: {}", e)))?
+            .unwrap_or(serde_json::Value::Null);
+        client
+            .redact_compacted(&stream_id, &key, partition, sequence, &data)
+            .await
+            .map_err(|e| RecordError::from_core("Failed to redact compacted", e))?;
+        return Ok(());
+    }
+
     let data: serde_json::Value = new_image
         .get("data")
         .and_then(|v| {
@@ -103,17 +334,16 @@ async fn process_record(client: &DynamoClient, record: &EventRecord) -> Result<(
         .map(|dt| dt.with_timezone(&Utc))
         .unwrap_or_else(Utc::now);
 
-    // Check if we should update compacted state
-    // Only update if this is a newer sequence than what we have
-    if let Ok(Some(existing)) = client.get_compacted(&stream_id, &key).await {
-        if existing.sequence >= sequence {
-            // Existing compacted state is newer, skip
-            return Ok(());
-        }
-    }
+    // Set when `timestamp` isn't when the event was written, e.g. a backfill
+    let ingested_at = new_image
+        .get("wall_clock_timestamp")
+        .and_then(get_string)
+        .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
+        .map_or(timestamp, |dt| dt.with_timezone(&Utc));
 
     // Create compacted event
     let compacted = CompactedEvent {
+        id: event_id(&stream_id, partition, sequence),
         stream_id: stream_id.clone(),
         key: key.clone(),
         event_type,
@@ -123,11 +353,15 @@ async fn process_record(client: &DynamoClient, record: &EventRecord) -> Result<(
         timestamp,
     };
 
-    // Store compacted state
-    client
-        .put_compacted(&compacted)
+    // Store compacted state; the write is skipped if what's stored is newer
+    let updated = client
+        .put_compacted(&compacted, ingested_at, config.ttl_hours)
         .await
-        .map_err(|e| format!("Failed to put compacted: {}", e))?;
+        .map_err(|e| RecordError::from_core("Failed to put compacted", e))?;
+    if !updated {
+        // Existing compacted state is newer, skip
+        return Ok(());
+    }
 
     info!(
         stream_id = %stream_id,
@@ -139,25 +373,108 @@ async fn process_record(client: &DynamoClient, record: &EventRecord) -> Result<(
     Ok(())
 }
 
-async fn handler(event: LambdaEvent<Event>) -> Result<(), LambdaError> {
+/// Process a batch, collecting the sequence numbers of records that failed
+async fn process_batch(client: &DynamoClient, records: &[EventRecord]) -> DynamoDbEventResponse {
+    let max_attempts = dlq_max_attempts();
+    let config_cache = Mutex::new(HashMap::new());
+    let config_cache = &config_cache;
+
+    let results = for_each_by_key(records, compactor_concurrency(), |record| async move {
+        let e = process_record(client, record, config_cache).await.err()?;
+        error!(
+            error = %e,
+            sequence_number = ?record.change.sequence_number,
+            "Failed to process record"
+        );
+        // Continue processing other records; only failures are retried, and
+        // only records that can never succeed are dead-lettered
+        let retry = match &e {
+            RecordError::Poison(message) => {
+                should_retry(client, record, message, max_attempts).await
+            }
+            RecordError::Transient(_) => true,
+        };
+        retry.then(|| DynamoDbBatchItemFailure {
+            item_identifier: record.change.sequence_number.clone(),
+        })
+    })
+    .await;
+
+    // Lambda retries from the first failure, so everything before it is done
+    let done = results
+        .iter()
+        .position(Option::is_some)
+        .unwrap_or(records.len());
+    for ((stream_id, partition), sequence) in compacted_through(&records[..done]) {
+        if let Err(e) = client
+            .advance_compacted_through(&stream_id, partition, sequence)
+            .await
+        {
+            // Snapshots read further back until a later batch records it
+            warn!(
+                error = %e,
+                stream_id = %stream_id,
+                partition,
+                "Failed to record how far the partition is compacted"
+            );
+        }
+    }
+
+    DynamoDbEventResponse {
+        batch_item_failures: results.into_iter().flatten().collect(),
+    }
+}
+
+/// Highest event sequence in the records for each stream partition
+///
+/// A partition's records arrive in the order its events were written, so
+/// every event up to that sequence has been through the compactor.
+fn compacted_through(records: &[EventRecord]) -> HashMap<(String, u32), u64> {
+    let mut through = HashMap::new();
+    for record in records {
+        let new_image = &record.change.new_image;
+        if !new_image
+            .get("SK")
+            .and_then(get_string)
+            .is_some_and(|sk| sk.starts_with("SEQ#"))
+        {
+            continue;
+        }
+        let stream_id = record_stream_id(record);
+        let partition: Option<u32> = new_image
+            .get("partition")
+            .and_then(get_number_str)
+            .and_then(|n| n.parse().ok());
+        let sequence: Option<u64> = new_image
+            .get("sequence")
+            .and_then(get_number_str)
+            .and_then(|n| n.parse().ok());
+        if let (Some(stream_id), Some(partition), Some(sequence)) = (stream_id, partition, sequence)
+        {
+            let entry = through.entry((stream_id, partition)).or_insert(0);
+            *entry = sequence.max(*entry);
+        }
+    }
+    through
+}
+
+async fn handler(
+    client: &DynamoClient,
+    event: LambdaEvent<Event>,
+) -> Result<DynamoDbEventResponse, LambdaError> {
     let (payload, _context) = event.into_parts();
 
     info!(record_count = payload.records.len(), "Processing DynamoDB Stream batch");
 
-    // Initialize AWS clients
-    let config = aws_config::load_defaults(BehaviorVersion::latest()).await;
-    let dynamo_client = aws_sdk_dynamodb::Client::new(&config);
-    let client = DynamoClient::new(dynamo_client);
-
-    // Process each record
-    for record in &payload.records {
-        if let Err(e) = process_record(&client, record).await {
-            error!(error = %e, "Failed to process record");
-            // Continue processing other records
-        }
+    let response = process_batch(client, &payload.records).await;
+    if !response.batch_item_failures.is_empty() {
+        warn!(
+            failed = response.batch_item_failures.len(),
+            "Reporting failed records for retry"
+        );
     }
 
-    Ok(())
+    Ok(response)
 }
 
 #[tokio::main]
@@ -168,5 +485,301 @@ async fn main() -> Result<(), LambdaError> {
         .without_time()
         .init();
 
-    run(service_fn(handler)).await
+    // Initialize AWS clients once and reuse them across invocations
+    let config = aws_config::load_defaults(BehaviorVersion::latest()).await;
+    let client = DynamoClient::from_config(&config);
+    let client = &client;
+
+    run(service_fn(move |event| async move {
+        handler(client, event).await
+    }))
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eventledger_core::test_util::{fake_dynamo, offline_client, sdk_config, OK};
+
+    fn record(sequence_number: &str, new_image: serde_json::Value) -> EventRecord {
+        serde_json::from_value(serde_json::json!({
+            "awsRegion": "us-east-1",
+            "eventID": format!("event-{}", sequence_number),
+            "eventName": "INSERT",
+            "eventSource": "aws:dynamodb",
+            "dynamodb": {
+                "ApproximateCreationDateTime": 1700000000,
+                "Keys": {},
+                "NewImage": new_image,
+                "SequenceNumber": sequence_number,
+                "SizeBytes": 100,
+                "StreamViewType": "NEW_IMAGE"
+            }
+        }))
+        .expect("valid stream record")
+    }
+
+    #[test]
+    fn test_record_stream_id_falls_back_to_partition_key() {
+        let with_field = record(
+            "1",
+            serde_json::json!({
+                "PK": { "S": "STREAM#orders#P0" },
+                "stream_id": { "S": "orders" }
+            }),
+        );
+        assert_eq!(record_stream_id(&with_field).as_deref(), Some("orders"));
+
+        let from_pk = record(
+            "2",
+            serde_json::json!({
+                "PK": { "S": "STREAM#orders#P12" },
+                "SK": { "S": "SEQ#00000000000000000001" }
+            }),
+        );
+        assert_eq!(record_stream_id(&from_pk).as_deref(), Some("orders"));
+
+        let unknown = record("3", serde_json::json!({ "SK": { "S": "SEQ#1" } }));
+        assert_eq!(record_stream_id(&unknown), None);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_batch_applies_each_key_in_order() {
+        let keys = ["a", "b", "a", "c", "b", "a", "c", "b"];
+        let records: Vec<EventRecord> = keys
+            .iter()
+            .enumerate()
+            .map(|(i, key)| {
+                record(
+                    &i.to_string(),
+                    serde_json::json!({
+                        "PK": { "S": "STREAM#orders#P0" },
+                        "stream_id": { "S": "orders" },
+                        "key": { "S": key }
+                    }),
+                )
+            })
+            .collect();
+
+        let applied = Mutex::new(Vec::new());
+        let in_flight = std::sync::atomic::AtomicUsize::new(0);
+        let max_in_flight = std::sync::atomic::AtomicUsize::new(0);
+        let results = for_each_by_key(&records, 4, |record| {
+            let (applied, in_flight, max_in_flight) = (&applied, &in_flight, &max_in_flight);
+            async move {
+                use std::sync::atomic::Ordering::SeqCst;
+                let running = in_flight.fetch_add(1, SeqCst) + 1;
+                max_in_flight.fetch_max(running, SeqCst);
+
+                // Earlier records take longer, so ordering by completion would reorder them
+                let sequence: u64 = record
+                    .change
+                    .sequence_number
+                    .as_deref()
+                    .unwrap()
+                    .parse()
+                    .unwrap();
+                tokio::time::sleep(std::time::Duration::from_millis(40 - 5 * sequence)).await;
+                let key = get_string(&record.change.new_image["key"])
+                    .unwrap()
+                    .to_string();
+                applied.lock().unwrap().push((key, sequence));
+
+                in_flight.fetch_sub(1, SeqCst);
+                sequence
+            }
+        })
+        .await;
+
+        // Results follow the batch, whatever order they finished in
+        assert_eq!(results, (0..keys.len() as u64).collect::<Vec<_>>());
+        assert!(max_in_flight.into_inner() > 1);
+
+        let applied = applied.into_inner().unwrap();
+        for key in ["a", "b", "c"] {
+            let sequences: Vec<u64> = applied
+                .iter()
+                .filter(|(k, _)| k == key)
+                .map(|(_, s)| *s)
+                .collect();
+            let expected: Vec<u64> = (0..keys.len() as u64)
+                .filter(|&i| keys[i as usize] == key)
+                .collect();
+            assert_eq!(sequences, expected, "key {}", key);
+        }
+    }
+
+    #[test]
+    fn test_compacted_through_takes_each_partitions_last_event() {
+        let event = |sequence_number: &str, partition: u32, sequence: u64| {
+            record(
+                sequence_number,
+                serde_json::json!({
+                    "PK": { "S": format!("STREAM#orders#P{}", partition) },
+                    "SK": { "S": format!("SEQ#{:020}", sequence) },
+                    "stream_id": { "S": "orders" },
+                    "partition": { "N": partition.to_string() },
+                    "sequence": { "N": sequence.to_string() }
+                }),
+            )
+        };
+        let records = vec![
+            event("100", 0, 7),
+            event("200", 1, 3),
+            event("300", 0, 8),
+            record(
+                "400",
+                serde_json::json!({
+                    "PK": { "S": "STREAM#orders#P1" },
+                    "SK": { "S": "COUNTER" },
+                    "sequence": { "N": "9" }
+                }),
+            ),
+        ];
+
+        let through = compacted_through(&records);
+
+        assert_eq!(
+            through,
+            HashMap::from([
+                (("orders".to_string(), 0), 8),
+                (("orders".to_string(), 1), 3)
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_records_reported_for_retry() {
+        let client = offline_client();
+        let records = vec![
+            // Not an event record: skipped successfully
+            record(
+                "100",
+                serde_json::json!({
+                    "PK": { "S": "STREAM#orders" },
+                    "SK": { "S": "META" }
+                }),
+            ),
+            // Event record missing its stream_id: fails
+            record(
+                "200",
+                serde_json::json!({
+                    "PK": { "S": "STREAM#orders#P0" },
+                    "SK": { "S": "SEQ#00000000000000000001" },
+                    "key": { "S": "order-1" }
+                }),
+            ),
+        ];
+
+        let response = process_batch(&client, &records).await;
+
+        assert_eq!(
+            response.batch_item_failures,
+            vec![DynamoDbBatchItemFailure {
+                item_identifier: Some("200".to_string()),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unparseable_record_is_dead_lettered_after_max_attempts() {
+        // The attempt counter reaches the default maximum of 3, so the record
+        // is dead-lettered rather than reported for retry
+        let (endpoint, server) =
+            fake_dynamo([(OK, r#"{"Attributes":{"attempts":{"N":"3"}}}"#), (OK, "{}")]);
+        let client = DynamoClient::from_config_with_endpoint(&sdk_config(), Some(&endpoint));
+        let records = vec![record(
+            "4900",
+            serde_json::json!({
+                "PK": { "S": "STREAM#orders#P0" },
+                "SK": { "S": "SEQ#00000000000000000001" },
+                "stream_id": { "S": "orders" },
+                "key": { "S": "order-1" },
+                "event_type": { "S": "order.created" },
+                "sequence": { "S": "not-a-number" }
+            }),
+        )];
+
+        let response = process_batch(&client, &records).await;
+
+        assert!(response.batch_item_failures.is_empty());
+        let requests = server.join().unwrap();
+        assert!(requests[0].contains("ATTEMPT#4900"));
+        assert!(requests[1].contains("RECORD#4900"));
+        assert!(requests[1].contains("Missing or invalid sequence"));
+    }
+
+    #[tokio::test]
+    async fn test_throttled_record_is_retried_without_counting_toward_the_dlq() {
+        let (endpoint, server) = fake_dynamo([
+            (
+                "400 Bad Request",
+                r#"{"__type":"com.amazonaws.dynamodb.v20120810#ProvisionedThroughputExceededException","message":"Rate exceeded"}"#,
+            ),
+            (OK, "{}"),
+        ]);
+        let client = DynamoClient::from_config_with_endpoint(&sdk_config(), Some(&endpoint));
+        let records = vec![record(
+            "4900",
+            serde_json::json!({
+                "PK": { "S": "STREAM#orders#P0" },
+                "SK": { "S": "SEQ#00000000000000000001" },
+                "stream_id": { "S": "orders" },
+                "key": { "S": "order-1" },
+                "event_type": { "S": "order.created" },
+                "sequence": { "N": "1" },
+                "partition": { "N": "0" }
+            }),
+        )];
+
+        let response = process_batch(&client, &records).await;
+
+        assert_eq!(
+            response.batch_item_failures,
+            vec![DynamoDbBatchItemFailure {
+                item_identifier: Some("4900".to_string()),
+            }]
+        );
+        // Take the second answer ourselves; had the failure been counted, the
+        // attempt counter update would have taken it
+        let _ = client.head_stream("sentinel").await;
+        let requests = server.join().unwrap();
+        assert!(requests[0].contains("STREAM#orders"));
+        assert!(requests[1].contains("STREAM#sentinel"));
+    }
+
+    #[tokio::test]
+    async fn test_records_on_streams_without_compaction_are_skipped() {
+        let stream = r#"{"Item":{
+            "stream_id":{"S":"clicks"},
+            "partition_count":{"N":"3"},
+            "retention_hours":{"N":"24"},
+            "compaction":{"BOOL":false},
+            "created_at":{"S":"2024-01-01T00:00:00Z"}
+        }}"#;
+        let (endpoint, server) = fake_dynamo([(OK, stream), (OK, "{}")]);
+        let client = DynamoClient::from_config_with_endpoint(&sdk_config(), Some(&endpoint));
+        let records = vec![record(
+            "4900",
+            serde_json::json!({
+                "PK": { "S": "STREAM#clicks#P0" },
+                "SK": { "S": "SEQ#00000000000000000001" },
+                "stream_id": { "S": "clicks" },
+                "key": { "S": "" },
+                "event_type": { "S": "page.viewed" },
+                "sequence": { "N": "1" },
+                "partition": { "N": "0" }
+            }),
+        )];
+
+        let response = process_batch(&client, &records).await;
+
+        assert!(response.batch_item_failures.is_empty());
+        // The stream is read, and the partition marked done, but nothing is
+        // compacted
+        let requests = server.join().unwrap();
+        assert!(requests[0].contains("STREAM#clicks"));
+        assert!(requests[1].contains("COMPACTED_THROUGH"));
+        assert!(!requests.iter().any(|r| r.contains("PutItem")));
+    }
 }
@@ -3,17 +3,57 @@
 //! Handles:
 //! - GET /streams/{stream_id}/subscriptions/{subscription_id}/poll
 //! - POST /streams/{stream_id}/subscriptions/{subscription_id}/commit
+//! - POST /streams/{stream_id}/subscriptions/{subscription_id}/commit-poll
+//! - GET /streams/{stream_id}/subscriptions/{subscription_id}/offsets
+//! - POST /streams/{stream_id}/commit-batch
+//!
+//! `{stream_id}` may be an alias, which is resolved on every request.
 
 use aws_config::BehaviorVersion;
-use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use eventledger_core::response::{error_response, missing_path_param, recover};
 use eventledger_core::{
-    CommitRequest, CommitResponse, CursorState, DynamoClient, Error, ErrorResponse, Event,
-    PartitionOffset, PollResponse,
+    body, body::Codec, ApiVersion, CommitBatchRequest, CommitBatchResponse, CommitRequest,
+    CommitResponse, ConsumerOffset, CreateSubscriptionRequest, CursorEncoding, CursorState,
+    DeliverySemantics, DynamoClient, Error, ErrorResponse, Event, EventProjection, EventStore,
+    PartitionOffset, PartitionRemaining, PollMode, PollResponse, StartFrom,
+    SubscriptionCommitResult, ACCEPT_VERSION_HEADER, LAST_POLLED_RESOLUTION_SECS, MAX_POLL_LIMIT,
+    MAX_POLL_WAIT_SECONDS, TABLE_OVERRIDE_HEADER, TAIL_SUBSCRIPTION_ID,
 };
+use futures::future::join_all;
 use lambda_http::{run, service_fn, Body, Error as LambdaError, Request, RequestExt, Response};
-use tracing::{error, info};
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::future::Future;
+use std::time::{Duration, SystemTime};
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+/// Events returned by a poll when `limit` is not given
+const DEFAULT_POLL_LIMIT: u32 = 100;
+
+/// How often a long poll re-reads while waiting for events
+const LONG_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Marks a cursor as a stored token rather than inline offsets ('.' is not in
+/// the base64url alphabet, so inline cursors never start with it)
+const CURSOR_TOKEN_PREFIX: &str = "tok.";
+
+/// Time kept back from the invocation deadline to return the response
+const DEADLINE_MARGIN: Duration = Duration::from_secs(1);
+
+#[derive(Serialize)]
+struct ListOffsetsResponse {
+    offsets: Vec<ConsumerOffset>,
+}
 
-async fn handler(event: Request) -> Result<Response<Body>, LambdaError> {
+async fn handler<S: EventStore>(
+    base_client: &S,
+    shutdown: &CancellationToken,
+    event: Request,
+) -> Result<Response<Body>, LambdaError> {
     let method = event.method().as_str();
     let path = event.uri().path().to_string();
 
@@ -21,23 +61,42 @@ async fn handler(event: Request) -> Result<Response<Body>, LambdaError> {
     let path_params = event.path_parameters();
     let stream_id = path_params
         .first("stream_id")
-        .ok_or_else(|| "Missing stream_id")?
+        .ok_or_else(|| missing_path_param("stream_id"))?
         .to_string();
+
+    let table_override = event
+        .headers()
+        .get(TABLE_OVERRIDE_HEADER)
+        .and_then(|v| v.to_str().ok());
+    let client = base_client.for_request(table_override);
+    // Offsets and cursors belong to the stream, not to an alias naming it
+    let stream_id = match client.resolve_stream_id(&stream_id).await {
+        Ok(stream_id) => stream_id,
+        Err(e) => return error_response(e),
+    };
+
+    // Stream-level routes (no subscription in the path)
+    if method == "POST" && path.ends_with("/commit-batch") {
+        return handle_commit_batch(&client, &stream_id, &event).await;
+    }
+    if method == "GET" && path.ends_with("/tail") {
+        return handle_tail(&client, &stream_id, &event).await;
+    }
+
     let subscription_id = path_params
         .first("subscription_id")
-        .ok_or_else(|| "Missing subscription_id")?
+        .ok_or_else(|| missing_path_param("subscription_id"))?
         .to_string();
 
-    // Initialize AWS clients
-    let config = aws_config::load_defaults(BehaviorVersion::latest()).await;
-    let dynamo_client = aws_sdk_dynamodb::Client::new(&config);
-    let client = DynamoClient::new(dynamo_client);
-
     // Route based on method and path
     if method == "GET" && path.ends_with("/poll") {
-        handle_poll(&client, &stream_id, &subscription_id, &event).await
+        handle_poll(&client, shutdown, &stream_id, &subscription_id, &event).await
     } else if method == "POST" && path.ends_with("/commit") {
         handle_commit(&client, &stream_id, &subscription_id, &event).await
+    } else if method == "POST" && path.ends_with("/commit-poll") {
+        handle_commit_poll(&client, shutdown, &stream_id, &subscription_id, &event).await
+    } else if method == "GET" && path.ends_with("/offsets") {
+        handle_list_offsets(&client, &stream_id, &subscription_id).await
     } else {
         Ok(Response::builder()
             .status(404)
@@ -50,8 +109,126 @@ async fn handler(event: Request) -> Result<Response<Body>, LambdaError> {
     }
 }
 
-async fn handle_poll(
-    client: &DynamoClient,
+/// Parse the poll `limit` query parameter, rejecting values that are not
+/// a positive integer no larger than [`MAX_POLL_LIMIT`]
+fn parse_limit(raw: Option<&str>) -> Result<Option<u32>, Error> {
+    let Some(raw) = raw else {
+        return Ok(None);
+    };
+    match raw.parse::<u32>() {
+        Ok(0) => Err(Error::Validation("limit must be at least 1".to_string())),
+        Ok(limit) if limit > MAX_POLL_LIMIT => Err(Error::Validation(format!(
+            "limit {} exceeds the maximum of {}",
+            limit, MAX_POLL_LIMIT
+        ))),
+        Ok(limit) => Ok(Some(limit)),
+        Err(_) => Err(Error::Validation(format!(
+            "Invalid limit '{}': expected a positive integer",
+            raw
+        ))),
+    }
+}
+
+/// Parse the poll `wait_seconds` query parameter (0 to [`MAX_POLL_WAIT_SECONDS`])
+fn parse_wait_seconds(raw: Option<&str>) -> Result<Option<u32>, Error> {
+    let Some(raw) = raw else {
+        return Ok(None);
+    };
+    match raw.parse::<u32>() {
+        Ok(wait) if wait <= MAX_POLL_WAIT_SECONDS => Ok(Some(wait)),
+        _ => Err(Error::Validation(format!(
+            "Invalid wait_seconds '{}': expected 0 to {}",
+            raw, MAX_POLL_WAIT_SECONDS
+        ))),
+    }
+}
+
+/// Parse the poll `partitions` query parameter (e.g. `0,2,4`) into sorted,
+/// de-duplicated partition numbers; all partitions when absent
+fn parse_partitions(raw: Option<&str>, partition_count: u32) -> Result<Vec<u32>, Error> {
+    let Some(raw) = raw else {
+        return Ok((0..partition_count).collect());
+    };
+
+    let mut partitions = Vec::new();
+    for part in raw.split(',') {
+        let partition: u32 = part.trim().parse().map_err(|_| {
+            Error::Validation(format!(
+                "Invalid partitions '{}': expected comma-separated partition numbers",
+                raw
+            ))
+        })?;
+        if partition >= partition_count {
+            return Err(Error::Validation(format!(
+                "Partition {} does not exist (stream has {} partitions)",
+                partition, partition_count
+            )));
+        }
+        partitions.push(partition);
+    }
+    partitions.sort_unstable();
+    partitions.dedup();
+    Ok(partitions)
+}
+
+/// Poll a subscription.
+///
+/// `?mode=peek` (the default) always reads from the committed offset, so polling
+/// again without committing returns the same events: at-least-once delivery.
+///
+/// `?mode=consume` also records how far each poll read, and the next consume poll
+/// starts after that point even if nothing was committed, so back-to-back polls
+/// advance. The cursor still has to be committed; until it is, a peek poll will
+/// re-read the uncommitted window (e.g. when recovering from a crashed consumer).
+///
+/// `?cursor=` pages without committing: each partition is read from the
+/// cursor's offset rather than the committed one (whichever is further), and
+/// nothing is stored. Commit the last page's cursor to record the progress.
+///
+/// `?semantics=at_most_once` commits the polled offsets before responding, so
+/// a consumer that crashes mid-batch loses those events rather than seeing
+/// them again; the returned cursor is already committed. If another consumer
+/// committed since the read, the poll fails with 409 and delivers nothing.
+/// The default, `at_least_once`, leaves committing to the consumer.
+///
+/// `?auto_create=earliest|latest` creates a missing subscription before reading,
+/// for ephemeral consumers. Without it a missing subscription is a 404.
+///
+/// `?partitions=0,2` reads only those partitions, so workers sharing a
+/// subscription can each own a subset. The cursor then covers only those
+/// partitions and committing it leaves the others untouched.
+///
+/// `?merge=false` skips merging partitions into one timestamp order: events
+/// come back grouped by partition, each partition in sequence order, and
+/// there is no ordering guarantee across partitions. Each partition's cursor
+/// offset advances only past that partition's returned events.
+///
+/// `?wait_seconds=N` long-polls: an empty read is retried until events arrive
+/// or N seconds pass. The wait also ends early, returning the current (empty)
+/// batch, shortly before the invocation times out or when the runtime is
+/// shutting down. `limit` and `wait_seconds` fall back to the
+/// subscription's defaults when omitted.
+///
+/// `?fields=key,event_type,sequence` reads and returns only those event fields,
+/// for consumers that don't need the full payload.
+///
+/// A subscription created with a `filter` returns only matching events, but its
+/// cursor advances past the rest, so committing it skips them for good.
+///
+/// The cursor is also returned as the `ETag`. Sending it back in `If-None-Match`
+/// (or `?cursor=`) turns an empty poll whose partitions have nothing past that
+/// cursor into a bodiless 304 Not Modified. `If-None-Match: *` matches any
+/// cursor (RFC 9110), so it is answered with a 304 whenever the subscription
+/// exists, without reading or committing anything.
+///
+/// Subscriptions created with `cursor_encoding: token` get a short token in
+/// place of the base64 offsets; the offsets are stored server-side and looked
+/// up when the token is committed.
+///
+/// `Accept: application/cbor` returns the response as CBOR.
+async fn handle_poll<S: EventStore>(
+    client: &S,
+    shutdown: &CancellationToken,
     stream_id: &str,
     subscription_id: &str,
     event: &Request,
@@ -60,78 +237,668 @@ async fn handle_poll(
 
     // Parse limit from query string
     let query_params = event.query_string_parameters();
-    let limit: u32 = query_params
-        .first("limit")
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(100);
+    let limit = match parse_limit(query_params.first("limit")) {
+        Ok(limit) => limit,
+        Err(e) => return error_response(e),
+    };
+    let wait_seconds = match parse_wait_seconds(query_params.first("wait_seconds")) {
+        Ok(wait) => wait,
+        Err(e) => return error_response(e),
+    };
+    let if_none_match = event
+        .headers()
+        .get("If-None-Match")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim().trim_start_matches("W/").trim_matches('"'));
+    let any_cursor_matches = if_none_match == Some("*");
+    let page_cursor = query_params.first("cursor");
+    let known_cursor = match page_cursor.or(if_none_match.filter(|_| !any_cursor_matches)) {
+        Some(cursor) => match resolve_cursor(client, cursor, stream_id, subscription_id).await {
+            Ok(state) => Some((cursor, state)),
+            Err(e) => return error_response(e),
+        },
+        None => None,
+    };
+    // Only `?cursor=` pages; `If-None-Match` just allows a 304
+    let resume_from = known_cursor
+        .as_ref()
+        .filter(|_| page_cursor.is_some())
+        .map(|(_, state)| state);
+    let projection = match query_params
+        .first("fields")
+        .map(EventProjection::parse)
+        .transpose()
+    {
+        Ok(projection) => projection,
+        Err(e) => return error_response(e),
+    };
+    let include_partition_offsets = query_params.first("include_partition_offsets") == Some("true");
+    let include_remaining = query_params.first("include_remaining") == Some("true");
+    let version = match ApiVersion::from_header(
+        event
+            .headers()
+            .get(ACCEPT_VERSION_HEADER)
+            .and_then(|v| v.to_str().ok()),
+    ) {
+        Ok(version) => version,
+        Err(e) => return error_response(e),
+    };
+    let mode = match query_params.first("mode") {
+        None | Some("peek") => PollMode::Peek,
+        Some("consume") => PollMode::Consume,
+        Some(other) => {
+            return error_response(Error::Validation(format!(
+                "Invalid mode '{}': expected 'peek' or 'consume'",
+                other
+            )));
+        }
+    };
+    let semantics = match query_params.first("semantics") {
+        None | Some("at_least_once") => DeliverySemantics::AtLeastOnce,
+        Some("at_most_once") => DeliverySemantics::AtMostOnce,
+        Some(other) => {
+            return error_response(Error::Validation(format!(
+                "Invalid semantics '{}': expected 'at_least_once' or 'at_most_once'",
+                other
+            )));
+        }
+    };
+    let merge = match query_params.first("merge") {
+        None | Some("true") => true,
+        Some("false") => false,
+        Some(other) => {
+            return error_response(Error::Validation(format!(
+                "Invalid merge '{}': expected 'true' or 'false'",
+                other
+            )));
+        }
+    };
+    let auto_create = match query_params.first("auto_create") {
+        None => None,
+        Some("earliest") => Some(StartFrom::Earliest),
+        Some("latest") => Some(StartFrom::Latest),
+        Some(other) => {
+            return error_response(Error::Validation(format!(
+                "Invalid auto_create '{}': expected 'earliest' or 'latest'",
+                other
+            )));
+        }
+    };
 
     // Verify subscription exists and get stream info
-    let stream = match client.get_stream(stream_id).await {
+    let stream = match client.get_stream_cached(stream_id).await {
         Ok(s) => s,
         Err(e) => {
-            return Ok(error_response(e)?);
+            return error_response(e);
         }
     };
 
-    if let Err(e) = client.get_subscription(stream_id, subscription_id).await {
-        return Ok(error_response(e)?);
+    let subscription = match client.get_subscription(stream_id, subscription_id).await {
+        Ok(sub) => sub,
+        Err(Error::SubscriptionNotFound(_)) if auto_create.is_some() => {
+            let req = CreateSubscriptionRequest {
+                subscription_id: subscription_id.to_string(),
+                start_from: auto_create.unwrap_or_default(),
+                ..Default::default()
+            };
+            info!(stream_id = %stream_id, subscription_id = %subscription_id, "Auto-creating subscription");
+            match client.create_subscription(stream_id, &req).await {
+                Ok(sub) => sub,
+                // A concurrent poll may have created it first
+                Err(Error::SubscriptionAlreadyExists(_)) => {
+                    match client.get_subscription(stream_id, subscription_id).await {
+                        Ok(sub) => sub,
+                        Err(e) => return error_response(e),
+                    }
+                }
+                Err(e) => return error_response(e),
+            }
+        }
+        Err(e) => return error_response(e),
+    };
+
+    if any_cursor_matches {
+        return Ok(Response::builder().status(304).body(Body::Empty)?);
     }
 
-    // Collect events from all partitions
-    let mut all_events: Vec<Event> = Vec::new();
-    let mut offsets: Vec<PartitionOffset> = Vec::new();
-    let total_remaining: u64 = 0;
+    // Heartbeat for stale-subscription reports; a failed stamp shouldn't cost
+    // the consumer its events
+    let resolution = chrono::Duration::seconds(LAST_POLLED_RESOLUTION_SECS);
+    if subscription
+        .last_polled_at
+        .is_none_or(|at| at < Utc::now() - resolution)
+    {
+        if let Err(e) = client.record_poll(stream_id, subscription_id).await {
+            warn!(error = %e, subscription_id = %subscription_id, "Failed to record poll");
+        }
+    }
 
-    let per_partition_limit = (limit / stream.partition_count).max(1);
+    let limit = limit
+        .or(subscription.default_limit)
+        .unwrap_or(DEFAULT_POLL_LIMIT);
+    let wait_seconds = wait_seconds
+        .or(subscription.default_wait_seconds)
+        .unwrap_or(0);
 
-    for partition in 0..stream.partition_count {
-        let offset = client
-            .get_offset(stream_id, subscription_id, partition)
+    let partitions =
+        match parse_partitions(query_params.first("partitions"), stream.partition_count) {
+            Ok(partitions) => partitions,
+            Err(e) => return error_response(e),
+        };
+
+    // Long poll: re-read until something arrives or the wait runs out, never
+    // outliving the invocation
+    // Read before the offsets, so a commit that races this poll makes the
+    // cursor's commit conflict instead of slipping past it. Later pages keep
+    // the first page's value, so a commit made while paging conflicts too.
+    let committed_at = match resume_from.and_then(|state| state.committed_at) {
+        Some(committed_at) => committed_at,
+        None => match client.list_offsets(stream_id, subscription_id).await {
+            Ok(committed) => committed
+                .into_iter()
+                .filter(|o| partitions.contains(&o.partition))
+                .map(|o| o.committed_at)
+                .max()
+                .unwrap_or(DateTime::UNIX_EPOCH),
+            Err(e) => return error_response(e),
+        },
+    };
+
+    // Where each partition's read may start, at the earliest
+    let windows: Vec<PartitionOffset> = partitions
+        .iter()
+        .map(|&partition| PartitionOffset {
+            partition,
+            offset: resume_from
+                .and_then(|state| state.offsets.iter().find(|po| po.partition == partition))
+                .map_or(0, |po| po.offset),
+        })
+        .collect();
+
+    let per_partition_limit = (limit / partitions.len() as u32).max(1);
+    let mut deadline = Instant::now() + Duration::from_secs(wait_seconds.into());
+    if let Some(remaining) = event
+        .lambda_context_ref()
+        .map(|ctx| time_left(ctx.deadline()))
+    {
+        deadline = deadline.min(Instant::now() + remaining);
+    }
+    // A filter may match on fields the projection leaves out, so filtered
+    // subscriptions read whole events and project them afterwards
+    let read_projection = projection
+        .as_ref()
+        .filter(|_| subscription.filter.is_none());
+    let read = long_poll(deadline, shutdown, || {
+        read_partitions(
+            client,
+            stream_id,
+            subscription_id,
+            &windows,
+            mode,
+            per_partition_limit,
+            read_projection,
+        )
+    })
+    .await;
+    // Nothing is committed from a failed read, whose offsets would be guesses
+    let (mut offsets, mut all_events) = match read {
+        Ok(read) => read,
+        Err(e) => return error_response(e),
+    };
+
+    if let Some((known_cursor, known)) = &known_cursor {
+        if all_events.is_empty() {
+            match is_unchanged(client, stream_id, known, &partitions).await {
+                Ok(true) => {
+                    return Ok(Response::builder()
+                        .status(304)
+                        .header("ETag", format!("\"{}\"", known_cursor))
+                        .body(Body::Empty)?)
+                }
+                Ok(false) => {}
+                Err(e) => return error_response(e),
+            }
+        }
+    }
+
+    // Interleave partitions by time for a consistent order across them.
+    // Unmerged, the reads are already grouped by partition in sequence order.
+    if merge {
+        all_events = merge_by_time(all_events);
+    }
+    let drained = is_drained(&all_events, &partitions, per_partition_limit, limit);
+
+    // Truncate to limit, leaving the cut events for the next poll
+    let cut = all_events.split_off(all_events.len().min(limit as usize));
+    retreat_before(&mut offsets, &cut);
+
+    // At-most-once: commit before anything is handed out, so no later poll
+    // can return these events again
+    if semantics == DeliverySemantics::AtMostOnce && !all_events.is_empty() {
+        if let Err(e) = client
+            .commit_offsets(stream_id, subscription_id, &offsets, Some(committed_at))
             .await
-            .unwrap_or(0);
+        {
+            return error_response(e);
+        }
+    }
 
-        let events = client
-            .read_events(stream_id, partition, offset, per_partition_limit)
+    // Consume mode: the next poll starts after this window
+    if mode == PollMode::Consume {
+        if let Err(e) = client
+            .set_delivered_offsets(stream_id, subscription_id, &offsets)
             .await
-            .unwrap_or_default();
+        {
+            return error_response(e);
+        }
+    }
 
-        if let Some(last) = events.last() {
-            offsets.push(PartitionOffset {
-                partition,
-                offset: last.sequence,
-            });
-        } else {
-            offsets.push(PartitionOffset { partition, offset });
+    // Drop filtered events only now, so the offsets above already cover them
+    if let Some(filter) = &subscription.filter {
+        all_events.retain(|e| filter.matches(e));
+    }
+
+    // One latest-offset read per partition, so only when asked for
+    let remaining_per_partition = if include_remaining {
+        match remaining_after(client, stream_id, &offsets).await {
+            Ok(remaining) => remaining,
+            Err(e) => return error_response(e),
+        }
+    } else {
+        Vec::new()
+    };
+    let total_remaining = remaining_per_partition.iter().sum();
+    let has_more = !drained || total_remaining > 0;
+
+    // Encode cursor
+    let cursor_state = CursorState {
+        stream_id: stream_id.to_string(),
+        subscription_id: subscription_id.to_string(),
+        offsets,
+        remaining_per_partition,
+        committed_at: Some(committed_at),
+    };
+    let cursor = match subscription.cursor_encoding {
+        CursorEncoding::Inline => cursor_state.encode()?,
+        CursorEncoding::Token => match client.put_cursor_token(&cursor_state).await {
+            Ok(token) => format!("{}{}", CURSOR_TOKEN_PREFIX, token),
+            Err(e) => return error_response(e),
+        },
+    };
+    let etag = format!("\"{}\"", cursor);
+    let remaining_per_partition = (include_remaining && version >= ApiVersion::V2).then(|| {
+        cursor_state
+            .offsets
+            .iter()
+            .zip(&cursor_state.remaining_per_partition)
+            .map(|(po, &remaining)| PartitionRemaining {
+                partition: po.partition,
+                remaining,
+            })
+            .collect()
+    });
+    let offsets = include_partition_offsets.then_some(cursor_state.offsets);
+
+    let codec = response_codec(event);
+    let body = match &projection {
+        Some(projection) => {
+            let events = match all_events.iter().map(|e| projection.apply(e)).collect() {
+                Ok(events) => events,
+                Err(e) => return error_response(e),
+            };
+            codec.encode(&PollResponse::<serde_json::Value> {
+                events,
+                cursor,
+                remaining: total_remaining,
+                remaining_per_partition,
+                offsets,
+                caught_up: !has_more,
+                has_more,
+            })?
+        }
+        None => codec.encode(&PollResponse {
+            events: all_events,
+            cursor,
+            remaining: total_remaining,
+            remaining_per_partition,
+            offsets,
+            caught_up: !has_more,
+            has_more,
+        })?,
+    };
+
+    Ok(Response::builder()
+        .status(200)
+        .header("Content-Type", codec.content_type())
+        .header("Content-Version", version.number())
+        .header("ETag", etag)
+        .body(Body::from(body))?)
+}
+
+/// Whether a read returned everything published to `partitions`: none of
+/// them filled its `per_partition_limit` window, and nothing is about to be
+/// cut off at `limit`
+fn is_drained(events: &[Event], partitions: &[u32], per_partition_limit: u32, limit: u32) -> bool {
+    events.len() <= limit as usize
+        && partitions.iter().all(|&partition| {
+            let read = events.iter().filter(|e| e.partition == partition).count();
+            read < per_partition_limit as usize
+        })
+}
+
+/// Interleave partitions' events, each read in sequence order, by time
+///
+/// Only the partitions' next events are compared, earliest first and ties
+/// going to the lower partition, so each partition stays in sequence order
+/// even where a backfilled event is stamped earlier than the ones before it.
+fn merge_by_time(events: Vec<Event>) -> Vec<Event> {
+    let mut merged = Vec::with_capacity(events.len());
+    let mut partitions: BTreeMap<u32, VecDeque<Event>> = BTreeMap::new();
+    for event in events {
+        partitions
+            .entry(event.partition)
+            .or_default()
+            .push_back(event);
+    }
+    loop {
+        let next = partitions
+            .iter()
+            .filter_map(|(&partition, events)| events.front().map(|e| (e.timestamp, partition)))
+            .min();
+        let Some((_, partition)) = next else { break };
+        merged.extend(partitions.get_mut(&partition).and_then(VecDeque::pop_front));
+    }
+    merged
+}
+
+/// Move each partition's offset back so `cut` events, read but not returned,
+/// are read again from the cursor
+fn retreat_before(offsets: &mut [PartitionOffset], cut: &[Event]) {
+    for event in cut {
+        if let Some(po) = offsets
+            .iter_mut()
+            .find(|po| po.partition == event.partition)
+        {
+            po.offset = po.offset.min(event.sequence.saturating_sub(1));
+        }
+    }
+}
+
+/// Time a long poll may wait before the invocation ends, leaving
+/// [`DEADLINE_MARGIN`] to send the response
+fn time_left(invocation_deadline: SystemTime) -> Duration {
+    invocation_deadline
+        .duration_since(SystemTime::now())
+        .unwrap_or_default()
+        .saturating_sub(DEADLINE_MARGIN)
+}
+
+/// Re-run `read` until it returns events, `deadline` is reached, or `shutdown`
+/// is cancelled; whatever the last read returned is the result. A failed read
+/// ends the wait with its error.
+async fn long_poll<F, Fut>(
+    deadline: Instant,
+    shutdown: &CancellationToken,
+    mut read: F,
+) -> Result<(Vec<PartitionOffset>, Vec<Event>), Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<(Vec<PartitionOffset>, Vec<Event>), Error>>,
+{
+    loop {
+        let (offsets, events) = read().await?;
+        if !events.is_empty()
+            || shutdown.is_cancelled()
+            || Instant::now() + LONG_POLL_INTERVAL > deadline
+        {
+            return Ok((offsets, events));
         }
+        tokio::select! {
+            _ = tokio::time::sleep(LONG_POLL_INTERVAL) => {}
+            _ = shutdown.cancelled() => return Ok((offsets, events)),
+        }
+    }
+}
+
+/// Whether `known` already reaches the newest stored event of every polled partition
+async fn is_unchanged<S: EventStore>(
+    client: &S,
+    stream_id: &str,
+    known: &CursorState,
+    partitions: &[u32],
+) -> Result<bool, Error> {
+    for &partition in partitions {
+        let Some(seen) = known.offsets.iter().find(|po| po.partition == partition) else {
+            return Ok(false);
+        };
+        if client
+            .get_latest_written_offset(stream_id, partition)
+            .await?
+            > seen.offset
+        {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Events published after each offset, in the order of `offsets`
+///
+/// Counts up to the newest stored event, so sequences reserved by a publish
+/// that failed before writing them don't keep `has_more` set.
+async fn remaining_after<S: EventStore>(
+    client: &S,
+    stream_id: &str,
+    offsets: &[PartitionOffset],
+) -> Result<Vec<u64>, Error> {
+    let latest = join_all(
+        offsets
+            .iter()
+            .map(|po| client.get_latest_written_offset(stream_id, po.partition)),
+    )
+    .await;
+    offsets
+        .iter()
+        .zip(latest)
+        .map(|(po, latest)| Ok(latest?.saturating_sub(po.offset)))
+        .collect()
+}
+
+/// Read partitions concurrently; join_all keeps results in partition order
+///
+/// Each window names a partition and the offset its read may start at, at
+/// the earliest; reads start from the committed offset when that is further.
+async fn read_partitions<S: EventStore>(
+    client: &S,
+    stream_id: &str,
+    subscription_id: &str,
+    windows: &[PartitionOffset],
+    mode: PollMode,
+    per_partition_limit: u32,
+    projection: Option<&EventProjection>,
+) -> Result<(Vec<PartitionOffset>, Vec<Event>), Error> {
+    let reads = windows.iter().map(|window| {
+        read_partition(
+            client,
+            stream_id,
+            subscription_id,
+            window,
+            mode,
+            per_partition_limit,
+            projection,
+        )
+    });
 
+    let mut offsets = Vec::with_capacity(windows.len());
+    let mut all_events = Vec::new();
+    for read in join_all(reads).await {
+        let (offset, events) = read?;
+        offsets.push(offset);
         all_events.extend(events);
     }
+    Ok((offsets, all_events))
+}
 
-    // Sort by timestamp for consistent ordering across partitions
-    all_events.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+/// Read one partition's window, returning the offset it reaches and its events
+async fn read_partition<S: EventStore>(
+    client: &S,
+    stream_id: &str,
+    subscription_id: &str,
+    window: &PartitionOffset,
+    mode: PollMode,
+    limit: u32,
+    projection: Option<&EventProjection>,
+) -> Result<(PartitionOffset, Vec<Event>), Error> {
+    let partition = window.partition;
+    let mut offset = client
+        .get_offset(stream_id, subscription_id, partition)
+        .await?
+        .max(window.offset);
+
+    if mode == PollMode::Consume {
+        let delivered = client
+            .get_delivered_offset(stream_id, subscription_id, partition)
+            .await?;
+        offset = offset.max(delivered.unwrap_or(0));
+    }
 
-    // Truncate to limit
+    let events = match projection {
+        Some(projection) => {
+            client
+                .read_events_projected(stream_id, partition, offset, limit, projection)
+                .await
+        }
+        None => {
+            client
+                .read_events(stream_id, partition, offset, limit, true)
+                .await
+        }
+    }?;
+
+    let offset = events.last().map_or(offset, |last| last.sequence);
+    Ok((PartitionOffset { partition, offset }, events))
+}
+
+/// Read a stream without a subscription, for ad-hoc inspection.
+///
+/// `?from=earliest` (the default) or `?from=latest` picks the start; the
+/// returned cursor, passed back as `?cursor=`, continues after the events
+/// already returned. Nothing is stored server-side: no offsets are read or
+/// written, and the cursor can't be committed to a subscription. Like a poll,
+/// the response is CBOR with `Accept: application/cbor`.
+async fn handle_tail<S: EventStore>(
+    client: &S,
+    stream_id: &str,
+    event: &Request,
+) -> Result<Response<Body>, LambdaError> {
+    info!(stream_id = %stream_id, "Processing tail request");
+
+    let query_params = event.query_string_parameters();
+    let limit = match parse_limit(query_params.first("limit")) {
+        Ok(limit) => limit.unwrap_or(DEFAULT_POLL_LIMIT),
+        Err(e) => return error_response(e),
+    };
+    let cursor = match query_params.first("cursor") {
+        Some(cursor) => match decode_cursor(cursor, stream_id, TAIL_SUBSCRIPTION_ID) {
+            Ok(state) => Some(state),
+            Err(e) => return error_response(e),
+        },
+        None => None,
+    };
+    let from_latest = match query_params.first("from") {
+        None | Some("earliest") => false,
+        Some("latest") => true,
+        Some(other) => {
+            return error_response(Error::Validation(format!(
+                "Invalid from '{}': expected 'earliest' or 'latest'",
+                other
+            )));
+        }
+    };
+
+    let stream = match client.get_stream_cached(stream_id).await {
+        Ok(stream) => stream,
+        Err(e) => return error_response(e),
+    };
+
+    // A cursor continues where the last page stopped; otherwise start at `from`
+    let mut start = Vec::with_capacity(stream.partition_count as usize);
+    for partition in 0..stream.partition_count {
+        let offset = match &cursor {
+            Some(cursor) => cursor
+                .offsets
+                .iter()
+                .find(|po| po.partition == partition)
+                .map_or(0, |po| po.offset),
+            None if from_latest => match client.get_latest_offset(stream_id, partition).await {
+                Ok(offset) => offset,
+                Err(e) => return error_response(e),
+            },
+            None => 0,
+        };
+        start.push(PartitionOffset { partition, offset });
+    }
+
+    let per_partition_limit = (limit / stream.partition_count.max(1)).max(1);
+    let reads = start.iter().map(|po| {
+        client.read_events(
+            stream_id,
+            po.partition,
+            po.offset,
+            per_partition_limit,
+            true,
+        )
+    });
+    let mut all_events = Vec::new();
+    for events in join_all(reads).await {
+        match events {
+            Ok(events) => all_events.extend(events),
+            Err(e) => return error_response(e),
+        }
+    }
+
+    let mut all_events = merge_by_time(all_events);
+    let partitions: Vec<u32> = start.iter().map(|po| po.partition).collect();
+    let caught_up = is_drained(&all_events, &partitions, per_partition_limit, limit);
     all_events.truncate(limit as usize);
 
-    // Encode cursor
-    let cursor_state = CursorState { offsets };
-    let cursor_json = serde_json::to_string(&cursor_state)?;
-    let cursor = URL_SAFE_NO_PAD.encode(cursor_json.as_bytes());
+    // Advance each partition only past the events actually returned
+    let mut offsets = start;
+    for event in &all_events {
+        if let Some(po) = offsets
+            .iter_mut()
+            .find(|po| po.partition == event.partition)
+        {
+            po.offset = po.offset.max(event.sequence);
+        }
+    }
 
+    let cursor = CursorState {
+        stream_id: stream_id.to_string(),
+        subscription_id: TAIL_SUBSCRIPTION_ID.to_string(),
+        offsets,
+        remaining_per_partition: Vec::new(),
+        committed_at: None,
+    }
+    .encode()?;
     let response = PollResponse {
         events: all_events,
         cursor,
-        remaining: total_remaining,
+        remaining: 0,
+        remaining_per_partition: None,
+        offsets: None,
+        caught_up,
+        has_more: !caught_up,
     };
-
+    let codec = response_codec(event);
     Ok(Response::builder()
         .status(200)
-        .header("Content-Type", "application/json")
-        .body(Body::from(serde_json::to_string(&response)?))?)
+        .header("Content-Type", codec.content_type())
+        .body(Body::from(codec.encode(&response)?))?)
 }
 
-async fn handle_commit(
-    client: &DynamoClient,
+async fn handle_commit<S: EventStore>(
+    client: &S,
     stream_id: &str,
     subscription_id: &str,
     event: &Request,
@@ -139,22 +906,20 @@ async fn handle_commit(
     info!(stream_id = %stream_id, subscription_id = %subscription_id, "Processing commit request");
 
     // Parse request body
-    let body = event.body();
-    let body_str = std::str::from_utf8(body).map_err(|_| "Invalid UTF-8 in body")?;
-    let req: CommitRequest = serde_json::from_str(body_str)?;
-
-    // Decode cursor
-    let cursor_bytes = URL_SAFE_NO_PAD
-        .decode(&req.cursor)
-        .map_err(|_| Error::InvalidCursor("Invalid base64".to_string()))?;
-    let cursor_json = std::str::from_utf8(&cursor_bytes)
-        .map_err(|_| Error::InvalidCursor("Invalid UTF-8".to_string()))?;
-    let cursor_state: CursorState = serde_json::from_str(cursor_json)
-        .map_err(|_| Error::InvalidCursor("Invalid JSON".to_string()))?;
+    let req: CommitRequest = match parse_body(event.body()) {
+        Ok(req) => req,
+        Err(e) => return error_response(e),
+    };
+
+    let (offsets, unmodified_since) =
+        match resolve_commit_offsets(client, stream_id, subscription_id, &req).await {
+            Ok(resolved) => resolved,
+            Err(e) => return error_response(e),
+        };
 
     // Commit offsets
     match client
-        .commit_offsets(stream_id, subscription_id, &cursor_state.offsets)
+        .commit_offsets(stream_id, subscription_id, &offsets, unmodified_since)
         .await
     {
         Ok(_) => {
@@ -164,18 +929,257 @@ async fn handle_commit(
                 .header("Content-Type", "application/json")
                 .body(Body::from(serde_json::to_string(&response)?))?)
         }
-        Err(e) => Ok(error_response(e)?),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Commit a cursor, then poll from it in the same request.
+///
+/// The body is a commit request; query parameters are the same as for a poll.
+/// The offset write is acknowledged before the read starts, and offsets are
+/// read with strong consistency, so the poll always starts after the commit.
+async fn handle_commit_poll<S: EventStore>(
+    client: &S,
+    shutdown: &CancellationToken,
+    stream_id: &str,
+    subscription_id: &str,
+    event: &Request,
+) -> Result<Response<Body>, LambdaError> {
+    info!(stream_id = %stream_id, subscription_id = %subscription_id, "Processing commit-poll request");
+
+    let req: CommitRequest = match parse_body(event.body()) {
+        Ok(req) => req,
+        Err(e) => return error_response(e),
+    };
+
+    let (offsets, unmodified_since) =
+        match resolve_commit_offsets(client, stream_id, subscription_id, &req).await {
+            Ok(resolved) => resolved,
+            Err(e) => return error_response(e),
+        };
+
+    if let Err(e) = client
+        .commit_offsets(stream_id, subscription_id, &offsets, unmodified_since)
+        .await
+    {
+        return error_response(e);
+    }
+
+    handle_poll(client, shutdown, stream_id, subscription_id, event).await
+}
+
+/// Resolve offsets to commit from the cursor or the timestamp watermark, with
+/// the `committed_at` they must not have been committed after
+async fn resolve_commit_offsets<S: EventStore>(
+    client: &S,
+    stream_id: &str,
+    subscription_id: &str,
+    req: &CommitRequest,
+) -> Result<(Vec<PartitionOffset>, Option<DateTime<Utc>>), Error> {
+    match (&req.cursor, req.up_to_timestamp) {
+        (Some(cursor), None) => {
+            let state = resolve_cursor(client, cursor, stream_id, subscription_id).await?;
+            let committed_at = commit_guard(&state)?;
+            Ok((state.offsets, Some(committed_at)))
+        }
+        (None, Some(up_to)) => Ok((
+            offsets_at_time(client, stream_id, subscription_id, up_to).await?,
+            None,
+        )),
+        _ => Err(Error::Validation(
+            "Provide exactly one of cursor or up_to_timestamp".to_string(),
+        )),
+    }
+}
+
+/// The `committed_at` a cursor's commit is conditioned on
+///
+/// Cursors without one would commit unconditionally, overwriting whatever
+/// another consumer committed since, so they are refused.
+fn commit_guard(state: &CursorState) -> Result<DateTime<Utc>, Error> {
+    state.committed_at.ok_or_else(|| Error::InvalidCursor {
+        message: "Cursor can't be committed; poll again for a new one".to_string(),
+        details: None,
+    })
+}
+
+/// Per-partition offsets covering every event published at or before `up_to`
+async fn offsets_at_time<S: EventStore>(
+    client: &S,
+    stream_id: &str,
+    subscription_id: &str,
+    up_to: DateTime<Utc>,
+) -> Result<Vec<PartitionOffset>, Error> {
+    let stream = client.get_stream(stream_id).await?;
+    client.get_subscription(stream_id, subscription_id).await?;
+
+    let mut offsets = Vec::with_capacity(stream.partition_count as usize);
+    for partition in 0..stream.partition_count {
+        let offset = client.offset_at_time(stream_id, partition, up_to).await?;
+        offsets.push(PartitionOffset { partition, offset });
     }
+    Ok(offsets)
 }
 
-fn error_response(e: Error) -> Result<Response<Body>, LambdaError> {
-    error!(error = %e, "Request failed");
-    let status = e.status_code();
-    let body = ErrorResponse::new(e.code(), e.to_string());
+async fn handle_list_offsets<S: EventStore>(
+    client: &S,
+    stream_id: &str,
+    subscription_id: &str,
+) -> Result<Response<Body>, LambdaError> {
+    info!(stream_id = %stream_id, subscription_id = %subscription_id, "Listing committed offsets");
+
+    // 404 for unknown subscriptions; an existing one with no offsets yet is an empty list
+    if let Err(e) = client.get_subscription(stream_id, subscription_id).await {
+        return error_response(e);
+    }
+
+    match client.list_offsets(stream_id, subscription_id).await {
+        Ok(offsets) => Ok(Response::builder()
+            .status(200)
+            .header("Content-Type", "application/json")
+            .body(Body::from(serde_json::to_string(&ListOffsetsResponse {
+                offsets,
+            })?))?),
+        Err(e) => error_response(e),
+    }
+}
+
+async fn handle_commit_batch<S: EventStore>(
+    client: &S,
+    stream_id: &str,
+    event: &Request,
+) -> Result<Response<Body>, LambdaError> {
+    info!(stream_id = %stream_id, "Processing batch commit request");
+
+    // Parse request body
+    let req: CommitBatchRequest = match parse_body(event.body()) {
+        Ok(req) => req,
+        Err(e) => return error_response(e),
+    };
+
+    if req.commits.is_empty() {
+        return error_response(Error::Validation("No commits provided".to_string()));
+    }
+
+    // Commit each subscription independently so one bad cursor doesn't abort the rest
+    let mut results = Vec::with_capacity(req.commits.len());
+    for commit in &req.commits {
+        let resolved =
+            resolve_cursor(client, &commit.cursor, stream_id, &commit.subscription_id).await;
+        let outcome = match resolved.and_then(|state| commit_guard(&state).map(|at| (state, at))) {
+            Ok((cursor_state, committed_at)) => {
+                client
+                    .commit_offsets(
+                        stream_id,
+                        &commit.subscription_id,
+                        &cursor_state.offsets,
+                        Some(committed_at),
+                    )
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+
+        let result = match outcome {
+            Ok(_) => SubscriptionCommitResult {
+                subscription_id: commit.subscription_id.clone(),
+                success: true,
+                error: None,
+            },
+            Err(e) => {
+                error!(
+                    subscription_id = %commit.subscription_id,
+                    error = %e,
+                    "Batch commit entry failed"
+                );
+                SubscriptionCommitResult {
+                    subscription_id: commit.subscription_id.clone(),
+                    success: false,
+                    error: Some(e.to_response()),
+                }
+            }
+        };
+        results.push(result);
+    }
+
+    let response = CommitBatchResponse { results };
     Ok(Response::builder()
-        .status(status)
+        .status(200)
         .header("Content-Type", "application/json")
-        .body(Body::from(serde_json::to_string(&body)?))?)
+        .body(Body::from(serde_json::to_string(&response)?))?)
+}
+
+fn parse_body<T: DeserializeOwned>(body: &[u8]) -> Result<T, Error> {
+    body::parse_json(body::body_str(body::non_empty(body)?)?)
+}
+
+/// Resolve a cursor from a poll response, inline or stored, into its offsets
+///
+/// Either way a cursor only resolves for the subscription it was issued to.
+async fn resolve_cursor<S: EventStore>(
+    client: &S,
+    cursor: &str,
+    stream_id: &str,
+    subscription_id: &str,
+) -> Result<CursorState, Error> {
+    match cursor.strip_prefix(CURSOR_TOKEN_PREFIX) {
+        Some(token) => {
+            client
+                .get_cursor_token(stream_id, subscription_id, token)
+                .await
+        }
+        None => decode_cursor(cursor, stream_id, subscription_id),
+    }
+}
+
+/// Decode an opaque cursor string back into its partition offsets
+///
+/// Rejects cursors issued for a different stream or subscription, so one
+/// consumer's cursor can't overwrite another's offsets. A cursor that doesn't
+/// decode reports which subscription it was sent to alongside what failed.
+fn decode_cursor(
+    cursor: &str,
+    stream_id: &str,
+    subscription_id: &str,
+) -> Result<CursorState, Error> {
+    let mut state = match CursorState::decode(cursor) {
+        Err(Error::InvalidCursor {
+            message,
+            details: Some(mut details),
+        }) => {
+            details["stream_id"] = stream_id.into();
+            details["subscription_id"] = subscription_id.into();
+            return Err(Error::InvalidCursor {
+                message,
+                details: Some(details),
+            });
+        }
+        decoded => decoded?,
+    };
+
+    // Stream IDs can't be empty, so this is a cursor from before they named
+    // one, which is taken to be for the subscription it was sent to
+    if state.stream_id.is_empty() {
+        state.stream_id = stream_id.to_string();
+        state.subscription_id = subscription_id.to_string();
+    }
+
+    if state.stream_id != stream_id || state.subscription_id != subscription_id {
+        return Err(Error::InvalidCursor {
+            message: format!(
+                "Cursor was issued for subscription '{}' on stream '{}'",
+                state.subscription_id, state.stream_id
+            ),
+            details: None,
+        });
+    }
+
+    Ok(state)
+}
+
+/// Codec for poll and tail responses, from the `Accept` header
+fn response_codec(event: &Request) -> Codec {
+    Codec::from_header(event.headers().get("accept").and_then(|v| v.to_str().ok()))
 }
 
 #[tokio::main]
@@ -186,5 +1190,954 @@ async fn main() -> Result<(), LambdaError> {
         .without_time()
         .init();
 
-    run(service_fn(handler)).await
+    // Initialize AWS clients once and reuse them across invocations
+    let config = aws_config::load_defaults(BehaviorVersion::latest()).await;
+    let client = DynamoClient::from_config(&config);
+    let client = &client;
+
+    // Lambda sends SIGTERM before shutting the environment down (when an
+    // extension is registered); in-flight long polls return what they have
+    let shutdown = CancellationToken::new();
+    let on_sigterm = shutdown.clone();
+    tokio::spawn(async move {
+        if let Ok(mut sigterm) = signal(SignalKind::terminate()) {
+            sigterm.recv().await;
+            info!("SIGTERM received, ending long polls");
+            on_sigterm.cancel();
+        }
+    });
+    let shutdown = &shutdown;
+
+    run(service_fn(move |event| async move {
+        recover(handler(client, shutdown, event).await)
+    }))
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eventledger_core::{MemoryStore, PublishEvent, Stream, SubscriptionFilter};
+    use std::collections::HashMap;
+
+    async fn empty_read() -> Result<(Vec<PartitionOffset>, Vec<Event>), Error> {
+        Ok((
+            vec![PartitionOffset {
+                partition: 0,
+                offset: 4,
+            }],
+            Vec::new(),
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_long_poll_returns_by_deadline_without_events() {
+        let started = Instant::now();
+        let deadline = started + Duration::from_secs(2);
+
+        let (offsets, events) = long_poll(deadline, &CancellationToken::new(), empty_read)
+            .await
+            .unwrap();
+
+        assert!(events.is_empty());
+        assert_eq!(offsets[0].offset, 4);
+        assert!(Instant::now() <= deadline);
+        assert!(started.elapsed() >= Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_long_poll_ends_on_shutdown() {
+        let shutdown = CancellationToken::new();
+        let trigger = shutdown.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            trigger.cancel();
+        });
+
+        let started = Instant::now();
+        let deadline = started + Duration::from_secs(20);
+        let (_, events) = long_poll(deadline, &shutdown, empty_read).await.unwrap();
+
+        assert!(events.is_empty());
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_inline_cursor_never_looks_like_a_token() {
+        let state = CursorState {
+            stream_id: "orders".to_string(),
+            subscription_id: "billing".to_string(),
+            offsets: (0..200)
+                .map(|partition| PartitionOffset {
+                    partition,
+                    offset: 7,
+                })
+                .collect(),
+            remaining_per_partition: Vec::new(),
+            committed_at: None,
+        };
+        let cursor = state.encode().unwrap();
+        assert!(!cursor.contains('.'));
+        assert!(decode_cursor(&cursor, "orders", "billing").is_ok());
+
+        let token = format!("{}{}", CURSOR_TOKEN_PREFIX, "0".repeat(32));
+        assert!(matches!(
+            decode_cursor(&token, "orders", "billing"),
+            Err(Error::InvalidCursor {
+                details: Some(_),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_cursor_from_before_cursors_named_their_subscription_still_decodes() {
+        // Decodes the same as one without the fields at all
+        let legacy = CursorState {
+            stream_id: String::new(),
+            subscription_id: String::new(),
+            offsets: vec![PartitionOffset {
+                partition: 1,
+                offset: 4,
+            }],
+            remaining_per_partition: Vec::new(),
+            committed_at: None,
+        };
+        let state = decode_cursor(&legacy.encode().unwrap(), "orders", "billing").unwrap();
+        assert_eq!(state.stream_id, "orders");
+        assert_eq!(state.subscription_id, "billing");
+        assert_eq!(state.offsets[0].offset, 4);
+        assert!(state.committed_at.is_none());
+    }
+
+    #[test]
+    fn test_time_left_keeps_a_margin() {
+        let deadline = SystemTime::now() + Duration::from_secs(10);
+        let left = time_left(deadline);
+        assert!(left <= Duration::from_secs(9) && left > Duration::from_secs(8));
+
+        // Already past the deadline: don't wait at all
+        assert_eq!(
+            time_left(SystemTime::now() - Duration::from_secs(1)),
+            Duration::ZERO
+        );
+    }
+
+    /// Store with a two-partition `orders` stream holding `count` events
+    async fn store_with_events(count: usize) -> MemoryStore {
+        store_with_partitioned_events(2, count).await
+    }
+
+    async fn store_with_partitioned_events(partition_count: u32, count: usize) -> MemoryStore {
+        let store = MemoryStore::new();
+        store.put_stream(Stream::new("orders".to_string(), partition_count, 24));
+        let events: Vec<PublishEvent> = (0..count)
+            .map(|i| PublishEvent {
+                key: format!("k{}", i),
+                partition_key: None,
+                order_key: None,
+                timestamp: None,
+                event_type: "order.created".parse().unwrap(),
+                data: serde_json::json!({ "n": i }),
+            })
+            .collect();
+        store.publish_events("orders", &events).await.unwrap();
+        store
+    }
+
+    fn request(
+        method: &str,
+        path: &str,
+        params: &[(&str, &str)],
+        query: &[(&str, &str)],
+        body: &str,
+    ) -> Request {
+        let to_map = |pairs: &[(&str, &str)]| {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<HashMap<_, _>>()
+        };
+        lambda_http::http::Request::builder()
+            .method(method)
+            .uri(path)
+            .body(Body::from(body.to_string()))
+            .unwrap()
+            .with_path_parameters(to_map(params))
+            .with_query_string_parameters(to_map(query))
+    }
+
+    const SUBSCRIPTION: [(&str, &str); 2] =
+        [("stream_id", "orders"), ("subscription_id", "billing")];
+
+    fn poll_request(query: &[(&str, &str)]) -> Request {
+        let path = "/streams/orders/subscriptions/billing/poll";
+        request("GET", path, &SUBSCRIPTION, query, "")
+    }
+
+    fn error_code(response: &Response<Body>) -> String {
+        let body: ErrorResponse = serde_json::from_slice(response.body()).unwrap();
+        body.error
+    }
+
+    #[tokio::test]
+    async fn test_missing_path_params_are_bad_requests() {
+        let store = store_with_events(0).await;
+        let shutdown = CancellationToken::new();
+        let path = "/streams/orders/subscriptions/billing/poll";
+
+        for params in [&[][..], &[("stream_id", "orders")][..]] {
+            let event = request("GET", path, params, &[], "");
+            let response = recover(handler(&store, &shutdown, event).await)
+                .expect("should respond rather than fail the invocation");
+            assert_eq!(response.status(), 400);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unknown_route_is_not_found() {
+        let store = store_with_events(0).await;
+        let path = "/streams/orders/subscriptions/billing/rewind";
+        let event = request("POST", path, &SUBSCRIPTION, &[], "");
+
+        let response = handler(&store, &CancellationToken::new(), event)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 404);
+        assert_eq!(error_code(&response), "not_found");
+    }
+
+    #[tokio::test]
+    async fn test_poll_error_mapping() {
+        let store = store_with_events(1).await;
+        let shutdown = CancellationToken::new();
+
+        let response = handler(&store, &shutdown, poll_request(&[])).await.unwrap();
+        assert_eq!(response.status(), 404);
+        assert_eq!(error_code(&response), "subscription_not_found");
+
+        let event = poll_request(&[("auto_create", "earliest"), ("limit", "0")]);
+        let response = handler(&store, &shutdown, event).await.unwrap();
+        assert_eq!(response.status(), 400);
+        assert_eq!(error_code(&response), "validation_error");
+
+        let event = poll_request(&[("cursor", "not-a-cursor"), ("auto_create", "earliest")]);
+        let response = handler(&store, &shutdown, event).await.unwrap();
+        assert_eq!(response.status(), 400);
+    }
+
+    #[tokio::test]
+    async fn test_poll_commit_round_trip() {
+        let store = store_with_events(3).await;
+        let shutdown = CancellationToken::new();
+
+        let event = poll_request(&[("auto_create", "earliest")]);
+        let response = handler(&store, &shutdown, event).await.unwrap();
+        assert_eq!(response.status(), 200);
+        let polled: PollResponse = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(polled.events.len(), 3);
+
+        let path = "/streams/orders/subscriptions/billing/commit";
+        let body = serde_json::json!({ "cursor": polled.cursor }).to_string();
+        let event = request("POST", path, &SUBSCRIPTION, &[], &body);
+        let response = handler(&store, &shutdown, event).await.unwrap();
+        assert_eq!(response.status(), 200);
+
+        let offsets = store.list_offsets("orders", "billing").await.unwrap();
+        assert_eq!(offsets.iter().map(|o| o.offset).sum::<u64>(), 3);
+
+        let response = handler(&store, &shutdown, poll_request(&[])).await.unwrap();
+        let polled: PollResponse = serde_json::from_slice(response.body()).unwrap();
+        assert!(polled.events.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cursor_pages_before_commit() {
+        // Fewer events per page than partitions, so each read is cut to the limit
+        let store = store_with_partitioned_events(4, 6).await;
+        let shutdown = CancellationToken::new();
+
+        let mut seen = Vec::new();
+        let mut cursor: Option<String> = None;
+        for _ in 0..3 {
+            let mut query = vec![("auto_create", "earliest"), ("limit", "2")];
+            if let Some(cursor) = &cursor {
+                query.push(("cursor", cursor));
+            }
+            let response = handler(&store, &shutdown, poll_request(&query))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), 200);
+            let polled: PollResponse = serde_json::from_slice(response.body()).unwrap();
+            assert_eq!(polled.events.len(), 2);
+            seen.extend(polled.events.iter().map(|e| (e.partition, e.sequence)));
+            cursor = Some(polled.cursor);
+
+            // Paging stores nothing
+            let offsets = store.list_offsets("orders", "billing").await.unwrap();
+            assert!(offsets.iter().all(|o| o.offset == 0));
+        }
+        seen.sort_unstable();
+        seen.dedup();
+        assert_eq!(seen.len(), 6, "every page should return new events");
+
+        let path = "/streams/orders/subscriptions/billing/commit";
+        let body = serde_json::json!({ "cursor": cursor }).to_string();
+        let event = request("POST", path, &SUBSCRIPTION, &[], &body);
+        let response = handler(&store, &shutdown, event).await.unwrap();
+        assert_eq!(response.status(), 200);
+
+        let offsets = store.list_offsets("orders", "billing").await.unwrap();
+        assert_eq!(offsets.iter().map(|o| o.offset).sum::<u64>(), 6);
+        let response = handler(&store, &shutdown, poll_request(&[])).await.unwrap();
+        let polled: PollResponse = serde_json::from_slice(response.body()).unwrap();
+        assert!(polled.events.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unmerged_poll_groups_by_partition_and_tracks_each_offset() {
+        let store = store_with_events(6).await;
+        let shutdown = CancellationToken::new();
+
+        let query = [
+            ("auto_create", "earliest"),
+            ("merge", "false"),
+            ("include_partition_offsets", "true"),
+        ];
+        let response = handler(&store, &shutdown, poll_request(&query))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        let polled: PollResponse = serde_json::from_slice(response.body()).unwrap();
+        let order: Vec<(u32, u64)> = polled
+            .events
+            .iter()
+            .map(|e| (e.partition, e.sequence))
+            .collect();
+        let mut grouped = order.clone();
+        grouped.sort_unstable();
+        assert_eq!(order, grouped);
+        assert_eq!(order.len(), 6);
+        for po in polled.offsets.unwrap() {
+            let last = order
+                .iter()
+                .filter(|(p, _)| *p == po.partition)
+                .map(|(_, s)| *s)
+                .max();
+            assert_eq!(po.offset, last.unwrap_or(0));
+        }
+
+        // Pages of one event: a partition cut from a page is read again, not skipped
+        let mut seen = Vec::new();
+        let mut cursor: Option<String> = None;
+        for _ in 0..10 {
+            let mut query = vec![("merge", "false"), ("limit", "1")];
+            if let Some(cursor) = &cursor {
+                query.push(("cursor", cursor));
+            }
+            let response = handler(&store, &shutdown, poll_request(&query))
+                .await
+                .unwrap();
+            // Nothing past the cursor
+            if response.status() == 304 {
+                break;
+            }
+            let polled: PollResponse = serde_json::from_slice(response.body()).unwrap();
+            seen.extend(polled.events.iter().map(|e| (e.partition, e.sequence)));
+            cursor = Some(polled.cursor);
+        }
+        assert_eq!(seen, grouped);
+    }
+
+    #[tokio::test]
+    async fn test_if_none_match_any_is_not_modified_while_the_subscription_exists() {
+        let store = store_with_events(3).await;
+        let shutdown = CancellationToken::new();
+
+        let mut event = poll_request(&[]);
+        event
+            .headers_mut()
+            .insert("If-None-Match", "*".parse().unwrap());
+        let response = handler(&store, &shutdown, event).await.unwrap();
+        assert_eq!(response.status(), 404);
+
+        let query = [("auto_create", "earliest"), ("semantics", "at_most_once")];
+        let mut event = poll_request(&query);
+        event
+            .headers_mut()
+            .insert("If-None-Match", "*".parse().unwrap());
+        let response = handler(&store, &shutdown, event).await.unwrap();
+        assert_eq!(response.status(), 304);
+
+        // Nothing was committed, so every event is still there to poll
+        let response = handler(&store, &shutdown, poll_request(&[])).await.unwrap();
+        let polled: PollResponse = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(polled.events.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_merged_pages_smaller_than_the_partition_count_skip_nothing() {
+        let store = store_with_events(6).await;
+        let shutdown = CancellationToken::new();
+
+        // Each read takes one event per partition and returns only the earliest.
+        // At-least-once pages on from the cursor; at-most-once commits each
+        // page itself, so the next poll needs none.
+        for semantics in ["at_least_once", "at_most_once"] {
+            let subscription = format!("billing-{}", semantics);
+            let path = format!("/streams/orders/subscriptions/{}/poll", subscription);
+            let params = [
+                ("stream_id", "orders"),
+                ("subscription_id", subscription.as_str()),
+            ];
+            let mut seen = Vec::new();
+            let mut cursor: Option<String> = None;
+            for _ in 0..10 {
+                let mut query = vec![
+                    ("auto_create", "earliest"),
+                    ("limit", "1"),
+                    ("semantics", semantics),
+                ];
+                if let Some(cursor) = cursor.as_ref().filter(|_| semantics == "at_least_once") {
+                    query.push(("cursor", cursor));
+                }
+                let response = handler(
+                    &store,
+                    &shutdown,
+                    request("GET", &path, &params, &query, ""),
+                )
+                .await
+                .unwrap();
+                if response.status() == 304 {
+                    break;
+                }
+                let polled: PollResponse = serde_json::from_slice(response.body()).unwrap();
+                if polled.events.is_empty() {
+                    break;
+                }
+                seen.extend(polled.events.iter().map(|e| (e.partition, e.sequence)));
+                cursor = Some(polled.cursor);
+            }
+            assert_eq!(seen.len(), 6, "{}: {:?}", semantics, seen);
+            seen.sort_unstable();
+            seen.dedup();
+            assert_eq!(seen.len(), 6, "{}: every event exactly once", semantics);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_merged_poll_keeps_backfilled_events_in_sequence_order() {
+        let store = MemoryStore::new();
+        store.put_stream(Stream::new("orders".to_string(), 2, 24));
+        // The later event in each partition is backfilled with an older time
+        let events: Vec<PublishEvent> = ["now", "2020-01-01T00:00:00Z"]
+            .iter()
+            .flat_map(|timestamp| {
+                (0..4).map(|i| PublishEvent {
+                    key: format!("k{}", i),
+                    partition_key: None,
+                    order_key: None,
+                    timestamp: timestamp.parse().ok(),
+                    event_type: "order.created".parse().unwrap(),
+                    data: serde_json::json!({ "n": i }),
+                })
+            })
+            .collect();
+        store.publish_events("orders", &events).await.unwrap();
+        let shutdown = CancellationToken::new();
+
+        let query = [("auto_create", "earliest"), ("limit", "100")];
+        let response = handler(&store, &shutdown, poll_request(&query))
+            .await
+            .unwrap();
+        let polled: PollResponse = serde_json::from_slice(response.body()).unwrap();
+
+        assert_eq!(polled.events.len(), 8);
+        for partition in 0..2 {
+            let sequences: Vec<u64> = polled
+                .events
+                .iter()
+                .filter(|e| e.partition == partition)
+                .map(|e| e.sequence)
+                .collect();
+            assert!(sequences.windows(2).all(|w| w[0] < w[1]), "{:?}", sequences);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_at_most_once_poll_does_not_redeliver_a_lost_batch() {
+        let store = store_with_events(4).await;
+        let shutdown = CancellationToken::new();
+        let query = [
+            ("auto_create", "earliest"),
+            ("limit", "2"),
+            ("semantics", "at_most_once"),
+        ];
+
+        // The consumer crashes with this batch: it never commits
+        let response = handler(&store, &shutdown, poll_request(&query))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        let lost: PollResponse = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(lost.events.len(), 2);
+        let committed = store.list_offsets("orders", "billing").await.unwrap();
+        assert_eq!(committed.iter().map(|o| o.offset).sum::<u64>(), 2);
+
+        // Neither mode of polling hands the lost batch out again
+        for query in [&query[..], &[][..]] {
+            let response = handler(&store, &shutdown, poll_request(query))
+                .await
+                .unwrap();
+            let polled: PollResponse = serde_json::from_slice(response.body()).unwrap();
+            assert!(!polled.events.is_empty());
+            assert!(polled
+                .events
+                .iter()
+                .all(|e| !lost.events.iter().any(|l| l.id == e.id)));
+        }
+
+        let response = handler(&store, &shutdown, poll_request(&[("semantics", "once")]))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 400);
+    }
+
+    #[tokio::test]
+    async fn test_failed_read_fails_the_poll_without_committing() {
+        let store = store_with_events(4).await;
+        let shutdown = CancellationToken::new();
+
+        for operation in ["get_offset", "read_events"] {
+            store.set_throttled(operation, true);
+            let query = [("auto_create", "earliest"), ("semantics", "at_most_once")];
+            let response = handler(&store, &shutdown, poll_request(&query))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), 429, "{}", operation);
+            assert_eq!(error_code(&response), "throttled");
+            store.set_throttled(operation, false);
+
+            let committed = store.list_offsets("orders", "billing").await.unwrap();
+            assert!(committed.iter().all(|o| o.offset == 0), "{}", operation);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_delivered_read_does_not_rewind_consume_mode() {
+        let store = store_with_events(4).await;
+        let shutdown = CancellationToken::new();
+        let query = [("auto_create", "earliest"), ("mode", "consume")];
+
+        let response = handler(&store, &shutdown, poll_request(&query))
+            .await
+            .unwrap();
+        let first: PollResponse = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(first.events.len(), 4);
+
+        store.set_throttled("get_delivered_offset", true);
+        let response = handler(&store, &shutdown, poll_request(&query))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 429);
+        store.set_throttled("get_delivered_offset", false);
+
+        // Still past the first window, so nothing is delivered twice
+        let response = handler(&store, &shutdown, poll_request(&query))
+            .await
+            .unwrap();
+        let next: PollResponse = serde_json::from_slice(response.body()).unwrap();
+        assert!(next.events.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_poll_stamps_last_polled_at() {
+        let store = store_with_events(2).await;
+        let shutdown = CancellationToken::new();
+        let before = Utc::now();
+
+        let query = [("auto_create", "earliest")];
+        let response = handler(&store, &shutdown, poll_request(&query))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        let polled_at = store
+            .get_subscription("orders", "billing")
+            .await
+            .unwrap()
+            .last_polled_at;
+        assert!(polled_at.unwrap() >= before);
+
+        // Polled again within the resolution: no second write
+        handler(&store, &shutdown, poll_request(&[])).await.unwrap();
+        let subscription = store.get_subscription("orders", "billing").await.unwrap();
+        assert_eq!(subscription.last_polled_at, polled_at);
+    }
+
+    #[tokio::test]
+    async fn test_empty_commit_body_is_a_bad_request() {
+        let store = store_with_events(1).await;
+        let shutdown = CancellationToken::new();
+
+        for endpoint in ["commit", "commit-poll"] {
+            let path = format!("/streams/orders/subscriptions/billing/{}", endpoint);
+            let event = request("POST", &path, &SUBSCRIPTION, &[], "");
+            let response = handler(&store, &shutdown, event).await.unwrap();
+            assert_eq!(response.status(), 400);
+            assert_eq!(error_code(&response), "validation_error");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_malformed_commit_cursor_reports_details() {
+        let store = store_with_events(1).await;
+        let shutdown = CancellationToken::new();
+
+        let path = "/streams/orders/subscriptions/billing/commit";
+        let body = serde_json::json!({ "cursor": "not*base64" }).to_string();
+        let event = request("POST", path, &SUBSCRIPTION, &[], &body);
+        let response = handler(&store, &shutdown, event).await.unwrap();
+        assert_eq!(response.status(), 400);
+
+        let body: ErrorResponse = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body.error, "invalid_cursor");
+        let details = body.details.unwrap();
+        assert_eq!(details["step"], "base64");
+        assert_eq!(details["position"], 3);
+        assert_eq!(details["cursor_length"], 10);
+        assert_eq!(details["subscription_id"], "billing");
+        assert!(!details.to_string().contains("not*base64"));
+    }
+
+    #[tokio::test]
+    async fn test_stale_commit_after_another_consumer_is_rejected() {
+        let store = store_with_events(6).await;
+        let shutdown = CancellationToken::new();
+        let path = "/streams/orders/subscriptions/billing/commit";
+        let poll = |limit: &'static str| {
+            let store = &store;
+            let shutdown = &shutdown;
+            async move {
+                let event = poll_request(&[("auto_create", "earliest"), ("limit", limit)]);
+                let response = handler(store, shutdown, event).await.unwrap();
+                serde_json::from_slice::<PollResponse>(response.body())
+                    .unwrap()
+                    .cursor
+            }
+        };
+        let commit = |cursor: String| {
+            let store = &store;
+            let shutdown = &shutdown;
+            async move {
+                let body = serde_json::json!({ "cursor": cursor }).to_string();
+                let event = request("POST", path, &SUBSCRIPTION, &[], &body);
+                handler(store, shutdown, event).await.unwrap()
+            }
+        };
+
+        let first = poll("2").await;
+        assert_eq!(commit(first).await.status(), 200);
+
+        // Two consumers poll the same offsets; the second commits first
+        let stale = poll("4").await;
+        let fresh = poll("2").await;
+        assert_eq!(commit(fresh).await.status(), 200);
+
+        let response = commit(stale).await;
+        assert_eq!(response.status(), 409);
+        assert_eq!(error_code(&response), "commit_conflict");
+        let offsets = store.list_offsets("orders", "billing").await.unwrap();
+        assert_eq!(offsets.iter().map(|o| o.offset).sum::<u64>(), 4);
+
+        // Polling again picks up the other consumer's commit
+        let cursor = poll("2").await;
+        assert_eq!(commit(cursor).await.status(), 200);
+
+        // A cursor without a guard would overwrite blindly, so it is refused
+        let unguarded = CursorState {
+            stream_id: "orders".to_string(),
+            subscription_id: "billing".to_string(),
+            offsets: vec![PartitionOffset {
+                partition: 0,
+                offset: 6,
+            }],
+            remaining_per_partition: Vec::new(),
+            committed_at: None,
+        }
+        .encode()
+        .unwrap();
+        let response = commit(unguarded).await;
+        assert_eq!(response.status(), 400);
+        assert_eq!(error_code(&response), "invalid_cursor");
+    }
+
+    #[tokio::test]
+    async fn test_caught_up_once_backlog_is_consumed() {
+        let store = store_with_events(7).await;
+        let shutdown = CancellationToken::new();
+        let path = "/streams/orders/subscriptions/billing/commit";
+
+        // A poll that fills its window can't tell whether more is waiting, so
+        // the signal may come one (empty) poll after the last event
+        let mut consumed = 0;
+        let mut caught_up = false;
+        for _ in 0..8 {
+            let event = poll_request(&[("auto_create", "earliest"), ("limit", "4")]);
+            let response = handler(&store, &shutdown, event).await.unwrap();
+            let polled: PollResponse = serde_json::from_slice(response.body()).unwrap();
+            consumed += polled.events.len();
+            caught_up = polled.caught_up;
+            if caught_up {
+                break;
+            }
+
+            let body = serde_json::json!({ "cursor": polled.cursor }).to_string();
+            let event = request("POST", path, &SUBSCRIPTION, &[], &body);
+            handler(&store, &shutdown, event).await.unwrap();
+        }
+        assert!(caught_up);
+        assert_eq!(consumed, 7);
+
+        // Stays live while nothing new arrives
+        let response = handler(&store, &shutdown, poll_request(&[])).await.unwrap();
+        let polled: PollResponse = serde_json::from_slice(response.body()).unwrap();
+        assert!(polled.caught_up);
+    }
+
+    #[tokio::test]
+    async fn test_has_more_until_backlog_is_paged_through() {
+        let store = store_with_events(10).await;
+        let shutdown = CancellationToken::new();
+        let path = "/streams/orders/subscriptions/billing/commit";
+
+        let mut consumed = 0;
+        let mut pages = Vec::new();
+        for _ in 0..8 {
+            let event = poll_request(&[("auto_create", "earliest"), ("limit", "4")]);
+            let response = handler(&store, &shutdown, event).await.unwrap();
+            let polled: PollResponse = serde_json::from_slice(response.body()).unwrap();
+            consumed += polled.events.len();
+            pages.push(polled.has_more);
+            if !polled.has_more {
+                break;
+            }
+
+            let body = serde_json::json!({ "cursor": polled.cursor }).to_string();
+            let event = request("POST", path, &SUBSCRIPTION, &[], &body);
+            handler(&store, &shutdown, event).await.unwrap();
+        }
+        assert_eq!(consumed, 10);
+        // Set on every page mid-backlog, cleared only once drained
+        assert!(pages.len() >= 3, "{:?}", pages);
+        assert_eq!(pages.last(), Some(&false));
+        assert!(pages[..pages.len() - 1].iter().all(|&has_more| has_more));
+
+        // Drained, the next poll has nothing more either
+        let response = handler(&store, &shutdown, poll_request(&[])).await.unwrap();
+        let polled: PollResponse = serde_json::from_slice(response.body()).unwrap();
+        assert!(!polled.has_more);
+    }
+
+    #[tokio::test]
+    async fn test_timestamp_subscription_replays_only_later_events() {
+        let store = store_with_events(3).await;
+        let cut = (0..2)
+            .flat_map(|partition| store.partition_events("orders", partition))
+            .map(|e| e.timestamp)
+            .max()
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(5)).await;
+
+        let later: Vec<PublishEvent> = (0..4)
+            .map(|i| PublishEvent {
+                key: format!("later-{}", i),
+                partition_key: None,
+                order_key: None,
+                timestamp: None,
+                event_type: "order.created".parse().unwrap(),
+                data: serde_json::json!({ "later": true }),
+            })
+            .collect();
+        store.publish_events("orders", &later).await.unwrap();
+
+        let req = CreateSubscriptionRequest {
+            subscription_id: "billing".to_string(),
+            start_from: StartFrom::Timestamp(cut),
+            ..Default::default()
+        };
+        store.create_subscription("orders", &req).await.unwrap();
+
+        let response = handler(&store, &CancellationToken::new(), poll_request(&[]))
+            .await
+            .unwrap();
+        let polled: PollResponse = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(polled.events.len(), 4);
+        assert!(polled.events.iter().all(|e| e.data["later"] == true));
+    }
+
+    #[tokio::test]
+    async fn test_filtered_subscription_skips_unmatched_events() {
+        let store = MemoryStore::new();
+        store.put_stream(Stream::new("orders".to_string(), 2, 24));
+        let events: Vec<PublishEvent> = (0..6)
+            .map(|i| PublishEvent {
+                key: format!("k{}", i),
+                partition_key: None,
+                order_key: None,
+                timestamp: None,
+                event_type: if i % 3 == 0 {
+                    "order.cancelled"
+                } else {
+                    "order.created"
+                }
+                .parse()
+                .unwrap(),
+                data: serde_json::json!({ "n": i }),
+            })
+            .collect();
+        store.publish_events("orders", &events).await.unwrap();
+
+        let req = CreateSubscriptionRequest {
+            subscription_id: "billing".to_string(),
+            start_from: StartFrom::Earliest,
+            filter: Some(SubscriptionFilter {
+                event_types: Some(vec!["order.cancelled".to_string()]),
+                key_prefix: None,
+            }),
+            ..Default::default()
+        };
+        store.create_subscription("orders", &req).await.unwrap();
+        let shutdown = CancellationToken::new();
+
+        let query = [("fields", "key")];
+        let response = handler(&store, &shutdown, poll_request(&query))
+            .await
+            .unwrap();
+        let polled: PollResponse<serde_json::Value> =
+            serde_json::from_slice(response.body()).unwrap();
+        let mut keys: Vec<_> = polled.events.iter().map(|e| e["key"].clone()).collect();
+        keys.sort_by_key(|k| k.to_string());
+        assert_eq!(keys, vec!["k0", "k3"]);
+
+        // The cursor covers the filtered-out events too
+        for po in CursorState::decode(&polled.cursor).unwrap().offsets {
+            let published = store.partition_events("orders", po.partition).len() as u64;
+            assert_eq!(po.offset, published);
+        }
+
+        let path = "/streams/orders/subscriptions/billing/commit";
+        let body = serde_json::json!({ "cursor": polled.cursor }).to_string();
+        let event = request("POST", path, &SUBSCRIPTION, &[], &body);
+        assert_eq!(
+            handler(&store, &shutdown, event).await.unwrap().status(),
+            200
+        );
+
+        let response = handler(&store, &shutdown, poll_request(&[])).await.unwrap();
+        let polled: PollResponse = serde_json::from_slice(response.body()).unwrap();
+        assert!(polled.events.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cursor_carries_remaining_when_asked() {
+        let store = store_with_events(10).await;
+        let shutdown = CancellationToken::new();
+
+        let query = [
+            ("auto_create", "earliest"),
+            ("limit", "4"),
+            ("include_remaining", "true"),
+        ];
+        let response = handler(&store, &shutdown, poll_request(&query))
+            .await
+            .unwrap();
+        let polled: PollResponse = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(polled.events.len(), 4);
+        assert_eq!(polled.remaining, 6);
+
+        let cursor = CursorState::decode(&polled.cursor).unwrap();
+        assert_eq!(cursor.remaining_per_partition.len(), cursor.offsets.len());
+        for (po, remaining) in cursor.offsets.iter().zip(&cursor.remaining_per_partition) {
+            let published = store.partition_events("orders", po.partition).len() as u64;
+            assert_eq!(*remaining, published - po.offset);
+        }
+
+        // Not asked for: no latest-offset reads and nothing extra in the cursor
+        let response = handler(&store, &shutdown, poll_request(&[])).await.unwrap();
+        let polled: PollResponse = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(polled.remaining, 0);
+        assert!(CursorState::decode(&polled.cursor)
+            .unwrap()
+            .remaining_per_partition
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_accept_version_selects_response_shape() {
+        let store = store_with_events(10).await;
+        let shutdown = CancellationToken::new();
+        let query = [
+            ("auto_create", "earliest"),
+            ("limit", "4"),
+            ("include_remaining", "true"),
+        ];
+        let poll = |version: Option<&'static str>| {
+            let mut event = poll_request(&query);
+            if let Some(version) = version {
+                event
+                    .headers_mut()
+                    .insert(ACCEPT_VERSION_HEADER, version.parse().unwrap());
+            }
+            handler(&store, &shutdown, event)
+        };
+
+        // v1: the total only
+        let response = poll(Some("1")).await.unwrap();
+        assert_eq!(response.headers()["Content-Version"], "1");
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body["remaining"], 6);
+        assert!(body.get("remaining_per_partition").is_none());
+
+        // v2, also the default: broken down by partition
+        for version in [Some("2"), None] {
+            let response = poll(version).await.unwrap();
+            assert_eq!(response.headers()["Content-Version"], "2");
+            let polled: PollResponse = serde_json::from_slice(response.body()).unwrap();
+            let per_partition = polled.remaining_per_partition.unwrap();
+            assert_eq!(per_partition.len(), 2);
+            assert_eq!(
+                per_partition.iter().map(|p| p.remaining).sum::<u64>(),
+                polled.remaining
+            );
+        }
+
+        let response = poll(Some("7")).await.unwrap();
+        assert_eq!(response.status(), 400);
+    }
+
+    #[tokio::test]
+    async fn test_cbor_poll_matches_json() {
+        let store = store_with_events(4).await;
+        let shutdown = CancellationToken::new();
+        let query = [("auto_create", "earliest")];
+
+        let response = handler(&store, &shutdown, poll_request(&query))
+            .await
+            .unwrap();
+        let from_json: PollResponse = serde_json::from_slice(response.body()).unwrap();
+
+        let mut event = poll_request(&query);
+        event
+            .headers_mut()
+            .insert("Accept", body::CBOR_CONTENT_TYPE.parse().unwrap());
+        let response = handler(&store, &shutdown, event).await.unwrap();
+        assert_eq!(response.headers()["Content-Type"], "application/cbor");
+        let from_cbor: PollResponse = body::parse_cbor(response.body()).unwrap();
+
+        assert_eq!(from_cbor.events.len(), 4);
+        assert_eq!(
+            serde_json::to_value(&from_cbor).unwrap(),
+            serde_json::to_value(&from_json).unwrap()
+        );
+    }
 }
@@ -1,35 +1,108 @@
 //! EventLedger Publish Lambda
 //!
-//! Handles POST /streams/{stream_id}/events
+//! Handles POST /streams/{stream_id}/events, and POST /publish-batch for
+//! publishing to several streams in one request
+//!
+//! Accepts a single event, a JSON array, `{"events": [...]}`, or a JSON Lines
+//! body (`Content-Type: application/x-ndjson`). NDJSON bodies are validated in
+//! full before anything is written, then published in chunks.
+//!
+//! Any of these may be sent with `Content-Encoding: gzip`.
+//!
+//! A stream may be named by an alias, which is resolved on every request.
+//!
+//! Bodies may also be CBOR (`Content-Type: application/cbor`) in the same
+//! shapes, and `Accept: application/cbor` gets a CBOR response. Errors are
+//! always JSON.
+//!
+//! A JSON or CBOR body sent with an `Idempotency-Key` header is published
+//! once: repeats within the stream's idempotency window get the original
+//! response back. The key is claimed before anything is written, so a repeat
+//! that arrives while the first attempt is still publishing gets a 409, and
+//! reusing a key for a different body gets a 422.
 
 use aws_config::BehaviorVersion;
-use eventledger_core::{DynamoClient, Error, ErrorResponse, PublishEvent, PublishRequest, PublishResponse};
+use eventledger_core::response::{
+    error_body_response, error_response, missing_path_param, recover,
+};
+use eventledger_core::{
+    body, body::Codec, check_timestamp_overrides, idempotency_body_hash, validate_idempotency_key,
+    DynamoClient, Error, ErrorResponse, EventStore, IdempotencyRecord, PublishBatchRequest,
+    PublishBatchResponse, PublishEvent, PublishRequest, PublishResponse, StreamPublishResult,
+    TABLE_OVERRIDE_HEADER,
+};
+use lambda_http::http::HeaderValue;
 use lambda_http::{run, service_fn, Body, Error as LambdaError, Request, RequestExt, Response};
+use serde::Serialize;
+use serde_json::json;
+use std::borrow::Cow;
 use tracing::{error, info};
 
-async fn handler(event: Request) -> Result<Response<Body>, LambdaError> {
+/// Content type for JSON Lines bodies
+const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// Events written per `publish_events` call for NDJSON bodies
+const NDJSON_CHUNK_SIZE: usize = 100;
+
+/// Header naming a publish so that retries of it aren't written twice
+const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+async fn handler<S: EventStore>(
+    base_client: &S,
+    event: Request,
+) -> Result<Response<Body>, LambdaError> {
+    let codec = Codec::from_header(event.headers().get("accept").and_then(|v| v.to_str().ok()));
+
+    if event.uri().path().ends_with("/publish-batch") {
+        return handle_publish_batch(&request_client(base_client, &event), codec, &event).await;
+    }
+
     // Extract stream_id from path
     let path_params = event.path_parameters();
     let stream_id = path_params
         .first("stream_id")
-        .ok_or_else(|| "Missing stream_id")?
+        .ok_or_else(|| missing_path_param("stream_id"))?
         .to_string();
 
     info!(stream_id = %stream_id, "Processing publish request");
 
-    // Parse request body
-    let body = event.body();
-    let body_str = std::str::from_utf8(body).map_err(|_| "Invalid UTF-8 in body")?;
+    let content_type = event
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok());
+    let is_ndjson = content_type.is_some_and(|v| v.starts_with(NDJSON_CONTENT_TYPE));
+    let is_cbor = Codec::from_header(content_type) == Codec::Cbor;
 
-    // Support both single event and batch
-    let events: Vec<PublishEvent> = if body_str.trim().starts_with('[') {
-        serde_json::from_str(body_str)?
-    } else if body_str.contains("\"events\"") {
-        let req: PublishRequest = serde_json::from_str(body_str)?;
-        req.events
+    let is_gzip = event
+        .headers()
+        .get("content-encoding")
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.eq_ignore_ascii_case("gzip"));
+
+    if let Err(e) = body::non_empty(event.body()) {
+        return error_response(e);
+    }
+
+    let raw_body = if is_gzip {
+        match body::gunzip(event.body(), body::MAX_DECOMPRESSED_BODY_BYTES) {
+            Ok(decompressed) => Cow::Owned(decompressed),
+            Err(e) => return error_response(e),
+        }
     } else {
-        // Single event
-        vec![serde_json::from_str(body_str)?]
+        Cow::Borrowed(event.body().as_ref())
+    };
+
+    // Parse request body
+    let parsed = if is_ndjson {
+        body::body_str(&raw_body).and_then(body::parse_ndjson)
+    } else if is_cbor {
+        parse_cbor_events(&raw_body)
+    } else {
+        parse_events(&raw_body)
+    };
+    let events: Vec<PublishEvent> = match parsed {
+        Ok(events) => events,
+        Err(e) => return error_response(e),
     };
 
     if events.is_empty() {
@@ -42,30 +115,238 @@ async fn handler(event: Request) -> Result<Response<Body>, LambdaError> {
             ))?))?);
     }
 
-    // Initialize AWS clients
-    let config = aws_config::load_defaults(BehaviorVersion::latest()).await;
-    let dynamo_client = aws_sdk_dynamodb::Client::new(&config);
-    let client = DynamoClient::new(dynamo_client);
+    if let Err(e) = check_timestamp_overrides(&events, allows_timestamp_override(&event)) {
+        return error_response(e);
+    }
 
-    // Publish events
-    match client.publish_events(&stream_id, &events).await {
-        Ok(published) => {
-            let response = PublishResponse { events: published };
-            Ok(Response::builder()
-                .status(200)
-                .header("Content-Type", "application/json")
-                .body(Body::from(serde_json::to_string(&response)?))?)
+    let client = request_client(base_client, &event);
+    let stream_id = match client.resolve_stream_id(&stream_id).await {
+        Ok(stream_id) => stream_id,
+        Err(e) => return error_response(e),
+    };
+
+    if is_ndjson {
+        return publish_chunked(&client, codec, &stream_id, &events).await;
+    }
+
+    let idempotency_key = event
+        .headers()
+        .get(IDEMPOTENCY_KEY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let claim = match idempotency_key {
+        Some(key) => {
+            if let Err(e) = validate_idempotency_key(&key) {
+                return error_response(e);
+            }
+            let body_hash = idempotency_body_hash(&raw_body);
+            let claim = IdempotencyRecord::claim(&stream_id, key, body_hash.clone());
+            match client.claim_idempotency_key(&claim).await {
+                Ok(None) => Some(claim),
+                Ok(Some(record)) => {
+                    if let Err(e) = record.check_replay(&body_hash) {
+                        return error_response(e);
+                    }
+                    info!(stream_id = %stream_id, "Replaying idempotent publish");
+                    let mut response = success_response(
+                        codec,
+                        &PublishResponse {
+                            events: record.events,
+                        },
+                    )?;
+                    response
+                        .headers_mut()
+                        .insert("Idempotent-Replayed", HeaderValue::from_static("true"));
+                    return Ok(response);
+                }
+                Err(e) => return error_response(e),
+            }
         }
+        None => None,
+    };
+
+    // Publish events
+    let published = match client.publish_events(&stream_id, &events).await {
+        Ok(published) => published,
         Err(e) => {
-            error!(error = %e, "Failed to publish events");
-            let status = e.status_code();
-            let body = ErrorResponse::new(e.code(), e.to_string());
-            Ok(Response::builder()
-                .status(status)
-                .header("Content-Type", "application/json")
-                .body(Body::from(serde_json::to_string(&body)?))?)
+            // Nothing was written, so a retry with the key may publish
+            if let Some(claim) = &claim {
+                if let Err(e) = client.release_idempotency_key(claim).await {
+                    error!(error = %e, stream_id = %stream_id, "Failed to release idempotency key");
+                }
+            }
+            return error_response(e);
+        }
+    };
+
+    if let Some(claim) = claim {
+        let recorded = match client.get_stream_cached(&stream_id).await {
+            Ok(stream) => {
+                let record = claim.complete(&stream, published.clone());
+                client.put_idempotency_record(&record).await
+            }
+            Err(e) => Err(e),
+        };
+        // The events are already written; a retry would duplicate them, but
+        // failing this request would too, so report success
+        if let Err(e) = recorded {
+            error!(error = %e, stream_id = %stream_id, "Failed to record idempotency key");
+        }
+    }
+
+    success_response(codec, &PublishResponse { events: published })
+}
+
+/// Publish a large body in chunks, reporting how many landed if a chunk fails
+async fn publish_chunked<S: EventStore>(
+    client: &S,
+    codec: Codec,
+    stream_id: &str,
+    events: &[PublishEvent],
+) -> Result<Response<Body>, LambdaError> {
+    let mut published = Vec::with_capacity(events.len());
+
+    for chunk in events.chunks(NDJSON_CHUNK_SIZE) {
+        match client.publish_events(stream_id, chunk).await {
+            // Indexes come back relative to the chunk
+            Ok(chunk_published) => {
+                let start = published.len();
+                published.extend(chunk_published.into_iter().map(|mut event| {
+                    event.input_index += start;
+                    event
+                }));
+            }
+            Err(e) => {
+                error!(error = %e, published = published.len(), "NDJSON publish stopped partway");
+                let body = e
+                    .to_response()
+                    .with_details(json!({ "published": published.len() }));
+                return error_body_response(&e, &body);
+            }
+        }
+    }
+
+    success_response(codec, &PublishResponse { events: published })
+}
+
+/// Parse a publish body: a single event, a bare array, or `{"events": [...]}`
+fn parse_events(body: &[u8]) -> Result<Vec<PublishEvent>, Error> {
+    let body_str = body::body_str(body)?;
+
+    // Support both single event and batch
+    if body_str.trim().starts_with('[') {
+        body::parse_json(body_str)
+    } else if body_str.contains("\"events\"") {
+        let req: PublishRequest = body::parse_json(body_str)?;
+        Ok(req.events)
+    } else {
+        // Single event
+        Ok(vec![body::parse_json(body_str)?])
+    }
+}
+
+/// Parse a CBOR publish body, accepting the same shapes as [`parse_events`]
+fn parse_cbor_events(body: &[u8]) -> Result<Vec<PublishEvent>, Error> {
+    match body::parse_cbor::<serde_json::Value>(body)? {
+        serde_json::Value::Array(_) => body::parse_cbor(body),
+        serde_json::Value::Object(fields) if fields.contains_key("events") => {
+            Ok(body::parse_cbor::<PublishRequest>(body)?.events)
         }
+        _ => Ok(vec![body::parse_cbor(body)?]),
+    }
+}
+
+/// Publish to several streams, one `publish_events` call per stream
+///
+/// A stream that is missing or rejects its events fails on its own; the other
+/// streams are still published and the response is a 200 with per-stream results.
+async fn handle_publish_batch<S: EventStore>(
+    client: &S,
+    codec: Codec,
+    event: &Request,
+) -> Result<Response<Body>, LambdaError> {
+    let content_type = event
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok());
+    let parsed =
+        body::non_empty(event.body()).and_then(|raw| match Codec::from_header(content_type) {
+            Codec::Json => body::body_str(raw).and_then(body::parse_json),
+            Codec::Cbor => body::parse_cbor(raw),
+        });
+    let req: PublishBatchRequest = match parsed {
+        Ok(req) => req,
+        Err(e) => return error_response(e),
+    };
+    if req.items.is_empty() {
+        return error_response(Error::Validation("No items provided".to_string()));
+    }
+    let allowed = allows_timestamp_override(event);
+    for item in &req.items {
+        if let Err(e) = check_timestamp_overrides(&item.events, allowed) {
+            return error_response(e);
+        }
+    }
+
+    let mut results = Vec::with_capacity(req.items.len());
+    for item in req.grouped() {
+        info!(stream_id = %item.stream_id, events = item.events.len(), "Publishing batch item");
+
+        let outcome = if item.events.is_empty() {
+            Err(Error::Validation("No events provided".to_string()))
+        } else {
+            match client.resolve_stream_id(&item.stream_id).await {
+                Ok(stream_id) => client.publish_events(&stream_id, &item.events).await,
+                Err(e) => Err(e),
+            }
+        };
+
+        let result = match outcome {
+            Ok(events) => StreamPublishResult {
+                stream_id: item.stream_id,
+                success: true,
+                events,
+                error: None,
+            },
+            Err(e) => {
+                error!(stream_id = %item.stream_id, error = %e, "Batch publish item failed");
+                StreamPublishResult {
+                    stream_id: item.stream_id,
+                    success: false,
+                    events: Vec::new(),
+                    error: Some(e.to_response()),
+                }
+            }
+        };
+        results.push(result);
     }
+
+    success_response(codec, &PublishBatchResponse { results })
+}
+
+/// Whether the publish opted in to events carrying their own `timestamp`
+fn allows_timestamp_override(event: &Request) -> bool {
+    event
+        .query_string_parameters()
+        .first("allow_timestamp_override")
+        == Some("true")
+}
+
+/// Client for this request, honoring the table override header
+fn request_client<S: EventStore>(base_client: &S, event: &Request) -> S {
+    let table_override = event
+        .headers()
+        .get(TABLE_OVERRIDE_HEADER)
+        .and_then(|v| v.to_str().ok());
+    base_client.for_request(table_override)
+}
+
+/// 200 response with the body in the codec the client accepts
+fn success_response<T: Serialize>(codec: Codec, body: &T) -> Result<Response<Body>, LambdaError> {
+    Ok(Response::builder()
+        .status(200)
+        .header("Content-Type", codec.content_type())
+        .body(Body::from(codec.encode(body)?))?)
 }
 
 #[tokio::main]
@@ -76,5 +357,416 @@ async fn main() -> Result<(), LambdaError> {
         .without_time()
         .init();
 
-    run(service_fn(handler)).await
+    // Initialize AWS clients once and reuse them across invocations
+    let config = aws_config::load_defaults(BehaviorVersion::latest()).await;
+    let client = DynamoClient::from_config(&config);
+    let client = &client;
+
+    run(service_fn(move |event| async move {
+        recover(handler(client, event).await)
+    }))
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eventledger_core::{MemoryStore, Stream, MAX_IDEMPOTENCY_KEY_LENGTH};
+    use std::collections::HashMap;
+
+    fn store() -> MemoryStore {
+        let store = MemoryStore::new();
+        store.put_stream(Stream::new("orders".to_string(), 2, 24));
+        store
+    }
+
+    fn publish_request(stream_id: &str, body: impl Into<Body>) -> Request {
+        lambda_http::http::Request::builder()
+            .method("POST")
+            .uri(format!("/streams/{}/events", stream_id))
+            .body(body.into())
+            .unwrap()
+            .with_path_parameters(HashMap::from([(
+                "stream_id".to_string(),
+                stream_id.to_string(),
+            )]))
+    }
+
+    fn error_code(response: &Response<Body>) -> String {
+        let body: ErrorResponse = serde_json::from_slice(response.body()).unwrap();
+        body.error
+    }
+
+    const ORDER: &str = r#"{"key": "o-1", "type": "order.created", "data": {}}"#;
+
+    #[tokio::test]
+    async fn test_missing_stream_id_is_a_bad_request() {
+        let event = lambda_http::http::Request::builder()
+            .method("POST")
+            .uri("/streams//events")
+            .body(Body::from(ORDER))
+            .unwrap();
+        let response = recover(handler(&store(), event).await)
+            .expect("should respond rather than fail the invocation");
+        assert_eq!(response.status(), 400);
+    }
+
+    #[tokio::test]
+    async fn test_empty_body_is_a_bad_request() {
+        for body in [Body::Empty, Body::from(""), Body::from("\n")] {
+            let response = handler(&store(), publish_request("orders", body))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), 400);
+            assert_eq!(error_code(&response), "validation_error");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_publish_writes_events() {
+        let store = store();
+        let response = handler(&store, publish_request("orders", ORDER))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+
+        let published: PublishResponse = serde_json::from_slice(response.body()).unwrap();
+        let event = &published.events[0];
+        assert_eq!(event.sequence, 1);
+        assert_eq!(store.partition_events("orders", event.partition).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_ndjson_input_indexes_span_chunks() {
+        let store = store();
+        let lines = NDJSON_CHUNK_SIZE + 5;
+        let body = (0..lines)
+            .map(|i| {
+                format!(
+                    r#"{{"key": "o-{}", "type": "order.created", "data": {{}}}}"#,
+                    i
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut event = publish_request("orders", body);
+        event
+            .headers_mut()
+            .insert("content-type", NDJSON_CONTENT_TYPE.parse().unwrap());
+        let response = handler(&store, event).await.unwrap();
+        assert_eq!(response.status(), 200);
+
+        let published: PublishResponse = serde_json::from_slice(response.body()).unwrap();
+        let indexes: Vec<usize> = published.events.iter().map(|e| e.input_index).collect();
+        assert_eq!(indexes, (0..lines).collect::<Vec<_>>());
+        assert_eq!(
+            published.events[NDJSON_CHUNK_SIZE].key,
+            format!("o-{}", NDJSON_CHUNK_SIZE)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_publish_through_an_alias_writes_to_its_stream() {
+        let store = store();
+        store.put_alias("orders-live", "orders");
+        let response = handler(&store, publish_request("orders-live", ORDER))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+
+        let published: PublishResponse = serde_json::from_slice(response.body()).unwrap();
+        let event = &published.events[0];
+        assert_eq!(event.stream_id, "orders");
+        assert_eq!(store.partition_events("orders", event.partition).len(), 1);
+
+        let response = handler(&store, publish_request("missing", ORDER))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 404);
+        assert_eq!(error_code(&response), "stream_not_found");
+    }
+
+    #[tokio::test]
+    async fn test_backfill_keeps_historical_timestamps() {
+        let store = store();
+        // A live event first, so the backfill lands behind the partition's clock
+        let live = r#"{"key": "o-1", "type": "order.created", "data": {}}"#;
+        assert_eq!(
+            handler(&store, publish_request("orders", live))
+                .await
+                .unwrap()
+                .status(),
+            200
+        );
+
+        let body = r#"[
+            {"key": "o-1", "type": "order.created", "data": {}, "timestamp": "2019-03-01T09:30:00Z"},
+            {"key": "o-1", "type": "order.shipped", "data": {}, "timestamp": "2019-03-02T14:00:00.123456Z"}
+        ]"#;
+        let response = handler(&store, publish_request("orders", body))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 400);
+        assert_eq!(error_code(&response), "validation_error");
+
+        let event =
+            publish_request("orders", body).with_query_string_parameters(HashMap::from([(
+                "allow_timestamp_override".to_string(),
+                "true".to_string(),
+            )]));
+        let response = handler(&store, event).await.unwrap();
+        assert_eq!(response.status(), 200);
+        let published: PublishResponse = serde_json::from_slice(response.body()).unwrap();
+        let timestamps: Vec<String> = published
+            .events
+            .iter()
+            .map(|e| e.timestamp.to_rfc3339())
+            .collect();
+        assert_eq!(
+            timestamps,
+            [
+                "2019-03-01T09:30:00+00:00",
+                "2019-03-02T14:00:00.123456+00:00"
+            ]
+        );
+
+        let stored = store.partition_events("orders", published.events[0].partition);
+        assert_eq!(stored.len(), 3);
+        assert!(stored[1].timestamp < stored[0].timestamp);
+        // When it was written is kept alongside, e.g. to expire its compacted state
+        assert!(stored[1]
+            .wall_clock_timestamp
+            .is_some_and(|t| t >= stored[0].timestamp));
+    }
+
+    #[tokio::test]
+    async fn test_publish_error_mapping() {
+        let store = store();
+
+        let response = handler(&store, publish_request("missing", ORDER))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 404);
+        assert_eq!(error_code(&response), "stream_not_found");
+
+        let response = handler(&store, publish_request("orders", "[]"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 400);
+
+        let response = handler(&store, publish_request("orders", "{not json"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 400);
+        assert_eq!(error_code(&response), "validation_error");
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_stream_returns_retry_after() {
+        let store = store();
+        let mut stream = Stream::new("limited".to_string(), 1, 24);
+        stream.max_publish_per_second = Some(2);
+        store.put_stream(stream);
+
+        for _ in 0..2 {
+            let response = handler(&store, publish_request("limited", ORDER))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), 200);
+        }
+
+        let response = handler(&store, publish_request("limited", ORDER))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 429);
+        assert_eq!(error_code(&response), "rate_limited");
+        assert_eq!(response.headers().get("Retry-After").unwrap(), "1");
+        assert_eq!(store.partition_events("limited", 0).len(), 2);
+
+        // Other streams keep publishing
+        let response = handler(&store, publish_request("orders", ORDER))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+    }
+
+    #[tokio::test]
+    async fn test_idempotent_publish_is_replayed() {
+        let store = store();
+        let keyed = || {
+            let mut event = publish_request("orders", ORDER);
+            event
+                .headers_mut()
+                .insert(IDEMPOTENCY_KEY_HEADER, "retry-1".parse().unwrap());
+            event
+        };
+
+        let first = handler(&store, keyed()).await.unwrap();
+        assert!(first.headers().get("Idempotent-Replayed").is_none());
+        let second = handler(&store, keyed()).await.unwrap();
+        assert_eq!(second.headers()["Idempotent-Replayed"], "true");
+        assert_eq!(first.body(), second.body());
+
+        let written: usize = (0..2)
+            .map(|p| store.partition_events("orders", p).len())
+            .sum();
+        assert_eq!(written, 1);
+    }
+
+    #[tokio::test]
+    async fn test_idempotency_key_reused_for_another_body_is_rejected() {
+        let store = store();
+        let keyed = |body: &str, key: &str| {
+            let mut event = publish_request("orders", body);
+            event
+                .headers_mut()
+                .insert(IDEMPOTENCY_KEY_HEADER, key.parse().unwrap());
+            event
+        };
+
+        let response = handler(&store, keyed(ORDER, "retry-1")).await.unwrap();
+        assert_eq!(response.status(), 200);
+        let other = r#"{"key": "order-2", "type": "order.created", "data": {}}"#;
+        let response = handler(&store, keyed(other, "retry-1")).await.unwrap();
+        assert_eq!(response.status(), 422);
+        assert_eq!(error_code(&response), "idempotency_key_mismatch");
+
+        let too_long = "k".repeat(MAX_IDEMPOTENCY_KEY_LENGTH + 1);
+        let response = handler(&store, keyed(other, &too_long)).await.unwrap();
+        assert_eq!(response.status(), 400);
+
+        let written: usize = (0..2)
+            .map(|p| store.partition_events("orders", p).len())
+            .sum();
+        assert_eq!(written, 1);
+    }
+
+    #[tokio::test]
+    async fn test_idempotency_key_held_by_an_unfinished_publish_is_in_use() {
+        let store = store();
+        let hash = idempotency_body_hash(ORDER.as_bytes());
+        let claim = IdempotencyRecord::claim("orders", "retry-1".to_string(), hash);
+        assert!(store.claim_idempotency_key(&claim).await.unwrap().is_none());
+
+        let mut event = publish_request("orders", ORDER);
+        event
+            .headers_mut()
+            .insert(IDEMPOTENCY_KEY_HEADER, "retry-1".parse().unwrap());
+        let response = handler(&store, event).await.unwrap();
+        assert_eq!(response.status(), 409);
+        assert_eq!(error_code(&response), "idempotency_key_in_use");
+        let written: usize = (0..2)
+            .map(|p| store.partition_events("orders", p).len())
+            .sum();
+        assert_eq!(written, 0);
+
+        // Once released, the key can publish again
+        store.release_idempotency_key(&claim).await.unwrap();
+        let mut event = publish_request("orders", ORDER);
+        event
+            .headers_mut()
+            .insert(IDEMPOTENCY_KEY_HEADER, "retry-1".parse().unwrap());
+        assert_eq!(handler(&store, event).await.unwrap().status(), 200);
+    }
+
+    #[tokio::test]
+    async fn test_publish_results_follow_input_order() {
+        // Keys hash across both partitions, so partition order differs from input order
+        let events: Vec<_> = (0..20)
+            .map(|i| json!({ "key": format!("o-{}", i), "type": "order.created", "data": {} }))
+            .collect();
+        let body = serde_json::to_string(&events).unwrap();
+        let response = handler(&store(), publish_request("orders", body))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+
+        let published: PublishResponse = serde_json::from_slice(response.body()).unwrap();
+        assert!(published.events.iter().any(|e| e.partition == 0));
+        assert!(published.events.iter().any(|e| e.partition == 1));
+        for (i, event) in published.events.iter().enumerate() {
+            assert_eq!(event.input_index, i);
+            assert_eq!(event.key, format!("o-{}", i));
+        }
+
+        // Items for the same stream are merged, and indexes count across them
+        let body = r#"{"items": [
+            {"stream_id": "orders", "events": [{"key": "a", "type": "t", "data": {}}]},
+            {"stream_id": "orders", "events": [{"key": "b", "type": "t", "data": {}},
+                                               {"key": "c", "type": "t", "data": {}}]}]}"#;
+        let event = lambda_http::http::Request::builder()
+            .method("POST")
+            .uri("/publish-batch")
+            .body(Body::from(body))
+            .unwrap();
+        let response = handler(&store(), event).await.unwrap();
+        let batch: PublishBatchResponse = serde_json::from_slice(response.body()).unwrap();
+        let indexed: Vec<_> = batch.results[0]
+            .events
+            .iter()
+            .map(|e| (e.input_index, e.key.as_str()))
+            .collect();
+        assert_eq!(indexed, vec![(0, "a"), (1, "b"), (2, "c")]);
+    }
+
+    #[tokio::test]
+    async fn test_publish_batch_isolates_failed_streams() {
+        let body = format!(
+            r#"{{"items": [{{"stream_id": "orders", "events": [{0}]}},
+                          {{"stream_id": "missing", "events": [{0}]}}]}}"#,
+            ORDER
+        );
+        let event = lambda_http::http::Request::builder()
+            .method("POST")
+            .uri("/publish-batch")
+            .body(Body::from(body))
+            .unwrap();
+
+        let response = handler(&store(), event).await.unwrap();
+        assert_eq!(response.status(), 200);
+        let batch: PublishBatchResponse = serde_json::from_slice(response.body()).unwrap();
+        assert!(batch.results[0].success);
+        assert!(!batch.results[1].success);
+        assert_eq!(
+            batch.results[1].error.as_ref().unwrap().error,
+            "stream_not_found"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cbor_publish_matches_json() {
+        let events = json!([
+            { "key": "o-1", "type": "order.created", "data": { "total": 10.5, "items": [1, 2] } },
+            { "key": "o-2", "type": "order.shipped", "data": null },
+        ]);
+
+        let json_store = store();
+        let response = handler(&json_store, publish_request("orders", events.to_string()));
+        let response = response.await.unwrap();
+        assert_eq!(response.headers()["Content-Type"], "application/json");
+        let from_json: PublishResponse = serde_json::from_slice(response.body()).unwrap();
+
+        let cbor_store = store();
+        let mut event = publish_request("orders", Codec::Cbor.encode(&events).unwrap());
+        for header in ["Content-Type", "Accept"] {
+            event
+                .headers_mut()
+                .insert(header, HeaderValue::from_static(body::CBOR_CONTENT_TYPE));
+        }
+        let response = handler(&cbor_store, event).await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["Content-Type"], "application/cbor");
+        let from_cbor: PublishResponse = body::parse_cbor(response.body()).unwrap();
+
+        let ids = |r: &PublishResponse| r.events.iter().map(|e| e.id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(&from_cbor), ids(&from_json));
+
+        let stored = |store: &MemoryStore| {
+            (0..2)
+                .flat_map(|p| store.partition_events("orders", p))
+                .map(|e| (e.id, e.key, e.event_type, e.data))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(stored(&cbor_store), stored(&json_store));
+    }
 }
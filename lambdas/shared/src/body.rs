@@ -0,0 +1,336 @@
+//! Request body parsing helpers
+//!
+//! Malformed bodies are reported as validation errors (HTTP 400) with the
+//! position of the failure, rather than surfacing as opaque 500s.
+//!
+//! Bodies are JSON unless the client negotiates CBOR ([`Codec`]).
+
+use flate2::read::GzDecoder;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::error::Category;
+use std::io::Read;
+
+use crate::errors::{Error, Result};
+use crate::redact::redact;
+
+/// Decode a request body as UTF-8
+pub fn body_str(body: &[u8]) -> Result<&str> {
+    std::str::from_utf8(body).map_err(|e| {
+        Error::Validation(format!("Invalid UTF-8 in body at byte {}", e.valid_up_to()))
+    })
+}
+
+/// Reject a missing or blank body up front, rather than as a JSON syntax error
+pub fn non_empty(body: &[u8]) -> Result<&[u8]> {
+    if body.iter().all(u8::is_ascii_whitespace) {
+        return Err(Error::Validation("Request body is empty".to_string()));
+    }
+    Ok(body)
+}
+
+/// Upper bound on a gzip body once decompressed (guards against decompression bombs)
+pub const MAX_DECOMPRESSED_BODY_BYTES: usize = 10 * 1024 * 1024;
+
+/// Decompress a `Content-Encoding: gzip` body
+///
+/// Stops reading once the output passes `max_size` bytes, so a small body
+/// can't expand into an arbitrarily large allocation.
+pub fn gunzip(body: &[u8], max_size: usize) -> Result<Vec<u8>> {
+    let mut decompressed = Vec::new();
+    GzDecoder::new(body)
+        .take(max_size as u64 + 1)
+        .read_to_end(&mut decompressed)
+        .map_err(|e| Error::Validation(format!("Invalid gzip body: {}", e)))?;
+
+    if decompressed.len() > max_size {
+        return Err(Error::PayloadTooLarge(format!(
+            "Decompressed body exceeds {} bytes",
+            max_size
+        )));
+    }
+
+    Ok(decompressed)
+}
+
+/// Parse a JSON request body
+pub fn parse_json<T: DeserializeOwned>(body: &str) -> Result<T> {
+    serde_json::from_str(body).map_err(|e| invalid_json(&e))
+}
+
+/// Content type for CBOR bodies
+pub const CBOR_CONTENT_TYPE: &str = "application/cbor";
+
+/// Content type for JSON bodies
+pub const JSON_CONTENT_TYPE: &str = "application/json";
+
+/// Wire format of a request or response body
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Codec {
+    #[default]
+    Json,
+    Cbor,
+}
+
+impl Codec {
+    /// Codec named by a `Content-Type` or `Accept` header
+    ///
+    /// Media type parameters are ignored. For a list, the first of JSON or
+    /// CBOR listed wins; anything else (or no header) means JSON.
+    pub fn from_header(value: Option<&str>) -> Self {
+        value
+            .into_iter()
+            .flat_map(|v| v.split(','))
+            .map(|media| media.split(';').next().unwrap_or_default().trim())
+            .find_map(|media| {
+                if media.eq_ignore_ascii_case(CBOR_CONTENT_TYPE) {
+                    Some(Codec::Cbor)
+                } else if media.eq_ignore_ascii_case(JSON_CONTENT_TYPE) {
+                    Some(Codec::Json)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Codec::Json => JSON_CONTENT_TYPE,
+            Codec::Cbor => CBOR_CONTENT_TYPE,
+        }
+    }
+
+    /// Serialize a response body
+    pub fn encode<T: Serialize>(self, value: &T) -> Result<Vec<u8>> {
+        match self {
+            Codec::Json => serde_json::to_vec(value).map_err(|e| Error::Internal(e.to_string())),
+            Codec::Cbor => {
+                let mut out = Vec::new();
+                ciborium::into_writer(value, &mut out)
+                    .map_err(|e| Error::Internal(e.to_string()))?;
+                Ok(out)
+            }
+        }
+    }
+}
+
+/// Parse a CBOR request body
+pub fn parse_cbor<T: DeserializeOwned>(body: &[u8]) -> Result<T> {
+    ciborium::from_reader(body)
+        .map_err(|e| Error::Validation(format!("Malformed CBOR body: {}", redact(&e.to_string()))))
+}
+
+/// Parse a JSON Lines (NDJSON) body, one value per non-blank line
+///
+/// The body is validated as a whole: if any line fails to parse, the error
+/// names that line and nothing from the body should be applied.
+pub fn parse_ndjson<T: DeserializeOwned>(body: &str) -> Result<Vec<T>> {
+    let mut values = Vec::new();
+
+    for (index, line) in body.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let value = serde_json::from_str(line).map_err(|e| {
+            let line_number = index + 1;
+            let reason = redact(&e.to_string());
+            Error::ValidationDetails {
+                message: format!(
+                    "Malformed NDJSON at line {}: {}; no events were published",
+                    line_number, reason
+                ),
+                details: serde_json::json!({
+                    "line": line_number,
+                    "column": e.column(),
+                    "reason": reason,
+                    "published": 0,
+                }),
+            }
+        })?;
+        values.push(value);
+    }
+
+    Ok(values)
+}
+
+/// Convert a serde error into a validation error with line/column details
+pub fn invalid_json(err: &serde_json::Error) -> Error {
+    let category = match err.classify() {
+        Category::Io => "io",
+        Category::Syntax => "syntax",
+        Category::Data => "data",
+        Category::Eof => "eof",
+    };
+
+    // serde appends " at line X column Y"; keep the reason on its own
+    let message = redact(&err.to_string());
+    let reason = message
+        .rsplit_once(" at line ")
+        .map(|(reason, _)| reason.to_string())
+        .unwrap_or_else(|| message.clone());
+
+    Error::ValidationDetails {
+        message: format!("Malformed JSON body: {}", message),
+        details: serde_json::json!({
+            "line": err.line(),
+            "column": err.column(),
+            "category": category,
+            "reason": reason,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::PublishEvent;
+
+    #[test]
+    fn test_syntax_error_reports_position() {
+        let err = parse_json::<PublishEvent>("{\n  \"key\": \"a\",\n  oops\n}").unwrap_err();
+        assert_eq!(err.status_code(), 400);
+
+        let details = err.details().unwrap();
+        assert_eq!(details["line"], 3);
+        assert_eq!(details["category"], "syntax");
+    }
+
+    #[test]
+    fn test_missing_field_names_the_field() {
+        let err = parse_json::<PublishEvent>(r#"{"key": "a", "data": {}}"#).unwrap_err();
+
+        let details = err.details().unwrap();
+        assert_eq!(details["category"], "data");
+        assert!(details["reason"].as_str().unwrap().contains("`type`"));
+    }
+
+    #[test]
+    fn test_error_does_not_echo_payload_values() {
+        let err =
+            parse_json::<PublishEvent>(r#"{"key": "a", "type": "sk_live_secret"}"#).unwrap_err();
+        assert!(!err.to_string().contains("sk_live_secret"));
+
+        let err = parse_json::<crate::models::CreateStreamRequest>(
+            r#"{"stream_id": "s", "partition_count": "sk_live_secret"}"#,
+        )
+        .unwrap_err();
+        let response = err.to_response();
+        assert!(!response.message.contains("sk_live_secret"));
+        assert!(!err
+            .details()
+            .unwrap()
+            .to_string()
+            .contains("sk_live_secret"));
+    }
+
+    #[test]
+    fn test_ndjson_parses_each_line() {
+        let body = "{\"key\": \"a\", \"type\": \"t\", \"data\": {}}\n\n{\"key\": \"b\", \"type\": \"t\", \"data\": 1}\n";
+        let events: Vec<PublishEvent> = parse_ndjson(body).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].key, "b");
+    }
+
+    #[test]
+    fn test_ndjson_reports_bad_line() {
+        let body = "{\"key\": \"a\", \"type\": \"t\", \"data\": {}}\n{not json}\n{\"key\": \"c\", \"type\": \"t\", \"data\": {}}";
+        let err = parse_ndjson::<PublishEvent>(body).unwrap_err();
+        assert_eq!(err.status_code(), 400);
+        assert_eq!(err.details().unwrap()["line"], 2);
+        assert!(err.to_string().contains("no events were published"));
+    }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_gunzip_round_trip() {
+        let body = br#"[{"key": "a", "type": "t", "data": {}}]"#;
+        assert_eq!(gunzip(&gzip(body), 1024).unwrap(), body);
+    }
+
+    #[test]
+    fn test_gunzip_rejects_oversized_output() {
+        // 1 MiB of zeros compresses to about 1 KiB
+        let compressed = gzip(&vec![0u8; 1024 * 1024]);
+        let err = gunzip(&compressed, 64 * 1024).unwrap_err();
+        assert_eq!(err.code(), "payload_too_large");
+        assert_eq!(err.status_code(), 413);
+    }
+
+    #[test]
+    fn test_gunzip_rejects_non_gzip() {
+        let err = gunzip(b"not gzip", 1024).unwrap_err();
+        assert_eq!(err.code(), "validation_error");
+    }
+
+    #[test]
+    fn test_codec_from_header() {
+        assert_eq!(Codec::from_header(None), Codec::Json);
+        assert_eq!(Codec::from_header(Some("application/cbor")), Codec::Cbor);
+        assert_eq!(
+            Codec::from_header(Some("Application/CBOR; q=1")),
+            Codec::Cbor
+        );
+        assert_eq!(
+            Codec::from_header(Some("text/html, application/cbor")),
+            Codec::Cbor
+        );
+        assert_eq!(
+            Codec::from_header(Some("application/json, application/cbor")),
+            Codec::Json
+        );
+        assert_eq!(Codec::from_header(Some("*/*")), Codec::Json);
+    }
+
+    #[test]
+    fn test_cbor_round_trip() {
+        let event: PublishEvent =
+            parse_json(r#"{"key": "a", "type": "order.created", "data": {"n": [1, 2.5, null]}}"#)
+                .unwrap();
+        let encoded = Codec::Cbor.encode(&event).unwrap();
+        let decoded: PublishEvent = parse_cbor(&encoded).unwrap();
+        assert_eq!(
+            serde_json::to_value(&decoded).unwrap(),
+            serde_json::to_value(&event).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_malformed_cbor_is_a_validation_error() {
+        let encoded = Codec::Cbor
+            .encode(&serde_json::json!({"key": "sk_live_secret"}))
+            .unwrap();
+        let err = parse_cbor::<PublishEvent>(&encoded).unwrap_err();
+        assert_eq!(err.code(), "validation_error");
+        assert!(!err.to_string().contains("sk_live_secret"));
+
+        let err = parse_cbor::<PublishEvent>(&[0xff, 0x00]).unwrap_err();
+        assert_eq!(err.code(), "validation_error");
+    }
+
+    #[test]
+    fn test_empty_body_is_rejected() {
+        for empty in [&b""[..], b" \n"] {
+            let err = non_empty(empty).unwrap_err();
+            assert_eq!(err.code(), "validation_error");
+            assert_eq!(err.to_string(), "Validation error: Request body is empty");
+        }
+        assert!(non_empty(b"{}").is_ok());
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let err = body_str(&[b'{', 0xff]).unwrap_err();
+        assert_eq!(err.code(), "validation_error");
+        assert!(err.to_string().contains("byte 1"));
+    }
+}
@@ -0,0 +1,32 @@
+//! Source of the current time
+//!
+//! [`DynamoClient`](crate::DynamoClient) reads the time through a [`Clock`]
+//! rather than calling `Utc::now()`, so tests can pin it and assert exact
+//! timestamps.
+
+use chrono::{DateTime, Utc};
+
+/// Provides the current time
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// The system's wall clock, used unless a client is given another
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock stopped at one instant
+#[derive(Debug, Clone, Copy)]
+pub struct FixedClock(pub DateTime<Utc>);
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0
+    }
+}
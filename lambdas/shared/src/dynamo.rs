@@ -2,45 +2,177 @@
 //!
 //! Single-table design with the following key patterns:
 //!
-//! | PK                          | SK                    | Purpose              |
-//! |-----------------------------|-----------------------|----------------------|
-//! | STREAM#{id}                 | META                  | Stream metadata      |
-//! | STREAM#{id}                 | SUB#{sub_id}          | Subscription config  |
-//! | STREAM#{id}#P{n}            | SEQ#{seq:020}         | Event in partition   |
-//! | STREAM#{id}#SUB#{sub_id}    | OFFSET#P{n}           | Consumer offset      |
-//! | STREAM#{id}#COMPACT         | KEY#{key}             | Compacted state      |
-//! | STREAM#{id}#P{n}            | COUNTER               | Sequence counter     |
-
-use aws_sdk_dynamodb::types::AttributeValue;
+//! | PK                              | SK                  | Purpose              |
+//! |---------------------------------|---------------------|----------------------|
+//! | STREAM#{id}                     | META                | Stream metadata      |
+//! | ALIAS#{alias}                   | META                | Stream alias         |
+//! | STREAM#{id}                     | SUB#{sub_id}        | Subscription config  |
+//! | STREAM#{id}#P{n}                | SEQ#{seq:020}       | Event in partition   |
+//! | STREAM#{id}#SUB#{sub_id}        | OFFSET#P{n}         | Consumer offset      |
+//! | STREAM#{id}#SUB#{sub_id}        | DELIVERED#P{n}      | Consume-mode offset  |
+//! | STREAM#{id}#SUB#{sub_id}#CURSOR | TOKEN#{uuid}        | Stored poll cursor   |
+//! | STREAM#{id}#COMPACT             | KEY#{key}           | Compacted state      |
+//! | STREAM#{id}#COMPACT_LOG         | LOG#{pos:020}       | Compaction changelog |
+//! | STREAM#{id}#COMPACT_LOG         | APPLIED#P{n}#{seq}  | Event's log position |
+//! | STREAM#{id}#COMPACT_LOG         | COUNTER             | Changelog position   |
+//! | STREAM#{id}#P{n}                | COUNTER             | Sequence counter     |
+//! | STREAM#{id}#GLOBAL              | COUNTER             | Global position      |
+//! | STREAM#{id}#ROUND_ROBIN         | COUNTER             | Empty-key rotation   |
+//! | STREAM#{id}#IDEMPOTENCY         | KEY#{key}           | Idempotent publish   |
+//! | STREAM#{id}#REPARTITION         | FROM#{source_id}    | Repartition progress |
+//! | STREAM#{id}#DLQ                 | ATTEMPT#{record_id} | Compaction failures  |
+//! | STREAM#{id}#DLQ                 | RECORD#{record_id}  | Dead-lettered record |
+//!
+//! Stream metadata items also carry `streams_pk`, which puts them (and only
+//! them) in the sparse `streams-index` GSI keyed by `streams_pk`/`stream_id`,
+//! so streams can be listed in ID order without scanning the table.
+
+use aws_config::SdkConfig;
+use aws_sdk_dynamodb::error::{ProvideErrorMetadata, SdkError};
+use aws_sdk_dynamodb::operation::transact_write_items::TransactWriteItemsError;
+use aws_sdk_dynamodb::types::{
+    AttributeValue, CancellationReason, DeleteRequest, KeysAndAttributes, Put, ReturnValue, Select,
+    TransactWriteItem, Update, WriteRequest,
+};
 use aws_sdk_dynamodb::Client;
-use chrono::Utc;
-use serde_dynamo::{from_item, to_item};
-use std::collections::HashMap;
+use chrono::{DateTime, SecondsFormat, Utc};
+use futures::stream::{self, StreamExt};
+use serde_dynamo::{from_item, to_attribute_value, to_item};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
 
+use crate::clock::{Clock, SystemClock};
 use crate::errors::{Error, Result};
 use crate::models::*;
-use crate::partitioner::Partitioner;
+use crate::schema;
+use crate::stream_cache::StreamCache;
 
 /// DynamoDB table name (from environment)
 const TABLE_NAME_ENV: &str = "EVENTLEDGER_TABLE";
 const DEFAULT_TABLE_NAME: &str = "eventledger";
 
+/// Endpoint override (e.g. local DynamoDB at `http://localhost:8000`)
+const ENDPOINT_ENV: &str = "EVENTLEDGER_DYNAMO_ENDPOINT";
+
+/// Request header selecting an alternate table (honored only when allowed)
+pub const TABLE_OVERRIDE_HEADER: &str = "x-eventledger-table";
+/// Set to "true" to honor `TABLE_OVERRIDE_HEADER` (test environments only)
+const ALLOW_TABLE_OVERRIDE_ENV: &str = "EVENTLEDGER_ALLOW_TABLE_OVERRIDE";
+
+/// Partitions scanned concurrently by a compaction backfill
+const BACKFILL_CONCURRENCY: usize = 4;
+/// Partitions counted concurrently for stream stats
+const STATS_CONCURRENCY: usize = 8;
+/// Events read per query during a compaction backfill
+const BACKFILL_PAGE_SIZE: u32 = 500;
+/// Events republished per publish while repartitioning a stream
+const REPARTITION_BATCH_SIZE: usize = 100;
+/// Events read per query when searching a partition by timestamp
+const OFFSET_AT_TIME_PAGE_SIZE: u32 = 100;
+/// Writes DynamoDB accepts in a single TransactWriteItems
+const TRANSACT_WRITE_MAX_ITEMS: usize = 100;
+/// Keys DynamoDB accepts in a single BatchGetItem
+const BATCH_GET_MAX_KEYS: usize = 100;
+/// Writes DynamoDB accepts in a single BatchWriteItem
+const BATCH_WRITE_MAX_ITEMS: usize = 25;
+/// Times unprocessed BatchGetItem keys (or BatchWriteItem writes) are
+/// retried before giving up
+const BATCH_GET_MAX_RETRIES: u32 = 5;
+/// Backoff before the first retry of unprocessed keys, doubled on each retry
+const BATCH_GET_BACKOFF: Duration = Duration::from_millis(50);
+/// Sparse GSI listing stream metadata in `stream_id` order
+const STREAMS_INDEX: &str = "streams-index";
+/// Hash key of `STREAMS_INDEX`, set (to `STREAMS_INDEX_PK_VALUE`) only on META items
+const STREAMS_INDEX_PK: &str = "streams_pk";
+const STREAMS_INDEX_PK_VALUE: &str = "STREAM";
+/// Sparse GSI listing each stream's compacted state by event timestamp,
+/// keyed by `PK` and the `timestamp_ns` only compacted items carry
+const COMPACTED_TIME_INDEX: &str = "compacted-time-index";
+/// How long a stored cursor token can be resolved after it was issued
+const CURSOR_TOKEN_TTL_HOURS: i64 = 24;
+/// Attempts to update a publish rate bucket that other publishes keep changing
+const RATE_BUCKET_MAX_ATTEMPTS: u32 = 3;
+/// Attempts to take the next compaction changelog position while other
+/// changes to the stream keep taking it first
+const COMPACTION_LOG_MAX_ATTEMPTS: u32 = 25;
+
+/// Whether requests may pick their table with [`TABLE_OVERRIDE_HEADER`]
+pub fn table_override_allowed() -> bool {
+    std::env::var(ALLOW_TABLE_OVERRIDE_ENV).is_ok_and(|v| v == "true")
+}
+
 /// DynamoDB client for EventLedger operations
+#[derive(Clone)]
 pub struct DynamoClient {
     client: Client,
     table_name: String,
+    clock: Arc<dyn Clock>,
+    /// Shared by every client cloned from this one, across requests
+    streams: Arc<StreamCache>,
 }
 
 impl DynamoClient {
     /// Create a new DynamoDB client
     pub fn new(client: Client) -> Self {
         let table_name = std::env::var(TABLE_NAME_ENV).unwrap_or_else(|_| DEFAULT_TABLE_NAME.to_string());
-        Self { client, table_name }
+        Self::with_table_name(client, table_name)
     }
 
     /// Create with explicit table name (for testing)
     pub fn with_table_name(client: Client, table_name: String) -> Self {
-        Self { client, table_name }
+        Self {
+            client,
+            table_name,
+            clock: Arc::new(SystemClock),
+            streams: Arc::default(),
+        }
+    }
+
+    /// Read the time from `clock` instead of the system clock (for testing)
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Create from shared SDK config, honoring `EVENTLEDGER_DYNAMO_ENDPOINT`
+    ///
+    /// Lambdas load config once at startup and reuse the client across invocations.
+    pub fn from_config(config: &SdkConfig) -> Self {
+        let endpoint_url = std::env::var(ENDPOINT_ENV).ok();
+        Self::from_config_with_endpoint(config, endpoint_url.as_deref())
+    }
+
+    /// Create from shared SDK config with an explicit endpoint (e.g. local DynamoDB)
+    pub fn from_config_with_endpoint(config: &SdkConfig, endpoint_url: Option<&str>) -> Self {
+        let mut builder = aws_sdk_dynamodb::config::Builder::from(config);
+        if let Some(endpoint_url) = endpoint_url {
+            builder = builder.endpoint_url(endpoint_url);
+        }
+        Self::new(Client::from_conf(builder.build()))
+    }
+
+    /// Client for a single request, honoring a table override header value
+    /// only when `EVENTLEDGER_ALLOW_TABLE_OVERRIDE=true`
+    pub fn for_request(&self, table_override: Option<&str>) -> Self {
+        self.with_table_override(table_override, table_override_allowed())
+    }
+
+    /// [`for_request`](Self::for_request) with the opt-in passed in
+    fn with_table_override(&self, table_override: Option<&str>, allowed: bool) -> Self {
+        match table_override {
+            Some(table_name) if allowed && !table_name.is_empty() => Self {
+                table_name: table_name.to_string(),
+                ..self.clone()
+            },
+            _ => self.clone(),
+        }
+    }
+
+    /// Name of the table this client operates on
+    pub fn table_name(&self) -> &str {
+        &self.table_name
     }
 
     // =========================================================================
@@ -49,15 +181,44 @@ impl DynamoClient {
 
     /// Create a new stream
     pub async fn create_stream(&self, req: &CreateStreamRequest) -> Result<Stream> {
-        let stream = Stream::new(
+        let mut stream = Stream::new(
             req.stream_id.clone(),
-            req.partition_count,
-            req.retention_hours,
+            req.resolved_partition_count(),
+            req.resolved_retention_hours(),
         );
+        stream.global_ordering = req.global_ordering;
+        stream.allowed_event_types = req.allowed_event_types.clone();
+        stream.schema = req.schema.clone();
+        stream.partition_overrides = req.partition_overrides.clone();
+        stream.empty_key_strategy = req.empty_key_strategy;
+        stream.compaction = req.compaction.unwrap_or(stream.compaction);
+        stream.idempotency_ttl_hours = req.idempotency_ttl_hours;
+        stream.compacted_ttl_hours = req.compacted_ttl_hours;
+        stream.max_publish_per_second = req.max_publish_per_second;
+        stream.tags = req.tags.clone();
+        stream.created_at = self.clock.now();
+
+        // Reject schemas that can't be compiled and out-of-range overrides
+        // before anything is stored
+        if let Some(schema) = &stream.schema {
+            schema::compile(schema)?;
+        }
+        stream.check_partition_count()?;
+        stream.partitioner()?;
+        stream.check_retention()?;
+        stream.check_idempotency_ttl()?;
+        stream.check_compacted_ttl()?;
+        stream.check_empty_key_strategy()?;
+        stream.check_publish_rate()?;
+        stream.check_tags()?;
 
         let mut item: HashMap<String, AttributeValue> = to_item(&stream).map_err(|e| Error::DynamoSerialization(e.to_string()))?;
         item.insert("PK".to_string(), AttributeValue::S(format!("STREAM#{}", stream.stream_id)));
         item.insert("SK".to_string(), AttributeValue::S("META".to_string()));
+        item.insert(
+            STREAMS_INDEX_PK.to_string(),
+            AttributeValue::S(STREAMS_INDEX_PK_VALUE.to_string()),
+        );
 
         // Use condition to prevent overwriting existing stream
         self.client
@@ -68,27 +229,177 @@ impl DynamoClient {
             .send()
             .await
             .map_err(|e| {
-                if e.to_string().contains("ConditionalCheckFailed") {
+                if is_conditional_check_failed(&e) {
                     Error::StreamAlreadyExists(req.stream_id.clone())
                 } else {
-                    Error::Database(e.to_string())
+                    database_error(e)
                 }
             })?;
 
         // Initialize sequence counters for each partition
-        for partition in 0..req.partition_count {
-            self.init_partition_counter(&req.stream_id, partition).await?;
+        for partition in 0..stream.partition_count {
+            self.init_counter(format!("STREAM#{}#P{}", req.stream_id, partition), &stream)
+                .await?;
+        }
+
+        if stream.global_ordering {
+            self.init_counter(format!("STREAM#{}#GLOBAL", req.stream_id), &stream)
+                .await?;
+        }
+
+        if stream.empty_key_strategy == EmptyKeyStrategy::RoundRobin {
+            self.init_counter(format!("STREAM#{}#ROUND_ROBIN", req.stream_id), &stream)
+                .await?;
         }
 
         Ok(stream)
     }
 
-    /// Initialize sequence counter for a partition
-    async fn init_partition_counter(&self, stream_id: &str, partition: u32) -> Result<()> {
+    /// Apply a config update if the stream is still at `expected_version`
+    ///
+    /// Bumps the version so that another writer holding the old version gets
+    /// [`Error::PreconditionFailed`] instead of silently overwriting this change.
+    pub async fn update_stream(
+        &self,
+        stream_id: &str,
+        expected_version: u64,
+        req: &UpdateStreamRequest,
+    ) -> Result<Stream> {
+        if req.is_empty() {
+            return Err(Error::Validation("No stream fields to update".to_string()));
+        }
+        if let Some(schema) = &req.schema {
+            schema::compile(schema)?;
+        }
+        // No stream can be at the last version, since nothing could follow it
+        let next_version = expected_version.checked_add(1).ok_or_else(|| {
+            Error::Validation(format!("Invalid stream version {}", expected_version))
+        })?;
+
+        // 404 rather than 412 for a stream that doesn't exist
+        let mut current = self.get_stream(stream_id).await?;
+        if let Some(hours) = req.retention_hours {
+            current.retention_hours = hours;
+            current.check_retention()?;
+            current.check_idempotency_ttl()?;
+        }
+        if let Some(rate) = req.max_publish_per_second {
+            current.max_publish_per_second = Some(rate);
+            current.check_publish_rate()?;
+        }
+        if let Some(tags) = &req.tags {
+            current.tags = tags.clone();
+            current.check_tags()?;
+        }
+
+        let mut sets = vec!["#version = :next"];
+        let mut update = self
+            .client
+            .update_item()
+            .table_name(&self.table_name)
+            .key("PK", AttributeValue::S(format!("STREAM#{}", stream_id)))
+            .key("SK", AttributeValue::S("META".to_string()))
+            .expression_attribute_names("#version", "version")
+            .expression_attribute_values(
+                ":expected",
+                AttributeValue::N(expected_version.to_string()),
+            )
+            .expression_attribute_values(":next", AttributeValue::N(next_version.to_string()));
+
+        if let Some(hours) = req.retention_hours {
+            sets.push("#retention_hours = :retention_hours");
+            update = update
+                .expression_attribute_names("#retention_hours", "retention_hours")
+                .expression_attribute_values(
+                    ":retention_hours",
+                    AttributeValue::N(hours.to_string()),
+                );
+        }
+        if let Some(types) = &req.allowed_event_types {
+            sets.push("#allowed_event_types = :allowed_event_types");
+            update = update
+                .expression_attribute_names("#allowed_event_types", "allowed_event_types")
+                .expression_attribute_values(
+                    ":allowed_event_types",
+                    to_attribute_value(types)
+                        .map_err(|e| Error::DynamoSerialization(e.to_string()))?,
+                );
+        }
+        if let Some(rate) = req.max_publish_per_second {
+            sets.push("#max_publish_per_second = :max_publish_per_second");
+            update = update
+                .expression_attribute_names("#max_publish_per_second", "max_publish_per_second")
+                .expression_attribute_values(
+                    ":max_publish_per_second",
+                    AttributeValue::N(rate.to_string()),
+                );
+        }
+        if let Some(tags) = &req.tags {
+            sets.push("#tags = :tags");
+            update = update
+                .expression_attribute_names("#tags", "tags")
+                .expression_attribute_values(
+                    ":tags",
+                    to_attribute_value(tags)
+                        .map_err(|e| Error::DynamoSerialization(e.to_string()))?,
+                );
+        }
+        if let Some(schema) = &req.schema {
+            sets.push("#schema = :schema");
+            update = update
+                .expression_attribute_names("#schema", "schema")
+                .expression_attribute_values(
+                    ":schema",
+                    to_attribute_value(schema)
+                        .map_err(|e| Error::DynamoSerialization(e.to_string()))?,
+                );
+        }
+
+        // Streams created before versioning have no version attribute and count as 0
+        let condition = if expected_version == 0 {
+            "attribute_exists(PK) AND (attribute_not_exists(#version) OR #version = :expected)"
+        } else {
+            "attribute_exists(PK) AND #version = :expected"
+        };
+
+        let result = update
+            .update_expression(format!("SET {}", sets.join(", ")))
+            .condition_expression(condition)
+            .return_values(ReturnValue::AllNew)
+            .send()
+            .await
+            .map_err(|e| {
+                if is_conditional_check_failed(&e) {
+                    Error::PreconditionFailed(format!(
+                        "Stream {} is no longer at version {}",
+                        stream_id, expected_version
+                    ))
+                } else {
+                    database_error(e)
+                }
+            })?;
+
+        self.streams.invalidate(&self.table_name, stream_id);
+        let item = result
+            .attributes
+            .ok_or_else(|| Error::Internal("No attributes returned".to_string()))?;
+        from_item(item).map_err(|e| Error::DynamoSerialization(e.to_string()))
+    }
+
+    /// Initialize a counter item (partition sequence or global position) at zero
+    ///
+    /// The counter records when its stream was created, so a publisher routing
+    /// by a cached copy of a stream that has since been deleted and recreated
+    /// can tell that its copy is stale.
+    async fn init_counter(&self, pk: String, stream: &Stream) -> Result<()> {
         let mut item = HashMap::new();
-        item.insert("PK".to_string(), AttributeValue::S(format!("STREAM#{}#P{}", stream_id, partition)));
+        item.insert("PK".to_string(), AttributeValue::S(pk));
         item.insert("SK".to_string(), AttributeValue::S("COUNTER".to_string()));
         item.insert("sequence".to_string(), AttributeValue::N("0".to_string()));
+        item.insert(
+            "stream_created_us".to_string(),
+            AttributeValue::N(stream.created_at.timestamp_micros().to_string()),
+        );
 
         self.client
             .put_item()
@@ -96,7 +407,7 @@ impl DynamoClient {
             .set_item(Some(item))
             .send()
             .await
-            .map_err(|e| Error::Database(e.to_string()))?;
+            .map_err(database_error)?;
 
         Ok(())
     }
@@ -111,7 +422,7 @@ impl DynamoClient {
             .key("SK", AttributeValue::S("META".to_string()))
             .send()
             .await
-            .map_err(|e| Error::Database(e.to_string()))?;
+            .map_err(database_error)?;
 
         match result.item {
             Some(item) => from_item(item).map_err(|e| Error::DynamoSerialization(e.to_string())),
@@ -119,34 +430,321 @@ impl DynamoClient {
         }
     }
 
-    /// List all streams
-    pub async fn list_streams(&self) -> Result<Vec<Stream>> {
-        // Use Scan with filter since we can't use begins_with on partition key in Query
+    /// Get a stream by ID, from the in-process cache when it was read in the
+    /// last [`STREAM_CACHE_TTL`](crate::stream_cache::STREAM_CACHE_TTL)
+    ///
+    /// For hot paths that can tolerate config changed by another instance
+    /// taking that long to apply. Missing streams aren't cached, so a newly
+    /// created one is found at once.
+    pub async fn get_stream_cached(&self, stream_id: &str) -> Result<Stream> {
+        if let Some(stream) = self.streams.get(&self.table_name, stream_id) {
+            return Ok(stream);
+        }
+        let stream = self.get_stream(stream_id).await?;
+        self.streams.insert(&self.table_name, stream.clone());
+        Ok(stream)
+    }
+
+    /// Whether a stream exists, answered from the cache when possible (see
+    /// [`Self::get_stream_cached`])
+    pub async fn stream_exists_cached(&self, stream_id: &str) -> Result<bool> {
+        match self.get_stream_cached(stream_id).await {
+            Ok(_) => Ok(true),
+            Err(Error::StreamNotFound(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Whether a stream exists, without reading or deserializing its metadata
+    pub async fn head_stream(&self, stream_id: &str) -> Result<bool> {
         let result = self
             .client
-            .scan()
+            .get_item()
             .table_name(&self.table_name)
-            .filter_expression("begins_with(PK, :prefix) AND SK = :meta")
-            .expression_attribute_values(":prefix", AttributeValue::S("STREAM#".to_string()))
-            .expression_attribute_values(":meta", AttributeValue::S("META".to_string()))
+            .key("PK", AttributeValue::S(format!("STREAM#{}", stream_id)))
+            .key("SK", AttributeValue::S("META".to_string()))
+            .projection_expression("PK")
             .send()
             .await
-            .map_err(|e| Error::Database(e.to_string()))?;
+            .map_err(database_error)?;
 
-        let streams: Vec<Stream> = result
-            .items
-            .unwrap_or_default()
-            .into_iter()
-            .filter_map(|item| from_item(item).ok())
+        Ok(result.item.is_some())
+    }
+
+    /// Get several streams with BatchGetItem, in the order of `stream_ids`
+    ///
+    /// Missing streams are skipped rather than reported, and repeated IDs are
+    /// returned once. Keys DynamoDB leaves unprocessed (under throttling) are
+    /// retried with backoff. At most [`MAX_BATCH_GET_STREAMS`] IDs are accepted.
+    pub async fn batch_get_streams(&self, stream_ids: &[String]) -> Result<Vec<Stream>> {
+        if stream_ids.len() > MAX_BATCH_GET_STREAMS {
+            return Err(Error::Validation(format!(
+                "At most {} streams can be fetched at once, got {}",
+                MAX_BATCH_GET_STREAMS,
+                stream_ids.len()
+            )));
+        }
+
+        // BatchGetItem rejects a request that names the same key twice
+        let mut unique: Vec<&String> = Vec::with_capacity(stream_ids.len());
+        for stream_id in stream_ids {
+            if !unique.contains(&stream_id) {
+                unique.push(stream_id);
+            }
+        }
+
+        let keys = unique
+            .iter()
+            .map(|stream_id| {
+                HashMap::from([
+                    (
+                        "PK".to_string(),
+                        AttributeValue::S(format!("STREAM#{}", stream_id)),
+                    ),
+                    ("SK".to_string(), AttributeValue::S("META".to_string())),
+                ])
+            })
             .collect();
+        let mut found: HashMap<String, Stream> = HashMap::with_capacity(unique.len());
+        for item in self.batch_get(keys).await? {
+            let stream: Stream =
+                from_item(item).map_err(|e| Error::DynamoSerialization(e.to_string()))?;
+            found.insert(stream.stream_id.clone(), stream);
+        }
+
+        Ok(unique
+            .into_iter()
+            .filter_map(|stream_id| found.remove(stream_id))
+            .collect())
+    }
+
+    /// Fetch items by key with BatchGetItem, in chunks of the API's limit
+    ///
+    /// Items come back in no particular order and missing keys are left out.
+    /// Keys DynamoDB leaves unprocessed are retried with backoff. The keys must
+    /// be distinct.
+    async fn batch_get(
+        &self,
+        keys: Vec<HashMap<String, AttributeValue>>,
+    ) -> Result<Vec<HashMap<String, AttributeValue>>> {
+        let mut items = Vec::with_capacity(keys.len());
+        for chunk in keys.chunks(BATCH_GET_MAX_KEYS) {
+            let mut pending = Some(
+                KeysAndAttributes::builder()
+                    .set_keys(Some(chunk.to_vec()))
+                    .build()
+                    .map_err(|e| Error::Database(e.to_string()))?,
+            );
+
+            let mut retries = 0;
+            while let Some(request) = pending.take() {
+                let result = self
+                    .client
+                    .batch_get_item()
+                    .request_items(&self.table_name, request)
+                    .send()
+                    .await
+                    .map_err(database_error)?;
+
+                items.extend(
+                    result
+                        .responses
+                        .and_then(|mut responses| responses.remove(&self.table_name))
+                        .unwrap_or_default(),
+                );
+
+                pending = result
+                    .unprocessed_keys
+                    .and_then(|mut unprocessed| unprocessed.remove(&self.table_name))
+                    .filter(|request| !request.keys().is_empty());
+                if pending.is_some() {
+                    if retries == BATCH_GET_MAX_RETRIES {
+                        return Err(Error::Database(format!(
+                            "BatchGetItem left keys unprocessed after {} retries",
+                            retries
+                        )));
+                    }
+                    tokio::time::sleep(BATCH_GET_BACKOFF * 2u32.pow(retries)).await;
+                    retries += 1;
+                }
+            }
+        }
+
+        Ok(items)
+    }
+
+    /// Delete every item under partition key `pk` with BatchWriteItem, in
+    /// chunks of the API's limit
+    ///
+    /// Requests DynamoDB leaves unprocessed are retried with backoff, as in
+    /// [`Self::batch_get`].
+    async fn delete_partition(&self, pk: &str) -> Result<()> {
+        let mut exclusive_start = None;
+        loop {
+            let result = self
+                .client
+                .query()
+                .table_name(&self.table_name)
+                .key_condition_expression("PK = :pk")
+                .expression_attribute_values(":pk", AttributeValue::S(pk.to_string()))
+                .projection_expression("PK, SK")
+                .set_exclusive_start_key(exclusive_start)
+                .send()
+                .await
+                .map_err(database_error)?;
+
+            let keys = result.items.unwrap_or_default();
+            for chunk in keys.chunks(BATCH_WRITE_MAX_ITEMS) {
+                let mut pending = chunk
+                    .iter()
+                    .map(|key| {
+                        let delete = DeleteRequest::builder()
+                            .set_key(Some(key.clone()))
+                            .build()
+                            .map_err(|e| Error::Internal(e.to_string()))?;
+                        Ok(WriteRequest::builder().delete_request(delete).build())
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+
+                let mut retries = 0;
+                while !pending.is_empty() {
+                    let result = self
+                        .client
+                        .batch_write_item()
+                        .request_items(&self.table_name, pending)
+                        .send()
+                        .await
+                        .map_err(database_error)?;
+
+                    pending = result
+                        .unprocessed_items
+                        .and_then(|mut unprocessed| unprocessed.remove(&self.table_name))
+                        .unwrap_or_default();
+                    if !pending.is_empty() {
+                        if retries == BATCH_GET_MAX_RETRIES {
+                            return Err(Error::Database(format!(
+                                "BatchWriteItem left items unprocessed after {} retries",
+                                retries
+                            )));
+                        }
+                        tokio::time::sleep(BATCH_GET_BACKOFF * 2u32.pow(retries)).await;
+                        retries += 1;
+                    }
+                }
+            }
+
+            exclusive_start = result.last_evaluated_key;
+            if exclusive_start.is_none() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// One page of streams in ID order, optionally only IDs starting with `prefix`
+    ///
+    /// Starts after the stream `start_key` (exclusive) and returns up to
+    /// `limit` streams, plus the ID to pass as `start_key` for the next page
+    /// when more may remain. Reads the streams GSI, so only stream metadata
+    /// is read and billed.
+    pub async fn list_streams_page(
+        &self,
+        prefix: Option<&str>,
+        start_key: Option<&str>,
+        limit: usize,
+    ) -> Result<(Vec<Stream>, Option<String>)> {
+        if limit == 0 {
+            return Err(Error::Validation("limit must be at least 1".to_string()));
+        }
+
+        let mut exclusive_start = start_key.map(|stream_id| {
+            HashMap::from([
+                (
+                    "PK".to_string(),
+                    AttributeValue::S(format!("STREAM#{}", stream_id)),
+                ),
+                ("SK".to_string(), AttributeValue::S("META".to_string())),
+                (
+                    STREAMS_INDEX_PK.to_string(),
+                    AttributeValue::S(STREAMS_INDEX_PK_VALUE.to_string()),
+                ),
+                (
+                    "stream_id".to_string(),
+                    AttributeValue::S(stream_id.to_string()),
+                ),
+            ])
+        });
+        let mut streams = Vec::new();
+
+        // A page can stop short of `limit` at the 1MB cap, so keep reading
+        loop {
+            let remaining = (limit - streams.len()).min(i32::MAX as usize) as i32;
+            let mut query = self
+                .client
+                .query()
+                .table_name(&self.table_name)
+                .index_name(STREAMS_INDEX)
+                .expression_attribute_names("#streams", STREAMS_INDEX_PK)
+                .expression_attribute_values(
+                    ":streams",
+                    AttributeValue::S(STREAMS_INDEX_PK_VALUE.to_string()),
+                )
+                .limit(remaining)
+                .set_exclusive_start_key(exclusive_start);
+            // begins_with rejects an empty prefix
+            query = match prefix.filter(|p| !p.is_empty()) {
+                Some(prefix) => query
+                    .key_condition_expression(
+                        "#streams = :streams AND begins_with(stream_id, :prefix)",
+                    )
+                    .expression_attribute_values(":prefix", AttributeValue::S(prefix.to_string())),
+                None => query.key_condition_expression("#streams = :streams"),
+            };
+            let result = query.send().await.map_err(database_error)?;
+
+            streams.extend(
+                result
+                    .items
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|item| from_item::<_, Stream>(item).ok()),
+            );
+
+            exclusive_start = result.last_evaluated_key;
+            if exclusive_start.is_none() || streams.len() >= limit {
+                break;
+            }
+        }
 
-        Ok(streams)
+        let next_key = exclusive_start.and_then(|key| match key.get("stream_id") {
+            Some(AttributeValue::S(stream_id)) => Some(stream_id.clone()),
+            _ => None,
+        });
+        Ok((streams, next_key))
     }
 
     /// Delete a stream and all associated data
-    pub async fn delete_stream(&self, stream_id: &str) -> Result<()> {
+    ///
+    /// Refuses with [`Error::StreamInUse`] while the stream has subscriptions,
+    /// unless `force` is set, in which case they are deleted with it.
+    pub async fn delete_stream(&self, stream_id: &str, force: bool) -> Result<()> {
         // First verify stream exists
         let stream = self.get_stream(stream_id).await?;
+        self.streams.invalidate(&self.table_name, stream_id);
+
+        let subscriptions = self.list_subscriptions(stream_id).await?;
+        if !force && !subscriptions.is_empty() {
+            return Err(Error::StreamInUse(format!(
+                "'{}' has {} subscription(s); delete with force=true to remove it anyway",
+                stream_id,
+                subscriptions.len()
+            )));
+        }
+
+        // Before the metadata, so a delete that fails partway can be retried
+        for subscription in &subscriptions {
+            self.delete_subscription_items(stream_id, &subscription.subscription_id)
+                .await?;
+        }
 
         // Delete stream metadata
         self.client
@@ -156,9 +754,9 @@ impl DynamoClient {
             .key("SK", AttributeValue::S("META".to_string()))
             .send()
             .await
-            .map_err(|e| Error::Database(e.to_string()))?;
+            .map_err(database_error)?;
 
-        // Delete partition counters
+        // Delete partition counters and how far each partition is compacted
         for partition in 0..stream.partition_count {
             self.client
                 .delete_item()
@@ -167,380 +765,4133 @@ impl DynamoClient {
                 .key("SK", AttributeValue::S("COUNTER".to_string()))
                 .send()
                 .await
-                .map_err(|e| Error::Database(e.to_string()))?;
+                .map_err(database_error)?;
+            self.client
+                .delete_item()
+                .table_name(&self.table_name)
+                .key(
+                    "PK",
+                    AttributeValue::S(format!("STREAM#{}#COMPACTED_THROUGH", stream_id)),
+                )
+                .key("SK", AttributeValue::S(format!("P{}", partition)))
+                .send()
+                .await
+                .map_err(database_error)?;
+        }
+
+        if stream.global_ordering {
+            self.client
+                .delete_item()
+                .table_name(&self.table_name)
+                .key(
+                    "PK",
+                    AttributeValue::S(format!("STREAM#{}#GLOBAL", stream_id)),
+                )
+                .key("SK", AttributeValue::S("COUNTER".to_string()))
+                .send()
+                .await
+                .map_err(database_error)?;
+        }
+
+        if stream.empty_key_strategy == EmptyKeyStrategy::RoundRobin {
+            self.client
+                .delete_item()
+                .table_name(&self.table_name)
+                .key(
+                    "PK",
+                    AttributeValue::S(format!("STREAM#{}#ROUND_ROBIN", stream_id)),
+                )
+                .key("SK", AttributeValue::S("COUNTER".to_string()))
+                .send()
+                .await
+                .map_err(database_error)?;
         }
 
-        // Note: In production, you'd want to delete events, subscriptions, etc.
-        // This could be done via a background job or TTL
+        // The limit may have been lifted since the bucket was written, so
+        // remove it regardless of the current config
+        self.client
+            .delete_item()
+            .table_name(&self.table_name)
+            .key(
+                "PK",
+                AttributeValue::S(format!("STREAM#{}#RATE", stream_id)),
+            )
+            .key("SK", AttributeValue::S("COUNTER".to_string()))
+            .send()
+            .await
+            .map_err(database_error)?;
+
+        // The changelog holds copies of the compacted state, so both go
+        // together; a stream recreated under this ID starts without either
+        self.delete_partition(&format!("STREAM#{}#COMPACT", stream_id))
+            .await?;
+        self.delete_partition(&format!("STREAM#{}#COMPACT_LOG", stream_id))
+            .await?;
+        // How far repartitions into this stream got, which a stream recreated
+        // under this ID must not resume from
+        self.delete_partition(&format!("STREAM#{}#REPARTITION", stream_id))
+            .await?;
+
+        // Note: events are left to expire by TTL
 
         Ok(())
     }
 
-    // =========================================================================
-    // Event Operations
-    // =========================================================================
+    /// Approximate number of events in a stream
+    ///
+    /// Sums the partition sequence counters, so this is the number of events ever
+    /// published: an upper bound that still includes events removed by TTL.
+    pub async fn approximate_event_count(&self, stream_id: &str) -> Result<u64> {
+        let stream = self.get_stream(stream_id).await?;
+        let latest = self.latest_sequences(&stream).await?;
+        Ok(latest.iter().map(|po| po.offset).sum())
+    }
 
-    /// Publish events to a stream
-    pub async fn publish_events(
-        &self,
-        stream_id: &str,
-        events: &[PublishEvent],
-    ) -> Result<Vec<PublishedEvent>> {
+    /// Per-partition sequence counters, flagging partitions that are hot
+    pub async fn partition_load(&self, stream_id: &str) -> Result<PartitionLoad> {
         let stream = self.get_stream(stream_id).await?;
-        let partitioner = Partitioner::new(stream.partition_count);
-        let now = Utc::now();
+        let latest = self.latest_sequences(&stream).await?;
+        Ok(PartitionLoad::new(latest))
+    }
 
-        let mut published = Vec::with_capacity(events.len());
+    /// Per-partition sequence counters, live event counts and time ranges, and
+    /// stream totals
+    ///
+    /// Time ranges and `event_count` cover events still stored, so after
+    /// retention has expired some events they drop while `offset` doesn't.
+    /// Counting reads every stored event's key, so this costs more on large
+    /// streams; up to `STATS_CONCURRENCY` partitions are read at once.
+    pub async fn stream_stats(&self, stream_id: &str) -> Result<StreamStats> {
+        let stream = self.get_stream(stream_id).await?;
+        let PartitionLoad {
+            partitions: latest,
+            hot_partitions,
+        } = PartitionLoad::new(self.latest_sequences(&stream).await?);
 
-        for event in events {
-            let partition = partitioner.partition(&event.key);
-            let sequence = self.increment_sequence(stream_id, partition).await?;
+        let partitions = stream::iter(latest)
+            .map(|PartitionOffset { partition, offset }| async move {
+                let (earliest_timestamp, latest_timestamp) =
+                    self.partition_time_range(stream_id, partition).await?;
+                let event_count = self
+                    .count_events_in_partition(stream_id, partition, 0)
+                    .await?;
+                Ok(PartitionStats {
+                    partition,
+                    offset,
+                    event_count,
+                    earliest_timestamp,
+                    latest_timestamp,
+                })
+            })
+            .buffered(STATS_CONCURRENCY)
+            .collect::<Vec<Result<PartitionStats>>>()
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>>>()?;
 
-            let stored_event = Event {
-                stream_id: stream_id.to_string(),
-                partition,
-                sequence,
-                key: event.key.clone(),
-                event_type: event.event_type.clone(),
-                data: event.data.clone(),
-                timestamp: now,
-            };
+        Ok(StreamStats {
+            stream_id: stream.stream_id,
+            partition_count: stream.partition_count,
+            approximate_event_count: partitions.iter().map(|p| p.offset).sum(),
+            event_count: partitions.iter().map(|p| p.event_count).sum(),
+            earliest_timestamp: partitions.iter().filter_map(|p| p.earliest_timestamp).min(),
+            latest_timestamp: partitions.iter().filter_map(|p| p.latest_timestamp).max(),
+            hot_partitions,
+            partitions,
+        })
+    }
 
-            // Store the event
-            let mut item: HashMap<String, AttributeValue> = to_item(&stored_event).map_err(|e| Error::DynamoSerialization(e.to_string()))?;
-            item.insert(
-                "PK".to_string(),
-                AttributeValue::S(format!("STREAM#{}#P{}", stream_id, partition)),
-            );
-            item.insert(
-                "SK".to_string(),
-                AttributeValue::S(format!("SEQ#{:020}", sequence)),
-            );
+    /// Timestamps of the first and last events stored in a partition
+    async fn partition_time_range(
+        &self,
+        stream_id: &str,
+        partition: u32,
+    ) -> Result<(Option<DateTime<Utc>>, Option<DateTime<Utc>>)> {
+        let projection = EventProjection::parse("timestamp")?;
+        let first = self
+            .query_events(stream_id, partition, 0, 1, true, Some(&projection))
+            .await?;
+        let last = self
+            .query_events(stream_id, partition, 0, 1, false, Some(&projection))
+            .await?;
 
-            self.client
-                .put_item()
+        Ok((
+            first.first().map(|e| e.timestamp),
+            last.first().map(|e| e.timestamp),
+        ))
+    }
+
+    /// Number of events still stored in a partition after sequence `from_offset`
+    ///
+    /// Unlike the sequence counter, this leaves out events that are gone (e.g.
+    /// expired by TTL). DynamoDB counts at most 1MB of items per request, so
+    /// large partitions take several.
+    pub async fn count_events_in_partition(
+        &self,
+        stream_id: &str,
+        partition: u32,
+        from_offset: u64,
+    ) -> Result<u64> {
+        let mut count = 0;
+        let mut exclusive_start = None;
+        loop {
+            let result = self
+                .client
+                .query()
                 .table_name(&self.table_name)
-                .set_item(Some(item))
+                .key_condition_expression("PK = :pk AND SK BETWEEN :lower AND :upper")
+                .expression_attribute_values(
+                    ":pk",
+                    AttributeValue::S(format!("STREAM#{}#P{}", stream_id, partition)),
+                )
+                .expression_attribute_values(
+                    ":lower",
+                    AttributeValue::S(format!("SEQ#{:020}", from_offset.saturating_add(1))),
+                )
+                .expression_attribute_values(
+                    ":upper",
+                    AttributeValue::S(format!("SEQ#{:020}", u64::MAX)),
+                )
+                .select(Select::Count)
+                .set_exclusive_start_key(exclusive_start)
                 .send()
                 .await
-                .map_err(|e| Error::Database(e.to_string()))?;
+                .map_err(database_error)?;
 
-            published.push(PublishedEvent {
-                stream_id: stream_id.to_string(),
-                partition,
-                sequence,
-                key: event.key.clone(),
-                timestamp: now,
-            });
+            count += result.count.max(0) as u64;
+            exclusive_start = result.last_evaluated_key;
+            if exclusive_start.is_none() {
+                return Ok(count);
+            }
         }
+    }
 
-        Ok(published)
+    /// Latest sequence number of each partition
+    async fn latest_sequences(&self, stream: &Stream) -> Result<Vec<PartitionOffset>> {
+        let mut latest = Vec::with_capacity(stream.partition_count as usize);
+        for partition in 0..stream.partition_count {
+            let offset = self.get_latest_offset(&stream.stream_id, partition).await?;
+            latest.push(PartitionOffset { partition, offset });
+        }
+        Ok(latest)
     }
 
-    /// Increment and return the next sequence number for a partition
-    async fn increment_sequence(&self, stream_id: &str, partition: u32) -> Result<u64> {
+    // =========================================================================
+    // Alias Operations
+    // =========================================================================
+
+    /// The stream a publish or poll names, following an alias when no stream
+    /// has that name
+    ///
+    /// Streams come from the cache as in [`Self::get_stream_cached`]; aliases
+    /// are read on every call, so a swap applies at once.
+    pub async fn resolve_stream_id(&self, name: &str) -> Result<String> {
+        match self.get_stream_cached(name).await {
+            Ok(stream) => Ok(stream.stream_id),
+            Err(Error::StreamNotFound(_)) => match self.get_alias(name).await {
+                Ok(alias) => Ok(alias.stream_id),
+                Err(Error::AliasNotFound(_)) => Err(Error::StreamNotFound(name.to_string())),
+                Err(e) => Err(e),
+            },
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Get an alias
+    pub async fn get_alias(&self, alias: &str) -> Result<StreamAlias> {
         let result = self
             .client
-            .update_item()
+            .get_item()
             .table_name(&self.table_name)
-            .key("PK", AttributeValue::S(format!("STREAM#{}#P{}", stream_id, partition)))
-            .key("SK", AttributeValue::S("COUNTER".to_string()))
-            .update_expression("SET #seq = #seq + :inc")
-            .expression_attribute_names("#seq", "sequence")
-            .expression_attribute_values(":inc", AttributeValue::N("1".to_string()))
-            .return_values(aws_sdk_dynamodb::types::ReturnValue::UpdatedNew)
+            .key("PK", AttributeValue::S(format!("ALIAS#{}", alias)))
+            .key("SK", AttributeValue::S("META".to_string()))
             .send()
             .await
-            .map_err(|e| Error::Database(e.to_string()))?;
+            .map_err(database_error)?;
 
-        let attrs = result.attributes.ok_or_else(|| Error::Internal("No attributes returned".to_string()))?;
-        let seq_attr = attrs.get("sequence").ok_or_else(|| Error::Internal("No sequence attribute".to_string()))?;
-
-        match seq_attr {
-            AttributeValue::N(n) => n.parse::<u64>().map_err(|e| Error::Internal(e.to_string())),
-            _ => Err(Error::Internal("Invalid sequence type".to_string())),
+        match result.item {
+            Some(item) => from_item(item).map_err(|e| Error::DynamoSerialization(e.to_string())),
+            None => Err(Error::AliasNotFound(alias.to_string())),
         }
     }
 
-    /// Read events from a partition starting at an offset
-    pub async fn read_events(
+    /// Point an alias at a stream, creating the alias if needed
+    ///
+    /// With `expected`, the alias must be unset or point at that stream (or at
+    /// `stream_id` already), else this fails with [`Error::PreconditionFailed`].
+    /// A stream named like the alias would hide it, so that is refused.
+    pub async fn put_alias(
         &self,
+        alias: &str,
         stream_id: &str,
-        partition: u32,
-        from_offset: u64,
-        limit: u32,
-    ) -> Result<Vec<Event>> {
-        let result = self
+        expected: Option<&str>,
+    ) -> Result<StreamAlias> {
+        validate_alias(alias)?;
+        if self.head_stream(alias).await? {
+            return Err(Error::Validation(format!(
+                "A stream named '{}' exists, so it can't be an alias",
+                alias
+            )));
+        }
+        self.get_stream(stream_id).await?;
+
+        let record = StreamAlias {
+            alias: alias.to_string(),
+            stream_id: stream_id.to_string(),
+            updated_at: self.clock.now(),
+        };
+        let mut item: HashMap<String, AttributeValue> =
+            to_item(&record).map_err(|e| Error::DynamoSerialization(e.to_string()))?;
+        item.insert(
+            "PK".to_string(),
+            AttributeValue::S(format!("ALIAS#{}", alias)),
+        );
+        item.insert("SK".to_string(), AttributeValue::S("META".to_string()));
+
+        let mut put = self
             .client
-            .query()
+            .put_item()
             .table_name(&self.table_name)
-            .key_condition_expression("PK = :pk AND SK > :sk")
-            .expression_attribute_values(
-                ":pk",
-                AttributeValue::S(format!("STREAM#{}#P{}", stream_id, partition)),
-            )
-            .expression_attribute_values(
-                ":sk",
-                AttributeValue::S(format!("SEQ#{:020}", from_offset)),
-            )
-            .limit(limit as i32)
-            .send()
-            .await
-            .map_err(|e| Error::Database(e.to_string()))?;
+            .set_item(Some(item));
+        if let Some(expected) = expected {
+            put = put
+                .condition_expression(
+                    "attribute_not_exists(PK) OR #stream = :expected OR #stream = :stream",
+                )
+                .expression_attribute_names("#stream", "stream_id")
+                .expression_attribute_values(":expected", AttributeValue::S(expected.to_string()))
+                .expression_attribute_values(":stream", AttributeValue::S(stream_id.to_string()));
+        }
+        put.send().await.map_err(|e| {
+            if is_conditional_check_failed(&e) {
+                Error::PreconditionFailed(format!(
+                    "Alias '{}' no longer points at stream '{}'",
+                    alias,
+                    expected.unwrap_or_default()
+                ))
+            } else {
+                database_error(e)
+            }
+        })?;
 
-        let events: Vec<Event> = result
-            .items
-            .unwrap_or_default()
-            .into_iter()
-            .filter_map(|item| from_item(item).ok())
-            .collect();
+        Ok(record)
+    }
 
-        Ok(events)
+    /// Delete an alias; the stream it points at is left alone
+    pub async fn delete_alias(&self, alias: &str) -> Result<()> {
+        self.client
+            .delete_item()
+            .table_name(&self.table_name)
+            .key("PK", AttributeValue::S(format!("ALIAS#{}", alias)))
+            .key("SK", AttributeValue::S("META".to_string()))
+            .condition_expression("attribute_exists(PK)")
+            .send()
+            .await
+            .map_err(|e| {
+                if is_conditional_check_failed(&e) {
+                    Error::AliasNotFound(alias.to_string())
+                } else {
+                    database_error(e)
+                }
+            })?;
+        Ok(())
     }
 
     // =========================================================================
-    // Subscription Operations
+    // Event Operations
     // =========================================================================
 
-    /// Create a subscription
-    pub async fn create_subscription(
+    /// Publish events to a stream
+    ///
+    /// Returns one reference per event, in the order of `events`; each also
+    /// carries its `input_index`.
+    pub async fn publish_events(
         &self,
         stream_id: &str,
-        req: &CreateSubscriptionRequest,
-    ) -> Result<Subscription> {
-        // Verify stream exists
-        let stream = self.get_stream(stream_id).await?;
+        events: &[PublishEvent],
+    ) -> Result<Vec<PublishedEvent>> {
+        let stored_events = self.prepare_events(stream_id, events, true).await?;
+        let mut published = Vec::with_capacity(stored_events.len());
+
+        for (input_index, stored_event) in stored_events.into_iter().enumerate() {
+            // Never overwrite an existing event, even if the counter is corrupted
+            self.client
+                .put_item()
+                .table_name(&self.table_name)
+                .set_item(Some(event_item(&stored_event)?))
+                .condition_expression("attribute_not_exists(SK)")
+                .send()
+                .await
+                .map_err(|e| {
+                    if is_conditional_check_failed(&e) {
+                        sequence_collision(&stored_event)
+                    } else {
+                        database_error(e)
+                    }
+                })?;
 
-        let subscription = Subscription::new(stream_id.to_string(), req.subscription_id.clone());
+            published.push(published_event(input_index, stored_event));
+        }
 
-        let mut item: HashMap<String, AttributeValue> = to_item(&subscription).map_err(|e| Error::DynamoSerialization(e.to_string()))?;
-        item.insert("PK".to_string(), AttributeValue::S(format!("STREAM#{}", stream_id)));
-        item.insert("SK".to_string(), AttributeValue::S(format!("SUB#{}", req.subscription_id)));
+        Ok(published)
+    }
+
+    /// Publish derived events and commit the subscription offsets they were
+    /// derived from, in one transaction
+    ///
+    /// For exactly-once processing: either the events are written and the
+    /// source subscription's offsets advance, or neither happens, so a consumer
+    /// that fails partway re-reads the same input rather than publishing its
+    /// output twice. Sequences are reserved before the transaction, so a
+    /// cancelled one leaves a gap in the target's partitions, as a failed
+    /// publish does. `unmodified_since` guards the offsets as in
+    /// [`Self::commit_offsets`], failing with [`Error::CommitConflict`]; it is
+    /// required, so that a caller retrying after a lost response can't publish
+    /// the same output twice. Retries the SDK makes itself carry the same
+    /// client request token, so DynamoDB applies the transaction once.
+    ///
+    /// A transaction holds at most 100 writes, one per event and one per
+    /// partition offset.
+    pub async fn transact_publish_and_commit(
+        &self,
+        target_stream_id: &str,
+        events: &[PublishEvent],
+        source_stream_id: &str,
+        subscription_id: &str,
+        offsets: &[PartitionOffset],
+        unmodified_since: DateTime<Utc>,
+    ) -> Result<Vec<PublishedEvent>> {
+        let writes = events.len() + offsets.len();
+        if writes > TRANSACT_WRITE_MAX_ITEMS {
+            return Err(Error::Validation(format!(
+                "A transactional publish holds at most {} events and offsets combined, got {}",
+                TRANSACT_WRITE_MAX_ITEMS, writes
+            )));
+        }
+        if writes == 0 {
+            return Ok(Vec::new());
+        }
+
+        let stored_events = self.prepare_events(target_stream_id, events, true).await?;
+        let mut items = Vec::with_capacity(writes);
+        for stored_event in &stored_events {
+            let put = Put::builder()
+                .table_name(&self.table_name)
+                .set_item(Some(event_item(stored_event)?))
+                .condition_expression("attribute_not_exists(SK)")
+                .build()
+                .map_err(|e| Error::Internal(e.to_string()))?;
+            items.push(TransactWriteItem::builder().put(put).build());
+        }
+
+        for po in offsets {
+            let update = self.offset_update(
+                source_stream_id,
+                subscription_id,
+                po,
+                Some(unmodified_since),
+            )?;
+            items.push(TransactWriteItem::builder().update(update).build());
+        }
 
-        // Use condition to prevent overwriting
         self.client
-            .put_item()
-            .table_name(&self.table_name)
-            .set_item(Some(item))
-            .condition_expression("attribute_not_exists(PK)")
+            .transact_write_items()
+            .set_transact_items(Some(items))
+            .client_request_token(Uuid::new_v4().to_string())
             .send()
             .await
             .map_err(|e| {
-                if e.to_string().contains("ConditionalCheckFailed") {
-                    Error::SubscriptionAlreadyExists(req.subscription_id.clone())
-                } else {
-                    Error::Database(e.to_string())
+                let Some(TransactWriteItemsError::TransactionCanceledException(cancelled)) =
+                    e.as_service_error()
+                else {
+                    return database_error(e);
+                };
+                // Reasons line up with the items: events first, then offsets
+                let failed = cancelled
+                    .cancellation_reasons()
+                    .iter()
+                    .position(|reason| reason.code() == Some("ConditionalCheckFailed"));
+                match failed {
+                    Some(index) if index < stored_events.len() => {
+                        sequence_collision(&stored_events[index])
+                    }
+                    Some(_) => Error::CommitConflict(format!(
+                        "Subscription {} was committed after this cursor was issued",
+                        subscription_id
+                    )),
+                    None => database_error(e),
                 }
             })?;
 
-        // Initialize offsets based on start_from
-        let initial_offset = match req.start_from {
-            StartFrom::Earliest => 0,
-            StartFrom::Latest => self.get_latest_offset(stream_id, 0).await.unwrap_or(0),
-            StartFrom::Compacted => 0, // Will read from compacted first
-        };
+        Ok(stored_events
+            .into_iter()
+            .enumerate()
+            .map(|(input_index, stored_event)| published_event(input_index, stored_event))
+            .collect())
+    }
 
-        for partition in 0..stream.partition_count {
-            let offset = if matches!(req.start_from, StartFrom::Latest) {
-                self.get_latest_offset(stream_id, partition).await.unwrap_or(0)
+    /// Validate a batch for a stream and assign each event its partition,
+    /// sequence and timestamp, without writing the events themselves
+    ///
+    /// Returns the events to store, in the order of `events`. The stream comes
+    /// from the cache, so if another instance deleted and recreated it since,
+    /// the partition counters no longer match it; then it is read again and
+    /// the batch routed by the stream as it is now. `validate` is false only
+    /// for copies of stored events, which were checked when first published.
+    async fn prepare_events(
+        &self,
+        stream_id: &str,
+        events: &[PublishEvent],
+        validate: bool,
+    ) -> Result<Vec<Event>> {
+        for _ in 0..2 {
+            let stream = self.get_stream_cached(stream_id).await?;
+
+            // Validate the whole batch before writing any of it
+            if validate {
+                let validator = stream.schema.as_ref().map(schema::compile).transpose()?;
+                for (index, event) in events.iter().enumerate() {
+                    stream.check_event_type(event.event_type.as_str())?;
+                    if let Some(validator) = &validator {
+                        schema::validate_data(validator, &event.data, index)?;
+                    }
+                }
+            }
+
+            if events.is_empty() {
+                return Ok(Vec::new());
+            }
+
+            if let Some(stored_events) = self.assign_positions(&stream, events).await? {
+                return Ok(stored_events);
+            }
+            self.streams.invalidate(&self.table_name, stream_id);
+        }
+
+        Err(Error::Internal(format!(
+            "Stream {} changed again while its publish was being retried",
+            stream_id
+        )))
+    }
+
+    /// Assign each event of a validated batch its partition, sequence and
+    /// timestamp, or `None` if `stream` is not the incarnation whose partition
+    /// counters are in the table
+    async fn assign_positions(
+        &self,
+        stream: &Stream,
+        events: &[PublishEvent],
+    ) -> Result<Option<Vec<Event>>> {
+        let stream_id = stream.stream_id.as_str();
+        if stream.max_publish_per_second.is_some() {
+            self.charge_publish_rate(stream, events.len()).await?;
+        }
+
+        let partitioner = stream.partitioner()?;
+        let now = to_timestamp_precision(self.clock.now());
+
+        // Empty-key events take their turns in the rotation as a block, like
+        // sequences below. The counter hands out blocks from 1; turns count
+        // from 0, so the first empty-key event goes to partition 0.
+        let empty_keys = events.iter().filter(|e| e.routing_key().is_empty()).count() as u64;
+        let mut next_turn =
+            if stream.empty_key_strategy == EmptyKeyStrategy::RoundRobin && empty_keys > 0 {
+                let pk = format!("STREAM#{}#ROUND_ROBIN", stream_id);
+                self.reserve_counter(pk, empty_keys).await? - 1
             } else {
-                initial_offset
+                0
+            };
+        let partitions: Vec<u32> = events
+            .iter()
+            .map(|event| match event.routing_key() {
+                "" => {
+                    let turn = next_turn;
+                    next_turn += 1;
+                    partitioner.partition_empty_key(stream.empty_key_strategy, turn)
+                }
+                key => partitioner.partition(key),
+            })
+            .collect();
+
+        // Reserve each partition's sequences (and the global positions) with one
+        // counter update per batch rather than one per event. Concurrent batches
+        // get disjoint blocks, so sequences stay unique; a batch that fails partway
+        // leaves the rest of its block unwritten, a gap readers already skip over
+        // since they query by sequence range.
+        let mut counts: BTreeMap<u32, u64> = BTreeMap::new();
+        for &partition in &partitions {
+            *counts.entry(partition).or_default() += 1;
+        }
+        let mut next_sequence = HashMap::with_capacity(counts.len());
+        let mut timestamps = HashMap::with_capacity(counts.len());
+        for (partition, count) in counts {
+            let Some((first, latest)) = self
+                .reserve_sequences(stream, partition, count, now)
+                .await?
+            else {
+                return Ok(None);
+            };
+            next_sequence.insert(partition, first);
+            timestamps.insert(partition, partition_timestamp(now, latest));
+        }
+        let mut next_global_position = if stream.global_ordering {
+            let pk = format!("STREAM#{}#GLOBAL", stream_id);
+            Some(self.reserve_counter(pk, events.len() as u64).await?)
+        } else {
+            None
+        };
+
+        let mut stored_events = Vec::with_capacity(events.len());
+
+        for (event, partition) in events.iter().zip(partitions) {
+            let sequence = next_sequence[&partition];
+            next_sequence.insert(partition, sequence + 1);
+            let global_position = next_global_position;
+            next_global_position = next_global_position.map(|position| position + 1);
+
+            // A backfilled event keeps its own time: it is neither held to nor
+            // moves the partition's clock, so its partition's timestamps may go
+            // backward and timestamp seeks into it are approximate
+            let (timestamp, wall_clock_timestamp) = match event.timestamp {
+                Some(timestamp) => (to_timestamp_precision(timestamp), Some(now)),
+                None => timestamps[&partition],
             };
-            self.set_offset(stream_id, &req.subscription_id, partition, offset).await?;
+
+            stored_events.push(Event {
+                id: event_id(stream_id, partition, sequence),
+                stream_id: stream_id.to_string(),
+                partition,
+                sequence,
+                global_position,
+                key: event.key.clone(),
+                partition_key: event.partition_key.clone(),
+                order_key: event.order_key,
+                event_type: event.event_type.to_string(),
+                data: event.data.clone(),
+                timestamp,
+                wall_clock_timestamp,
+            });
         }
 
-        Ok(subscription)
+        Ok(Some(stored_events))
     }
 
-    /// Get the latest sequence number for a partition
-    async fn get_latest_offset(&self, stream_id: &str, partition: u32) -> Result<u64> {
+    /// Charge a publish of `count` events against the stream's rate bucket
+    ///
+    /// The bucket is read and written back conditioned on being unchanged, so
+    /// concurrent publishes can't both spend the same tokens.
+    async fn charge_publish_rate(&self, stream: &Stream, count: usize) -> Result<()> {
+        let pk = format!("STREAM#{}#RATE", stream.stream_id);
+
+        for _ in 0..RATE_BUCKET_MAX_ATTEMPTS {
+            let result = self
+                .client
+                .get_item()
+                .table_name(&self.table_name)
+                .key("PK", AttributeValue::S(pk.clone()))
+                .key("SK", AttributeValue::S("COUNTER".to_string()))
+                .consistent_read(true)
+                .send()
+                .await
+                .map_err(database_error)?;
+            let current: Option<TokenBucket> = result
+                .item
+                .map(from_item)
+                .transpose()
+                .map_err(|e| Error::DynamoSerialization(e.to_string()))?;
+
+            let now_ms = self.clock.now().timestamp_millis();
+            let Some(next) = stream.charge_publish(current, count, now_ms)? else {
+                return Ok(());
+            };
+
+            let mut item: HashMap<String, AttributeValue> =
+                to_item(next).map_err(|e| Error::DynamoSerialization(e.to_string()))?;
+            item.insert("PK".to_string(), AttributeValue::S(pk.clone()));
+            item.insert("SK".to_string(), AttributeValue::S("COUNTER".to_string()));
+
+            let mut put = self
+                .client
+                .put_item()
+                .table_name(&self.table_name)
+                .set_item(Some(item));
+            put = match current {
+                Some(previous) => put
+                    .condition_expression("#refilled_at_ms = :refilled_at_ms AND #tokens = :tokens")
+                    .expression_attribute_names("#refilled_at_ms", "refilled_at_ms")
+                    .expression_attribute_names("#tokens", "tokens")
+                    .expression_attribute_values(
+                        ":refilled_at_ms",
+                        AttributeValue::N(previous.refilled_at_ms.to_string()),
+                    )
+                    .expression_attribute_values(
+                        ":tokens",
+                        AttributeValue::N(previous.tokens.to_string()),
+                    ),
+                None => put.condition_expression("attribute_not_exists(PK)"),
+            };
+
+            match put.send().await {
+                Ok(_) => return Ok(()),
+                // Another publish spent tokens first; recompute from its bucket
+                Err(e) if is_conditional_check_failed(&e) => continue,
+                Err(e) => return Err(database_error(e)),
+            }
+        }
+
+        // Lost every race: the stream is busy enough that waiting is fair
+        Err(Error::RateLimited {
+            message: format!(
+                "Stream {} is being published to too quickly",
+                stream.stream_id
+            ),
+            retry_after_secs: 1,
+        })
+    }
+
+    /// Reserve `count` consecutive sequence numbers in a partition, returning the
+    /// first
+    ///
+    /// The partition's counter also records the latest event timestamp. It is
+    /// only moved forward, so a publisher whose clock is behind gets that
+    /// timestamp back to stamp its events with instead of its own.
+    ///
+    /// Returns `None` without reserving anything if the counter is missing or
+    /// belongs to another incarnation of the stream than `stream`.
+    async fn reserve_sequences(
+        &self,
+        stream: &Stream,
+        partition: u32,
+        count: u64,
+        now: DateTime<Utc>,
+    ) -> Result<Option<(u64, Option<DateTime<Utc>>)>> {
+        let pk = format!("STREAM#{}#P{}", stream.stream_id, partition);
+        let created = AttributeValue::N(stream.created_at.timestamp_micros().to_string());
+        // Counters from before the creation time was recorded match any stream
+        let same_stream = "attribute_exists(#seq) \
+            AND (attribute_not_exists(#created) OR #created = :created)";
         let result = self
             .client
-            .get_item()
+            .update_item()
             .table_name(&self.table_name)
-            .key("PK", AttributeValue::S(format!("STREAM#{}#P{}", stream_id, partition)))
+            .key("PK", AttributeValue::S(pk.clone()))
             .key("SK", AttributeValue::S("COUNTER".to_string()))
+            .update_expression("SET #seq = #seq + :inc, #latest = :now")
+            .condition_expression(format!(
+                "{} AND (attribute_not_exists(#latest) OR #latest <= :now)",
+                same_stream
+            ))
+            .expression_attribute_names("#seq", "sequence")
+            .expression_attribute_names("#created", "stream_created_us")
+            .expression_attribute_names("#latest", "latest_timestamp_us")
+            .expression_attribute_values(":inc", AttributeValue::N(count.to_string()))
+            .expression_attribute_values(":created", created.clone())
+            .expression_attribute_values(
+                ":now",
+                AttributeValue::N(now.timestamp_micros().to_string()),
+            )
+            .return_values(ReturnValue::UpdatedNew)
             .send()
-            .await
-            .map_err(|e| Error::Database(e.to_string()))?;
+            .await;
 
-        match result.item {
-            Some(item) => {
-                let seq = item.get("sequence").ok_or_else(|| Error::Internal("No sequence".to_string()))?;
-                match seq {
-                    AttributeValue::N(n) => n.parse::<u64>().map_err(|e| Error::Internal(e.to_string())),
-                    _ => Err(Error::Internal("Invalid sequence type".to_string())),
-                }
-            }
-            None => Ok(0),
+        match result {
+            Ok(output) => return Ok(Some((first_reserved(output.attributes, count)?, None))),
+            // Either the clock is behind the partition's latest event or the
+            // stream is stale, which the update below tells apart
+            Err(e) if is_conditional_check_failed(&e) => {}
+            Err(e) => return Err(database_error(e)),
         }
-    }
 
-    /// Set consumer offset for a partition
-    async fn set_offset(
-        &self,
-        stream_id: &str,
-        subscription_id: &str,
-        partition: u32,
-        offset: u64,
-    ) -> Result<()> {
-        let mut item = HashMap::new();
-        item.insert(
-            "PK".to_string(),
-            AttributeValue::S(format!("STREAM#{}#SUB#{}", stream_id, subscription_id)),
-        );
-        item.insert(
-            "SK".to_string(),
-            AttributeValue::S(format!("OFFSET#P{}", partition)),
-        );
-        item.insert("offset".to_string(), AttributeValue::N(offset.to_string()));
-        item.insert(
-            "committed_at".to_string(),
-            AttributeValue::S(Utc::now().to_rfc3339()),
-        );
+        let result = self
+            .client
+            .update_item()
+            .table_name(&self.table_name)
+            .key("PK", AttributeValue::S(pk))
+            .key("SK", AttributeValue::S("COUNTER".to_string()))
+            .update_expression("SET #seq = #seq + :inc")
+            .condition_expression(same_stream)
+            .expression_attribute_names("#seq", "sequence")
+            .expression_attribute_names("#created", "stream_created_us")
+            .expression_attribute_values(":inc", AttributeValue::N(count.to_string()))
+            .expression_attribute_values(":created", created)
+            .return_values(ReturnValue::AllNew)
+            .send()
+            .await;
 
-        self.client
-            .put_item()
+        let result = match result {
+            Ok(result) => result,
+            Err(e) if is_conditional_check_failed(&e) => return Ok(None),
+            Err(e) => return Err(database_error(e)),
+        };
+
+        let latest = result
+            .attributes
+            .as_ref()
+            .and_then(|a| a.get("latest_timestamp_us"));
+        let latest = match latest {
+            Some(AttributeValue::N(n)) => {
+                let micros = n
+                    .parse::<i64>()
+                    .map_err(|e| Error::Internal(e.to_string()))?;
+                DateTime::from_timestamp_micros(micros)
+            }
+            _ => None,
+        };
+        Ok(Some((first_reserved(result.attributes, count)?, latest)))
+    }
+
+    /// Atomically advance a counter item by `count`, returning the first value
+    /// of the reserved block
+    async fn reserve_counter(&self, pk: String, count: u64) -> Result<u64> {
+        let result = self
+            .client
+            .update_item()
             .table_name(&self.table_name)
-            .set_item(Some(item))
+            .key("PK", AttributeValue::S(pk))
+            .key("SK", AttributeValue::S("COUNTER".to_string()))
+            .update_expression("SET #seq = #seq + :inc")
+            .expression_attribute_names("#seq", "sequence")
+            .expression_attribute_values(":inc", AttributeValue::N(count.to_string()))
+            .return_values(ReturnValue::UpdatedNew)
             .send()
             .await
-            .map_err(|e| Error::Database(e.to_string()))?;
+            .map_err(database_error)?;
 
-        Ok(())
+        first_reserved(result.attributes, count)
     }
 
-    /// Get consumer offset for a partition
-    pub async fn get_offset(
+    /// Read events from a partition starting at an offset
+    ///
+    /// Forward reads return events after `from_offset`, oldest first. Reverse
+    /// reads (`scan_forward = false`) return events before `from_offset`, newest
+    /// first; a `from_offset` of 0 starts from the tail of the partition.
+    pub async fn read_events(
         &self,
         stream_id: &str,
-        subscription_id: &str,
         partition: u32,
-    ) -> Result<u64> {
-        let result = self
-            .client
-            .get_item()
-            .table_name(&self.table_name)
-            .key(
-                "PK",
-                AttributeValue::S(format!("STREAM#{}#SUB#{}", stream_id, subscription_id)),
-            )
-            .key("SK", AttributeValue::S(format!("OFFSET#P{}", partition)))
-            .send()
+        from_offset: u64,
+        limit: u32,
+        scan_forward: bool,
+    ) -> Result<Vec<Event>> {
+        self.query_events(stream_id, partition, from_offset, limit, scan_forward, None)
             .await
-            .map_err(|e| Error::Database(e.to_string()))?;
+    }
 
-        match result.item {
-            Some(item) => {
-                let offset = item.get("offset").ok_or_else(|| Error::Internal("No offset".to_string()))?;
-                match offset {
-                    AttributeValue::N(n) => n.parse::<u64>().map_err(|e| Error::Internal(e.to_string())),
-                    _ => Err(Error::Internal("Invalid offset type".to_string())),
-                }
+    /// Read events after an offset, fetching only the projected attributes
+    ///
+    /// Fields left out of the projection come back empty on the returned events.
+    pub async fn read_events_projected(
+        &self,
+        stream_id: &str,
+        partition: u32,
+        from_offset: u64,
+        limit: u32,
+        projection: &EventProjection,
+    ) -> Result<Vec<Event>> {
+        self.query_events(
+            stream_id,
+            partition,
+            from_offset,
+            limit,
+            true,
+            Some(projection),
+        )
+        .await
+    }
+
+    /// Fetch specific events from a partition with BatchGetItem
+    ///
+    /// Found events come back in the order of `sequences`, repeats once; the
+    /// sequences with no event are listed in `missing`.
+    pub async fn batch_get_events(
+        &self,
+        stream_id: &str,
+        partition: u32,
+        sequences: &[u64],
+    ) -> Result<BatchGetEventsResponse> {
+        let stream = self.get_stream(stream_id).await?;
+        if partition >= stream.partition_count {
+            return Err(Error::Validation(format!(
+                "Partition {} out of range (stream has {})",
+                partition, stream.partition_count
+            )));
+        }
+
+        // BatchGetItem rejects a request that names the same key twice
+        let mut unique: Vec<u64> = Vec::with_capacity(sequences.len());
+        for &sequence in sequences {
+            if !unique.contains(&sequence) {
+                unique.push(sequence);
+            }
+        }
+
+        let pk = format!("STREAM#{}#P{}", stream_id, partition);
+        let keys = unique
+            .iter()
+            .map(|sequence| {
+                HashMap::from([
+                    ("PK".to_string(), AttributeValue::S(pk.clone())),
+                    (
+                        "SK".to_string(),
+                        AttributeValue::S(format!("SEQ#{:020}", sequence)),
+                    ),
+                ])
+            })
+            .collect();
+        let mut found: HashMap<u64, Event> = HashMap::with_capacity(unique.len());
+        for item in self.batch_get(keys).await? {
+            let event: Event =
+                from_item(item).map_err(|e| Error::DynamoSerialization(e.to_string()))?;
+            found.insert(event.sequence, event);
+        }
+
+        let mut events = Vec::with_capacity(found.len());
+        let mut missing = Vec::new();
+        for sequence in unique {
+            match found.remove(&sequence) {
+                Some(event) => events.push(event),
+                None => missing.push(sequence),
             }
-            None => Err(Error::SubscriptionNotFound(subscription_id.to_string())),
         }
+        Ok(BatchGetEventsResponse { events, missing })
     }
 
-    /// Commit offsets from cursor
-    pub async fn commit_offsets(
+    /// Replace an event's `data` with a [`redaction_tombstone`], keeping its
+    /// sequence so the partition has no gap
+    ///
+    /// The event's compacted state is redacted too, see [`Self::redact_compacted`].
+    pub async fn redact_event(
         &self,
         stream_id: &str,
-        subscription_id: &str,
-        offsets: &[PartitionOffset],
-    ) -> Result<()> {
-        for po in offsets {
-            self.set_offset(stream_id, subscription_id, po.partition, po.offset).await?;
+        partition: u32,
+        sequence: u64,
+    ) -> Result<Event> {
+        let stream = self.get_stream(stream_id).await?;
+        if partition >= stream.partition_count {
+            return Err(Error::Validation(format!(
+                "Partition {} out of range (stream has {})",
+                partition, stream.partition_count
+            )));
         }
-        Ok(())
-    }
 
-    /// Get subscription
-    pub async fn get_subscription(&self, stream_id: &str, subscription_id: &str) -> Result<Subscription> {
+        let tombstone = redaction_tombstone(self.clock.now());
+        let tombstone_value: AttributeValue = to_attribute_value(&tombstone)
+            .map_err(|e| Error::DynamoSerialization(e.to_string()))?;
         let result = self
             .client
-            .get_item()
+            .update_item()
             .table_name(&self.table_name)
-            .key("PK", AttributeValue::S(format!("STREAM#{}", stream_id)))
-            .key("SK", AttributeValue::S(format!("SUB#{}", subscription_id)))
+            .key(
+                "PK",
+                AttributeValue::S(format!("STREAM#{}#P{}", stream_id, partition)),
+            )
+            .key("SK", AttributeValue::S(format!("SEQ#{:020}", sequence)))
+            .update_expression("SET #data = :tombstone")
+            .condition_expression("attribute_exists(SK)")
+            .expression_attribute_names("#data", "data")
+            .expression_attribute_values(":tombstone", tombstone_value.clone())
+            .return_values(ReturnValue::AllNew)
             .send()
             .await
-            .map_err(|e| Error::Database(e.to_string()))?;
+            .map_err(|e| {
+                if is_conditional_check_failed(&e) {
+                    Error::EventNotFound(event_id(stream_id, partition, sequence))
+                } else {
+                    database_error(e)
+                }
+            })?;
 
-        match result.item {
-            Some(item) => from_item(item).map_err(|e| Error::DynamoSerialization(e.to_string())),
-            None => Err(Error::SubscriptionNotFound(subscription_id.to_string())),
+        let item = result
+            .attributes
+            .ok_or_else(|| Error::Internal("No attributes returned".to_string()))?;
+        let mut event: Event =
+            from_item(item).map_err(|e| Error::DynamoSerialization(e.to_string()))?;
+        if event.id.is_empty() {
+            event.id = event_id(stream_id, partition, sequence);
         }
+
+        self.redact_compacted(stream_id, &event.key, partition, sequence, &tombstone)
+            .await?;
+
+        Ok(event)
+    }
+
+    async fn query_events(
+        &self,
+        stream_id: &str,
+        partition: u32,
+        from_offset: u64,
+        limit: u32,
+        scan_forward: bool,
+        projection: Option<&EventProjection>,
+    ) -> Result<Vec<Event>> {
+        let mut query = self
+            .client
+            .query()
+            .table_name(&self.table_name)
+            .expression_attribute_values(
+                ":pk",
+                AttributeValue::S(format!("STREAM#{}#P{}", stream_id, partition)),
+            );
+
+        // Attribute names like `data` and `key` are reserved words, so every
+        // projected attribute goes through a placeholder
+        if let Some(projection) = projection {
+            let mut placeholders = Vec::new();
+            for (i, attribute) in projection.attributes().into_iter().enumerate() {
+                let placeholder = format!("#f{}", i);
+                query = query.expression_attribute_names(&placeholder, attribute);
+                placeholders.push(placeholder);
+            }
+            query = query.projection_expression(placeholders.join(", "));
+        }
+
+        let query = if scan_forward {
+            query
+                .key_condition_expression("PK = :pk AND SK > :sk")
+                .expression_attribute_values(
+                    ":sk",
+                    AttributeValue::S(format!("SEQ#{:020}", from_offset)),
+                )
+        } else {
+            // BETWEEN keeps the partition's COUNTER item out of the range
+            let upper = match from_offset {
+                0 => u64::MAX,
+                offset => offset - 1,
+            };
+            query
+                .key_condition_expression("PK = :pk AND SK BETWEEN :lower AND :upper")
+                .expression_attribute_values(":lower", AttributeValue::S(format!("SEQ#{:020}", 0)))
+                .expression_attribute_values(
+                    ":upper",
+                    AttributeValue::S(format!("SEQ#{:020}", upper)),
+                )
+                .scan_index_forward(false)
+        };
+
+        let result = query
+            .limit(limit as i32)
+            .send()
+            .await
+            .map_err(database_error)?;
+
+        let events: Vec<Event> = result
+            .items
+            .unwrap_or_default()
+            .into_iter()
+            .map(|mut item| {
+                if projection.is_some() {
+                    fill_unprojected(&mut item);
+                }
+                item
+            })
+            .filter_map(|item| from_item::<_, Event>(item).ok())
+            .map(|mut event| {
+                // Events stored before IDs existed get theirs derived on read
+                if event.id.is_empty() {
+                    event.id = event_id(&event.stream_id, event.partition, event.sequence);
+                }
+                event
+            })
+            .collect();
+
+        Ok(events)
     }
 
     // =========================================================================
-    // Compaction Operations
+    // Subscription Operations
     // =========================================================================
 
-    /// Store compacted state for a key
-    pub async fn put_compacted(&self, event: &CompactedEvent) -> Result<()> {
-        let mut item: HashMap<String, AttributeValue> = to_item(event).map_err(|e| Error::DynamoSerialization(e.to_string()))?;
+    /// Create a subscription
+    pub async fn create_subscription(
+        &self,
+        stream_id: &str,
+        req: &CreateSubscriptionRequest,
+    ) -> Result<Subscription> {
+        req.validate()?;
+
+        // Verify stream exists
+        let stream = self.get_stream(stream_id).await?;
+
+        let mut subscription =
+            Subscription::new(stream_id.to_string(), req.subscription_id.clone());
+        subscription.created_at = self.clock.now();
+        subscription.default_limit = req.default_limit;
+        subscription.default_wait_seconds = req.default_wait_seconds;
+        subscription.cursor_encoding = req.cursor_encoding;
+        subscription.filter = req.filter.clone();
+
+        let mut item: HashMap<String, AttributeValue> =
+            to_item(&subscription).map_err(|e| Error::DynamoSerialization(e.to_string()))?;
         item.insert(
             "PK".to_string(),
-            AttributeValue::S(format!("STREAM#{}#COMPACT", event.stream_id)),
+            AttributeValue::S(format!("STREAM#{}", stream_id)),
         );
         item.insert(
             "SK".to_string(),
-            AttributeValue::S(format!("KEY#{}", event.key)),
+            AttributeValue::S(format!("SUB#{}", req.subscription_id)),
         );
 
+        // Use condition to prevent overwriting
         self.client
             .put_item()
             .table_name(&self.table_name)
             .set_item(Some(item))
+            .condition_expression("attribute_not_exists(PK)")
             .send()
             .await
-            .map_err(|e| Error::Database(e.to_string()))?;
+            .map_err(|e| {
+                if is_conditional_check_failed(&e) {
+                    Error::SubscriptionAlreadyExists(req.subscription_id.clone())
+                } else {
+                    database_error(e)
+                }
+            })?;
 
-        Ok(())
+        // Initialize offsets based on start_from
+        let offsets = self.start_offsets(&stream, &req.start_from).await?;
+        self.commit_offsets(stream_id, &req.subscription_id, &offsets, None)
+            .await?;
+
+        Ok(subscription)
     }
 
-    /// Get compacted state for a key
-    pub async fn get_compacted(&self, stream_id: &str, key: &str) -> Result<Option<CompactedEvent>> {
+    /// Offsets for each partition corresponding to a starting position
+    async fn start_offsets(
+        &self,
+        stream: &Stream,
+        start_from: &StartFrom,
+    ) -> Result<Vec<PartitionOffset>> {
+        let mut offsets = Vec::with_capacity(stream.partition_count as usize);
+        for partition in 0..stream.partition_count {
+            let offset = match start_from {
+                StartFrom::Earliest => 0,
+                StartFrom::Latest => self
+                    .get_latest_offset(&stream.stream_id, partition)
+                    .await
+                    .unwrap_or(0),
+                StartFrom::Compacted => 0, // Will read from compacted first
+                StartFrom::Timestamp(at) => {
+                    self.offset_at_time(&stream.stream_id, partition, *at)
+                        .await?
+                }
+            };
+            offsets.push(PartitionOffset { partition, offset });
+        }
+        Ok(offsets)
+    }
+
+    /// List all subscriptions on a stream, following the query past 1MB pages
+    pub async fn list_subscriptions(&self, stream_id: &str) -> Result<Vec<Subscription>> {
+        let mut subscriptions: Vec<Subscription> = Vec::new();
+        let mut start_key = None;
+        loop {
+            let result = self
+                .client
+                .query()
+                .table_name(&self.table_name)
+                .key_condition_expression("PK = :pk AND begins_with(SK, :prefix)")
+                .expression_attribute_values(
+                    ":pk",
+                    AttributeValue::S(format!("STREAM#{}", stream_id)),
+                )
+                .expression_attribute_values(":prefix", AttributeValue::S("SUB#".to_string()))
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await
+                .map_err(database_error)?;
+
+            subscriptions.extend(
+                result
+                    .items
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|item| from_item(item).ok()),
+            );
+            start_key = result.last_evaluated_key;
+            if start_key.is_none() {
+                return Ok(subscriptions);
+            }
+        }
+    }
+
+    /// Reposition every subscription on a stream, as [`Self::seek_subscription`]
+    ///
+    /// One subscription failing doesn't stop the rest; it is reported in
+    /// `failed` and left where it was.
+    pub async fn seek_all_subscriptions(
+        &self,
+        stream_id: &str,
+        to: &StartFrom,
+    ) -> Result<SeekAllResponse> {
+        self.get_stream(stream_id).await?;
+        let subscriptions = self.list_subscriptions(stream_id).await?;
+
+        let mut response = SeekAllResponse {
+            repositioned: 0,
+            failed: Vec::new(),
+        };
+        for subscription in subscriptions {
+            match self
+                .seek_subscription(stream_id, &subscription.subscription_id, to)
+                .await
+            {
+                Ok(_) => response.repositioned += 1,
+                Err(e) => response.failed.push(SubscriptionSeekFailure {
+                    subscription_id: subscription.subscription_id,
+                    error: e.to_response(),
+                }),
+            }
+        }
+        Ok(response)
+    }
+
+    /// Reposition a subscription to the earliest or latest events
+    ///
+    /// Resets both committed and consume-mode offsets, so the next poll in
+    /// either mode starts from the new position.
+    pub async fn seek_subscription(
+        &self,
+        stream_id: &str,
+        subscription_id: &str,
+        to: &StartFrom,
+    ) -> Result<Vec<PartitionOffset>> {
+        let stream = self.get_stream(stream_id).await?;
+        self.get_subscription(stream_id, subscription_id).await?;
+
+        let offsets = self.start_offsets(&stream, to).await?;
+        self.commit_offsets(stream_id, subscription_id, &offsets, None)
+            .await?;
+        self.set_delivered_offsets(stream_id, subscription_id, &offsets)
+            .await?;
+        Ok(offsets)
+    }
+
+    /// Committed offset versus latest sequence for each partition
+    pub async fn subscription_lag(
+        &self,
+        stream_id: &str,
+        subscription_id: &str,
+    ) -> Result<SubscriptionLag> {
+        let stream = self.get_stream(stream_id).await?;
+        self.get_subscription(stream_id, subscription_id).await?;
+
+        let latest = self.latest_sequences(&stream).await?;
+        let mut partitions = Vec::with_capacity(latest.len());
+        for po in latest {
+            let committed = self
+                .get_offset(stream_id, subscription_id, po.partition)
+                .await?;
+            partitions.push(PartitionLag::new(po.partition, committed, po.offset));
+        }
+
+        Ok(SubscriptionLag {
+            stream_id: stream_id.to_string(),
+            subscription_id: subscription_id.to_string(),
+            total_lag: partitions.iter().map(|p| p.lag).sum(),
+            partitions,
+        })
+    }
+
+    /// Delete a subscription along with its committed and consume-mode
+    /// offsets and stored cursor tokens
+    pub async fn delete_subscription(&self, stream_id: &str, subscription_id: &str) -> Result<()> {
+        self.get_stream(stream_id).await?;
+        self.get_subscription(stream_id, subscription_id).await?;
+        self.delete_subscription_items(stream_id, subscription_id)
+            .await
+    }
+
+    /// Delete a subscription's config, offsets and cursor tokens
+    async fn delete_subscription_items(
+        &self,
+        stream_id: &str,
+        subscription_id: &str,
+    ) -> Result<()> {
+        self.client
+            .delete_item()
+            .table_name(&self.table_name)
+            .key("PK", AttributeValue::S(format!("STREAM#{}", stream_id)))
+            .key("SK", AttributeValue::S(format!("SUB#{}", subscription_id)))
+            .send()
+            .await
+            .map_err(database_error)?;
+
+        // OFFSET# and DELIVERED# items share the subscription's partition key
+        self.delete_partition(&format!("STREAM#{}#SUB#{}", stream_id, subscription_id))
+            .await?;
+        self.delete_partition(&cursor_token_pk(stream_id, subscription_id))
+            .await
+    }
+
+    /// Store a poll cursor server-side and return the token that names it
+    ///
+    /// Tokens expire (by TTL) [`CURSOR_TOKEN_TTL_HOURS`] after they are last
+    /// issued. The same state always gets the same token (see
+    /// [`CursorState::token`]), so storing it again only pushes that back.
+    pub async fn put_cursor_token(&self, state: &CursorState) -> Result<String> {
+        let token = state.token()?;
+
+        let mut item: HashMap<String, AttributeValue> =
+            to_item(state).map_err(|e| Error::DynamoSerialization(e.to_string()))?;
+        item.insert(
+            "PK".to_string(),
+            AttributeValue::S(cursor_token_pk(&state.stream_id, &state.subscription_id)),
+        );
+        item.insert(
+            "SK".to_string(),
+            AttributeValue::S(format!("TOKEN#{}", token)),
+        );
+        let expires_at =
+            (self.clock.now() + chrono::Duration::hours(CURSOR_TOKEN_TTL_HOURS)).timestamp();
+        item.insert(
+            "expires_at".to_string(),
+            AttributeValue::N(expires_at.to_string()),
+        );
+
+        self.client
+            .put_item()
+            .table_name(&self.table_name)
+            .set_item(Some(item))
+            .send()
+            .await
+            .map_err(database_error)?;
+
+        Ok(token)
+    }
+
+    /// Resolve a token issued by [`Self::put_cursor_token`] for this subscription
+    ///
+    /// A token issued to another subscription is not found, like an unknown one.
+    pub async fn get_cursor_token(
+        &self,
+        stream_id: &str,
+        subscription_id: &str,
+        token: &str,
+    ) -> Result<CursorState> {
         let result = self
             .client
             .get_item()
             .table_name(&self.table_name)
-            .key("PK", AttributeValue::S(format!("STREAM#{}#COMPACT", stream_id)))
-            .key("SK", AttributeValue::S(format!("KEY#{}", key)))
+            .key(
+                "PK",
+                AttributeValue::S(cursor_token_pk(stream_id, subscription_id)),
+            )
+            .key("SK", AttributeValue::S(format!("TOKEN#{}", token)))
             .send()
             .await
-            .map_err(|e| Error::Database(e.to_string()))?;
+            .map_err(database_error)?;
 
         match result.item {
-            Some(item) => Ok(Some(from_item(item).map_err(|e| Error::DynamoSerialization(e.to_string()))?)),
-            None => Ok(None),
+            Some(item) => from_item(item).map_err(|e| Error::DynamoSerialization(e.to_string())),
+            None => Err(Error::InvalidCursor {
+                message: "Unknown or expired cursor token".to_string(),
+                details: None,
+            }),
         }
     }
 
-    /// List all compacted events for a stream
-    pub async fn list_compacted(&self, stream_id: &str) -> Result<Vec<CompactedEvent>> {
+    /// Get the latest sequence number for a partition
+    pub async fn get_latest_offset(&self, stream_id: &str, partition: u32) -> Result<u64> {
+        let result = self
+            .client
+            .get_item()
+            .table_name(&self.table_name)
+            .key(
+                "PK",
+                AttributeValue::S(format!("STREAM#{}#P{}", stream_id, partition)),
+            )
+            .key("SK", AttributeValue::S("COUNTER".to_string()))
+            .send()
+            .await
+            .map_err(database_error)?;
+
+        match result.item {
+            Some(item) => {
+                let seq = item
+                    .get("sequence")
+                    .ok_or_else(|| Error::Internal("No sequence".to_string()))?;
+                match seq {
+                    AttributeValue::N(n) => {
+                        n.parse::<u64>().map_err(|e| Error::Internal(e.to_string()))
+                    }
+                    _ => Err(Error::Internal("Invalid sequence type".to_string())),
+                }
+            }
+            None => Ok(0),
+        }
+    }
+
+    /// Sequence of the newest event stored in a partition (0 if it has none)
+    ///
+    /// Unlike [`Self::get_latest_offset`], this skips sequences that were
+    /// reserved but never written, e.g. by a batch that failed partway, so a
+    /// reader that has everything stored isn't told more is coming.
+    pub async fn get_latest_written_offset(&self, stream_id: &str, partition: u32) -> Result<u64> {
         let result = self
             .client
             .query()
             .table_name(&self.table_name)
-            .key_condition_expression("PK = :pk AND begins_with(SK, :prefix)")
+            .key_condition_expression("PK = :pk AND begins_with(SK, :seq)")
             .expression_attribute_values(
                 ":pk",
-                AttributeValue::S(format!("STREAM#{}#COMPACT", stream_id)),
+                AttributeValue::S(format!("STREAM#{}#P{}", stream_id, partition)),
             )
-            .expression_attribute_values(":prefix", AttributeValue::S("KEY#".to_string()))
+            .expression_attribute_values(":seq", AttributeValue::S("SEQ#".to_string()))
+            .projection_expression("#seq")
+            .expression_attribute_names("#seq", "sequence")
+            .scan_index_forward(false)
+            .limit(1)
             .send()
             .await
-            .map_err(|e| Error::Database(e.to_string()))?;
+            .map_err(database_error)?;
 
-        let events: Vec<CompactedEvent> = result
-            .items
-            .unwrap_or_default()
-            .into_iter()
-            .filter_map(|item| from_item(item).ok())
-            .collect();
+        let Some(item) = result.items.unwrap_or_default().into_iter().next() else {
+            return Ok(0);
+        };
+        match item.get("sequence") {
+            Some(AttributeValue::N(n)) => {
+                n.parse::<u64>().map_err(|e| Error::Internal(e.to_string()))
+            }
+            _ => Err(Error::Internal("Invalid sequence type".to_string())),
+        }
+    }
 
-        Ok(events)
+    /// Transaction write setting consumer offset for a partition
+    ///
+    /// With `unmodified_since`, the write is conditioned on the partition not
+    /// having been committed after it.
+    fn offset_update(
+        &self,
+        stream_id: &str,
+        subscription_id: &str,
+        po: &PartitionOffset,
+        unmodified_since: Option<DateTime<Utc>>,
+    ) -> Result<Update> {
+        let mut update = Update::builder()
+            .table_name(&self.table_name)
+            .key(
+                "PK",
+                AttributeValue::S(format!("STREAM#{}#SUB#{}", stream_id, subscription_id)),
+            )
+            .key("SK", AttributeValue::S(format!("OFFSET#P{}", po.partition)))
+            .update_expression("SET #offset = :offset, #committed_at = :committed_at")
+            .expression_attribute_names("#offset", "offset")
+            .expression_attribute_names("#committed_at", "committed_at")
+            .expression_attribute_values(":offset", AttributeValue::N(po.offset.to_string()))
+            .expression_attribute_values(
+                ":committed_at",
+                AttributeValue::S(committed_at_value(self.clock.now())),
+            );
+        if let Some(since) = unmodified_since {
+            // Fixed-width timestamps, so comparing them as strings is chronological
+            update = update
+                .condition_expression(
+                    "attribute_not_exists(#committed_at) OR #committed_at <= :since",
+                )
+                .expression_attribute_values(
+                    ":since",
+                    AttributeValue::S(committed_at_value(since)),
+                );
+        }
+
+        update.build().map_err(|e| Error::Internal(e.to_string()))
+    }
+
+    /// Get consumer offset for a partition
+    ///
+    /// Strongly consistent, so a poll right after a commit sees the new offset.
+    pub async fn get_offset(
+        &self,
+        stream_id: &str,
+        subscription_id: &str,
+        partition: u32,
+    ) -> Result<u64> {
+        let result = self
+            .client
+            .get_item()
+            .table_name(&self.table_name)
+            .key(
+                "PK",
+                AttributeValue::S(format!("STREAM#{}#SUB#{}", stream_id, subscription_id)),
+            )
+            .key("SK", AttributeValue::S(format!("OFFSET#P{}", partition)))
+            .consistent_read(true)
+            .send()
+            .await
+            .map_err(database_error)?;
+
+        match result.item {
+            Some(item) => {
+                let offset = item
+                    .get("offset")
+                    .ok_or_else(|| Error::Internal("No offset".to_string()))?;
+                match offset {
+                    AttributeValue::N(n) => {
+                        n.parse::<u64>().map_err(|e| Error::Internal(e.to_string()))
+                    }
+                    _ => Err(Error::Internal("Invalid offset type".to_string())),
+                }
+            }
+            None => Err(Error::SubscriptionNotFound(subscription_id.to_string())),
+        }
+    }
+
+    /// Highest sequence in a partition published at or before `timestamp`
+    ///
+    /// Scans back from the tail, relying on timestamps never decreasing within a
+    /// partition. Returns 0 if every event is newer.
+    pub async fn offset_at_time(
+        &self,
+        stream_id: &str,
+        partition: u32,
+        timestamp: DateTime<Utc>,
+    ) -> Result<u64> {
+        let mut before = 0;
+        loop {
+            let events = self
+                .read_events(
+                    stream_id,
+                    partition,
+                    before,
+                    OFFSET_AT_TIME_PAGE_SIZE,
+                    false,
+                )
+                .await?;
+
+            if let Some(event) = events.iter().find(|e| e.timestamp <= timestamp) {
+                return Ok(event.sequence);
+            }
+            match events.last() {
+                Some(oldest) if oldest.sequence > 1 => before = oldest.sequence,
+                _ => return Ok(0),
+            }
+        }
+    }
+
+    /// List committed offsets for a subscription, ordered by partition
+    pub async fn list_offsets(
+        &self,
+        stream_id: &str,
+        subscription_id: &str,
+    ) -> Result<Vec<ConsumerOffset>> {
+        let result = self
+            .client
+            .query()
+            .table_name(&self.table_name)
+            .key_condition_expression("PK = :pk AND begins_with(SK, :prefix)")
+            .expression_attribute_values(
+                ":pk",
+                AttributeValue::S(format!("STREAM#{}#SUB#{}", stream_id, subscription_id)),
+            )
+            .expression_attribute_values(":prefix", AttributeValue::S("OFFSET#".to_string()))
+            .send()
+            .await
+            .map_err(database_error)?;
+
+        let mut offsets = Vec::new();
+        for item in result.items.unwrap_or_default() {
+            let partition = match item.get("SK") {
+                Some(AttributeValue::S(sk)) => sk
+                    .strip_prefix("OFFSET#P")
+                    .and_then(|p| p.parse::<u32>().ok())
+                    .ok_or_else(|| Error::Internal(format!("Invalid offset key: {}", sk)))?,
+                _ => return Err(Error::Internal("No SK".to_string())),
+            };
+            let offset = match item.get("offset") {
+                Some(AttributeValue::N(n)) => n
+                    .parse::<u64>()
+                    .map_err(|e| Error::Internal(e.to_string()))?,
+                _ => return Err(Error::Internal("Invalid offset type".to_string())),
+            };
+            let committed_at = match item.get("committed_at") {
+                Some(AttributeValue::S(ts)) => DateTime::parse_from_rfc3339(ts)
+                    .map_err(|e| Error::Internal(e.to_string()))?
+                    .with_timezone(&Utc),
+                _ => return Err(Error::Internal("No committed_at".to_string())),
+            };
+
+            offsets.push(ConsumerOffset {
+                stream_id: stream_id.to_string(),
+                subscription_id: subscription_id.to_string(),
+                partition,
+                offset,
+                committed_at,
+            });
+        }
+
+        // SK sorts lexically (P10 before P2)
+        offsets.sort_by_key(|o| o.partition);
+        Ok(offsets)
+    }
+
+    /// Commit offsets from cursor
+    ///
+    /// With `unmodified_since` (the cursor's `committed_at`), fails with
+    /// [`Error::CommitConflict`] if another commit landed on any of the
+    /// partitions after it. The partitions are written in one transaction, so
+    /// a conflict leaves every one of them as it was; a stream with more than
+    /// `TRANSACT_WRITE_MAX_ITEMS` partitions commits each group of that many
+    /// in a transaction of its own.
+    pub async fn commit_offsets(
+        &self,
+        stream_id: &str,
+        subscription_id: &str,
+        offsets: &[PartitionOffset],
+        unmodified_since: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        for chunk in offsets.chunks(TRANSACT_WRITE_MAX_ITEMS) {
+            let items = chunk
+                .iter()
+                .map(|po| {
+                    self.offset_update(stream_id, subscription_id, po, unmodified_since)
+                        .map(|update| TransactWriteItem::builder().update(update).build())
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            self.client
+                .transact_write_items()
+                .set_transact_items(Some(items))
+                .client_request_token(Uuid::new_v4().to_string())
+                .send()
+                .await
+                .map_err(|e| {
+                    let conflict = match e.as_service_error() {
+                        Some(TransactWriteItemsError::TransactionCanceledException(cancelled)) => {
+                            cancelled
+                                .cancellation_reasons()
+                                .iter()
+                                .any(|reason| reason.code() == Some("ConditionalCheckFailed"))
+                        }
+                        _ => false,
+                    };
+                    if conflict {
+                        Error::CommitConflict(format!(
+                            "Subscription {} was committed after this cursor was issued",
+                            subscription_id
+                        ))
+                    } else {
+                        database_error(e)
+                    }
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Get the highest offset handed out by a consume-mode poll, if any
+    pub async fn get_delivered_offset(
+        &self,
+        stream_id: &str,
+        subscription_id: &str,
+        partition: u32,
+    ) -> Result<Option<u64>> {
+        let result = self
+            .client
+            .get_item()
+            .table_name(&self.table_name)
+            .key(
+                "PK",
+                AttributeValue::S(format!("STREAM#{}#SUB#{}", stream_id, subscription_id)),
+            )
+            .key("SK", AttributeValue::S(format!("DELIVERED#P{}", partition)))
+            .send()
+            .await
+            .map_err(database_error)?;
+
+        match result.item.as_ref().and_then(|item| item.get("offset")) {
+            Some(AttributeValue::N(n)) => n
+                .parse::<u64>()
+                .map(Some)
+                .map_err(|e| Error::Internal(e.to_string())),
+            Some(_) => Err(Error::Internal("Invalid offset type".to_string())),
+            None => Ok(None),
+        }
+    }
+
+    /// Record the offsets handed out by a consume-mode poll
+    pub async fn set_delivered_offsets(
+        &self,
+        stream_id: &str,
+        subscription_id: &str,
+        offsets: &[PartitionOffset],
+    ) -> Result<()> {
+        for po in offsets {
+            let mut item = HashMap::new();
+            item.insert(
+                "PK".to_string(),
+                AttributeValue::S(format!("STREAM#{}#SUB#{}", stream_id, subscription_id)),
+            );
+            item.insert(
+                "SK".to_string(),
+                AttributeValue::S(format!("DELIVERED#P{}", po.partition)),
+            );
+            item.insert(
+                "offset".to_string(),
+                AttributeValue::N(po.offset.to_string()),
+            );
+            item.insert(
+                "delivered_at".to_string(),
+                AttributeValue::S(self.clock.now().to_rfc3339()),
+            );
+
+            self.client
+                .put_item()
+                .table_name(&self.table_name)
+                .set_item(Some(item))
+                .send()
+                .await
+                .map_err(database_error)?;
+        }
+        Ok(())
+    }
+
+    /// Get subscription
+    pub async fn get_subscription(
+        &self,
+        stream_id: &str,
+        subscription_id: &str,
+    ) -> Result<Subscription> {
+        let result = self
+            .client
+            .get_item()
+            .table_name(&self.table_name)
+            .key("PK", AttributeValue::S(format!("STREAM#{}", stream_id)))
+            .key("SK", AttributeValue::S(format!("SUB#{}", subscription_id)))
+            .send()
+            .await
+            .map_err(database_error)?;
+
+        match result.item {
+            Some(item) => from_item(item).map_err(|e| Error::DynamoSerialization(e.to_string())),
+            None => Err(Error::SubscriptionNotFound(subscription_id.to_string())),
+        }
+    }
+
+    /// Stamp a subscription's `last_polled_at` with the current time
+    ///
+    /// Fails with [`Error::SubscriptionNotFound`] rather than recreating a
+    /// subscription deleted since it was read.
+    pub async fn record_poll(&self, stream_id: &str, subscription_id: &str) -> Result<()> {
+        self.client
+            .update_item()
+            .table_name(&self.table_name)
+            .key("PK", AttributeValue::S(format!("STREAM#{}", stream_id)))
+            .key("SK", AttributeValue::S(format!("SUB#{}", subscription_id)))
+            .update_expression("SET last_polled_at = :now")
+            .condition_expression("attribute_exists(PK)")
+            .expression_attribute_values(":now", AttributeValue::S(self.clock.now().to_rfc3339()))
+            .send()
+            .await
+            .map_err(|e| {
+                if is_conditional_check_failed(&e) {
+                    Error::SubscriptionNotFound(subscription_id.to_string())
+                } else {
+                    database_error(e)
+                }
+            })?;
+        Ok(())
+    }
+
+    // =========================================================================
+    // Idempotency Operations
+    // =========================================================================
+
+    /// Look up an unexpired publish (or claim) made with `idempotency_key`
+    pub async fn get_idempotency_record(
+        &self,
+        stream_id: &str,
+        idempotency_key: &str,
+    ) -> Result<Option<IdempotencyRecord>> {
+        let result = self
+            .client
+            .get_item()
+            .table_name(&self.table_name)
+            .key(
+                "PK",
+                AttributeValue::S(format!("STREAM#{}#IDEMPOTENCY", stream_id)),
+            )
+            .key("SK", AttributeValue::S(format!("KEY#{}", idempotency_key)))
+            .consistent_read(true)
+            .send()
+            .await
+            .map_err(database_error)?;
+
+        match result.item {
+            Some(item) => {
+                let record: IdempotencyRecord =
+                    from_item(item).map_err(|e| Error::DynamoSerialization(e.to_string()))?;
+                Ok((!record.is_expired_at(self.clock.now())).then_some(record))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Claim `claim.idempotency_key` for a publish about to write its events
+    ///
+    /// Returns `None` once the key is claimed, or the unexpired record (a
+    /// finished publish, or another publish's claim) already holding it.
+    pub async fn claim_idempotency_key(
+        &self,
+        claim: &IdempotencyRecord,
+    ) -> Result<Option<IdempotencyRecord>> {
+        // A record can expire between the failed put and the read; then try again
+        for _ in 0..2 {
+            let result = self
+                .client
+                .put_item()
+                .table_name(&self.table_name)
+                .set_item(Some(idempotency_item(claim)?))
+                .condition_expression("attribute_not_exists(PK) OR expires_at <= :now")
+                .expression_attribute_values(
+                    ":now",
+                    AttributeValue::N(self.clock.now().timestamp().to_string()),
+                )
+                .send()
+                .await;
+
+            match result {
+                Ok(_) => return Ok(None),
+                Err(e) if is_conditional_check_failed(&e) => {}
+                Err(e) => return Err(database_error(e)),
+            }
+            let existing = self
+                .get_idempotency_record(&claim.stream_id, &claim.idempotency_key)
+                .await?;
+            if existing.is_some() {
+                return Ok(existing);
+            }
+        }
+        Err(Error::Internal(format!(
+            "Could not claim idempotency key '{}'",
+            claim.idempotency_key
+        )))
+    }
+
+    /// Replace a claim with its publish's result, kept until the stream's
+    /// idempotency window ends
+    ///
+    /// Fails with [`Error::IdempotencyKeyInUse`] if the claim expired and
+    /// another publish has taken the key since.
+    pub async fn put_idempotency_record(&self, record: &IdempotencyRecord) -> Result<()> {
+        self.client
+            .put_item()
+            .table_name(&self.table_name)
+            .set_item(Some(idempotency_item(record)?))
+            .condition_expression("#created_at = :created_at")
+            .expression_attribute_names("#created_at", "created_at")
+            .expression_attribute_values(":created_at", claim_created_at(record)?)
+            .send()
+            .await
+            .map_err(|e| {
+                if is_conditional_check_failed(&e) {
+                    Error::IdempotencyKeyInUse(format!(
+                        "The claim on '{}' expired before its publish finished",
+                        record.idempotency_key
+                    ))
+                } else {
+                    database_error(e)
+                }
+            })?;
+        Ok(())
+    }
+
+    /// Give up a claim whose publish failed, so a retry can publish
+    ///
+    /// Does nothing if the claim has already been replaced.
+    pub async fn release_idempotency_key(&self, claim: &IdempotencyRecord) -> Result<()> {
+        let result = self
+            .client
+            .delete_item()
+            .table_name(&self.table_name)
+            .key(
+                "PK",
+                AttributeValue::S(format!("STREAM#{}#IDEMPOTENCY", claim.stream_id)),
+            )
+            .key(
+                "SK",
+                AttributeValue::S(format!("KEY#{}", claim.idempotency_key)),
+            )
+            .condition_expression("#created_at = :created_at AND #pending = :pending")
+            .expression_attribute_names("#created_at", "created_at")
+            .expression_attribute_names("#pending", "pending")
+            .expression_attribute_values(":created_at", claim_created_at(claim)?)
+            .expression_attribute_values(":pending", AttributeValue::Bool(true))
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) if is_conditional_check_failed(&e) => Ok(()),
+            Err(e) => Err(database_error(e)),
+        }
+    }
+
+    // =========================================================================
+    // Dead-letter Operations
+    // =========================================================================
+
+    /// Count a failed compaction attempt for a stream record
+    ///
+    /// Once `max_attempts` is reached the record is written to the stream's DLQ
+    /// along with `error`. Returns true if the record was dead-lettered, in
+    /// which case it should not be retried.
+    pub async fn dead_letter_on_failure(
+        &self,
+        stream_id: &str,
+        record_id: &str,
+        record: &serde_json::Value,
+        error: &str,
+        max_attempts: u32,
+    ) -> Result<bool> {
+        // Stream records are only retained for 24 hours, so neither are the counts
+        let expires_at = (self.clock.now() + chrono::Duration::hours(24)).timestamp();
+        let result = self
+            .client
+            .update_item()
+            .table_name(&self.table_name)
+            .key("PK", AttributeValue::S(format!("STREAM#{}#DLQ", stream_id)))
+            .key("SK", AttributeValue::S(format!("ATTEMPT#{}", record_id)))
+            .update_expression("ADD attempts :one SET expires_at = :expires_at")
+            .expression_attribute_values(":one", AttributeValue::N("1".to_string()))
+            .expression_attribute_values(":expires_at", AttributeValue::N(expires_at.to_string()))
+            .return_values(ReturnValue::UpdatedNew)
+            .send()
+            .await
+            .map_err(database_error)?;
+
+        let attempts = match result.attributes.as_ref().and_then(|a| a.get("attempts")) {
+            Some(AttributeValue::N(n)) => n
+                .parse::<u32>()
+                .map_err(|e| Error::Internal(e.to_string()))?,
+            _ => return Err(Error::Internal("Missing attempt count".to_string())),
+        };
+        if attempts < max_attempts {
+            return Ok(false);
+        }
+
+        let dead_letter = DeadLetter {
+            stream_id: stream_id.to_string(),
+            record_id: record_id.to_string(),
+            record: record.clone(),
+            error: error.to_string(),
+            attempts,
+            failed_at: self.clock.now(),
+        };
+        let mut item: HashMap<String, AttributeValue> =
+            to_item(&dead_letter).map_err(|e| Error::DynamoSerialization(e.to_string()))?;
+        item.insert(
+            "PK".to_string(),
+            AttributeValue::S(format!("STREAM#{}#DLQ", stream_id)),
+        );
+        item.insert(
+            "SK".to_string(),
+            AttributeValue::S(format!("RECORD#{}", record_id)),
+        );
+
+        self.client
+            .put_item()
+            .table_name(&self.table_name)
+            .set_item(Some(item))
+            .send()
+            .await
+            .map_err(database_error)?;
+
+        Ok(true)
+    }
+
+    /// List records dead-lettered for a stream
+    pub async fn list_dlq(&self, stream_id: &str) -> Result<Vec<DeadLetter>> {
+        let mut dead_letters = Vec::new();
+        let mut start_key = None;
+
+        loop {
+            let result = self
+                .client
+                .query()
+                .table_name(&self.table_name)
+                .key_condition_expression("PK = :pk AND begins_with(SK, :prefix)")
+                .expression_attribute_values(
+                    ":pk",
+                    AttributeValue::S(format!("STREAM#{}#DLQ", stream_id)),
+                )
+                .expression_attribute_values(":prefix", AttributeValue::S("RECORD#".to_string()))
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await
+                .map_err(database_error)?;
+
+            for item in result.items.unwrap_or_default() {
+                dead_letters
+                    .push(from_item(item).map_err(|e| Error::DynamoSerialization(e.to_string()))?);
+            }
+
+            match result.last_evaluated_key {
+                Some(key) => start_key = Some(key),
+                None => break,
+            }
+        }
+
+        Ok(dead_letters)
+    }
+
+    // =========================================================================
+    // Compaction Operations
+    // =========================================================================
+
+    /// Store compacted state for a key unless the stored state already
+    /// supersedes it, recording the change in the stream's compaction
+    /// changelog
+    ///
+    /// The write is conditional, so the compactor, a backfill and a redaction
+    /// racing on one key can't replace newer state with older state; returns
+    /// whether the state was written. With `ttl_hours` (the stream's
+    /// `compacted_ttl_hours`) the item and its changelog entry get an
+    /// `expires_at` that many hours after `ingested_at`, when the event was
+    /// written (see [`Event::ingested_at`]), so DynamoDB TTL reaps them. A
+    /// backfilled event's older `timestamp` doesn't shorten that.
+    pub async fn put_compacted(
+        &self,
+        event: &CompactedEvent,
+        ingested_at: DateTime<Utc>,
+        ttl_hours: Option<u32>,
+    ) -> Result<bool> {
+        let mut change: HashMap<String, AttributeValue> =
+            to_item(event).map_err(|e| Error::DynamoSerialization(e.to_string()))?;
+        if let Some(hours) = ttl_hours {
+            let expires_at = ingested_at + chrono::Duration::hours(hours.into());
+            change.insert(
+                "expires_at".to_string(),
+                AttributeValue::N(expires_at.timestamp().to_string()),
+            );
+        }
+
+        // The RFC 3339 `timestamp` doesn't sort as a string, so the condition
+        // compares this instead
+        let timestamp_ns = compacted_timestamp_ns(event.timestamp);
+        let mut item = change.clone();
+        item.insert(
+            "timestamp_ns".to_string(),
+            AttributeValue::N(timestamp_ns.to_string()),
+        );
+        item.insert(
+            "PK".to_string(),
+            AttributeValue::S(format!("STREAM#{}#COMPACT", event.stream_id)),
+        );
+        item.insert(
+            "SK".to_string(),
+            AttributeValue::S(format!("KEY#{}", event.key)),
+        );
+
+        let log_pk = format!("STREAM#{}#COMPACT_LOG", event.stream_id);
+        self.append_compaction_change(&event.stream_id, &change, |position| {
+            // The same ordering as CompactedEvent::supersedes; state written
+            // before timestamp_ns existed is replaced by any other partition
+            let state = Put::builder()
+                .table_name(&self.table_name)
+                .set_item(Some(item.clone()))
+                .condition_expression(
+                    "attribute_not_exists(PK) \
+                     OR (#partition = :partition AND #sequence < :sequence) \
+                     OR (#partition <> :partition AND (attribute_not_exists(#ts) \
+                         OR #ts < :ts OR (#ts = :ts AND #partition < :partition)))",
+                )
+                .expression_attribute_names("#partition", "partition")
+                .expression_attribute_names("#sequence", "sequence")
+                .expression_attribute_names("#ts", "timestamp_ns")
+                .expression_attribute_values(
+                    ":partition",
+                    AttributeValue::N(event.partition.to_string()),
+                )
+                .expression_attribute_values(
+                    ":sequence",
+                    AttributeValue::N(event.sequence.to_string()),
+                )
+                .expression_attribute_values(":ts", AttributeValue::N(timestamp_ns.to_string()))
+                .build()
+                .map_err(|e| Error::Internal(e.to_string()))?;
+
+            // Where this event's payload is in the changelog, for redact_event
+            let mut applied = HashMap::from([
+                ("PK".to_string(), AttributeValue::S(log_pk.clone())),
+                (
+                    "SK".to_string(),
+                    AttributeValue::S(applied_sk(event.partition, event.sequence)),
+                ),
+                (
+                    "position".to_string(),
+                    AttributeValue::N(position.to_string()),
+                ),
+            ]);
+            if let Some(expires_at) = change.get("expires_at") {
+                applied.insert("expires_at".to_string(), expires_at.clone());
+            }
+            let applied = Put::builder()
+                .table_name(&self.table_name)
+                .set_item(Some(applied))
+                .build()
+                .map_err(|e| Error::Internal(e.to_string()))?;
+
+            Ok(vec![
+                TransactWriteItem::builder().put(state).build(),
+                TransactWriteItem::builder().put(applied).build(),
+            ])
+        })
+        .await
+    }
+
+    /// Append `change` to the stream's compaction changelog under the next
+    /// position, in one transaction with `writes`
+    ///
+    /// The position is taken by a conditional update of the changelog counter
+    /// in the same transaction, so every position up to the counter has its
+    /// entry and tailers never skip one that is written late. A writer that
+    /// loses the position to another retries with the next. If a condition in
+    /// `writes` fails, nothing is written and this returns false.
+    async fn append_compaction_change<F>(
+        &self,
+        stream_id: &str,
+        change: &HashMap<String, AttributeValue>,
+        writes: F,
+    ) -> Result<bool>
+    where
+        F: Fn(u64) -> Result<Vec<TransactWriteItem>>,
+    {
+        let pk = format!("STREAM#{}#COMPACT_LOG", stream_id);
+
+        for _ in 0..COMPACTION_LOG_MAX_ATTEMPTS {
+            let result = self
+                .client
+                .get_item()
+                .table_name(&self.table_name)
+                .key("PK", AttributeValue::S(pk.clone()))
+                .key("SK", AttributeValue::S("COUNTER".to_string()))
+                .consistent_read(true)
+                .send()
+                .await
+                .map_err(database_error)?;
+            let current = match result.item.as_ref().and_then(|item| item.get("sequence")) {
+                Some(AttributeValue::N(n)) => n
+                    .parse::<u64>()
+                    .map_err(|e| Error::Internal(format!("Invalid changelog counter: {}", e)))?,
+                _ => 0,
+            };
+            let position = current + 1;
+
+            let counter = Update::builder()
+                .table_name(&self.table_name)
+                .key("PK", AttributeValue::S(pk.clone()))
+                .key("SK", AttributeValue::S("COUNTER".to_string()))
+                .update_expression("SET #seq = :position")
+                .condition_expression("attribute_not_exists(#seq) OR #seq = :current")
+                .expression_attribute_names("#seq", "sequence")
+                .expression_attribute_values(":position", AttributeValue::N(position.to_string()))
+                .expression_attribute_values(":current", AttributeValue::N(current.to_string()))
+                .build()
+                .map_err(|e| Error::Internal(e.to_string()))?;
+
+            // LOG# rather than SEQ#, which the compactor would take for an event
+            let mut entry = change.clone();
+            entry.insert("PK".to_string(), AttributeValue::S(pk.clone()));
+            entry.insert(
+                "SK".to_string(),
+                AttributeValue::S(format!("LOG#{:020}", position)),
+            );
+            entry.insert(
+                "position".to_string(),
+                AttributeValue::N(position.to_string()),
+            );
+            let entry = Put::builder()
+                .table_name(&self.table_name)
+                .set_item(Some(entry))
+                .build()
+                .map_err(|e| Error::Internal(e.to_string()))?;
+
+            let mut items = vec![
+                TransactWriteItem::builder().update(counter).build(),
+                TransactWriteItem::builder().put(entry).build(),
+            ];
+            items.extend(writes(position)?);
+
+            let result = self
+                .client
+                .transact_write_items()
+                .set_transact_items(Some(items))
+                .send()
+                .await;
+            let Err(e) = result else { return Ok(true) };
+            let Some(TransactWriteItemsError::TransactionCanceledException(cancelled)) =
+                e.as_service_error()
+            else {
+                return Err(database_error(e));
+            };
+            // Reasons line up with the items: the counter, the entry, then `writes`
+            let reasons = cancelled.cancellation_reasons();
+            let failed =
+                |reason: &CancellationReason| reason.code() == Some("ConditionalCheckFailed");
+            if reasons.iter().skip(2).any(failed) {
+                return Ok(false);
+            }
+            // Another change took the position, or the transactions collided
+            let retry = reasons.first().is_some_and(failed)
+                || reasons
+                    .iter()
+                    .any(|r| r.code() == Some("TransactionConflict"));
+            if !retry {
+                return Err(database_error(e));
+            }
+        }
+
+        Err(Error::Throttled(format!(
+            "Compaction changelog of stream {} is being written too quickly",
+            stream_id
+        )))
+    }
+
+    /// Replace an event's payload in compacted state with `data`, after the
+    /// event itself was redacted
+    ///
+    /// Redacts the changelog entry the event was logged under and, if the
+    /// event is still its key's compacted state, that state, logging the
+    /// redacted state as a new change. The state is only written while it is
+    /// still this event, so a racing compactor can't bring the payload back;
+    /// the compactor calls this too when it sees the redaction, in case it
+    /// applies the event after this ran.
+    pub async fn redact_compacted(
+        &self,
+        stream_id: &str,
+        key: &str,
+        partition: u32,
+        sequence: u64,
+        data: &serde_json::Value,
+    ) -> Result<()> {
+        let data_value: AttributeValue =
+            to_attribute_value(data).map_err(|e| Error::DynamoSerialization(e.to_string()))?;
+        let log_pk = format!("STREAM#{}#COMPACT_LOG", stream_id);
+
+        let applied = self
+            .client
+            .get_item()
+            .table_name(&self.table_name)
+            .key("PK", AttributeValue::S(log_pk.clone()))
+            .key("SK", AttributeValue::S(applied_sk(partition, sequence)))
+            .consistent_read(true)
+            .send()
+            .await
+            .map_err(database_error)?;
+        if let Some(AttributeValue::N(position)) =
+            applied.item.as_ref().and_then(|item| item.get("position"))
+        {
+            let result = self
+                .client
+                .update_item()
+                .table_name(&self.table_name)
+                .key("PK", AttributeValue::S(log_pk))
+                .key("SK", AttributeValue::S(format!("LOG#{:0>20}", position)))
+                .update_expression("SET #data = :data")
+                .condition_expression("attribute_exists(SK)")
+                .expression_attribute_names("#data", "data")
+                .expression_attribute_values(":data", data_value.clone())
+                .send()
+                .await;
+            match result {
+                // The entry expired after the pointer was read
+                Err(e) if is_conditional_check_failed(&e) => {}
+                result => {
+                    result.map_err(database_error)?;
+                }
+            }
+        }
+
+        let state = self
+            .client
+            .get_item()
+            .table_name(&self.table_name)
+            .key(
+                "PK",
+                AttributeValue::S(format!("STREAM#{}#COMPACT", stream_id)),
+            )
+            .key("SK", AttributeValue::S(format!("KEY#{}", key)))
+            .consistent_read(true)
+            .send()
+            .await
+            .map_err(database_error)?;
+        let Some(mut item) = state.item else {
+            return Ok(());
+        };
+        let is_event = |name: &str, value: String| matches!(item.get(name), Some(AttributeValue::N(n)) if *n == value);
+        if !is_event("partition", partition.to_string())
+            || !is_event("sequence", sequence.to_string())
+            || item.get("data") == Some(&data_value)
+        {
+            return Ok(());
+        }
+
+        item.insert("data".to_string(), data_value);
+        let mut change = item.clone();
+        for name in ["PK", "SK", "timestamp_ns"] {
+            change.remove(name);
+        }
+        self.append_compaction_change(stream_id, &change, |_| {
+            let state = Put::builder()
+                .table_name(&self.table_name)
+                .set_item(Some(item.clone()))
+                .condition_expression("#partition = :partition AND #sequence = :sequence")
+                .expression_attribute_names("#partition", "partition")
+                .expression_attribute_names("#sequence", "sequence")
+                .expression_attribute_values(":partition", AttributeValue::N(partition.to_string()))
+                .expression_attribute_values(":sequence", AttributeValue::N(sequence.to_string()))
+                .build()
+                .map_err(|e| Error::Internal(e.to_string()))?;
+            Ok(vec![TransactWriteItem::builder().put(state).build()])
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    /// Read up to `limit` changelog entries after position `after`, oldest first
+    pub async fn read_compaction_changelog(
+        &self,
+        stream_id: &str,
+        after: u64,
+        limit: u32,
+    ) -> Result<Vec<CompactionChange>> {
+        if limit == 0 {
+            return Err(Error::Validation("limit must be at least 1".to_string()));
+        }
+
+        // Entries sort after the COUNTER item, so it never matches
+        let result = self
+            .client
+            .query()
+            .table_name(&self.table_name)
+            .key_condition_expression("PK = :pk AND SK > :after")
+            .expression_attribute_values(
+                ":pk",
+                AttributeValue::S(format!("STREAM#{}#COMPACT_LOG", stream_id)),
+            )
+            .expression_attribute_values(":after", AttributeValue::S(format!("LOG#{:020}", after)))
+            .limit(limit.min(i32::MAX as u32) as i32)
+            .send()
+            .await
+            .map_err(database_error)?;
+
+        result
+            .items
+            .unwrap_or_default()
+            .into_iter()
+            .map(|item| {
+                let position = match item.get("position") {
+                    Some(AttributeValue::N(n)) => n.parse().ok(),
+                    _ => None,
+                }
+                .ok_or_else(|| Error::Internal("Changelog entry without a position".to_string()))?;
+                let state =
+                    from_item(item).map_err(|e| Error::DynamoSerialization(e.to_string()))?;
+                Ok(CompactionChange { position, state })
+            })
+            .collect()
+    }
+
+    /// Get compacted state for a key
+    pub async fn get_compacted(
+        &self,
+        stream_id: &str,
+        key: &str,
+    ) -> Result<Option<CompactedEvent>> {
+        let result = self
+            .client
+            .get_item()
+            .table_name(&self.table_name)
+            .key(
+                "PK",
+                AttributeValue::S(format!("STREAM#{}#COMPACT", stream_id)),
+            )
+            .key("SK", AttributeValue::S(format!("KEY#{}", key)))
+            .send()
+            .await
+            .map_err(database_error)?;
+
+        match result.item {
+            Some(item) => Ok(Some(
+                from_item(item).map_err(|e| Error::DynamoSerialization(e.to_string()))?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// List all compacted events for a stream
+    pub async fn list_compacted(&self, stream_id: &str) -> Result<Vec<CompactedEvent>> {
+        let mut events = Vec::new();
+        let mut start_key = None;
+
+        // Each query page is capped at 1MB, so keep going until the last page
+        loop {
+            let result = self
+                .client
+                .query()
+                .table_name(&self.table_name)
+                .key_condition_expression("PK = :pk AND begins_with(SK, :prefix)")
+                .expression_attribute_values(
+                    ":pk",
+                    AttributeValue::S(format!("STREAM#{}#COMPACT", stream_id)),
+                )
+                .expression_attribute_values(":prefix", AttributeValue::S("KEY#".to_string()))
+                .set_exclusive_start_key(start_key)
+                .send()
+                .await
+                .map_err(database_error)?;
+
+            events.extend(
+                result
+                    .items
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|item| from_item::<_, CompactedEvent>(item).ok()),
+            );
+
+            match result.last_evaluated_key {
+                Some(key) => start_key = Some(key),
+                None => break,
+            }
+        }
+
+        Ok(events)
+    }
+
+    /// One page of compacted events in key order
+    ///
+    /// Starts after `start_key` (exclusive) and returns up to `limit` events,
+    /// plus the key to pass as `start_key` for the next page when more may remain.
+    ///
+    /// `since_sequence` keeps only events with a higher sequence. Compacted
+    /// items are keyed by `KEY#`, so this is a filter expression: every
+    /// skipped item is still read and billed, and a selective filter can take
+    /// many round trips to fill a page.
+    pub async fn list_compacted_page(
+        &self,
+        stream_id: &str,
+        start_key: Option<&str>,
+        limit: usize,
+        descending: bool,
+        since_sequence: Option<u64>,
+    ) -> Result<(Vec<CompactedEvent>, Option<String>)> {
+        if limit == 0 {
+            return Err(Error::Validation("limit must be at least 1".to_string()));
+        }
+
+        let pk = format!("STREAM#{}#COMPACT", stream_id);
+        let exclusive_start = start_key.map(|key| {
+            HashMap::from([
+                ("PK".to_string(), AttributeValue::S(pk.clone())),
+                ("SK".to_string(), AttributeValue::S(format!("KEY#{}", key))),
+            ])
+        });
+        let (events, last_key) = self
+            .query_compacted_page(
+                &pk,
+                None,
+                exclusive_start,
+                limit,
+                descending,
+                since_sequence,
+            )
+            .await?;
+
+        let next_key = last_key.and_then(|key| match key.get("SK") {
+            Some(AttributeValue::S(sk)) => sk.strip_prefix("KEY#").map(str::to_string),
+            _ => None,
+        });
+        Ok((events, next_key))
+    }
+
+    /// One page of compacted events in event timestamp order
+    ///
+    /// Reads `COMPACTED_TIME_INDEX`, so pages like
+    /// [`list_compacted_page`](Self::list_compacted_page) but takes and
+    /// returns its own `start_key` (`{timestamp_ns}#{key}`). Events with the
+    /// same timestamp come back in no particular order. State written before
+    /// `timestamp_ns` existed isn't in the index until its key is next compacted.
+    pub async fn list_compacted_page_by_timestamp(
+        &self,
+        stream_id: &str,
+        start_key: Option<&str>,
+        limit: usize,
+        descending: bool,
+        since_sequence: Option<u64>,
+    ) -> Result<(Vec<CompactedEvent>, Option<String>)> {
+        if limit == 0 {
+            return Err(Error::Validation("limit must be at least 1".to_string()));
+        }
+
+        let pk = format!("STREAM#{}#COMPACT", stream_id);
+        let exclusive_start = match start_key {
+            None => None,
+            Some(start_key) => {
+                let (timestamp_ns, key) = start_key
+                    .split_once('#')
+                    .filter(|(ts, _)| ts.parse::<i64>().is_ok())
+                    .ok_or_else(|| {
+                        Error::Validation(format!("Invalid start_key '{}'", start_key))
+                    })?;
+                Some(HashMap::from([
+                    ("PK".to_string(), AttributeValue::S(pk.clone())),
+                    ("SK".to_string(), AttributeValue::S(format!("KEY#{}", key))),
+                    (
+                        "timestamp_ns".to_string(),
+                        AttributeValue::N(timestamp_ns.to_string()),
+                    ),
+                ]))
+            }
+        };
+        let (events, last_key) = self
+            .query_compacted_page(
+                &pk,
+                Some(COMPACTED_TIME_INDEX),
+                exclusive_start,
+                limit,
+                descending,
+                since_sequence,
+            )
+            .await?;
+
+        let next_key = last_key.and_then(|key| match (key.get("timestamp_ns"), key.get("SK")) {
+            (Some(AttributeValue::N(ts)), Some(AttributeValue::S(sk))) => {
+                sk.strip_prefix("KEY#").map(|key| format!("{}#{}", ts, key))
+            }
+            _ => None,
+        });
+        Ok((events, next_key))
+    }
+
+    /// Read compacted items of partition `pk` (from `index` if given) until
+    /// `limit` are found or there are no more, returning them and the last
+    /// evaluated key when more may remain
+    async fn query_compacted_page(
+        &self,
+        pk: &str,
+        index: Option<&str>,
+        mut exclusive_start: Option<HashMap<String, AttributeValue>>,
+        limit: usize,
+        descending: bool,
+        since_sequence: Option<u64>,
+    ) -> Result<(Vec<CompactedEvent>, Option<HashMap<String, AttributeValue>>)> {
+        let mut events = Vec::new();
+
+        // A page can stop short of `limit` at the 1MB cap, so keep reading
+        loop {
+            let remaining = (limit - events.len()).min(i32::MAX as usize) as i32;
+            let mut query = self
+                .client
+                .query()
+                .table_name(&self.table_name)
+                .set_index_name(index.map(str::to_string))
+                .expression_attribute_values(":pk", AttributeValue::S(pk.to_string()))
+                .scan_index_forward(!descending)
+                .limit(remaining)
+                .set_exclusive_start_key(exclusive_start);
+            // Only compacted items carry timestamp_ns, so the index needs no prefix
+            query = match index {
+                Some(_) => query.key_condition_expression("PK = :pk"),
+                None => query
+                    .key_condition_expression("PK = :pk AND begins_with(SK, :prefix)")
+                    .expression_attribute_values(":prefix", AttributeValue::S("KEY#".to_string())),
+            };
+            if let Some(since) = since_sequence {
+                query = query
+                    .filter_expression("#sequence > :since")
+                    .expression_attribute_names("#sequence", "sequence")
+                    .expression_attribute_values(":since", AttributeValue::N(since.to_string()));
+            }
+            let result = query.send().await.map_err(database_error)?;
+
+            events.extend(
+                result
+                    .items
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|item| from_item::<_, CompactedEvent>(item).ok()),
+            );
+
+            exclusive_start = result.last_evaluated_key;
+            if exclusive_start.is_none() || events.len() >= limit {
+                break;
+            }
+        }
+
+        Ok((events, exclusive_start))
+    }
+
+    /// Compacted state paired with the partition offsets it reflects
+    ///
+    /// Each partition's latest offset is read before the compacted state, and
+    /// entries from events past those offsets are dropped: reading on from the
+    /// offsets returns those events, so nothing is seen twice. The compactor
+    /// runs asynchronously, so the events between where it has compacted each
+    /// partition through and that offset are read and folded in as well;
+    /// however far it lags, every event is in one part or the other.
+    pub async fn compacted_snapshot(
+        &self,
+        stream_id: &str,
+    ) -> Result<(Vec<CompactedEvent>, Vec<PartitionOffset>)> {
+        let stream = self.get_stream(stream_id).await?;
+
+        let tails = self.latest_sequences(&stream).await?;
+        let compacted_through = self.compacted_through(&stream).await?;
+
+        let mut compacted: HashMap<String, CompactedEvent> = self
+            .list_compacted(stream_id)
+            .await?
+            .into_iter()
+            .filter(|event| {
+                tails
+                    .get(event.partition as usize)
+                    .is_some_and(|tail| event.sequence <= tail.offset)
+            })
+            .map(|event| (event.key.clone(), event))
+            .collect();
+
+        // Apply what the compactor hasn't yet, up to each tail
+        for (tail, mut offset) in tails.iter().zip(compacted_through) {
+            while offset < tail.offset {
+                let events = self
+                    .read_events(stream_id, tail.partition, offset, BACKFILL_PAGE_SIZE, true)
+                    .await?;
+                let Some(last) = events.last() else { break };
+                offset = last.sequence;
+
+                for event in events.into_iter().filter(|e| e.sequence <= tail.offset) {
+                    let state = compacted_state(event);
+                    if compacted
+                        .get(&state.key)
+                        .is_none_or(|existing| state.supersedes(existing))
+                    {
+                        compacted.insert(state.key.clone(), state);
+                    }
+                }
+            }
+        }
+
+        let mut compacted: Vec<CompactedEvent> = compacted.into_values().collect();
+        compacted.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok((compacted, tails))
+    }
+
+    /// Record that every event in `partition` up to `sequence` is compacted
+    ///
+    /// Only ever moves forward, so a batch retried after a later one can't
+    /// pull it back.
+    pub async fn advance_compacted_through(
+        &self,
+        stream_id: &str,
+        partition: u32,
+        sequence: u64,
+    ) -> Result<()> {
+        let result = self
+            .client
+            .update_item()
+            .table_name(&self.table_name)
+            .key(
+                "PK",
+                AttributeValue::S(format!("STREAM#{}#COMPACTED_THROUGH", stream_id)),
+            )
+            .key("SK", AttributeValue::S(format!("P{}", partition)))
+            .update_expression("SET #seq = :seq")
+            .condition_expression("attribute_not_exists(#seq) OR #seq < :seq")
+            .expression_attribute_names("#seq", "sequence")
+            .expression_attribute_values(":seq", AttributeValue::N(sequence.to_string()))
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            // Already as far or further
+            Err(e) if is_conditional_check_failed(&e) => Ok(()),
+            Err(e) => Err(database_error(e)),
+        }
+    }
+
+    /// Sequence each partition is compacted through, 0 where none is recorded
+    async fn compacted_through(&self, stream: &Stream) -> Result<Vec<u64>> {
+        let result = self
+            .client
+            .query()
+            .table_name(&self.table_name)
+            .key_condition_expression("PK = :pk")
+            .expression_attribute_values(
+                ":pk",
+                AttributeValue::S(format!("STREAM#{}#COMPACTED_THROUGH", stream.stream_id)),
+            )
+            .consistent_read(true)
+            .send()
+            .await
+            .map_err(database_error)?;
+
+        // One small item per partition, so a single page holds them all
+        let mut through = vec![0; stream.partition_count as usize];
+        for item in result.items.unwrap_or_default() {
+            let partition = match item.get("SK") {
+                Some(AttributeValue::S(sk)) => sk.strip_prefix('P').and_then(|p| p.parse().ok()),
+                _ => None,
+            };
+            let sequence = match item.get("sequence") {
+                Some(AttributeValue::N(n)) => n.parse().ok(),
+                _ => None,
+            };
+            if let (Some(partition), Some(sequence)) = (partition, sequence) {
+                if let Some(entry) = through.get_mut::<usize>(partition) {
+                    *entry = sequence;
+                }
+            }
+        }
+        Ok(through)
+    }
+
+    /// Rebuild compacted state from the events already in a stream
+    ///
+    /// Scans each partition in sequence order from `req.from_offsets` (default:
+    /// the start) and writes the latest event per key, leaving keys whose
+    /// compacted state is already as new. Up to `BACKFILL_CONCURRENCY`
+    /// partitions are scanned at once; partitions that haven't finished when
+    /// `budget` runs out are left for a later run and the result is marked
+    /// incomplete, with the offsets to resume from.
+    pub async fn backfill_compacted(
+        &self,
+        stream_id: &str,
+        req: &CompactionBackfillRequest,
+        budget: Duration,
+    ) -> Result<CompactionBackfillResult> {
+        let stream = self.get_stream(stream_id).await?;
+        let deadline = Instant::now() + budget;
+
+        let mut offsets = vec![0; stream.partition_count as usize];
+        for position in req.from_offsets.iter().flatten() {
+            let offset = offsets
+                .get_mut(position.partition as usize)
+                .ok_or_else(|| {
+                    Error::Validation(format!(
+                        "Stream '{}' has no partition {}",
+                        stream_id, position.partition
+                    ))
+                })?;
+            *offset = position.offset;
+        }
+
+        let outcomes: Vec<Result<PartitionBackfill>> =
+            stream::iter(offsets.into_iter().enumerate())
+                .map(|(partition, from)| {
+                    self.backfill_partition(
+                        stream_id,
+                        partition as u32,
+                        from,
+                        stream.compacted_ttl_hours,
+                        deadline,
+                    )
+                })
+                .buffer_unordered(BACKFILL_CONCURRENCY)
+                .collect()
+                .await;
+
+        let mut result = CompactionBackfillResult {
+            stream_id: stream_id.to_string(),
+            events_scanned: 0,
+            keys_compacted: 0,
+            partitions_completed: 0,
+            offsets: Vec::with_capacity(stream.partition_count as usize),
+            complete: true,
+        };
+        for outcome in outcomes {
+            let partition = outcome?;
+            result.events_scanned += partition.events_scanned;
+            result.keys_compacted += partition.keys_compacted;
+            if partition.complete {
+                result.partitions_completed += 1;
+            } else {
+                result.complete = false;
+            }
+            result.offsets.push(PartitionOffset {
+                partition: partition.partition,
+                offset: partition.offset,
+            });
+        }
+        result.offsets.sort_by_key(|position| position.partition);
+
+        Ok(result)
+    }
+
+    /// Backfill compacted state from a single partition, after sequence `from`
+    async fn backfill_partition(
+        &self,
+        stream_id: &str,
+        partition: u32,
+        from: u64,
+        compacted_ttl_hours: Option<u32>,
+        deadline: Instant,
+    ) -> Result<PartitionBackfill> {
+        let mut latest: HashMap<String, Event> = HashMap::new();
+        let mut events_scanned = 0;
+        let mut offset = from;
+        let mut complete = true;
+
+        loop {
+            if Instant::now() >= deadline {
+                complete = false;
+                break;
+            }
+
+            let events = self
+                .read_events(stream_id, partition, offset, BACKFILL_PAGE_SIZE, true)
+                .await?;
+            let Some(last) = events.last() else { break };
+            offset = last.sequence;
+            events_scanned += events.len() as u64;
+
+            // Pages arrive in sequence order, so later events replace earlier ones
+            for event in events {
+                latest.insert(event.key.clone(), event);
+            }
+        }
+
+        // The writes are conditional, so state the compactor wrote meanwhile wins
+        let mut keys_compacted = 0;
+        for event in latest.into_values() {
+            let ingested_at = event.ingested_at();
+            let compacted = compacted_state(event);
+            if self
+                .put_compacted(&compacted, ingested_at, compacted_ttl_hours)
+                .await?
+            {
+                keys_compacted += 1;
+            }
+        }
+
+        // Every event scanned is compacted now, wherever the compactor is
+        if offset > from {
+            self.advance_compacted_through(stream_id, partition, offset)
+                .await?;
+        }
+
+        Ok(PartitionBackfill {
+            partition,
+            offset,
+            events_scanned,
+            keys_compacted,
+            complete,
+        })
+    }
+
+    /// Copy a stream's events into a new stream with a different partition count
+    ///
+    /// Creates `target_stream_id` with the source's config, apart from the
+    /// publish rate limit, and republishes every event so its key is hashed
+    /// over the new partitions. Events keep their `timestamp` and are copied
+    /// in timestamp order, each source partition in sequence order, so the
+    /// events for a key stay in order. Subscriptions and compacted state are
+    /// not copied. With `alias`, the alias is moved from the source to the
+    /// target once the copy completes, so publishers and consumers using it
+    /// switch over; events published to the source just before the swap are
+    /// copied by resuming once more.
+    ///
+    /// If `budget` runs out the result is marked incomplete; passing its
+    /// `offsets` back as `from_offsets` carries on into the existing target.
+    /// Each batch of copies is written together with a checkpoint of the
+    /// source offsets it reaches, which lasts as long as the target, so
+    /// resuming from earlier offsets, e.g. after a run that failed partway,
+    /// skips what was copied rather than duplicating it.
+    pub async fn repartition_stream(
+        &self,
+        stream_id: &str,
+        req: &RepartitionRequest,
+        budget: Duration,
+    ) -> Result<RepartitionResult> {
+        if req.target_stream_id == stream_id {
+            return Err(Error::Validation(
+                "target_stream_id must differ from the stream being repartitioned".to_string(),
+            ));
+        }
+        if let Some(alias) = &req.alias {
+            validate_alias(alias)?;
+        }
+        let source = self.get_stream(stream_id).await?;
+        let deadline = Instant::now() + budget;
+        let partitions = source.partition_count as usize;
+
+        let mut checkpoint = RepartitionCheckpoint {
+            copied: 0,
+            offsets: vec![0; partitions],
+        };
+        match &req.from_offsets {
+            None => {
+                let create = CreateStreamRequest {
+                    stream_id: req.target_stream_id.clone(),
+                    partition_count: Some(req.partition_count),
+                    retention_hours: Some(source.retention_hours),
+                    global_ordering: source.global_ordering,
+                    allowed_event_types: source.allowed_event_types.clone(),
+                    schema: source.schema.clone(),
+                    partition_overrides: source.partition_overrides.clone(),
+                    empty_key_strategy: source.empty_key_strategy,
+                    compaction: Some(source.compaction),
+                    idempotency_ttl_hours: source.idempotency_ttl_hours,
+                    compacted_ttl_hours: source.compacted_ttl_hours,
+                    // The copy would be throttled by it; set it again once done
+                    max_publish_per_second: None,
+                    tags: source.tags.clone(),
+                };
+                self.create_stream(&create).await?;
+            }
+            Some(from_offsets) => {
+                let target = self.get_stream(&req.target_stream_id).await?;
+                if target.partition_count != req.partition_count {
+                    return Err(Error::Validation(format!(
+                        "Stream '{}' has {} partitions, not {}",
+                        target.stream_id, target.partition_count, req.partition_count
+                    )));
+                }
+                for position in from_offsets {
+                    if position.partition as usize >= partitions {
+                        return Err(Error::Validation(format!(
+                            "Stream '{}' has no partition {}",
+                            stream_id, position.partition
+                        )));
+                    }
+                }
+                if let Some(saved) = self
+                    .get_repartition_checkpoint(&req.target_stream_id, stream_id)
+                    .await?
+                {
+                    checkpoint = saved;
+                }
+            }
+        }
+
+        // Start past both the offsets asked for and what was already copied
+        for position in req.from_offsets.iter().flatten() {
+            let offset = &mut checkpoint.offsets[position.partition as usize];
+            *offset = (*offset).max(position.offset);
+        }
+
+        // Unpublished events read from each partition, and where reading resumes
+        let mut pending: Vec<VecDeque<Event>> = vec![VecDeque::new(); partitions];
+        let mut read_offsets = checkpoint.offsets.clone();
+        let mut exhausted = vec![false; partitions];
+        let mut batch = Vec::with_capacity(REPARTITION_BATCH_SIZE);
+        let mut events_copied = 0;
+        let mut complete = true;
+
+        loop {
+            for partition in 0..partitions {
+                if !pending[partition].is_empty() || exhausted[partition] {
+                    continue;
+                }
+                let events = self
+                    .read_events(
+                        stream_id,
+                        partition as u32,
+                        read_offsets[partition],
+                        BACKFILL_PAGE_SIZE,
+                        true,
+                    )
+                    .await?;
+                match events.last() {
+                    Some(last) => read_offsets[partition] = last.sequence,
+                    None => exhausted[partition] = true,
+                }
+                pending[partition].extend(events);
+            }
+
+            // The earliest unpublished event, ties going to the lower partition
+            let next = pending
+                .iter()
+                .enumerate()
+                .filter_map(|(partition, events)| events.front().map(|e| (e.timestamp, partition)))
+                .min();
+            if let Some(event) = next.and_then(|(_, partition)| pending[partition].pop_front()) {
+                batch.push(event);
+            }
+            if batch.len() < REPARTITION_BATCH_SIZE && next.is_some() {
+                continue;
+            }
+
+            events_copied += self
+                .republish(stream_id, &req.target_stream_id, &batch, &mut checkpoint)
+                .await?;
+            batch.clear();
+            if next.is_none() {
+                break;
+            }
+            if Instant::now() >= deadline {
+                complete = false;
+                break;
+            }
+        }
+
+        // Swap only once the target has everything, and only away from the
+        // source, so a run that lost a race to another swap changes nothing
+        let mut alias = None;
+        if let (true, Some(name)) = (complete, &req.alias) {
+            self.put_alias(name, &req.target_stream_id, Some(stream_id))
+                .await?;
+            alias = Some(name.clone());
+        }
+
+        Ok(RepartitionResult {
+            stream_id: stream_id.to_string(),
+            target_stream_id: req.target_stream_id.clone(),
+            partition_count: req.partition_count,
+            events_copied,
+            offsets: checkpoint
+                .offsets
+                .into_iter()
+                .enumerate()
+                .map(|(partition, offset)| PartitionOffset {
+                    partition: partition as u32,
+                    offset,
+                })
+                .collect(),
+            complete,
+            alias,
+        })
+    }
+
+    /// Publish a batch of `source_id`'s events to `stream_id`, advancing
+    /// `checkpoint` past them
+    ///
+    /// The copies are stored as they are, without checking them against the
+    /// target's event types or schema. Each transaction also moves the
+    /// checkpoint on to the offsets it reaches, on condition that it is still
+    /// where this run left it, so a concurrent run copying the same events
+    /// fails with [`Error::CommitConflict`] instead. Returns how many events
+    /// were published.
+    async fn republish(
+        &self,
+        source_id: &str,
+        stream_id: &str,
+        batch: &[Event],
+        checkpoint: &mut RepartitionCheckpoint,
+    ) -> Result<u64> {
+        let mut published = 0;
+        // One write per event, and one for the checkpoint
+        for chunk in batch.chunks(TRANSACT_WRITE_MAX_ITEMS - 1) {
+            let events: Vec<PublishEvent> = chunk
+                .iter()
+                .map(|event| PublishEvent {
+                    key: event.key.clone(),
+                    partition_key: event.partition_key.clone(),
+                    order_key: event.order_key,
+                    timestamp: Some(event.timestamp),
+                    event_type: EventType::from_stored(event.event_type.clone()),
+                    data: event.data.clone(),
+                })
+                .collect();
+            let stored_events = self.prepare_events(stream_id, &events, false).await?;
+
+            let mut items = Vec::with_capacity(chunk.len() + 1);
+            for stored_event in &stored_events {
+                let put = Put::builder()
+                    .table_name(&self.table_name)
+                    .set_item(Some(event_item(stored_event)?))
+                    .condition_expression("attribute_not_exists(SK)")
+                    .build()
+                    .map_err(|e| Error::Internal(e.to_string()))?;
+                items.push(TransactWriteItem::builder().put(put).build());
+            }
+
+            // Each partition's events are batched in sequence order
+            let mut next = RepartitionCheckpoint {
+                copied: checkpoint.copied + chunk.len() as u64,
+                offsets: checkpoint.offsets.clone(),
+            };
+            for event in chunk {
+                next.offsets[event.partition as usize] = event.sequence;
+            }
+            let put = Put::builder()
+                .table_name(&self.table_name)
+                .set_item(Some(next.to_item(stream_id, source_id)))
+                .condition_expression("attribute_not_exists(PK) OR #copied = :copied")
+                .expression_attribute_names("#copied", "copied")
+                .expression_attribute_values(
+                    ":copied",
+                    AttributeValue::N(checkpoint.copied.to_string()),
+                )
+                .build()
+                .map_err(|e| Error::Internal(e.to_string()))?;
+            items.push(TransactWriteItem::builder().put(put).build());
+
+            self.client
+                .transact_write_items()
+                .set_transact_items(Some(items))
+                .send()
+                .await
+                .map_err(|e| {
+                    let Some(TransactWriteItemsError::TransactionCanceledException(cancelled)) =
+                        e.as_service_error()
+                    else {
+                        return database_error(e);
+                    };
+                    // Reasons line up with the items: each event, then the checkpoint
+                    let failed = cancelled
+                        .cancellation_reasons()
+                        .iter()
+                        .position(|reason| reason.code() == Some("ConditionalCheckFailed"));
+                    match failed {
+                        Some(index) if index < stored_events.len() => {
+                            sequence_collision(&stored_events[index])
+                        }
+                        Some(_) => Error::CommitConflict(format!(
+                            "Another repartition is copying stream '{}' into '{}'",
+                            source_id, stream_id
+                        )),
+                        None => database_error(e),
+                    }
+                })?;
+            *checkpoint = next;
+            published += chunk.len() as u64;
+        }
+        Ok(published)
+    }
+
+    /// How far a repartition from `source_id` into `stream_id` has copied, if
+    /// it has started
+    async fn get_repartition_checkpoint(
+        &self,
+        stream_id: &str,
+        source_id: &str,
+    ) -> Result<Option<RepartitionCheckpoint>> {
+        let result = self
+            .client
+            .get_item()
+            .table_name(&self.table_name)
+            .key(
+                "PK",
+                AttributeValue::S(format!("STREAM#{}#REPARTITION", stream_id)),
+            )
+            .key("SK", AttributeValue::S(format!("FROM#{}", source_id)))
+            .consistent_read(true)
+            .send()
+            .await
+            .map_err(database_error)?;
+
+        let Some(item) = result.item else {
+            return Ok(None);
+        };
+        let copied = match item.get("copied") {
+            Some(AttributeValue::N(n)) => n.parse().ok(),
+            _ => None,
+        };
+        let offsets = match item.get("offsets") {
+            Some(AttributeValue::L(values)) => values
+                .iter()
+                .map(|value| match value {
+                    AttributeValue::N(n) => n.parse().ok(),
+                    _ => None,
+                })
+                .collect(),
+            _ => None,
+        };
+        match (copied, offsets) {
+            (Some(copied), Some(offsets)) => Ok(Some(RepartitionCheckpoint { copied, offsets })),
+            _ => Err(Error::DynamoSerialization(format!(
+                "Malformed repartition checkpoint for stream '{}'",
+                stream_id
+            ))),
+        }
+    }
+}
+
+/// How far a repartition has copied its source, stored under the target
+/// stream so that it is deleted with it
+#[derive(Debug, Clone)]
+struct RepartitionCheckpoint {
+    /// Events copied so far, to detect another run writing concurrently
+    copied: u64,
+    /// Last source sequence copied from each partition
+    offsets: Vec<u64>,
+}
+
+impl RepartitionCheckpoint {
+    fn to_item(&self, stream_id: &str, source_id: &str) -> HashMap<String, AttributeValue> {
+        let offsets = self
+            .offsets
+            .iter()
+            .map(|offset| AttributeValue::N(offset.to_string()))
+            .collect();
+        HashMap::from([
+            (
+                "PK".to_string(),
+                AttributeValue::S(format!("STREAM#{}#REPARTITION", stream_id)),
+            ),
+            (
+                "SK".to_string(),
+                AttributeValue::S(format!("FROM#{}", source_id)),
+            ),
+            (
+                "copied".to_string(),
+                AttributeValue::N(self.copied.to_string()),
+            ),
+            ("offsets".to_string(), AttributeValue::L(offsets)),
+        ])
+    }
+}
+
+/// An idempotency record as stored under its stream
+fn idempotency_item(record: &IdempotencyRecord) -> Result<HashMap<String, AttributeValue>> {
+    let mut item: HashMap<String, AttributeValue> =
+        to_item(record).map_err(|e| Error::DynamoSerialization(e.to_string()))?;
+    item.insert(
+        "PK".to_string(),
+        AttributeValue::S(format!("STREAM#{}#IDEMPOTENCY", record.stream_id)),
+    );
+    item.insert(
+        "SK".to_string(),
+        AttributeValue::S(format!("KEY#{}", record.idempotency_key)),
+    );
+    Ok(item)
+}
+
+/// `created_at` of an idempotency claim as stored, identifying the claim
+fn claim_created_at(claim: &IdempotencyRecord) -> Result<AttributeValue> {
+    to_attribute_value(claim.created_at).map_err(|e| Error::DynamoSerialization(e.to_string()))
+}
+
+/// The compacted state an event leaves its key in
+fn compacted_state(event: Event) -> CompactedEvent {
+    CompactedEvent {
+        id: event.id,
+        stream_id: event.stream_id,
+        key: event.key,
+        event_type: event.event_type,
+        data: event.data,
+        sequence: event.sequence,
+        partition: event.partition,
+        timestamp: event.timestamp,
+    }
+}
+
+/// An event as stored in its partition
+fn event_item(event: &Event) -> Result<HashMap<String, AttributeValue>> {
+    let mut item: HashMap<String, AttributeValue> =
+        to_item(event).map_err(|e| Error::DynamoSerialization(e.to_string()))?;
+    item.insert(
+        "PK".to_string(),
+        AttributeValue::S(format!("STREAM#{}#P{}", event.stream_id, event.partition)),
+    );
+    item.insert(
+        "SK".to_string(),
+        AttributeValue::S(format!("SEQ#{:020}", event.sequence)),
+    );
+    Ok(item)
+}
+
+/// Reference to a stored event, for a publish response
+fn published_event(input_index: usize, event: Event) -> PublishedEvent {
+    PublishedEvent {
+        input_index,
+        id: event.id,
+        stream_id: event.stream_id,
+        partition: event.partition,
+        sequence: event.sequence,
+        global_position: event.global_position,
+        key: event.key,
+        timestamp: event.timestamp,
+    }
+}
+
+/// An event's write found its sequence already taken
+fn sequence_collision(event: &Event) -> Error {
+    Error::Internal(format!(
+        "Sequence collision: event {} already exists in partition {}",
+        event.sequence, event.partition
+    ))
+}
+
+/// `committed_at` as stored on offset items, at a fixed width so conditions
+/// can compare it
+fn committed_at_value(committed_at: DateTime<Utc>) -> String {
+    committed_at.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+/// Partition key holding a subscription's stored cursor tokens
+fn cursor_token_pk(stream_id: &str, subscription_id: &str) -> String {
+    format!("STREAM#{}#SUB#{}#CURSOR", stream_id, subscription_id)
+}
+
+/// Sort key of the changelog item recording where an event's compacted state
+/// was logged; it sorts before every `LOG#` entry
+fn applied_sk(partition: u32, sequence: u64) -> String {
+    format!("APPLIED#P{}#{:020}", partition, sequence)
+}
+
+/// A compacted state's timestamp as nanoseconds since the epoch, saturating
+/// outside the years 1677 to 2262
+/// Give the event attributes a projected read left out empty values, so the
+/// item still deserializes (`EventProjection::apply` drops them again)
+fn fill_unprojected(item: &mut HashMap<String, AttributeValue>) {
+    for (name, empty) in [
+        ("key", AttributeValue::S(String::new())),
+        ("event_type", AttributeValue::S(String::new())),
+        ("data", AttributeValue::Null(true)),
+    ] {
+        item.entry(name.to_string()).or_insert(empty);
+    }
+}
+
+fn compacted_timestamp_ns(timestamp: DateTime<Utc>) -> i64 {
+    timestamp
+        .timestamp_nanos_opt()
+        .unwrap_or(if timestamp.timestamp() < 0 {
+            i64::MIN
+        } else {
+            i64::MAX
+        })
+}
+
+/// First value of a block of `count` reserved by a counter update, from the
+/// `sequence` it returned
+fn first_reserved(attributes: Option<HashMap<String, AttributeValue>>, count: u64) -> Result<u64> {
+    let attrs = attributes.ok_or_else(|| Error::Internal("No attributes returned".to_string()))?;
+    let seq_attr = attrs
+        .get("sequence")
+        .ok_or_else(|| Error::Internal("No sequence attribute".to_string()))?;
+
+    let last = match seq_attr {
+        AttributeValue::N(n) => n
+            .parse::<u64>()
+            .map_err(|e| Error::Internal(e.to_string()))?,
+        _ => return Err(Error::Internal("Invalid sequence type".to_string())),
+    };
+    Ok(last + 1 - count)
+}
+
+/// Error codes DynamoDB uses when a request exceeds the table's throughput
+const THROTTLING_ERROR_CODES: [&str; 3] = [
+    "ProvisionedThroughputExceededException",
+    "ThrottlingException",
+    "RequestLimitExceeded",
+];
+
+/// Convert an SDK error, telling throttling (429, worth retrying) and a
+/// missing table (503, a misconfigured deployment) apart from other database
+/// failures
+fn database_error<E: ProvideErrorMetadata, R>(e: SdkError<E, R>) -> Error
+where
+    SdkError<E, R>: std::fmt::Display,
+{
+    match e.as_service_error().and_then(|se| se.code()) {
+        Some(code) if THROTTLING_ERROR_CODES.contains(&code) => Error::Throttled(code.to_string()),
+        Some("ResourceNotFoundException") => Error::TableNotFound(format!(
+            "{} must name an existing DynamoDB table in this account and region",
+            TABLE_NAME_ENV
+        )),
+        _ => Error::Database(e.to_string()),
+    }
+}
+
+/// Whether a conditional write failed its condition (as opposed to any other error)
+fn is_conditional_check_failed<E: ProvideErrorMetadata, R>(e: &SdkError<E, R>) -> bool {
+    e.as_service_error().and_then(|se| se.code()) == Some("ConditionalCheckFailedException")
+}
+
+/// Per-partition totals from a compaction backfill
+struct PartitionBackfill {
+    partition: u32,
+    /// Last sequence scanned
+    offset: u64,
+    events_scanned: u64,
+    keys_compacted: u64,
+    complete: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FixedClock;
+    use crate::test_util::{fake_dynamo, offline_client, sdk_config, OK};
+    use std::io::Read;
+    use std::net::TcpListener;
+
+    /// GetItem answer for a one-partition "orders" stream
+    const ORDERS_ITEM: &str = r#"{"Item":{"stream_id":{"S":"orders"},"partition_count":{"N":"1"},"retention_hours":{"N":"24"},"created_at":{"S":"2025-01-01T00:00:00Z"}}}"#;
+
+    #[test]
+    fn test_table_override_requires_opt_in() {
+        let base =
+            DynamoClient::with_table_name(Client::new(&sdk_config()), "eventledger".to_string());
+
+        let client = base.with_table_override(Some("isolated-table"), false);
+        assert_eq!(client.table_name(), "eventledger");
+
+        let client = base.with_table_override(Some("isolated-table"), true);
+        assert_eq!(client.table_name(), "isolated-table");
+
+        // No header falls back to the configured table
+        let client = base.with_table_override(None, true);
+        assert_eq!(client.table_name(), "eventledger");
+    }
+
+    #[tokio::test]
+    async fn test_from_config_uses_local_endpoint() {
+        // Stand-in for local DynamoDB: capture the first request and hang up
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 4096];
+            let n = stream.read(&mut buf).unwrap();
+            String::from_utf8_lossy(&buf[..n]).to_string()
+        });
+
+        let client = DynamoClient::from_config_with_endpoint(&sdk_config(), Some(&endpoint));
+        let result = client.get_stream("orders").await;
+        assert!(matches!(result, Err(Error::Database(_))));
+
+        let request = server.join().unwrap();
+        assert!(request.contains("DynamoDB_20120810.GetItem"));
+    }
+
+    #[tokio::test]
+    async fn test_head_stream_missing_is_not_an_error() {
+        // Answer the GetItem with no Item, as DynamoDB does for a missing key
+        let (endpoint, server) = fake_dynamo([(OK, "{}")]);
+
+        let client = DynamoClient::from_config_with_endpoint(&sdk_config(), Some(&endpoint));
+        let exists = client.head_stream("orders").await.unwrap();
+        assert!(!exists);
+
+        // Only the key is fetched, not the stream's metadata
+        let requests = server.join().unwrap();
+        assert!(requests[0].contains(r#""ProjectionExpression":"PK""#));
+    }
+
+    #[tokio::test]
+    async fn test_throughput_exceeded_is_throttled() {
+        let (endpoint, server) = fake_dynamo([(
+            "400 Bad Request",
+            r#"{"__type":"com.amazonaws.dynamodb.v20120810#ProvisionedThroughputExceededException","message":"Rate of requests exceeds the allowed throughput"}"#,
+        )]);
+
+        let client = DynamoClient::from_config_with_endpoint(&sdk_config(), Some(&endpoint));
+        let err = client.get_stream("orders").await.unwrap_err();
+        server.join().unwrap();
+
+        assert!(matches!(
+            err,
+            Error::Throttled(ref code) if code == "ProvisionedThroughputExceededException"
+        ));
+        assert_eq!(err.status_code(), 429);
+    }
+
+    #[tokio::test]
+    async fn test_missing_table_is_table_not_found() {
+        let (endpoint, server) = fake_dynamo([(
+            "400 Bad Request",
+            r#"{"__type":"com.amazonaws.dynamodb.v20120810#ResourceNotFoundException","message":"Requested resource not found"}"#,
+        )]);
+
+        let client = DynamoClient::from_config_with_endpoint(&sdk_config(), Some(&endpoint));
+        let err = client.get_stream("orders").await.unwrap_err();
+        server.join().unwrap();
+
+        assert!(matches!(err, Error::TableNotFound(_)), "{:?}", err);
+        assert_eq!(err.status_code(), 503);
+        assert!(err.to_string().contains(TABLE_NAME_ENV));
+    }
+
+    #[tokio::test]
+    async fn test_publish_reserves_sequences_once_per_batch() {
+        // Stream lookup, one counter update for the whole batch, then the writes
+        let (endpoint, server) = fake_dynamo(
+            [
+                ORDERS_ITEM,
+                r#"{"Attributes":{"sequence":{"N":"13"}}}"#,
+                "{}",
+                "{}",
+                "{}",
+            ]
+            .map(|body| (OK, body)),
+        );
+
+        let client = DynamoClient::from_config_with_endpoint(&sdk_config(), Some(&endpoint));
+        let events: Vec<PublishEvent> = (0..3)
+            .map(|i| PublishEvent {
+                key: format!("order-{}", i),
+                partition_key: None,
+                order_key: None,
+                timestamp: None,
+                event_type: "order.created".parse().unwrap(),
+                data: serde_json::json!({}),
+            })
+            .collect();
+        let published = client.publish_events("orders", &events).await.unwrap();
+
+        let sequences: Vec<u64> = published.iter().map(|e| e.sequence).collect();
+        assert_eq!(sequences, vec![11, 12, 13]);
+
+        let requests = server.join().unwrap();
+        let updates: Vec<_> = requests
+            .iter()
+            .filter(|r| r.contains("DynamoDB_20120810.UpdateItem"))
+            .collect();
+        assert_eq!(updates.len(), 1);
+        assert!(updates[0].contains(r#"":inc":{"N":"3"}"#));
+    }
+
+    #[tokio::test]
+    async fn test_publish_rereads_a_stream_recreated_since_it_was_cached() {
+        // The cached stream had 3 partitions; the one there now has 1 and its
+        // counters carry its own creation time, so both updates fail
+        let stale = r#"{"Item":{"stream_id":{"S":"orders"},"partition_count":{"N":"3"},"retention_hours":{"N":"24"},"created_at":{"S":"2024-01-01T00:00:00Z"}}}"#;
+        let condition_failed = r#"{"__type":"com.amazonaws.dynamodb.v20120810#ConditionalCheckFailedException","message":"The conditional request failed"}"#;
+        let (endpoint, server) = fake_dynamo([
+            (OK, stale),
+            ("400 Bad Request", condition_failed),
+            ("400 Bad Request", condition_failed),
+            (OK, ORDERS_ITEM),
+            (OK, r#"{"Attributes":{"sequence":{"N":"1"}}}"#),
+            (OK, "{}"),
+        ]);
+
+        let client = DynamoClient::from_config_with_endpoint(&sdk_config(), Some(&endpoint));
+        let event = PublishEvent {
+            key: "order-1".to_string(),
+            partition_key: None,
+            order_key: None,
+            timestamp: None,
+            event_type: "order.created".parse().unwrap(),
+            data: serde_json::json!({}),
+        };
+        let published = client.publish_events("orders", &[event]).await.unwrap();
+        assert_eq!((published[0].partition, published[0].sequence), (0, 1));
+
+        let requests = server.join().unwrap();
+        let stale_created = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z").unwrap();
+        assert!(requests[1].contains(&format!(
+            r#"":created":{{"N":"{}"}}"#,
+            stale_created.timestamp_micros()
+        )));
+        assert!(requests[3].contains("DynamoDB_20120810.GetItem"));
+        assert!(requests[4].contains("STREAM#orders#P0"));
+    }
+
+    #[tokio::test]
+    async fn test_publish_behind_partition_clock_keeps_timestamps_monotonic() {
+        // The counter's latest timestamp is ahead of this clock, so the guarded
+        // update fails and the plain one hands back the timestamp to use
+        let ahead = Utc::now() + chrono::Duration::hours(1);
+        let counter = format!(
+            r#"{{"Attributes":{{"PK":{{"S":"STREAM#orders#P0"}},"SK":{{"S":"COUNTER"}},"sequence":{{"N":"5"}},"latest_timestamp_us":{{"N":"{}"}}}}}}"#,
+            ahead.timestamp_micros()
+        );
+        let (endpoint, server) = fake_dynamo([
+            (OK, ORDERS_ITEM.to_string()),
+            (
+                "400 Bad Request",
+                r#"{"__type":"com.amazonaws.dynamodb.v20120810#ConditionalCheckFailedException","message":"The conditional request failed"}"#.to_string(),
+            ),
+            (OK, counter),
+            (OK, "{}".to_string()),
+            (OK, "{}".to_string()),
+        ]);
+
+        let client = DynamoClient::from_config_with_endpoint(&sdk_config(), Some(&endpoint));
+        let events: Vec<PublishEvent> = (0..2)
+            .map(|i| PublishEvent {
+                key: format!("order-{}", i),
+                partition_key: None,
+                order_key: None,
+                timestamp: None,
+                event_type: "order.created".parse().unwrap(),
+                data: serde_json::json!({}),
+            })
+            .collect();
+        let published = client.publish_events("orders", &events).await.unwrap();
+
+        let expected = DateTime::from_timestamp_micros(ahead.timestamp_micros()).unwrap();
+        assert!(published.iter().all(|e| e.timestamp == expected));
+
+        let requests = server.join().unwrap();
+        let puts: Vec<_> = requests
+            .iter()
+            .filter(|r| r.contains("DynamoDB_20120810.PutItem"))
+            .collect();
+        assert_eq!(puts.len(), 2);
+        assert!(puts.iter().all(|r| r.contains("wall_clock_timestamp")));
+    }
+
+    #[tokio::test]
+    async fn test_publish_stamps_events_from_injected_clock() {
+        let (endpoint, server) = fake_dynamo(
+            [
+                ORDERS_ITEM,
+                r#"{"Attributes":{"sequence":{"N":"2"}}}"#,
+                "{}",
+                "{}",
+            ]
+            .map(|body| (OK, body)),
+        );
+
+        let fixed = DateTime::parse_from_rfc3339("2025-06-01T12:00:00.123456Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let client = DynamoClient::from_config_with_endpoint(&sdk_config(), Some(&endpoint))
+            .with_clock(FixedClock(fixed));
+        let events: Vec<PublishEvent> = (0..2)
+            .map(|i| PublishEvent {
+                key: format!("order-{}", i),
+                partition_key: None,
+                order_key: None,
+                timestamp: None,
+                event_type: "order.created".parse().unwrap(),
+                data: serde_json::json!({}),
+            })
+            .collect();
+        let published = client.publish_events("orders", &events).await.unwrap();
+
+        assert!(published.iter().all(|e| e.timestamp == fixed));
+
+        // The partition counter records the same instant
+        let requests = server.join().unwrap();
+        let update = requests
+            .iter()
+            .find(|r| r.contains("DynamoDB_20120810.UpdateItem"))
+            .unwrap();
+        assert!(update.contains(&fixed.timestamp_micros().to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_transaction_reports_commit_conflict() {
+        // Stream lookup and counter update, then the transaction, cancelled by
+        // its offset update (the last item)
+        let (endpoint, server) = fake_dynamo([
+            (
+                OK,
+                r#"{"Item":{"stream_id":{"S":"enriched"},"partition_count":{"N":"1"},"retention_hours":{"N":"24"},"created_at":{"S":"2025-01-01T00:00:00Z"}}}"#,
+            ),
+            (OK, r#"{"Attributes":{"sequence":{"N":"2"}}}"#),
+            (
+                "400 Bad Request",
+                r#"{"__type":"com.amazonaws.dynamodb.v20120810#TransactionCanceledException","message":"Transaction cancelled","CancellationReasons":[{"Code":"None"},{"Code":"None"},{"Code":"ConditionalCheckFailed"}]}"#,
+            ),
+        ]);
+
+        let client = DynamoClient::from_config_with_endpoint(&sdk_config(), Some(&endpoint));
+        let events: Vec<PublishEvent> = (0..2)
+            .map(|i| PublishEvent {
+                key: format!("order-{}", i),
+                partition_key: None,
+                order_key: None,
+                timestamp: None,
+                event_type: "order.enriched".parse().unwrap(),
+                data: serde_json::json!({}),
+            })
+            .collect();
+        let offsets = [PartitionOffset {
+            partition: 0,
+            offset: 7,
+        }];
+        let since = Utc::now();
+        let err = client
+            .transact_publish_and_commit("enriched", &events, "orders", "enricher", &offsets, since)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::CommitConflict(_)), "{:?}", err);
+
+        // The events went only into the transaction, alongside the offset
+        let requests = server.join().unwrap();
+        assert!(!requests
+            .iter()
+            .any(|r| r.contains("DynamoDB_20120810.PutItem")));
+        let transaction = &requests[2];
+        assert!(transaction.contains("DynamoDB_20120810.TransactWriteItems"));
+        assert_eq!(transaction.matches(r#""Put":"#).count(), 2);
+        assert!(transaction.contains("STREAM#orders#SUB#enricher"));
+        assert!(transaction.contains(r#""ClientRequestToken":"#));
+    }
+
+    #[tokio::test]
+    async fn test_commit_offsets_writes_every_partition_in_one_transaction() {
+        let (endpoint, server) = fake_dynamo([(
+            "400 Bad Request",
+            r#"{"__type":"com.amazonaws.dynamodb.v20120810#TransactionCanceledException","message":"Transaction cancelled","CancellationReasons":[{"Code":"None"},{"Code":"ConditionalCheckFailed"}]}"#,
+        )]);
+
+        let client = DynamoClient::from_config_with_endpoint(&sdk_config(), Some(&endpoint));
+        let offsets = [
+            PartitionOffset {
+                partition: 0,
+                offset: 7,
+            },
+            PartitionOffset {
+                partition: 1,
+                offset: 3,
+            },
+        ];
+        let err = client
+            .commit_offsets("orders", "billing", &offsets, Some(Utc::now()))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::CommitConflict(_)), "{:?}", err);
+
+        // Both partitions were conditioned in the same request
+        let requests = server.join().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].contains("DynamoDB_20120810.TransactWriteItems"));
+        assert_eq!(requests[0].matches(r#""ConditionExpression":"#).count(), 2);
+        assert!(requests[0].contains("OFFSET#P0"));
+        assert!(requests[0].contains("OFFSET#P1"));
+    }
+
+    #[tokio::test]
+    async fn test_transaction_over_item_limit_is_rejected_up_front() {
+        // Nothing listens here; the request must fail before reaching DynamoDB
+        let client =
+            DynamoClient::from_config_with_endpoint(&sdk_config(), Some("http://127.0.0.1:9"));
+        let events: Vec<PublishEvent> = (0..TRANSACT_WRITE_MAX_ITEMS)
+            .map(|i| PublishEvent {
+                key: format!("order-{}", i),
+                partition_key: None,
+                order_key: None,
+                timestamp: None,
+                event_type: "order.enriched".parse().unwrap(),
+                data: serde_json::json!({}),
+            })
+            .collect();
+        let offsets = [PartitionOffset {
+            partition: 0,
+            offset: 7,
+        }];
+        let since = Utc::now();
+        let err = client
+            .transact_publish_and_commit("enriched", &events, "orders", "enricher", &offsets, since)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Validation(_)), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn test_create_stream_rejects_partition_counts_out_of_range() {
+        // Nothing listens here; the request must fail before reaching DynamoDB
+        let client =
+            DynamoClient::from_config_with_endpoint(&sdk_config(), Some("http://127.0.0.1:9"));
+        for partition_count in [0, max_partitions() + 1] {
+            let req = CreateStreamRequest {
+                stream_id: "orders".to_string(),
+                partition_count: Some(partition_count),
+                ..Default::default()
+            };
+            let err = client.create_stream(&req).await.unwrap_err();
+            assert!(matches!(err, Error::Validation(_)), "{:?}", err);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batch_get_streams_retries_unprocessed_keys() {
+        // First answer returns "orders" and leaves "payments" unprocessed; the
+        // retry finds nothing, as for a stream that doesn't exist
+        let (endpoint, server) = fake_dynamo(
+            [
+                r#"{"Responses":{"eventledger":[{"stream_id":{"S":"orders"},"partition_count":{"N":"4"},"retention_hours":{"N":"24"},"created_at":{"S":"2025-01-01T00:00:00Z"}}]},"UnprocessedKeys":{"eventledger":{"Keys":[{"PK":{"S":"STREAM#payments"},"SK":{"S":"META"}}]}}}"#,
+                r#"{"Responses":{"eventledger":[]},"UnprocessedKeys":{}}"#,
+            ]
+            .map(|body| (OK, body)),
+        );
+
+        let client = DynamoClient::with_table_name(
+            DynamoClient::from_config_with_endpoint(&sdk_config(), Some(&endpoint)).client,
+            "eventledger".to_string(),
+        );
+        let ids = ["payments", "orders", "orders"].map(String::from);
+        let streams = client.batch_get_streams(&ids).await.unwrap();
+
+        assert_eq!(streams.len(), 1);
+        assert_eq!(streams[0].stream_id, "orders");
+        assert_eq!(streams[0].partition_count, 4);
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].contains("DynamoDB_20120810.BatchGetItem"));
+        // The retry asks only for the unprocessed key
+        assert!(requests[1].contains("STREAM#payments"));
+        assert!(!requests[1].contains("STREAM#orders"));
+    }
+
+    #[tokio::test]
+    async fn test_list_streams_page_queries_the_streams_index() {
+        let (endpoint, server) = fake_dynamo([(
+            OK,
+            r#"{"Items":[{"PK":{"S":"STREAM#prod-orders"},"SK":{"S":"META"},"streams_pk":{"S":"STREAM"},"stream_id":{"S":"prod-orders"},"partition_count":{"N":"4"},"retention_hours":{"N":"24"},"created_at":{"S":"2025-01-01T00:00:00Z"}}],"LastEvaluatedKey":{"PK":{"S":"STREAM#prod-orders"},"SK":{"S":"META"},"streams_pk":{"S":"STREAM"},"stream_id":{"S":"prod-orders"}}}"#,
+        )]);
+
+        let client = DynamoClient::from_config_with_endpoint(&sdk_config(), Some(&endpoint));
+        let (streams, next) = client
+            .list_streams_page(Some("prod-"), Some("prod-audit"), 1)
+            .await
+            .unwrap();
+
+        assert_eq!(streams.len(), 1);
+        assert_eq!(streams[0].stream_id, "prod-orders");
+        assert_eq!(next.as_deref(), Some("prod-orders"));
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].contains("DynamoDB_20120810.Query"));
+        assert!(requests[0].contains(r#""IndexName":"streams-index""#));
+        assert!(requests[0].contains(r#""ExclusiveStartKey":"#));
+        assert!(requests[0].contains("STREAM#prod-audit"));
+        assert!(requests[0].contains(r#""Limit":1"#));
+    }
+
+    #[tokio::test]
+    async fn test_update_stream_rejects_the_last_version() {
+        let req = UpdateStreamRequest {
+            retention_hours: Some(24),
+            ..Default::default()
+        };
+        let err = offline_client()
+            .update_stream("orders", u64::MAX, &req)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Validation(_)));
+    }
+
+    #[tokio::test]
+    async fn test_list_subscriptions_follows_every_page() {
+        let sub = |id: &str| {
+            format!(
+                r#"{{"stream_id":{{"S":"orders"}},"subscription_id":{{"S":"{}"}},"created_at":{{"S":"2025-01-01T00:00:00Z"}}}}"#,
+                id
+            )
+        };
+        let (endpoint, server) = fake_dynamo([
+            (
+                OK,
+                format!(
+                    r#"{{"Items":[{}],"LastEvaluatedKey":{{"PK":{{"S":"STREAM#orders"}},"SK":{{"S":"SUB#billing"}}}}}}"#,
+                    sub("billing")
+                ),
+            ),
+            (OK, format!(r#"{{"Items":[{}]}}"#, sub("shipping"))),
+        ]);
+
+        let client = DynamoClient::from_config_with_endpoint(&sdk_config(), Some(&endpoint));
+        let subscriptions = client.list_subscriptions("orders").await.unwrap();
+
+        let ids: Vec<_> = subscriptions
+            .iter()
+            .map(|s| s.subscription_id.as_str())
+            .collect();
+        assert_eq!(ids, ["billing", "shipping"]);
+        let requests = server.join().unwrap();
+        assert!(!requests[0].contains("ExclusiveStartKey"));
+        assert!(requests[1].contains(r#""ExclusiveStartKey":"#));
+        assert!(requests[1].contains("SUB#billing"));
+    }
+
+    #[tokio::test]
+    async fn test_batch_get_streams_caps_the_id_count() {
+        let ids: Vec<String> = (0..=MAX_BATCH_GET_STREAMS)
+            .map(|i| format!("s{}", i))
+            .collect();
+        let result = offline_client().batch_get_streams(&ids).await;
+        assert!(matches!(result, Err(Error::Validation(_))));
+    }
+
+    #[tokio::test]
+    async fn test_projected_read_leaves_unread_fields_empty() {
+        let (endpoint, _server) = fake_dynamo([(
+            OK,
+            r#"{"Items":[{"id":{"S":"abc"},"stream_id":{"S":"orders"},"partition":{"N":"0"},"sequence":{"N":"7"},"timestamp":{"S":"2025-01-01T00:00:00Z"},"key":{"S":"order-1"}}]}"#,
+        )]);
+
+        let client = DynamoClient::from_config_with_endpoint(&sdk_config(), Some(&endpoint));
+        let projection = EventProjection::parse("key,sequence").unwrap();
+        let events = client
+            .read_events_projected("orders", 0, 0, 10, &projection)
+            .await
+            .unwrap();
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].key, "order-1");
+        assert!(events[0].event_type.is_empty());
+        assert!(events[0].data.is_null());
+    }
+
+    #[tokio::test]
+    async fn test_compacted_pages_by_timestamp_resume_from_index_key() {
+        let (endpoint, server) = fake_dynamo([(
+            OK,
+            r#"{"Items":[{"stream_id":{"S":"orders"},"key":{"S":"a#1"},"event_type":{"S":"test.event"},"data":{"M":{}},"sequence":{"N":"1"},"partition":{"N":"0"},"timestamp":{"S":"1970-01-01T00:00:00.000000042Z"}}],"LastEvaluatedKey":{"PK":{"S":"STREAM#orders#COMPACT"},"SK":{"S":"KEY#a#1"},"timestamp_ns":{"N":"42"}}}"#,
+        )]);
+
+        let client = DynamoClient::from_config_with_endpoint(&sdk_config(), Some(&endpoint));
+        let (events, next_key) = client
+            .list_compacted_page_by_timestamp("orders", Some("7#z"), 1, true, None)
+            .await
+            .unwrap();
+
+        assert_eq!(events.len(), 1);
+        // Keys may contain '#'; only the first one ends the timestamp
+        assert_eq!(next_key.as_deref(), Some("42#a#1"));
+        let requests = server.join().unwrap();
+        assert!(requests[0].contains(r#""IndexName":"compacted-time-index""#));
+        assert!(requests[0].contains(r#""timestamp_ns":{"N":"7"}"#));
+        assert!(requests[0].contains("KEY#z"));
+
+        let result = offline_client()
+            .list_compacted_page_by_timestamp("orders", Some("z"), 1, false, None)
+            .await;
+        assert!(matches!(result, Err(Error::Validation(_))));
+    }
+
+    #[tokio::test]
+    async fn test_seek_all_reports_subscriptions_it_could_not_move() {
+        let stream = r#"{"Item":{"stream_id":{"S":"orders"},"partition_count":{"N":"1"},"retention_hours":{"N":"24"},"created_at":{"S":"2025-01-01T00:00:00Z"}}}"#;
+        let (endpoint, _server) = fake_dynamo([
+            (OK, stream.to_string()),
+            (
+                OK,
+                r#"{"Items":[{"stream_id":{"S":"orders"},"subscription_id":{"S":"billing"},"created_at":{"S":"2025-01-01T00:00:00Z"}}]}"#.to_string(),
+            ),
+            (OK, stream.to_string()),
+            // Deleted since it was listed
+            (OK, "{}".to_string()),
+        ]);
+
+        let client = DynamoClient::from_config_with_endpoint(&sdk_config(), Some(&endpoint));
+        let response = client
+            .seek_all_subscriptions("orders", &StartFrom::Earliest)
+            .await
+            .unwrap();
+
+        assert_eq!(response.repositioned, 0);
+        assert_eq!(response.failed.len(), 1);
+        assert_eq!(response.failed[0].subscription_id, "billing");
+        assert_eq!(response.failed[0].error.error, "subscription_not_found");
+    }
+
+    #[tokio::test]
+    async fn test_latest_written_offset_reads_the_newest_stored_event() {
+        let (endpoint, server) = fake_dynamo([
+            (OK, r#"{"Items":[{"sequence":{"N":"7"}}],"Count":1}"#),
+            (OK, r#"{"Items":[],"Count":0}"#),
+        ]);
+
+        let client = DynamoClient::from_config_with_endpoint(&sdk_config(), Some(&endpoint));
+        assert_eq!(
+            client.get_latest_written_offset("orders", 2).await.unwrap(),
+            7
+        );
+        assert_eq!(
+            client.get_latest_written_offset("orders", 3).await.unwrap(),
+            0
+        );
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].contains("DynamoDB_20120810.Query"));
+        assert!(requests[0].contains("STREAM#orders#P2"));
+        assert!(requests[0].contains(r#""ScanIndexForward":false"#));
+        assert!(requests[0].contains(r#""Limit":1"#));
+    }
+
+    #[tokio::test]
+    async fn test_count_events_pages_through_the_partition() {
+        // The first page stops at the 1MB cap; the count continues after it
+        let (endpoint, server) = fake_dynamo(
+            [
+                r#"{"Count":2,"ScannedCount":2,"LastEvaluatedKey":{"PK":{"S":"STREAM#orders#P0"},"SK":{"S":"SEQ#00000000000000000007"}}}"#,
+                r#"{"Count":3,"ScannedCount":3}"#,
+            ]
+            .map(|body| (OK, body)),
+        );
+
+        let client = DynamoClient::from_config_with_endpoint(&sdk_config(), Some(&endpoint));
+        assert_eq!(
+            client
+                .count_events_in_partition("orders", 0, 4)
+                .await
+                .unwrap(),
+            5
+        );
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].contains(r#""Select":"COUNT""#));
+        // Counting starts after the offset and resumes where the first page stopped
+        assert!(requests[0].contains("SEQ#00000000000000000005"));
+        assert!(requests[1].contains(r#""ExclusiveStartKey""#));
+    }
+
+    #[tokio::test]
+    async fn test_compacted_snapshot_reads_past_a_lagging_compactor() {
+        let event = |sequence: u64, key: &str, status: &str| {
+            format!(
+                r#"{{"stream_id":{{"S":"orders"}},"partition":{{"N":"0"}},"sequence":{{"N":"{}"}},"key":{{"S":"{}"}},"event_type":{{"S":"order.updated"}},"data":{{"M":{{"status":{{"S":"{}"}}}}}},"timestamp":{{"S":"2025-01-01T00:00:0{}Z"}}}}"#,
+                sequence, key, status, sequence
+            )
+        };
+        // Published through 3, but the compactor has only applied through 1;
+        // order-3 was compacted after the tail was read
+        let (endpoint, server) = fake_dynamo(
+            [
+                ORDERS_ITEM.to_string(),
+                r#"{"Item":{"sequence":{"N":"3"}}}"#.to_string(),
+                r#"{"Items":[{"SK":{"S":"P0"},"sequence":{"N":"1"}}]}"#.to_string(),
+                format!(
+                    r#"{{"Items":[{},{}]}}"#,
+                    event(1, "order-1", "new"),
+                    event(4, "order-3", "new")
+                ),
+                format!(
+                    r#"{{"Items":[{},{}]}}"#,
+                    event(2, "order-1", "paid"),
+                    event(3, "order-2", "new")
+                ),
+            ]
+            .map(|body| (OK, body)),
+        );
+
+        let client = DynamoClient::from_config_with_endpoint(&sdk_config(), Some(&endpoint));
+        let (compacted, offsets) = client.compacted_snapshot("orders").await.unwrap();
+
+        let state: Vec<(&str, u64, &serde_json::Value)> = compacted
+            .iter()
+            .map(|event| (event.key.as_str(), event.sequence, &event.data["status"]))
+            .collect();
+        assert_eq!(
+            state,
+            vec![
+                ("order-1", 2, &serde_json::json!("paid")),
+                ("order-2", 3, &serde_json::json!("new")),
+            ]
+        );
+        let offsets: Vec<(u32, u64)> = offsets.iter().map(|o| (o.partition, o.offset)).collect();
+        assert_eq!(offsets, vec![(0, 3)]);
+
+        let requests = server.join().unwrap();
+        assert!(requests[2].contains("STREAM#orders#COMPACTED_THROUGH"));
+        // Events are read from where the compactor got to
+        assert!(requests[4].contains("SEQ#00000000000000000001"));
+    }
+
+    #[tokio::test]
+    async fn test_resumed_repartition_skips_events_already_copied() {
+        // A type the target no longer accepts is copied as is
+        let event = |sequence: u64| {
+            format!(
+                r#"{{"id":{{"S":"evt-{}"}},"stream_id":{{"S":"orders"}},"partition":{{"N":"0"}},"sequence":{{"N":"{}"}},"key":{{"S":"order-1"}},"event_type":{{"S":"order.legacy"}},"data":{{"M":{{}}}},"timestamp":{{"S":"2025-01-01T00:00:0{}Z"}}}}"#,
+                sequence, sequence, sequence
+            )
+        };
+        let target = r#"{"Item":{"stream_id":{"S":"orders-v2"},"partition_count":{"N":"1"},"retention_hours":{"N":"24"},"allowed_event_types":{"L":[{"S":"order.created"}]},"created_at":{"S":"2025-01-01T00:00:00Z"}}}"#;
+        // A failed run copied evt-1 but couldn't report its offsets
+        let checkpoint = r#"{"Item":{"copied":{"N":"1"},"offsets":{"L":[{"N":"1"}]}}}"#;
+        let (endpoint, server) = fake_dynamo(
+            [
+                ORDERS_ITEM.to_string(),
+                target.to_string(),
+                checkpoint.to_string(),
+                format!(r#"{{"Items":[{}]}}"#, event(2)),
+                r#"{"Items":[]}"#.to_string(),
+                target.to_string(),
+                r#"{"Attributes":{"sequence":{"N":"2"}}}"#.to_string(),
+                "{}".to_string(),
+            ]
+            .map(|body| (OK, body)),
+        );
+
+        let client = DynamoClient::from_config_with_endpoint(&sdk_config(), Some(&endpoint));
+        let req = RepartitionRequest {
+            target_stream_id: "orders-v2".to_string(),
+            partition_count: 1,
+            from_offsets: Some(vec![PartitionOffset {
+                partition: 0,
+                offset: 0,
+            }]),
+            alias: None,
+        };
+        let result = client
+            .repartition_stream("orders", &req, Duration::from_secs(30))
+            .await
+            .unwrap();
+
+        assert_eq!(result.events_copied, 1);
+        assert_eq!(result.offsets[0].offset, 2);
+        assert!(result.complete);
+
+        let requests = server.join().unwrap();
+        // Reading starts past the checkpoint, not the offset asked for
+        assert!(requests[3].contains("SEQ#00000000000000000001"));
+        // Only evt-2 is written, together with the checkpoint past it
+        let write = &requests[7];
+        assert!(write.contains("DynamoDB_20120810.TransactWriteItems"));
+        assert!(write.contains("order.legacy"));
+        assert!(write.contains("FROM#orders"));
+        assert!(write.contains(r#""offsets":{"L":[{"N":"2"}]}"#));
+        assert!(write.contains(r#"":copied":{"N":"1"}"#));
+        assert!(!write.contains("evt-1"));
+    }
+
+    fn compacted_order(sequence: u64) -> CompactedEvent {
+        CompactedEvent {
+            id: event_id("orders", 0, sequence),
+            stream_id: "orders".to_string(),
+            key: "order-1".to_string(),
+            event_type: "order.created".to_string(),
+            data: serde_json::json!({}),
+            sequence,
+            partition: 0,
+            timestamp: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_put_compacted_leaves_newer_state_alone() {
+        // The stored state supersedes this event, so the transaction is
+        // cancelled and neither the state nor the changelog is written
+        let (endpoint, server) = fake_dynamo([
+            (OK, r#"{"Item":{"sequence":{"N":"4"}}}"#),
+            (
+                "400 Bad Request",
+                r#"{"__type":"com.amazonaws.dynamodb.v20120810#TransactionCanceledException","message":"Transaction cancelled","CancellationReasons":[{"Code":"None"},{"Code":"None"},{"Code":"ConditionalCheckFailed"},{"Code":"None"}]}"#,
+            ),
+        ]);
+
+        let client = DynamoClient::from_config_with_endpoint(&sdk_config(), Some(&endpoint));
+        assert!(!client
+            .put_compacted(&compacted_order(3), Utc::now(), None)
+            .await
+            .unwrap());
+
+        let requests = server.join().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1].contains("DynamoDB_20120810.TransactWriteItems"));
+        assert!(requests[1].contains("#sequence < :sequence"));
+        assert!(requests[1].contains(r#""timestamp_ns""#));
+        assert!(requests[1].contains("LOG#00000000000000000005"));
+        assert!(requests[1].contains("APPLIED#P0#00000000000000000003"));
+    }
+
+    #[tokio::test]
+    async fn test_put_compacted_retries_a_taken_changelog_position() {
+        // Another change took position 5 between the read and the write, so
+        // the retry logs under 6 and never leaves 5 without an entry
+        let (endpoint, server) = fake_dynamo([
+            (OK, r#"{"Item":{"sequence":{"N":"4"}}}"#),
+            (
+                "400 Bad Request",
+                r#"{"__type":"com.amazonaws.dynamodb.v20120810#TransactionCanceledException","message":"Transaction cancelled","CancellationReasons":[{"Code":"ConditionalCheckFailed"},{"Code":"None"},{"Code":"None"},{"Code":"None"}]}"#,
+            ),
+            (OK, r#"{"Item":{"sequence":{"N":"5"}}}"#),
+            (OK, "{}"),
+        ]);
+
+        let client = DynamoClient::from_config_with_endpoint(&sdk_config(), Some(&endpoint));
+        assert!(client
+            .put_compacted(&compacted_order(3), Utc::now(), None)
+            .await
+            .unwrap());
+
+        let requests = server.join().unwrap();
+        assert_eq!(requests.len(), 4);
+        assert!(requests[1].contains("LOG#00000000000000000005"));
+        assert!(requests[3].contains("LOG#00000000000000000006"));
+        assert!(requests[3].contains(r#"":current":{"N":"5"}"#));
     }
 }
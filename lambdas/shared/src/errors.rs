@@ -2,6 +2,14 @@
 
 use thiserror::Error;
 
+use crate::models::ErrorResponse;
+
+/// Seconds a client is told to wait before retrying a throttled request
+///
+/// The SDK has already retried with backoff by the time throttling reaches
+/// the client, so this is a pause rather than an immediate retry.
+pub const THROTTLED_RETRY_AFTER_SECS: u64 = 1;
+
 /// Result type alias using EventLedger Error
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -16,6 +24,14 @@ pub enum Error {
     #[error("Stream already exists: {0}")]
     StreamAlreadyExists(String),
 
+    /// Stream alias not found
+    #[error("Alias not found: {0}")]
+    AliasNotFound(String),
+
+    /// Event not found
+    #[error("Event not found: {0}")]
+    EventNotFound(String),
+
     /// Subscription not found
     #[error("Subscription not found: {0}")]
     SubscriptionNotFound(String),
@@ -24,6 +40,22 @@ pub enum Error {
     #[error("Subscription already exists: {0}")]
     SubscriptionAlreadyExists(String),
 
+    /// Stream still has subscriptions and deletion wasn't forced
+    #[error("Stream in use: {0}")]
+    StreamInUse(String),
+
+    /// Offsets were committed by someone else since the cursor was issued
+    #[error("Commit conflict: {0}")]
+    CommitConflict(String),
+
+    /// An `Idempotency-Key` is held by a publish that hasn't finished
+    #[error("Idempotency key in use: {0}")]
+    IdempotencyKeyInUse(String),
+
+    /// An `Idempotency-Key` was reused with a different request body
+    #[error("Idempotency key mismatch: {0}")]
+    IdempotencyKeyMismatch(String),
+
     /// Invalid stream ID format
     #[error("Invalid stream ID: {0}")]
     InvalidStreamId(String),
@@ -32,9 +64,12 @@ pub enum Error {
     #[error("Invalid subscription ID: {0}")]
     InvalidSubscriptionId(String),
 
-    /// Invalid cursor
-    #[error("Invalid cursor: {0}")]
-    InvalidCursor(String),
+    /// Invalid cursor, with structured details for the response body if any
+    #[error("Invalid cursor: {message}")]
+    InvalidCursor {
+        message: String,
+        details: Option<serde_json::Value>,
+    },
 
     /// Invalid event key
     #[error("Invalid event key: {0}")]
@@ -44,12 +79,47 @@ pub enum Error {
     #[error("Validation error: {0}")]
     Validation(String),
 
+    /// Validation error with structured details for the response body
+    #[error("Validation error: {message}")]
+    ValidationDetails {
+        message: String,
+        details: serde_json::Value,
+    },
+
+    /// Request body exceeds a size limit
+    #[error("Payload too large: {0}")]
+    PayloadTooLarge(String),
+
+    /// `If-Match` did not match the current version
+    #[error("Precondition failed: {0}")]
+    PreconditionFailed(String),
+
+    /// A conditional request was sent without `If-Match`
+    #[error("Precondition required: {0}")]
+    PreconditionRequired(String),
+
     /// DynamoDB error
     #[error("Database error: {0}")]
     Database(String),
 
+    /// The configured DynamoDB table doesn't exist (a deployment problem,
+    /// not a bad request)
+    #[error("Configured table not found: {0}")]
+    TableNotFound(String),
+
+    /// DynamoDB rejected the request for exceeding its throughput
+    #[error("Throttled: {0}")]
+    Throttled(String),
+
+    /// A stream's `max_publish_per_second` was exceeded
+    #[error("Rate limited: {message}")]
+    RateLimited {
+        message: String,
+        retry_after_secs: u64,
+    },
+
     /// JSON Serialization error
-    #[error("Serialization error: {0}")]
+    #[error("Serialization error: {}", crate::redact::redact(&.0.to_string()))]
     Serialization(#[from] serde_json::Error),
 
     /// DynamoDB serialization error
@@ -67,14 +137,27 @@ impl Error {
         match self {
             Error::StreamNotFound(_) => "stream_not_found",
             Error::StreamAlreadyExists(_) => "stream_already_exists",
+            Error::AliasNotFound(_) => "alias_not_found",
+            Error::EventNotFound(_) => "event_not_found",
             Error::SubscriptionNotFound(_) => "subscription_not_found",
             Error::SubscriptionAlreadyExists(_) => "subscription_already_exists",
+            Error::StreamInUse(_) => "stream_in_use",
+            Error::CommitConflict(_) => "commit_conflict",
+            Error::IdempotencyKeyInUse(_) => "idempotency_key_in_use",
+            Error::IdempotencyKeyMismatch(_) => "idempotency_key_mismatch",
             Error::InvalidStreamId(_) => "invalid_stream_id",
             Error::InvalidSubscriptionId(_) => "invalid_subscription_id",
-            Error::InvalidCursor(_) => "invalid_cursor",
+            Error::InvalidCursor { .. } => "invalid_cursor",
             Error::InvalidEventKey(_) => "invalid_event_key",
             Error::Validation(_) => "validation_error",
+            Error::ValidationDetails { .. } => "validation_error",
+            Error::PayloadTooLarge(_) => "payload_too_large",
+            Error::PreconditionFailed(_) => "precondition_failed",
+            Error::PreconditionRequired(_) => "precondition_required",
             Error::Database(_) => "database_error",
+            Error::TableNotFound(_) => "table_not_found",
+            Error::Throttled(_) => "throttled",
+            Error::RateLimited { .. } => "rate_limited",
             Error::Serialization(_) => "serialization_error",
             Error::DynamoSerialization(_) => "serialization_error",
             Error::Internal(_) => "internal_error",
@@ -86,19 +169,61 @@ impl Error {
         match self {
             Error::StreamNotFound(_) => 404,
             Error::StreamAlreadyExists(_) => 409,
+            Error::AliasNotFound(_) => 404,
+            Error::EventNotFound(_) => 404,
             Error::SubscriptionNotFound(_) => 404,
             Error::SubscriptionAlreadyExists(_) => 409,
+            Error::StreamInUse(_) => 409,
+            Error::CommitConflict(_) => 409,
+            Error::IdempotencyKeyInUse(_) => 409,
+            Error::IdempotencyKeyMismatch(_) => 422,
             Error::InvalidStreamId(_) => 400,
             Error::InvalidSubscriptionId(_) => 400,
-            Error::InvalidCursor(_) => 400,
+            Error::InvalidCursor { .. } => 400,
             Error::InvalidEventKey(_) => 400,
             Error::Validation(_) => 400,
+            Error::ValidationDetails { .. } => 400,
+            Error::PayloadTooLarge(_) => 413,
+            Error::PreconditionFailed(_) => 412,
+            Error::PreconditionRequired(_) => 428,
             Error::Database(_) => 500,
+            Error::TableNotFound(_) => 503,
+            Error::Throttled(_) => 429,
+            Error::RateLimited { .. } => 429,
             Error::Serialization(_) => 400,
             Error::DynamoSerialization(_) => 500,
             Error::Internal(_) => 500,
         }
     }
+
+    /// Returns structured details for API responses, if any
+    pub fn details(&self) -> Option<&serde_json::Value> {
+        match self {
+            Error::ValidationDetails { details, .. } => Some(details),
+            Error::InvalidCursor { details, .. } => details.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Seconds to send in a `Retry-After` header, for errors worth retrying
+    pub fn retry_after(&self) -> Option<u64> {
+        match self {
+            Error::Throttled(_) => Some(THROTTLED_RETRY_AFTER_SECS),
+            Error::RateLimited {
+                retry_after_secs, ..
+            } => Some(*retry_after_secs),
+            _ => None,
+        }
+    }
+
+    /// Builds the API error response body for this error
+    pub fn to_response(&self) -> ErrorResponse {
+        let response = ErrorResponse::new(self.code(), self.to_string());
+        match self.details() {
+            Some(details) => response.with_details(details.clone()),
+            None => response,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -123,5 +248,71 @@ mod tests {
         let err = Error::Validation("stream_id is required".into());
         assert_eq!(err.code(), "validation_error");
         assert_eq!(err.status_code(), 400);
+        assert!(err.to_response().details.is_none());
+    }
+
+    #[test]
+    fn test_precondition_errors() {
+        let err = Error::PreconditionFailed("stale".into());
+        assert_eq!(err.code(), "precondition_failed");
+        assert_eq!(err.status_code(), 412);
+
+        let err = Error::PreconditionRequired("missing If-Match".into());
+        assert_eq!(err.status_code(), 428);
+    }
+
+    #[test]
+    fn test_stream_in_use_is_a_conflict() {
+        let err = Error::StreamInUse("orders".into());
+        assert_eq!(err.code(), "stream_in_use");
+        assert_eq!(err.status_code(), 409);
+
+        let err = Error::CommitConflict("billing".into());
+        assert_eq!(err.code(), "commit_conflict");
+        assert_eq!(err.status_code(), 409);
+    }
+
+    #[test]
+    fn test_throttled_is_retryable() {
+        let err = Error::Throttled("ProvisionedThroughputExceededException".into());
+        assert_eq!(err.code(), "throttled");
+        assert_eq!(err.status_code(), 429);
+        assert_eq!(err.retry_after(), Some(THROTTLED_RETRY_AFTER_SECS));
+
+        assert_eq!(Error::Database("boom".into()).retry_after(), None);
+
+        let err = Error::RateLimited {
+            message: "orders".into(),
+            retry_after_secs: 3,
+        };
+        assert_eq!(err.code(), "rate_limited");
+        assert_eq!(err.status_code(), 429);
+        assert_eq!(err.retry_after(), Some(3));
+    }
+
+    #[test]
+    fn test_missing_table_is_unavailable() {
+        let err = Error::TableNotFound("check EVENTLEDGER_TABLE".into());
+        assert_eq!(err.code(), "table_not_found");
+        assert_eq!(err.status_code(), 503);
+        assert_eq!(
+            err.to_string(),
+            "Configured table not found: check EVENTLEDGER_TABLE"
+        );
+        assert_eq!(err.retry_after(), None);
+    }
+
+    #[test]
+    fn test_validation_details_in_response() {
+        let err = Error::ValidationDetails {
+            message: "bad body".into(),
+            details: serde_json::json!({ "line": 1 }),
+        };
+        assert_eq!(err.code(), "validation_error");
+        assert_eq!(err.status_code(), 400);
+
+        let response = err.to_response();
+        assert_eq!(response.error, "validation_error");
+        assert_eq!(response.details, Some(serde_json::json!({ "line": 1 })));
     }
 }
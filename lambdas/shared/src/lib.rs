@@ -5,13 +5,38 @@
 //! - DynamoDB operations
 //! - Partitioning logic
 //! - Error types
+//! - Request body parsing
+//! - JSON Schema validation of event data
+//! - Redaction of payload values from errors and logs
+//! - The storage trait handlers are written against
+//! - An injectable clock
+//! - Response shape versioning
+//! - An in-process cache of stream metadata
+//! - Error responses for the HTTP handlers (`http` feature)
+//! - A stand-in DynamoDB for unit tests (`test-util` feature)
 
 pub mod models;
 pub mod dynamo;
 pub mod partitioner;
 pub mod errors;
+pub mod body;
+pub mod schema;
+pub mod redact;
+pub mod store;
+pub mod clock;
+pub mod version;
+pub mod stream_cache;
+#[cfg(feature = "http")]
+pub mod response;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
 
 pub use models::*;
-pub use dynamo::DynamoClient;
+pub use dynamo::{DynamoClient, TABLE_OVERRIDE_HEADER};
 pub use partitioner::Partitioner;
-pub use errors::{Error, Result};
+pub use errors::{Error, Result, THROTTLED_RETRY_AFTER_SECS};
+pub use store::EventStore;
+pub use clock::{Clock, FixedClock, SystemClock};
+pub use version::{ApiVersion, ACCEPT_VERSION_HEADER};
+#[cfg(any(test, feature = "test-util"))]
+pub use store::MemoryStore;
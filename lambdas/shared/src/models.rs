@@ -6,8 +6,15 @@
 //! - Subscriptions: Consumer configurations with offset tracking
 //! - Compacted State: Latest value per key
 
-use chrono::{DateTime, Utc};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, DecodeError, Engine};
+use chrono::{DateTime, Timelike, Utc};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::errors::{Error, Result};
+use crate::partitioner::Partitioner;
 
 /// Stream metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,6 +25,45 @@ pub struct Stream {
     pub partition_count: u32,
     /// Retention period in hours for hot storage
     pub retention_hours: u32,
+    /// Assign a stream-wide `global_position` to every event (serializes publishes
+    /// across partitions through a single counter)
+    #[serde(default)]
+    pub global_ordering: bool,
+    /// Event types accepted on publish (unrestricted when unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_event_types: Option<Vec<String>>,
+    /// JSON Schema that every event's `data` must match (unvalidated when unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<serde_json::Value>,
+    /// Keys pinned to a fixed partition instead of being hashed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub partition_overrides: Option<HashMap<String, u32>>,
+    /// Where events with an empty key (and no `partition_key`) are sent
+    #[serde(default)]
+    pub empty_key_strategy: EmptyKeyStrategy,
+    /// Keep the latest event per key as compacted state
+    #[serde(default = "default_compaction")]
+    pub compaction: bool,
+    /// How long a publish's `Idempotency-Key` is remembered (default: `retention_hours`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotency_ttl_hours: Option<u32>,
+    /// Hours after its event that a key's compacted state is reaped by TTL
+    ///
+    /// Independent of `retention_hours`, which only expires events: compacted
+    /// state is kept indefinitely unless this is set, and may be set shorter or
+    /// longer than retention.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compacted_ttl_hours: Option<u32>,
+    /// Publish limit in events per second, bursting up to one second's worth
+    /// (unlimited when unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_publish_per_second: Option<u32>,
+    /// Free-form labels for inventory, e.g. `team` or `cost-center`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub tags: HashMap<String, String>,
+    /// Incremented on every config update; returned as the `ETag`
+    #[serde(default)]
+    pub version: u64,
     /// When the stream was created
     pub created_at: DateTime<Utc>,
 }
@@ -28,51 +74,628 @@ impl Stream {
             stream_id,
             partition_count,
             retention_hours,
+            global_ordering: false,
+            allowed_event_types: None,
+            schema: None,
+            partition_overrides: None,
+            empty_key_strategy: EmptyKeyStrategy::Hash,
+            compaction: true,
+            idempotency_ttl_hours: None,
+            compacted_ttl_hours: None,
+            max_publish_per_second: None,
+            tags: HashMap::new(),
+            version: 1,
             created_at: Utc::now(),
         }
     }
+
+    /// Partitioner for this stream, honoring any partition overrides
+    pub fn partitioner(&self) -> Result<Partitioner> {
+        match &self.partition_overrides {
+            Some(overrides) => Partitioner::with_overrides(self.partition_count, overrides.clone()),
+            None => Ok(Partitioner::new(self.partition_count)),
+        }
+    }
+
+    /// A stream needs at least one partition and at most [`max_partitions`],
+    /// which bounds how many partitions a poll reads
+    pub fn check_partition_count(&self) -> Result<()> {
+        let max = max_partitions();
+        if !(1..=max).contains(&self.partition_count) {
+            return Err(Error::Validation(format!(
+                "partition_count must be between 1 and {}, got {}",
+                max, self.partition_count
+            )));
+        }
+        Ok(())
+    }
+
+    /// Hours an idempotency key is remembered for
+    pub fn idempotency_window_hours(&self) -> u32 {
+        self.idempotency_ttl_hours.unwrap_or(self.retention_hours)
+    }
+
+    /// Retention must be at least an hour, since events would otherwise expire
+    /// as soon as they are written, and at most [`MAX_RETENTION_HOURS`]
+    pub fn check_retention(&self) -> Result<()> {
+        if !(1..=MAX_RETENTION_HOURS).contains(&self.retention_hours) {
+            return Err(Error::Validation(format!(
+                "retention_hours must be between 1 and {}, got {}",
+                MAX_RETENTION_HOURS, self.retention_hours
+            )));
+        }
+        Ok(())
+    }
+
+    /// An idempotency window must be positive and fit within retention, since a
+    /// replayed response would otherwise point at events that have expired
+    pub fn check_idempotency_ttl(&self) -> Result<()> {
+        match self.idempotency_ttl_hours {
+            Some(0) => Err(Error::Validation(
+                "idempotency_ttl_hours must be at least 1".to_string(),
+            )),
+            Some(hours) if hours > self.retention_hours => Err(Error::Validation(format!(
+                "idempotency_ttl_hours ({}) must not exceed retention_hours ({})",
+                hours, self.retention_hours
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// A compacted TTL, when set, must be positive
+    pub fn check_compacted_ttl(&self) -> Result<()> {
+        match self.compacted_ttl_hours {
+            Some(0) => Err(Error::Validation(
+                "compacted_ttl_hours must be at least 1".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Spreading empty-key events across partitions needs compaction off:
+    /// they would all compact to one entry, and which event it held would no
+    /// longer follow publish order
+    pub fn check_empty_key_strategy(&self) -> Result<()> {
+        if self.compaction && self.empty_key_strategy != EmptyKeyStrategy::Hash {
+            return Err(Error::Validation(
+                "empty_key_strategy other than hash requires compaction=false".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// A publish rate limit, when set, must be positive
+    pub fn check_publish_rate(&self) -> Result<()> {
+        match self.max_publish_per_second {
+            Some(0) => Err(Error::Validation(
+                "max_publish_per_second must be at least 1".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Tags must have non-empty keys without `:` (the `?tag=key:value`
+    /// separator), and there may be at most [`MAX_STREAM_TAGS`] of them
+    pub fn check_tags(&self) -> Result<()> {
+        if self.tags.len() > MAX_STREAM_TAGS {
+            return Err(Error::Validation(format!(
+                "A stream may have at most {} tags, got {}",
+                MAX_STREAM_TAGS,
+                self.tags.len()
+            )));
+        }
+        match self
+            .tags
+            .keys()
+            .find(|key| key.is_empty() || key.contains(':'))
+        {
+            Some(key) => Err(Error::Validation(format!(
+                "Invalid tag key '{}': must be non-empty and must not contain ':'",
+                key
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether the stream carries the tag a filter asks for
+    pub fn has_tag(&self, filter: &TagFilter) -> bool {
+        self.tags.get(&filter.key) == Some(&filter.value)
+    }
+
+    /// Charge a publish of `count` events against the stream's rate bucket
+    /// (`None` if never charged), returning the bucket to store
+    ///
+    /// Returns `Ok(None)` for streams without a `max_publish_per_second`.
+    pub fn charge_publish(
+        &self,
+        bucket: Option<TokenBucket>,
+        count: usize,
+        now_ms: i64,
+    ) -> Result<Option<TokenBucket>> {
+        let Some(rate) = self.max_publish_per_second else {
+            return Ok(None);
+        };
+        let bucket = bucket.unwrap_or_else(|| TokenBucket::full(rate, now_ms));
+        match bucket.take(rate, count, now_ms) {
+            Ok(next) => Ok(Some(next)),
+            Err(retry_after_secs) => Err(Error::RateLimited {
+                message: format!(
+                    "Stream {} accepts at most {} events per second",
+                    self.stream_id, rate
+                ),
+                retry_after_secs,
+            }),
+        }
+    }
+
+    /// Reject event types outside `allowed_event_types`, listing the allowed set
+    pub fn check_event_type(&self, event_type: &str) -> Result<()> {
+        match &self.allowed_event_types {
+            Some(allowed) if !allowed.iter().any(|t| t == event_type) => {
+                Err(Error::ValidationDetails {
+                    message: format!(
+                        "Event type '{}' is not allowed on stream {}",
+                        event_type, self.stream_id
+                    ),
+                    details: serde_json::json!({
+                        "event_type": event_type,
+                        "allowed_event_types": allowed,
+                    }),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
-/// Request to create a new stream
+/// Size statistics for a stream
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamStats {
+    pub stream_id: String,
+    pub partition_count: u32,
+    /// Events ever published (includes any already expired)
+    pub approximate_event_count: u64,
+    /// Events still stored across all partitions
+    #[serde(default)]
+    pub event_count: u64,
+    /// Oldest stored event across all partitions (unset when none are stored)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub earliest_timestamp: Option<DateTime<Utc>>,
+    /// Newest stored event across all partitions (unset when none are stored)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latest_timestamp: Option<DateTime<Utc>>,
+    /// Partitions holding more than [`HOT_PARTITION_FACTOR`] times the mean
+    /// event count
+    #[serde(default)]
+    pub hot_partitions: Vec<u32>,
+    pub partitions: Vec<PartitionStats>,
+}
+
+/// How many times the mean event count a partition must exceed to count as hot
+pub const HOT_PARTITION_FACTOR: u64 = 2;
+
+/// Events ever published to each partition of a stream
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartitionLoad {
+    /// Each partition's sequence counter
+    pub partitions: Vec<PartitionOffset>,
+    /// Partitions whose count exceeds [`HOT_PARTITION_FACTOR`] times the mean,
+    /// a sign of skewed keys
+    pub hot_partitions: Vec<u32>,
+}
+
+impl PartitionLoad {
+    /// Flag the hot partitions among `partitions`
+    pub fn new(partitions: Vec<PartitionOffset>) -> Self {
+        let total: u64 = partitions.iter().map(|p| p.offset).sum();
+        let count = partitions.len() as u64;
+        // count > factor * (total / n), kept in integers
+        let hot_partitions = partitions
+            .iter()
+            .filter(|p| p.offset * count > HOT_PARTITION_FACTOR * total)
+            .map(|p| p.partition)
+            .collect();
+        Self {
+            partitions,
+            hot_partitions,
+        }
+    }
+}
+
+/// Statistics for one partition of a stream
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartitionStats {
+    pub partition: u32,
+    /// Latest sequence number
+    pub offset: u64,
+    /// Events still stored; below `offset` once some have expired
+    #[serde(default)]
+    pub event_count: u64,
+    /// Timestamp of the oldest event still stored (unset when the partition is empty)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub earliest_timestamp: Option<DateTime<Utc>>,
+    /// Timestamp of the newest event still stored (unset when the partition is empty)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latest_timestamp: Option<DateTime<Utc>>,
+}
+
+/// Streams stored before `compaction` was added were all compacted
+fn default_compaction() -> bool {
+    true
+}
+
+/// Request to create a new stream
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CreateStreamRequest {
     /// Unique stream identifier (alphanumeric, hyphens, underscores)
     pub stream_id: String,
-    /// Number of partitions (default: 3)
-    #[serde(default = "default_partition_count")]
+    /// Number of partitions (default: `EVENTLEDGER_DEFAULT_PARTITIONS`, else 3)
+    #[serde(default)]
+    pub partition_count: Option<u32>,
+    /// Retention period in hours (default: `EVENTLEDGER_DEFAULT_RETENTION_HOURS`, else 168 = 7 days)
+    #[serde(default)]
+    pub retention_hours: Option<u32>,
+    /// Enable stream-wide global positions (default: false)
+    #[serde(default)]
+    pub global_ordering: bool,
+    /// Restrict published events to these types (default: any type)
+    #[serde(default)]
+    pub allowed_event_types: Option<Vec<String>>,
+    /// JSON Schema for event data (default: no validation)
+    #[serde(default)]
+    pub schema: Option<serde_json::Value>,
+    /// Pin hot keys to specific partitions (default: hash every key)
+    #[serde(default)]
+    pub partition_overrides: Option<HashMap<String, u32>>,
+    /// Spread events with an empty key across partitions; anything but `hash`
+    /// requires `compaction: false` (default: `hash`)
+    #[serde(default)]
+    pub empty_key_strategy: EmptyKeyStrategy,
+    /// Keep the latest event per key as compacted state (default: true)
+    #[serde(default)]
+    pub compaction: Option<bool>,
+    /// Idempotency key window in hours, at most `retention_hours` (default: retention)
+    #[serde(default)]
+    pub idempotency_ttl_hours: Option<u32>,
+    /// Expire compacted state this many hours after its event (default: never)
+    #[serde(default)]
+    pub compacted_ttl_hours: Option<u32>,
+    /// Limit publishes to this many events per second (default: unlimited)
+    #[serde(default)]
+    pub max_publish_per_second: Option<u32>,
+    /// Labels for inventory (default: none)
+    #[serde(default)]
+    pub tags: HashMap<String, String>,
+}
+
+/// Where a stream sends events whose routing key is empty
+///
+/// Only [`EmptyKeyStrategy::Hash`] is allowed on streams with compaction (see
+/// [`Stream::check_empty_key_strategy`]).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmptyKeyStrategy {
+    /// Hash the empty key like any other, so every such event lands on one
+    /// partition
+    #[default]
+    Hash,
+    /// Rotate through the partitions in publish order
+    RoundRobin,
+    /// Pick a partition at random for each event
+    Random,
+}
+
+/// Changes to a stream's configuration; omitted fields are left as they are
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateStreamRequest {
+    /// New retention period in hours
+    #[serde(default)]
+    pub retention_hours: Option<u32>,
+    /// New set of accepted event types
+    #[serde(default)]
+    pub allowed_event_types: Option<Vec<String>>,
+    /// New JSON Schema for event data
+    #[serde(default)]
+    pub schema: Option<serde_json::Value>,
+    /// New publish limit in events per second
+    #[serde(default)]
+    pub max_publish_per_second: Option<u32>,
+    /// New tags, replacing all existing ones (`{}` removes them)
+    #[serde(default)]
+    pub tags: Option<HashMap<String, String>>,
+}
+
+impl UpdateStreamRequest {
+    /// Whether the request changes anything
+    pub fn is_empty(&self) -> bool {
+        self.retention_hours.is_none()
+            && self.allowed_event_types.is_none()
+            && self.schema.is_none()
+            && self.max_publish_per_second.is_none()
+            && self.tags.is_none()
+    }
+}
+
+/// Most tags a stream may carry
+pub const MAX_STREAM_TAGS: usize = 50;
+
+/// Longest retention a stream may have: one year
+pub const MAX_RETENTION_HOURS: u32 = 8_760;
+
+/// A `key:value` filter from `GET /streams?tag=team:payments`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagFilter {
+    pub key: String,
+    pub value: String,
+}
+
+impl FromStr for TagFilter {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.split_once(':') {
+            Some((key, value)) if !key.is_empty() => Ok(TagFilter {
+                key: key.to_string(),
+                value: value.to_string(),
+            }),
+            _ => Err(Error::Validation(format!(
+                "Invalid tag filter '{}': expected key:value",
+                s
+            ))),
+        }
+    }
+}
+
+/// Request to preview how a set of keys would spread over partitions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartitionPreviewRequest {
     pub partition_count: u32,
-    /// Retention period in hours (default: 168 = 7 days)
-    #[serde(default = "default_retention_hours")]
-    pub retention_hours: u32,
+    pub keys: Vec<String>,
+}
+
+/// Keys per partition for a candidate partition count
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartitionDistribution {
+    pub partition_count: u32,
+    pub key_count: u64,
+    /// Number of keys landing on each partition, indexed by partition
+    pub histogram: Vec<u64>,
+    /// Busiest partition's share relative to an even split (1.0 is perfectly
+    /// even, `partition_count` means every key on one partition; 0 with no keys)
+    pub skew: f64,
+}
+
+/// Environment variable overriding the default partition count
+pub const DEFAULT_PARTITIONS_ENV: &str = "EVENTLEDGER_DEFAULT_PARTITIONS";
+/// Environment variable overriding the default retention period
+pub const DEFAULT_RETENTION_HOURS_ENV: &str = "EVENTLEDGER_DEFAULT_RETENTION_HOURS";
+
+impl CreateStreamRequest {
+    /// Requested partition count, or the deployment default when omitted
+    pub fn resolved_partition_count(&self) -> u32 {
+        self.partition_count.unwrap_or_else(default_partition_count)
+    }
+
+    /// Requested retention, or the deployment default when omitted
+    pub fn resolved_retention_hours(&self) -> u32 {
+        self.retention_hours.unwrap_or_else(default_retention_hours)
+    }
 }
 
+// Read at stream-creation time rather than as serde defaults so the
+// environment is consulted when the stream is created, not when parsed.
 fn default_partition_count() -> u32 {
-    3
+    env_u32(DEFAULT_PARTITIONS_ENV).unwrap_or(3)
 }
 
 fn default_retention_hours() -> u32 {
-    168 // 7 days
+    env_u32(DEFAULT_RETENTION_HOURS_ENV).unwrap_or(168) // 7 days
+}
+
+/// Positive integer from the environment; unset or invalid values are ignored
+fn env_u32(var: &str) -> Option<u32> {
+    positive_u32(std::env::var(var).ok()?.as_str())
+}
+
+fn positive_u32(value: &str) -> Option<u32> {
+    value.trim().parse().ok().filter(|&n| n > 0)
 }
 
 /// An event in the log
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Event {
+    /// Opaque, stable identifier derived from stream, partition and sequence
+    #[serde(default)]
+    pub id: String,
     /// Stream this event belongs to
     pub stream_id: String,
     /// Partition number (0-based)
     pub partition: u32,
     /// Monotonically increasing sequence number within partition
     pub sequence: u64,
+    /// Stream-wide monotonic position (only on streams with `global_ordering`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub global_position: Option<u64>,
     /// Key for compaction (e.g., entity ID)
     pub key: String,
+    /// Key the partition was chosen by, when it differs from `key`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub partition_key: Option<String>,
+    /// Producer-defined position (e.g. a source offset) reads can sort by
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order_key: Option<u64>,
     /// Event type (e.g., "order.created")
     pub event_type: String,
     /// Event payload (JSON)
     pub data: serde_json::Value,
-    /// When the event was published
+    /// When the event was published, never earlier than the partition's
+    /// previous event. A backfilled event keeps the `timestamp` it was
+    /// published with instead, which may be earlier.
     pub timestamp: DateTime<Utc>,
+    /// Clock time at publish, when `timestamp` isn't it: the clock was behind
+    /// the partition's previous event and `timestamp` was held back to that
+    /// event's, or the event is backfilled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wall_clock_timestamp: Option<DateTime<Utc>>,
+}
+
+impl Event {
+    /// When the event was written, whatever `timestamp` it carries
+    pub fn ingested_at(&self) -> DateTime<Utc> {
+        self.wall_clock_timestamp.unwrap_or(self.timestamp)
+    }
+}
+
+/// Precision event timestamps are stored at
+pub const TIMESTAMP_PRECISION: &str = "microseconds";
+
+/// The current time, truncated to [`TIMESTAMP_PRECISION`]
+pub fn timestamp_now() -> DateTime<Utc> {
+    to_timestamp_precision(Utc::now())
+}
+
+/// `time` truncated to [`TIMESTAMP_PRECISION`]
+pub fn to_timestamp_precision(time: DateTime<Utc>) -> DateTime<Utc> {
+    time.with_nanosecond(time.nanosecond() / 1_000 * 1_000)
+        .unwrap_or(time)
+}
+
+/// Timestamp for an event published at `now` into a partition whose latest
+/// event is stamped `latest`
+///
+/// Returns the timestamp to store and, when the clock has gone backward past
+/// `latest`, the wall-clock time to keep alongside it.
+pub fn partition_timestamp(
+    now: DateTime<Utc>,
+    latest: Option<DateTime<Utc>>,
+) -> (DateTime<Utc>, Option<DateTime<Utc>>) {
+    match latest {
+        Some(latest) if latest > now => (latest, Some(now)),
+        _ => (now, None),
+    }
+}
+
+/// Sort events by `order_key` for reads that ask for the producer's order
+///
+/// This sorts the events already read, not how they are stored: a page is
+/// still chosen by sequence, so only events within it are reordered. Ties keep
+/// their read order, and events without an `order_key` come last.
+pub fn sort_by_order_key(events: &mut [Event], descending: bool) {
+    events.sort_by(|a, b| match (a.order_key, b.order_key) {
+        (Some(a), Some(b)) if descending => b.cmp(&a),
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => std::cmp::Ordering::Equal,
+    });
+}
+
+/// Event fields a read can be projected to
+pub const EVENT_FIELDS: [&str; 12] = [
+    "id",
+    "stream_id",
+    "partition",
+    "sequence",
+    "global_position",
+    "key",
+    "partition_key",
+    "order_key",
+    "event_type",
+    "data",
+    "timestamp",
+    "wall_clock_timestamp",
+];
+
+/// Fields every projected read fetches: polls order and checkpoint by them, and
+/// derive `id` from them for older events
+const PROJECTION_REQUIRED_FIELDS: [&str; 5] =
+    ["id", "stream_id", "partition", "sequence", "timestamp"];
+
+/// A subset of event fields to read and return, e.g. `?fields=key,sequence`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventProjection {
+    fields: Vec<String>,
+}
+
+impl EventProjection {
+    /// Parse a comma-separated list of [`EVENT_FIELDS`]
+    pub fn parse(raw: &str) -> Result<Self> {
+        let mut fields = Vec::new();
+        for field in raw.split(',').map(str::trim) {
+            if !EVENT_FIELDS.contains(&field) {
+                return Err(Error::ValidationDetails {
+                    message: format!(
+                        "Invalid field '{}': expected one of {}",
+                        field,
+                        EVENT_FIELDS.join(", ")
+                    ),
+                    details: serde_json::json!({
+                        "field": "fields",
+                        "accepted": EVENT_FIELDS,
+                    }),
+                });
+            }
+            if !fields.iter().any(|f| f == field) {
+                fields.push(field.to_string());
+            }
+        }
+        Ok(Self { fields })
+    }
+
+    /// Attributes to fetch: the requested fields plus those reads depend on
+    pub fn attributes(&self) -> Vec<&str> {
+        let mut attributes: Vec<&str> = PROJECTION_REQUIRED_FIELDS.to_vec();
+        for field in &self.fields {
+            if !attributes.contains(&field.as_str()) {
+                attributes.push(field);
+            }
+        }
+        attributes
+    }
+
+    /// The event as JSON with only the requested fields
+    pub fn apply(&self, event: &Event) -> Result<serde_json::Value> {
+        let mut value = serde_json::to_value(event)?;
+        if let Some(object) = value.as_object_mut() {
+            object.retain(|name, _| self.fields.iter().any(|f| f == name));
+        }
+        Ok(value)
+    }
+}
+
+/// Build the opaque ID for an event: `base64url(stream_id:partition:sequence)`
+///
+/// Deterministic, so it can be recomputed from an event's coordinates.
+pub fn event_id(stream_id: &str, partition: u32, sequence: u64) -> String {
+    URL_SAFE_NO_PAD.encode(format!("{}:{}:{}", stream_id, partition, sequence))
+}
+
+/// Decode an event ID back into `(stream_id, partition, sequence)`
+pub fn parse_event_id(id: &str) -> Option<(String, u32, u64)> {
+    let bytes = URL_SAFE_NO_PAD.decode(id).ok()?;
+    let decoded = String::from_utf8(bytes).ok()?;
+    let mut parts = decoded.rsplitn(3, ':');
+    let sequence = parts.next()?.parse().ok()?;
+    let partition = parts.next()?.parse().ok()?;
+    let stream_id = parts.next()?.to_string();
+    Some((stream_id, partition, sequence))
 }
 
+/// `data` left in place of a redacted event's payload
+///
+/// The event keeps its key, type and sequence, so partitions stay gap-free
+/// and consumers can tell a redaction from an event that never existed.
+pub fn redaction_tombstone(redacted_at: DateTime<Utc>) -> serde_json::Value {
+    serde_json::json!({ "redacted": true, "redacted_at": redacted_at })
+}
+
+/// Events clients should send in one JSON or CBOR publish, or across all
+/// streams of a multi-stream publish
+///
+/// Advertised in [`Capabilities`] for clients to size their batches by; a
+/// larger publish is still accepted.
+pub const MAX_PUBLISH_BATCH: usize = 1_000;
+
 /// Request to publish event(s)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PublishRequest {
@@ -80,18 +703,124 @@ pub struct PublishRequest {
     pub events: Vec<PublishEvent>,
 }
 
+/// Event type name: dot-separated lowercase segments, e.g. `order.created`
+///
+/// Validated when constructed, including when a publish body is parsed; on
+/// the wire it is a plain string.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct EventType(String);
+
+impl EventType {
+    /// Validate and wrap an event type name
+    pub fn new(event_type: impl Into<String>) -> Result<Self> {
+        let event_type = event_type.into();
+        let valid = event_type.split('.').all(|segment| {
+            !segment.is_empty()
+                && segment
+                    .bytes()
+                    .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit())
+        });
+        if !valid {
+            // The value itself is left out: it is caller input and may be a secret
+            return Err(Error::Validation(
+                "Invalid event type: expected lowercase letters and digits in dot-separated \
+                 segments, e.g. order.created"
+                    .to_string(),
+            ));
+        }
+        Ok(Self(event_type))
+    }
+
+    /// Wrap a type read back from a stored event without validating it again:
+    /// it passed the rules in force when it was published, which may since
+    /// have changed
+    pub(crate) fn from_stored(event_type: String) -> Self {
+        Self(event_type)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TryFrom<String> for EventType {
+    type Error = Error;
+
+    fn try_from(event_type: String) -> Result<Self> {
+        Self::new(event_type)
+    }
+}
+
+impl FromStr for EventType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::new(s)
+    }
+}
+
+impl From<EventType> for String {
+    fn from(event_type: EventType) -> Self {
+        event_type.0
+    }
+}
+
+impl std::fmt::Display for EventType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl PartialEq<&str> for EventType {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
 /// Single event to publish
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PublishEvent {
-    /// Key for partitioning and compaction
+    /// Key for compaction, and for partitioning unless `partition_key` is set
     pub key: String,
+    /// Key for partitioning when related entities should share a partition
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub partition_key: Option<String>,
+    /// Application-defined order within the partition, e.g. a source offset;
+    /// stored as given and only used to sort reads that ask for it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order_key: Option<u64>,
+    /// When the event happened, for backfills of historical data; stored as
+    /// its `timestamp` instead of the publish time (only accepted with
+    /// `?allow_timestamp_override=true`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<DateTime<Utc>>,
     /// Event type
     #[serde(rename = "type")]
-    pub event_type: String,
+    pub event_type: EventType,
     /// Event payload
     pub data: serde_json::Value,
 }
 
+impl PublishEvent {
+    /// Key that picks the partition: `partition_key`, falling back to `key`
+    pub fn routing_key(&self) -> &str {
+        self.partition_key.as_deref().unwrap_or(&self.key)
+    }
+}
+
+/// Reject events carrying their own `timestamp` unless the publish opted in
+/// with `allow_timestamp_override`, so a stray field can't backdate events
+pub fn check_timestamp_overrides(events: &[PublishEvent], allowed: bool) -> Result<()> {
+    match events.iter().position(|event| event.timestamp.is_some()) {
+        Some(index) if !allowed => Err(Error::Validation(format!(
+            "events[{}] sets timestamp, which requires allow_timestamp_override=true",
+            index
+        ))),
+        _ => Ok(()),
+    }
+}
+
 /// Response after publishing events
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PublishResponse {
@@ -102,128 +831,1073 @@ pub struct PublishResponse {
 /// Reference to a published event
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PublishedEvent {
+    /// Position of the event in the request's events (in a multi-stream
+    /// publish, among the events for this stream), so results can be matched
+    /// to inputs without relying on response order
+    #[serde(default)]
+    pub input_index: usize,
+    pub id: String,
     pub stream_id: String,
     pub partition: u32,
     pub sequence: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub global_position: Option<u64>,
     pub key: String,
     pub timestamp: DateTime<Utc>,
 }
 
-/// Subscription configuration
+/// Events for one stream in a multi-stream publish
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Subscription {
-    /// Stream being subscribed to
+pub struct StreamPublish {
     pub stream_id: String,
-    /// Unique subscription identifier
-    pub subscription_id: String,
-    /// When the subscription was created
-    pub created_at: DateTime<Utc>,
+    pub events: Vec<PublishEvent>,
 }
 
-impl Subscription {
-    pub fn new(stream_id: String, subscription_id: String) -> Self {
-        Self {
-            stream_id,
-            subscription_id,
-            created_at: Utc::now(),
+/// Request to publish to several streams at once
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublishBatchRequest {
+    pub items: Vec<StreamPublish>,
+}
+
+impl PublishBatchRequest {
+    /// Merge items naming the same stream, in the order streams first appear,
+    /// so each stream is written with a single publish
+    pub fn grouped(self) -> Vec<StreamPublish> {
+        let mut grouped: Vec<StreamPublish> = Vec::new();
+        for item in self.items {
+            match grouped.iter_mut().find(|g| g.stream_id == item.stream_id) {
+                Some(group) => group.events.extend(item.events),
+                None => grouped.push(item),
+            }
         }
+        grouped
     }
 }
 
-/// Request to create a subscription
+/// Outcome for one stream in a multi-stream publish
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CreateSubscriptionRequest {
-    /// Unique subscription identifier
-    pub subscription_id: String,
-    /// Where to start consuming from
+pub struct StreamPublishResult {
+    pub stream_id: String,
+    pub success: bool,
+    /// Published event references (empty on failure)
     #[serde(default)]
-    pub start_from: StartFrom,
-}
-
-/// Starting position for a new subscription
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
-pub enum StartFrom {
-    /// Start from the earliest available event
-    Earliest,
-    /// Start from new events only (default)
-    #[default]
-    Latest,
-    /// Start from compacted state (latest per key)
-    Compacted,
+    pub events: Vec<PublishedEvent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorResponse>,
 }
 
-/// Consumer offset for a subscription
+/// Response after a multi-stream publish (one result per stream, in the order
+/// streams first appear in the request)
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ConsumerOffset {
-    pub stream_id: String,
-    pub subscription_id: String,
-    pub partition: u32,
-    pub offset: u64,
-    pub committed_at: DateTime<Utc>,
+pub struct PublishBatchResponse {
+    pub results: Vec<StreamPublishResult>,
 }
 
-/// Request to poll for events
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PollRequest {
-    /// Maximum number of events to return
-    #[serde(default = "default_batch_size")]
-    pub limit: u32,
+/// Longest accepted `Idempotency-Key`
+pub const MAX_IDEMPOTENCY_KEY_LENGTH: usize = 255;
+
+/// Seconds a publish holds its `Idempotency-Key` before its result is
+/// recorded; a claim left behind by a publish that never finished frees the
+/// key after this long
+pub const IDEMPOTENCY_CLAIM_SECS: i64 = 60;
+
+/// Reject idempotency keys that are empty or too long to store
+pub fn validate_idempotency_key(idempotency_key: &str) -> Result<()> {
+    if idempotency_key.is_empty() || idempotency_key.len() > MAX_IDEMPOTENCY_KEY_LENGTH {
+        return Err(Error::Validation(format!(
+            "Idempotency-Key must be between 1 and {} characters",
+            MAX_IDEMPOTENCY_KEY_LENGTH
+        )));
+    }
+    Ok(())
 }
 
-fn default_batch_size() -> u32 {
-    100
+/// Hex SHA-256 of a publish body, so that a key reused for a different body
+/// is caught rather than replayed
+pub fn idempotency_body_hash(body: &[u8]) -> String {
+    sha256_hex(body)
 }
 
-/// Response from polling
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PollResponse {
-    /// Events retrieved
-    pub events: Vec<Event>,
-    /// Opaque cursor for committing
-    pub cursor: String,
-    /// Number of events remaining (approximate)
-    pub remaining: u64,
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
 }
 
-/// Cursor state (encoded in the cursor string)
+/// Remembered outcome of a publish sent with an `Idempotency-Key`
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CursorState {
+pub struct IdempotencyRecord {
+    pub stream_id: String,
+    pub idempotency_key: String,
+    /// Events the original publish wrote, replayed for repeats
+    pub events: Vec<PublishedEvent>,
+    /// SHA-256 of the original body (absent on records stored before it was kept)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_hash: Option<String>,
+    /// Set while the first publish with the key is still writing its events
+    #[serde(default)]
+    pub pending: bool,
+    pub created_at: DateTime<Utc>,
+    /// Epoch seconds after which the key is forgotten (DynamoDB TTL attribute)
+    pub expires_at: i64,
+}
+
+impl IdempotencyRecord {
+    /// A claim on the key, taken before the publish writes anything
+    pub fn claim(stream_id: &str, idempotency_key: String, body_hash: String) -> Self {
+        let created_at = Utc::now();
+        Self {
+            stream_id: stream_id.to_string(),
+            idempotency_key,
+            events: Vec::new(),
+            body_hash: Some(body_hash),
+            pending: true,
+            created_at,
+            expires_at: (created_at + chrono::Duration::seconds(IDEMPOTENCY_CLAIM_SECS))
+                .timestamp(),
+        }
+    }
+
+    /// The claim's publish finished, remembered for the stream's idempotency window
+    pub fn complete(self, stream: &Stream, events: Vec<PublishedEvent>) -> Self {
+        let window = chrono::Duration::hours(stream.idempotency_window_hours().into());
+        Self {
+            events,
+            pending: false,
+            expires_at: (self.created_at + window).timestamp(),
+            ..self
+        }
+    }
+
+    /// Whether a repeat with this key and `body_hash` can get the recorded
+    /// response back
+    ///
+    /// Fails for a different body, and while the first publish is still
+    /// writing (so there is nothing to replay yet).
+    pub fn check_replay(&self, body_hash: &str) -> Result<()> {
+        if self
+            .body_hash
+            .as_deref()
+            .is_some_and(|hash| hash != body_hash)
+        {
+            return Err(Error::IdempotencyKeyMismatch(format!(
+                "'{}' was used for a different request body",
+                self.idempotency_key
+            )));
+        }
+        if self.pending {
+            return Err(Error::IdempotencyKeyInUse(format!(
+                "A publish with '{}' is still in progress",
+                self.idempotency_key
+            )));
+        }
+        Ok(())
+    }
+
+    /// TTL deletion can lag by hours, so readers check expiry themselves
+    pub fn is_expired(&self) -> bool {
+        self.is_expired_at(Utc::now())
+    }
+
+    /// Whether the key has been forgotten as of `now`
+    pub fn is_expired_at(&self, now: DateTime<Utc>) -> bool {
+        now.timestamp() >= self.expires_at
+    }
+}
+
+/// Token bucket enforcing a stream's `max_publish_per_second`
+///
+/// Holds at most one second's worth of tokens and refills continuously, so a
+/// stream can burst up to its limit and then publish at the limit.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TokenBucket {
+    /// Tokens left as of `refilled_at_ms`
+    pub tokens: f64,
+    /// Epoch milliseconds the bucket was last brought up to date
+    pub refilled_at_ms: i64,
+}
+
+impl TokenBucket {
+    /// A bucket holding a full second's worth of tokens
+    pub fn full(rate: u32, now_ms: i64) -> Self {
+        Self {
+            tokens: rate.into(),
+            refilled_at_ms: now_ms,
+        }
+    }
+
+    /// Take `count` tokens at `now_ms`, returning the bucket to store, or the
+    /// whole seconds to wait when there aren't enough
+    ///
+    /// A batch larger than the limit can never fit, so it takes a full bucket.
+    pub fn take(&self, rate: u32, count: usize, now_ms: i64) -> std::result::Result<Self, u64> {
+        let rate = f64::from(rate);
+        let elapsed_secs = (now_ms - self.refilled_at_ms).max(0) as f64 / 1000.0;
+        let tokens = (self.tokens + elapsed_secs * rate).min(rate);
+        let cost = (count as f64).min(rate);
+        if tokens < cost {
+            return Err(((cost - tokens) / rate).ceil().max(1.0) as u64);
+        }
+        Ok(Self {
+            tokens: tokens - cost,
+            refilled_at_ms: now_ms,
+        })
+    }
+}
+
+/// Subscription configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Subscription {
+    /// Stream being subscribed to
+    pub stream_id: String,
+    /// Unique subscription identifier
+    pub subscription_id: String,
+    /// Poll `limit` used when the request doesn't give one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_limit: Option<u32>,
+    /// Poll `wait_seconds` used when the request doesn't give one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_wait_seconds: Option<u32>,
+    /// How poll responses hand out the cursor
+    #[serde(default)]
+    pub cursor_encoding: CursorEncoding,
+    /// Events polls return; the cursor still advances past the rest
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<SubscriptionFilter>,
+    /// When the subscription was created
+    pub created_at: DateTime<Utc>,
+    /// When the subscription was last polled, to within
+    /// [`LAST_POLLED_RESOLUTION_SECS`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_polled_at: Option<DateTime<Utc>>,
+}
+
+/// How stale `last_polled_at` may get before a poll stamps it again, so a
+/// busy consumer doesn't cost a write on every poll
+pub const LAST_POLLED_RESOLUTION_SECS: i64 = 60;
+
+impl Subscription {
+    pub fn new(stream_id: String, subscription_id: String) -> Self {
+        Self {
+            stream_id,
+            subscription_id,
+            default_limit: None,
+            default_wait_seconds: None,
+            cursor_encoding: CursorEncoding::default(),
+            filter: None,
+            created_at: Utc::now(),
+            last_polled_at: None,
+        }
+    }
+
+    /// Whether the subscription hasn't polled since `cutoff`; one that has
+    /// never polled counts from when it was created
+    pub fn is_stale(&self, cutoff: DateTime<Utc>) -> bool {
+        self.last_polled_at.unwrap_or(self.created_at) < cutoff
+    }
+}
+
+/// Which events a subscription's polls return
+///
+/// Events are matched on every condition given; an empty filter matches
+/// everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubscriptionFilter {
+    /// Only events of these types
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_types: Option<Vec<String>>,
+    /// Only events whose key starts with this
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_prefix: Option<String>,
+}
+
+impl SubscriptionFilter {
+    /// Whether a poll returns `event`
+    pub fn matches(&self, event: &Event) -> bool {
+        self.event_types
+            .as_ref()
+            .is_none_or(|types| types.iter().any(|t| t == event.event_type.as_str()))
+            && self
+                .key_prefix
+                .as_ref()
+                .is_none_or(|prefix| event.key.starts_with(prefix.as_str()))
+    }
+
+    /// An `event_types` list that can match nothing is almost certainly a mistake
+    pub fn validate(&self) -> Result<()> {
+        match &self.event_types {
+            Some(types) if types.is_empty() => Err(Error::Validation(
+                "filter.event_types must name at least one type".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Request to create a subscription
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CreateSubscriptionRequest {
+    /// Unique subscription identifier
+    pub subscription_id: String,
+    /// Where to start consuming from
+    #[serde(default)]
+    pub start_from: StartFrom,
+    /// Default poll batch size (1 to [`MAX_POLL_LIMIT`])
+    #[serde(default)]
+    pub default_limit: Option<u32>,
+    /// Default long-poll wait (0 to [`MAX_POLL_WAIT_SECONDS`])
+    #[serde(default)]
+    pub default_wait_seconds: Option<u32>,
+    /// How poll responses hand out the cursor
+    #[serde(default)]
+    pub cursor_encoding: CursorEncoding,
+    /// Return only matching events from polls (default: all events)
+    #[serde(default)]
+    pub filter: Option<SubscriptionFilter>,
+}
+
+impl CreateSubscriptionRequest {
+    /// Check the subscription ID, and that the poll defaults are within what a
+    /// poll would accept
+    pub fn validate(&self) -> Result<()> {
+        validate_subscription_id(&self.subscription_id)?;
+        if let Some(limit) = self.default_limit {
+            if limit == 0 || limit > MAX_POLL_LIMIT {
+                return Err(Error::Validation(format!(
+                    "default_limit must be between 1 and {}",
+                    MAX_POLL_LIMIT
+                )));
+            }
+        }
+        if let Some(wait) = self.default_wait_seconds {
+            if wait > MAX_POLL_WAIT_SECONDS {
+                return Err(Error::Validation(format!(
+                    "default_wait_seconds must be at most {}",
+                    MAX_POLL_WAIT_SECONDS
+                )));
+            }
+        }
+        if let Some(filter) = &self.filter {
+            filter.validate()?;
+        }
+        Ok(())
+    }
+}
+
+/// Longest accepted subscription ID
+pub const MAX_SUBSCRIPTION_ID_LENGTH: usize = 128;
+
+/// Reject subscription IDs that can't be embedded in a key
+///
+/// IDs are interpolated into keys like `STREAM#{id}#SUB#{sub_id}`, so like
+/// stream IDs they are limited to ASCII letters, digits, hyphens and underscores.
+pub fn validate_subscription_id(subscription_id: &str) -> Result<()> {
+    if subscription_id.is_empty() {
+        return Err(Error::InvalidSubscriptionId(
+            "must not be empty".to_string(),
+        ));
+    }
+    if subscription_id.len() > MAX_SUBSCRIPTION_ID_LENGTH {
+        return Err(Error::InvalidSubscriptionId(format!(
+            "must be at most {} characters",
+            MAX_SUBSCRIPTION_ID_LENGTH
+        )));
+    }
+    if let Some(c) = subscription_id
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || *c == '-' || *c == '_'))
+    {
+        return Err(Error::InvalidSubscriptionId(format!(
+            "'{}' is not allowed (use letters, digits, hyphens and underscores)",
+            c
+        )));
+    }
+    Ok(())
+}
+
+/// Starting position for a new subscription
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StartFrom {
+    /// Start from the earliest available event
+    Earliest,
+    /// Start from new events only (default)
+    #[default]
+    Latest,
+    /// Start from compacted state (latest per key)
+    Compacted,
+    /// Start after the last event published at or before this time, e.g.
+    /// `{"timestamp": "2025-02-03T10:00:00Z"}`
+    Timestamp(DateTime<Utc>),
+}
+
+impl StartFrom {
+    /// Values accepted for `start_from`
+    pub const ACCEPTED: [&'static str; 3] = ["earliest", "latest", "compacted"];
+}
+
+impl FromStr for StartFrom {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "earliest" => Ok(StartFrom::Earliest),
+            "latest" => Ok(StartFrom::Latest),
+            "compacted" => Ok(StartFrom::Compacted),
+            other => Err(Error::ValidationDetails {
+                message: format!(
+                    "Invalid start_from '{}': expected one of {}",
+                    other,
+                    Self::ACCEPTED.join(", ")
+                ),
+                details: serde_json::json!({
+                    "field": "start_from",
+                    "accepted": Self::ACCEPTED,
+                }),
+            }),
+        }
+    }
+}
+
+/// Request to reposition a single subscription
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeekRequest {
+    /// Position to move to
+    pub to: StartFrom,
+}
+
+/// Sequences accepted by one batch get
+pub const MAX_BATCH_GET_EVENTS: usize = 100;
+
+/// Stream IDs accepted by one batch get (`GET /streams?ids=`)
+pub const MAX_BATCH_GET_STREAMS: usize = 100;
+
+/// Request to fetch specific events from one partition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchGetEventsRequest {
+    pub sequences: Vec<u64>,
+}
+
+impl BatchGetEventsRequest {
+    /// Check there is at least one sequence and at most [`MAX_BATCH_GET_EVENTS`]
+    pub fn validate(&self) -> Result<()> {
+        if self.sequences.is_empty() {
+            return Err(Error::Validation("sequences must not be empty".to_string()));
+        }
+        if self.sequences.len() > MAX_BATCH_GET_EVENTS {
+            return Err(Error::Validation(format!(
+                "At most {} sequences can be fetched at once, got {}",
+                MAX_BATCH_GET_EVENTS,
+                self.sequences.len()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Events found by a batch get, in request order, and the sequences that
+/// don't exist
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchGetEventsResponse {
+    pub events: Vec<Event>,
+    pub missing: Vec<u64>,
+}
+
+/// Request to reposition every subscription on a stream
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeekAllRequest {
+    /// Position to move to
+    pub to: StartFrom,
+}
+
+impl SeekAllRequest {
+    /// Compacted state is only read when a subscription is created, so a
+    /// subscription can't be moved back to it
+    pub fn validate(&self) -> Result<()> {
+        if let StartFrom::Compacted = self.to {
+            return Err(Error::Validation(
+                "Subscriptions can only be moved to earliest, latest or a timestamp".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A subscription a seek-all couldn't reposition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubscriptionSeekFailure {
+    pub subscription_id: String,
+    pub error: ErrorResponse,
+}
+
+/// Response after repositioning subscriptions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeekAllResponse {
+    /// Number of subscriptions repositioned
+    pub repositioned: u32,
+    /// Subscriptions left where they were, each with the reason
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub failed: Vec<SubscriptionSeekFailure>,
+}
+
+/// How far a subscription is behind the head of each partition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubscriptionLag {
+    pub stream_id: String,
+    pub subscription_id: String,
+    /// Sum of the per-partition lag
+    pub total_lag: u64,
+    pub partitions: Vec<PartitionLag>,
+}
+
+/// Lag of a subscription on one partition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartitionLag {
+    pub partition: u32,
+    /// Last committed sequence
+    pub committed_offset: u64,
+    /// Latest published sequence
+    pub latest_offset: u64,
+    /// Events published but not yet committed
+    pub lag: u64,
+}
+
+impl PartitionLag {
+    pub fn new(partition: u32, committed_offset: u64, latest_offset: u64) -> Self {
+        Self {
+            partition,
+            committed_offset,
+            latest_offset,
+            lag: latest_offset.saturating_sub(committed_offset),
+        }
+    }
+}
+
+/// Consumer offset for a subscription
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsumerOffset {
+    pub stream_id: String,
+    pub subscription_id: String,
+    pub partition: u32,
+    pub offset: u64,
+    pub committed_at: DateTime<Utc>,
+}
+
+/// Largest `limit` a single poll will accept
+pub const MAX_POLL_LIMIT: u32 = 10_000;
+
+/// Longest a poll will wait for events; stays under the API Gateway timeout
+pub const MAX_POLL_WAIT_SECONDS: u32 = 20;
+
+/// Request to poll for events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PollRequest {
+    /// Maximum number of events to return
+    #[serde(default = "default_batch_size")]
+    pub limit: u32,
+}
+
+fn default_batch_size() -> u32 {
+    100
+}
+
+/// Response from polling
+///
+/// `E` is [`Event`], or JSON objects holding only the fields of a projected poll.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PollResponse<E = Event> {
+    /// Events retrieved
+    pub events: Vec<E>,
+    /// Opaque cursor for committing
+    pub cursor: String,
+    /// Events published after the cursor across polled partitions (only
+    /// counted with `include_remaining=true`; 0 otherwise)
+    pub remaining: u64,
+    /// `remaining` by partition (only with `include_remaining=true`, and
+    /// omitted under `Accept-Version: 1`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remaining_per_partition: Option<Vec<PartitionRemaining>>,
+    /// Per-partition offsets encoded in the cursor (only with `include_partition_offsets=true`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offsets: Option<Vec<PartitionOffset>>,
+    /// The poll returned everything published so far: the backlog is drained
+    /// and the consumer is live
+    #[serde(default)]
+    pub caught_up: bool,
+    /// More events are waiting past this poll: a partition's read filled its
+    /// window, or `remaining` counted events after the cursor. Page through a
+    /// backlog by committing and polling again until this is false.
+    #[serde(default)]
+    pub has_more: bool,
+}
+
+/// Events published after a poll's cursor in one partition
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartitionRemaining {
+    pub partition: u32,
+    pub remaining: u64,
+}
+
+/// How a poll chooses its starting offset
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PollMode {
+    /// Always read from the committed offset; re-polling returns the same window
+    #[default]
+    Peek,
+    /// Read past windows already handed out, so polls advance without a commit
+    Consume,
+}
+
+/// When a poll's events count as processed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliverySemantics {
+    /// Events are redelivered until the consumer commits the cursor
+    #[default]
+    AtLeastOnce,
+    /// The poll commits before responding: a consumer that crashes mid-batch
+    /// loses the batch instead of seeing it again
+    AtMostOnce,
+}
+
+/// How a subscription's poll cursors are represented
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CursorEncoding {
+    /// The offsets themselves, base64-encoded; grows with the partition count
+    #[default]
+    Inline,
+    /// A short token naming offsets stored server-side, for very wide streams
+    /// where an inline cursor would exceed URL or header limits
+    Token,
+}
+
+/// Length of the tokens naming cursors stored server-side (hex digits)
+pub const CURSOR_TOKEN_LENGTH: usize = 32;
+
+/// Cursor state (encoded in the cursor string)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CursorState {
+    /// Stream the cursor was issued for (empty on cursors issued before
+    /// cursors named their stream and subscription)
+    #[serde(default)]
+    pub stream_id: String,
+    /// Subscription the cursor was issued for
+    #[serde(default)]
+    pub subscription_id: String,
     /// Offsets per partition at time of poll
     pub offsets: Vec<PartitionOffset>,
+    /// Events published after each of `offsets`, in the same order (only
+    /// with `include_remaining=true`, to keep cursors for wide streams short)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remaining_per_partition: Vec<u64>,
+    /// Latest `committed_at` among the polled partitions when the poll read
+    /// their offsets (the Unix epoch if none had been committed). Committing
+    /// the cursor fails if any of them has been committed since; cursors
+    /// without it (tail cursors, or ones issued before it was added) can't be
+    /// committed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub committed_at: Option<DateTime<Utc>>,
+}
+
+impl CursorState {
+    /// Encode as the opaque cursor string handed to clients (base64 JSON)
+    pub fn encode(&self) -> Result<String> {
+        let json = serde_json::to_string(self).map_err(|e| Error::Internal(e.to_string()))?;
+        Ok(URL_SAFE_NO_PAD.encode(json.as_bytes()))
+    }
+
+    /// Name for this state in the server-side cursor store
+    ///
+    /// Derived from the state itself, so a poll that finds nothing new hands
+    /// back the token it handed out last time instead of storing another.
+    pub fn token(&self) -> Result<String> {
+        let json = serde_json::to_vec(self).map_err(|e| Error::Internal(e.to_string()))?;
+        Ok(sha256_hex(&json)[..CURSOR_TOKEN_LENGTH].to_string())
+    }
+
+    /// Decode a cursor string produced by [`Self::encode`]
+    ///
+    /// Doesn't check which stream or subscription the cursor was issued for.
+    /// A malformed cursor's error details name the step that failed and
+    /// where, but never echo the cursor or what it decoded to.
+    pub fn decode(cursor: &str) -> Result<Self> {
+        let invalid = |message: &str, details: serde_json::Value| {
+            let mut details = details;
+            details["cursor_length"] = cursor.len().into();
+            Error::InvalidCursor {
+                message: message.to_string(),
+                details: Some(details),
+            }
+        };
+
+        let cursor_bytes = URL_SAFE_NO_PAD.decode(cursor).map_err(|e| {
+            let position = match e {
+                DecodeError::InvalidByte(offset, _) | DecodeError::InvalidLastSymbol(offset, _) => {
+                    Some(offset)
+                }
+                DecodeError::InvalidLength(_) | DecodeError::InvalidPadding => None,
+            };
+            invalid(
+                "Invalid base64",
+                serde_json::json!({ "step": "base64", "position": position }),
+            )
+        })?;
+        let cursor_json = std::str::from_utf8(&cursor_bytes).map_err(|e| {
+            let position = e.valid_up_to();
+            invalid(
+                "Invalid UTF-8",
+                serde_json::json!({ "step": "utf8", "position": position }),
+            )
+        })?;
+        serde_json::from_str(cursor_json).map_err(|e| {
+            let details = serde_json::json!({ "step": "json", "column": e.column() });
+            invalid("Invalid JSON", details)
+        })
+    }
+}
+
+/// Subscription ID carried by tail cursors
+///
+/// Subscription IDs can't be empty, so a tail cursor can never be committed
+/// to a real subscription.
+pub const TAIL_SUBSCRIPTION_ID: &str = "";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartitionOffset {
+    pub partition: u32,
+    pub offset: u64,
+}
+
+/// Request to commit offset
+///
+/// Exactly one of `cursor` or `up_to_timestamp` must be set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitRequest {
+    /// Cursor from poll response
+    #[serde(default)]
+    pub cursor: Option<String>,
+    /// Commit every event published at or before this time, in every partition
+    #[serde(default)]
+    pub up_to_timestamp: Option<DateTime<Utc>>,
+}
+
+/// Response after committing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitResponse {
+    /// Whether the commit succeeded
+    pub success: bool,
+}
+
+/// Single entry in a batch commit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubscriptionCommit {
+    /// Subscription to commit
+    pub subscription_id: String,
+    /// Cursor from that subscription's poll response
+    pub cursor: String,
+}
+
+/// Request to commit several subscriptions at once
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitBatchRequest {
+    pub commits: Vec<SubscriptionCommit>,
+}
+
+/// Outcome of one entry in a batch commit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubscriptionCommitResult {
+    pub subscription_id: String,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorResponse>,
+}
+
+/// Response after a batch commit (one result per requested commit, in order)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitBatchResponse {
+    pub results: Vec<SubscriptionCommitResult>,
+}
+
+/// Compacted state (latest per key)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompactedEvent {
+    /// ID of the event this state came from
+    #[serde(default)]
+    pub id: String,
+    pub stream_id: String,
+    pub key: String,
+    pub event_type: String,
+    pub data: serde_json::Value,
+    /// Original sequence number
+    pub sequence: u64,
+    pub partition: u32,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl CompactedEvent {
+    /// Whether this state is newer than `existing` for the same key
+    ///
+    /// A `partition_key` can send one key's events to several partitions, whose
+    /// sequences are unrelated. Within a partition the later sequence wins;
+    /// across partitions the later timestamp does, ties going to the higher
+    /// partition.
+    pub fn supersedes(&self, existing: &CompactedEvent) -> bool {
+        if self.partition == existing.partition {
+            self.sequence > existing.sequence
+        } else {
+            (self.timestamp, self.partition) > (existing.timestamp, existing.partition)
+        }
+    }
+}
+
+/// One entry in a stream's compaction changelog: a key's compacted state as
+/// written by one compaction (including backfills and redactions)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompactionChange {
+    /// Position in the changelog, starting at 1; later changes have higher
+    /// positions
+    pub position: u64,
+    #[serde(flatten)]
+    pub state: CompactedEvent,
+}
+
+/// A page of the compaction changelog
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompactionChangelogResponse {
+    pub changes: Vec<CompactionChange>,
+    /// Pass back as `?cursor=` to continue after these changes
+    pub cursor: String,
+}
+
+/// Compacted state plus a tail cursor to continue from
+///
+/// Applying `compacted`, then tailing from `cursor`, sees every event's effect
+/// exactly once: compacted entries newer than the cursor are left out because
+/// the tail delivers them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotResponse {
+    pub compacted: Vec<CompactedEvent>,
+    /// Cursor for `GET /streams/{stream_id}/tail?cursor=`
+    pub cursor: String,
+}
+
+/// Request to rebuild compacted state from existing events
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CompactionBackfillRequest {
+    /// Offsets an incomplete run stopped at (default: scan from the start)
+    #[serde(default)]
+    pub from_offsets: Option<Vec<PartitionOffset>>,
+}
+
+/// Result of rebuilding compacted state from existing events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompactionBackfillResult {
+    pub stream_id: String,
+    /// Events read across all partitions
+    pub events_scanned: u64,
+    /// Keys whose compacted state was written
+    pub keys_compacted: u64,
+    /// Partitions scanned to the end
+    pub partitions_completed: u32,
+    /// Last sequence scanned in each partition
+    pub offsets: Vec<PartitionOffset>,
+    /// False if the time budget ran out; pass `offsets` back as
+    /// `from_offsets` to finish
+    pub complete: bool,
+}
+
+/// Request to copy a stream into a new one with a different partition count
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepartitionRequest {
+    /// Stream to create and copy the events into
+    pub target_stream_id: String,
+    /// Partition count of the new stream
+    pub partition_count: u32,
+    /// Source offsets an incomplete run stopped at; the target must already
+    /// exist (default: create the target and copy from the start)
+    #[serde(default)]
+    pub from_offsets: Option<Vec<PartitionOffset>>,
+    /// Alias to point at the target once every event is copied; it must be
+    /// unset or point at the source (default: leave aliases alone)
+    #[serde(default)]
+    pub alias: Option<String>,
+}
+
+/// Result of copying a stream's events into a repartitioned stream
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepartitionResult {
+    pub stream_id: String,
+    pub target_stream_id: String,
+    pub partition_count: u32,
+    /// Events republished by this run
+    pub events_copied: u64,
+    /// Last source sequence copied from each partition
+    pub offsets: Vec<PartitionOffset>,
+    /// False if the time budget ran out; pass `offsets` back as `from_offsets`
+    /// to finish
+    pub complete: bool,
+    /// The alias now pointing at the target, once complete
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
 }
 
+/// A name publishes and polls resolve to a stream, so that producers and
+/// consumers can be moved to another stream without changing their config
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PartitionOffset {
-    pub partition: u32,
-    pub offset: u64,
+pub struct StreamAlias {
+    pub alias: String,
+    pub stream_id: String,
+    pub updated_at: DateTime<Utc>,
 }
 
-/// Request to commit offset
+/// Request to point an alias at a stream
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CommitRequest {
-    /// Cursor from poll response
-    pub cursor: String,
+pub struct PutAliasRequest {
+    pub stream_id: String,
 }
 
-/// Response after committing
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CommitResponse {
-    /// Whether the commit succeeded
-    pub success: bool,
+/// Reject aliases that can't be embedded in a key, as for subscription IDs
+pub fn validate_alias(alias: &str) -> Result<()> {
+    if alias.is_empty() || alias.len() > MAX_SUBSCRIPTION_ID_LENGTH {
+        return Err(Error::Validation(format!(
+            "alias must be between 1 and {} characters",
+            MAX_SUBSCRIPTION_ID_LENGTH
+        )));
+    }
+    if let Some(c) = alias
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || *c == '-' || *c == '_'))
+    {
+        return Err(Error::Validation(format!(
+            "'{}' is not allowed in an alias (use letters, digits, hyphens and underscores)",
+            c
+        )));
+    }
+    Ok(())
 }
 
-/// Compacted state (latest per key)
+/// Environment variable setting how many failed compaction attempts a stream
+/// record gets before it is dead-lettered
+pub const DLQ_MAX_ATTEMPTS_ENV: &str = "EVENTLEDGER_DLQ_MAX_ATTEMPTS";
+
+/// Failed attempts before a record is dead-lettered (default: `EVENTLEDGER_DLQ_MAX_ATTEMPTS`, else 3)
+pub fn dlq_max_attempts() -> u32 {
+    env_u32(DLQ_MAX_ATTEMPTS_ENV).unwrap_or(3)
+}
+
+/// Environment variable capping how many partitions a stream may have
+pub const MAX_PARTITIONS_ENV: &str = "EVENTLEDGER_MAX_PARTITIONS";
+
+/// Most partitions a stream may have (default: `EVENTLEDGER_MAX_PARTITIONS`, else 1024)
+///
+/// Polls read every partition of a stream, so this also bounds their fan-out.
+pub fn max_partitions() -> u32 {
+    env_u32(MAX_PARTITIONS_ENV).unwrap_or(1024)
+}
+
+/// Environment variable setting how many keys the compactor works on at once
+pub const COMPACTOR_CONCURRENCY_ENV: &str = "EVENTLEDGER_COMPACTOR_CONCURRENCY";
+
+/// Keys a compactor batch updates concurrently (default: `EVENTLEDGER_COMPACTOR_CONCURRENCY`, else 10)
+pub fn compactor_concurrency() -> usize {
+    env_u32(COMPACTOR_CONCURRENCY_ENV).unwrap_or(10) as usize
+}
+
+/// A DynamoDB Streams record the compactor gave up on
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CompactedEvent {
+pub struct DeadLetter {
     pub stream_id: String,
-    pub key: String,
-    pub event_type: String,
-    pub data: serde_json::Value,
-    /// Original sequence number
-    pub sequence: u64,
-    pub partition: u32,
-    pub timestamp: DateTime<Utc>,
+    /// Sequence number of the failed stream record
+    pub record_id: String,
+    /// The record's new image, as DynamoDB JSON
+    pub record: serde_json::Value,
+    /// Error from the final attempt
+    pub error: String,
+    pub attempts: u32,
+    pub failed_at: DateTime<Utc>,
+}
+
+/// Server limits, defaults and features, served so clients needn't hardcode them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Capabilities {
+    /// Server version
+    pub version: String,
+    /// Precision of event timestamps
+    pub timestamp_precision: String,
+    pub limits: CapabilityLimits,
+    pub defaults: CapabilityDefaults,
+    pub features: CapabilityFeatures,
+}
+
+/// Limits a request is checked against
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapabilityLimits {
+    /// Events to send in one JSON or CBOR publish, or one multi-stream publish
+    pub max_publish_batch: usize,
+    /// Size of a request body after gzip decompression
+    pub max_decompressed_body_bytes: usize,
+    /// Events returned by one poll
+    pub max_poll_limit: u32,
+    /// Long-poll wait
+    pub max_poll_wait_seconds: u32,
+    /// Length of a subscription ID
+    pub max_subscription_id_length: usize,
+}
+
+/// Values used when a stream is created without them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapabilityDefaults {
+    pub partition_count: u32,
+    pub retention_hours: u32,
+}
+
+/// Optional features this deployment supports
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapabilityFeatures {
+    /// Latest-event-per-key state and snapshots
+    pub compaction: bool,
+    /// Stream-wide `global_position` on streams created with `global_ordering`
+    pub global_ordering: bool,
+    /// `application/cbor` request and response bodies
+    pub cbor: bool,
+    /// `application/x-ndjson` publish bodies
+    pub ndjson: bool,
+    /// `Content-Encoding: gzip` publish bodies
+    pub gzip: bool,
+    /// Requests may pick their table with `X-EventLedger-Table`
+    pub table_override: bool,
+}
+
+impl Capabilities {
+    /// This server's capabilities, with defaults read from the environment
+    pub fn current() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            timestamp_precision: TIMESTAMP_PRECISION.to_string(),
+            limits: CapabilityLimits {
+                max_publish_batch: MAX_PUBLISH_BATCH,
+                max_decompressed_body_bytes: crate::body::MAX_DECOMPRESSED_BODY_BYTES,
+                max_poll_limit: MAX_POLL_LIMIT,
+                max_poll_wait_seconds: MAX_POLL_WAIT_SECONDS,
+                max_subscription_id_length: MAX_SUBSCRIPTION_ID_LENGTH,
+            },
+            defaults: CapabilityDefaults {
+                partition_count: default_partition_count(),
+                retention_hours: default_retention_hours(),
+            },
+            features: CapabilityFeatures {
+                compaction: true,
+                global_ordering: true,
+                cbor: true,
+                ndjson: true,
+                gzip: true,
+                table_override: crate::dynamo::table_override_allowed(),
+            },
+        }
+    }
 }
 
 /// API error response
@@ -254,6 +1928,152 @@ impl ErrorResponse {
 mod tests {
     use super::*;
 
+    fn sample_event() -> Event {
+        Event {
+            id: "abc".to_string(),
+            stream_id: "orders".to_string(),
+            partition: 1,
+            sequence: 7,
+            global_position: None,
+            key: "order-1".to_string(),
+            partition_key: None,
+            order_key: None,
+            event_type: "order.created".to_string(),
+            data: serde_json::json!({ "total": 10 }),
+            timestamp: Utc::now(),
+            wall_clock_timestamp: None,
+        }
+    }
+
+    #[test]
+    fn test_partition_load_flags_partitions_over_twice_the_mean() {
+        let load = |counts: &[u64]| {
+            let partitions = counts
+                .iter()
+                .enumerate()
+                .map(|(i, &offset)| PartitionOffset {
+                    partition: i as u32,
+                    offset,
+                })
+                .collect();
+            PartitionLoad::new(partitions).hot_partitions
+        };
+
+        // Mean 25: 80 is over 50, 50 itself isn't
+        assert_eq!(load(&[80, 10, 5, 5]), vec![0]);
+        assert_eq!(load(&[50, 30, 10, 10]), Vec::<u32>::new());
+        // Nothing is hot in an empty or single-partition stream
+        assert_eq!(load(&[0, 0, 0]), Vec::<u32>::new());
+        assert_eq!(load(&[100]), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn test_partition_timestamp_never_goes_backward() {
+        let now = timestamp_now();
+        let earlier = now - chrono::Duration::seconds(5);
+        let later = now + chrono::Duration::seconds(5);
+
+        assert_eq!(partition_timestamp(now, None), (now, None));
+        assert_eq!(partition_timestamp(now, Some(earlier)), (now, None));
+        assert_eq!(partition_timestamp(now, Some(now)), (now, None));
+        assert_eq!(partition_timestamp(now, Some(later)), (later, Some(now)));
+        assert_eq!(now.timestamp_subsec_nanos() % 1_000, 0);
+    }
+
+    #[test]
+    fn test_sort_by_order_key_differs_from_sequence_order() {
+        let events: Vec<Event> = [Some(30), None, Some(10), Some(20)]
+            .into_iter()
+            .enumerate()
+            .map(|(i, order_key)| Event {
+                sequence: i as u64 + 1,
+                order_key,
+                ..sample_event()
+            })
+            .collect();
+
+        let mut ascending = events.clone();
+        sort_by_order_key(&mut ascending, false);
+        let sequences: Vec<u64> = ascending.iter().map(|e| e.sequence).collect();
+        assert_eq!(sequences, vec![3, 4, 1, 2]);
+
+        let mut descending = events;
+        sort_by_order_key(&mut descending, true);
+        let sequences: Vec<u64> = descending.iter().map(|e| e.sequence).collect();
+        assert_eq!(sequences, vec![1, 4, 3, 2]);
+    }
+
+    #[test]
+    fn test_projection_keeps_only_requested_fields() {
+        let projection = EventProjection::parse("key, event_type,sequence,key").unwrap();
+        let value = projection.apply(&sample_event()).unwrap();
+
+        assert_eq!(
+            value,
+            serde_json::json!({ "key": "order-1", "event_type": "order.created", "sequence": 7 })
+        );
+        assert!(projection.attributes().contains(&"timestamp"));
+        assert!(!projection.attributes().contains(&"data"));
+    }
+
+    #[test]
+    fn test_projection_rejects_unknown_field() {
+        let err = EventProjection::parse("key,payload").unwrap_err();
+        assert!(err.to_string().contains("payload"));
+        assert_eq!(err.details().unwrap()["field"], "fields");
+    }
+
+    #[test]
+    fn test_event_without_payload_is_rejected() {
+        let partial = serde_json::json!({
+            "id": "abc",
+            "stream_id": "orders",
+            "partition": 1,
+            "sequence": 7,
+            "timestamp": "2025-01-01T00:00:00Z"
+        });
+        assert!(serde_json::from_value::<Event>(partial).is_err());
+    }
+
+    #[test]
+    fn test_routing_key_defaults_to_key() {
+        let event: PublishEvent =
+            serde_json::from_value(serde_json::json!({"key": "order-1", "type": "t", "data": {}}))
+                .unwrap();
+        assert_eq!(event.partition_key, None);
+        assert_eq!(event.routing_key(), "order-1");
+
+        let event = PublishEvent {
+            key: "line-7".to_string(),
+            partition_key: Some("order-1".to_string()),
+            order_key: None,
+            timestamp: None,
+            event_type: EventType::new("line.added").unwrap(),
+            data: serde_json::Value::Null,
+        };
+        assert_eq!(event.routing_key(), "order-1");
+    }
+
+    #[test]
+    fn test_start_from_parses_accepted_values() {
+        for value in StartFrom::ACCEPTED {
+            let parsed: StartFrom = value.parse().unwrap();
+            assert_eq!(serde_json::to_value(&parsed).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_start_from_rejects_typo_with_accepted_values() {
+        let err = "earlist".parse::<StartFrom>().unwrap_err();
+        assert!(err.to_string().contains("earlist"));
+        let details = err.details().unwrap();
+        assert_eq!(details["field"], "start_from");
+        assert_eq!(
+            details["accepted"],
+            serde_json::json!(["earliest", "latest", "compacted"])
+        );
+    }
+
     #[test]
     fn test_stream_creation() {
         let stream = Stream::new("orders".into(), 3, 168);
@@ -266,8 +2086,92 @@ mod tests {
     fn test_create_stream_request_defaults() {
         let json = r#"{"stream_id": "orders"}"#;
         let req: CreateStreamRequest = serde_json::from_str(json).unwrap();
-        assert_eq!(req.partition_count, 3);
-        assert_eq!(req.retention_hours, 168);
+        assert_eq!(req.partition_count, None);
+        assert_eq!(req.retention_hours, None);
+        assert!(!req.global_ordering);
+    }
+
+    #[test]
+    fn test_create_stream_request_env_defaults() {
+        let json = r#"{"stream_id": "orders"}"#;
+        let req: CreateStreamRequest = serde_json::from_str(json).unwrap();
+
+        // Explicit values win over the environment
+        let explicit = CreateStreamRequest {
+            partition_count: Some(2),
+            retention_hours: Some(48),
+            ..req.clone()
+        };
+        assert_eq!(explicit.resolved_partition_count(), 2);
+        assert_eq!(explicit.resolved_retention_hours(), 48);
+
+        // Environment values are parsed per call (the variables themselves
+        // aren't set here, as other tests read them concurrently)
+        assert_eq!(positive_u32("8"), Some(8));
+        assert_eq!(positive_u32(" 24\n"), Some(24));
+
+        // Invalid values fall back to the built-in defaults
+        assert_eq!(positive_u32("0"), None);
+        assert_eq!(positive_u32("a week"), None);
+    }
+
+    #[test]
+    fn test_stream_without_global_ordering_deserializes() {
+        // META items written before global ordering existed
+        let json = r#"{"stream_id": "orders", "partition_count": 3, "retention_hours": 168, "created_at": "2025-02-03T00:00:00Z"}"#;
+        let stream: Stream = serde_json::from_str(json).unwrap();
+        assert!(!stream.global_ordering);
+    }
+
+    #[test]
+    fn test_allowed_event_types() {
+        let mut stream = Stream::new("orders".into(), 3, 168);
+        assert!(stream.check_event_type("anything").is_ok());
+
+        stream.allowed_event_types = Some(vec!["order.created".into(), "order.shipped".into()]);
+        assert!(stream.check_event_type("order.created").is_ok());
+
+        let err = stream.check_event_type("order.deleted").unwrap_err();
+        assert_eq!(err.code(), "validation_error");
+        assert_eq!(
+            err.details().unwrap()["allowed_event_types"],
+            serde_json::json!(["order.created", "order.shipped"])
+        );
+    }
+
+    #[test]
+    fn test_event_id_round_trip() {
+        let id = event_id("orders", 2, 42);
+        assert_eq!(id, event_id("orders", 2, 42));
+        assert_ne!(id, event_id("orders", 2, 43));
+        assert_eq!(parse_event_id(&id), Some(("orders".to_string(), 2, 42)));
+        assert_eq!(parse_event_id("not-an-id"), None);
+    }
+
+    #[test]
+    fn test_commit_request_forms() {
+        let req: CommitRequest = serde_json::from_str(r#"{"cursor": "abc"}"#).unwrap();
+        assert_eq!(req.cursor.as_deref(), Some("abc"));
+        assert!(req.up_to_timestamp.is_none());
+
+        let req: CommitRequest =
+            serde_json::from_str(r#"{"up_to_timestamp": "2025-02-03T10:00:00Z"}"#).unwrap();
+        assert!(req.cursor.is_none());
+        assert_eq!(
+            req.up_to_timestamp.unwrap().to_rfc3339(),
+            "2025-02-03T10:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn test_poll_mode_serialization() {
+        assert_eq!(PollMode::default(), PollMode::Peek);
+        assert_eq!(
+            serde_json::to_string(&PollMode::Consume).unwrap(),
+            "\"consume\""
+        );
+        let mode: PollMode = serde_json::from_str("\"peek\"").unwrap();
+        assert_eq!(mode, PollMode::Peek);
     }
 
     #[test]
@@ -280,6 +2184,303 @@ mod tests {
             serde_json::to_string(&StartFrom::Compacted).unwrap(),
             r#""compacted""#
         );
+
+        let start: StartFrom =
+            serde_json::from_str(r#"{"timestamp": "2025-02-03T10:00:00Z"}"#).unwrap();
+        let StartFrom::Timestamp(at) = start else {
+            panic!("expected a timestamp, got {:?}", start);
+        };
+        assert_eq!(at.to_rfc3339(), "2025-02-03T10:00:00+00:00");
+    }
+
+    #[test]
+    fn test_subscription_poll_defaults_validated() {
+        let mut req = CreateSubscriptionRequest {
+            subscription_id: "sub".to_string(),
+            default_limit: Some(5),
+            default_wait_seconds: Some(MAX_POLL_WAIT_SECONDS),
+            ..Default::default()
+        };
+        assert!(req.validate().is_ok());
+
+        req.default_limit = Some(0);
+        assert!(req.validate().is_err());
+
+        req.default_limit = Some(MAX_POLL_LIMIT + 1);
+        assert!(req.validate().is_err());
+
+        req.default_limit = None;
+        req.default_wait_seconds = Some(MAX_POLL_WAIT_SECONDS + 1);
+        assert!(req.validate().is_err());
+
+        req.default_wait_seconds = None;
+        req.filter = Some(SubscriptionFilter {
+            event_types: Some(Vec::new()),
+            key_prefix: None,
+        });
+        assert!(req.validate().is_err());
+    }
+
+    #[test]
+    fn test_subscription_staleness() {
+        let now = Utc::now();
+        let mut sub = Subscription::new("orders".to_string(), "billing".to_string());
+        sub.created_at = now - chrono::Duration::hours(48);
+        assert!(sub.is_stale(now - chrono::Duration::hours(24)));
+
+        sub.last_polled_at = Some(now - chrono::Duration::hours(1));
+        assert!(!sub.is_stale(now - chrono::Duration::hours(24)));
+        assert!(sub.is_stale(now - chrono::Duration::minutes(30)));
+    }
+
+    #[test]
+    fn test_subscription_id_validated() {
+        for valid in ["billing", "shipping-service", "worker_2", "A1"] {
+            assert!(
+                validate_subscription_id(valid).is_ok(),
+                "{} rejected",
+                valid
+            );
+        }
+
+        let overlong = "a".repeat(MAX_SUBSCRIPTION_ID_LENGTH + 1);
+        for invalid in [
+            "",
+            overlong.as_str(),
+            "billing#1",
+            "SUB#billing",
+            "a b",
+            "a/b",
+        ] {
+            assert!(
+                matches!(
+                    validate_subscription_id(invalid),
+                    Err(Error::InvalidSubscriptionId(_))
+                ),
+                "{:?} accepted",
+                invalid
+            );
+        }
+        assert!(validate_subscription_id(&"a".repeat(MAX_SUBSCRIPTION_ID_LENGTH)).is_ok());
+
+        // create_subscription validates the whole request, ID included
+        let req = CreateSubscriptionRequest {
+            subscription_id: "billing#1".to_string(),
+            ..Default::default()
+        };
+        assert!(matches!(
+            req.validate(),
+            Err(Error::InvalidSubscriptionId(_))
+        ));
+    }
+
+    #[test]
+    fn test_idempotency_window_defaults_to_retention() {
+        let mut stream = Stream::new("orders".into(), 3, 48);
+        assert_eq!(stream.idempotency_window_hours(), 48);
+
+        stream.idempotency_ttl_hours = Some(6);
+        assert_eq!(stream.idempotency_window_hours(), 6);
+        assert!(stream.check_idempotency_ttl().is_ok());
+
+        let claim = IdempotencyRecord::claim("orders", "req-1".into(), "abc".into());
+        assert_eq!(
+            claim.expires_at - claim.created_at.timestamp(),
+            IDEMPOTENCY_CLAIM_SECS
+        );
+        let record = claim.complete(&stream, vec![]);
+        assert_eq!(record.expires_at - record.created_at.timestamp(), 6 * 3600);
+        assert!(!record.is_expired());
+    }
+
+    #[test]
+    fn test_idempotency_replay_requires_the_same_finished_body() {
+        let stream = Stream::new("orders".into(), 3, 48);
+        let hash = idempotency_body_hash(b"{}");
+        let claim = IdempotencyRecord::claim("orders", "req-1".into(), hash.clone());
+        assert_eq!(
+            claim.check_replay(&hash).unwrap_err().code(),
+            "idempotency_key_in_use"
+        );
+
+        let record = claim.complete(&stream, vec![]);
+        assert!(record.check_replay(&hash).is_ok());
+        let other = idempotency_body_hash(b"[]");
+        let err = record.check_replay(&other).unwrap_err();
+        assert_eq!(err.code(), "idempotency_key_mismatch");
+        assert_eq!(err.status_code(), 422);
+
+        assert!(validate_idempotency_key("checkout-7f3a").is_ok());
+        assert!(validate_idempotency_key("").is_err());
+        assert!(validate_idempotency_key(&"k".repeat(MAX_IDEMPOTENCY_KEY_LENGTH + 1)).is_err());
+    }
+
+    #[test]
+    fn test_compacted_ttl_must_be_positive() {
+        let mut stream = Stream::new("orders".into(), 3, 48);
+        assert!(stream.check_compacted_ttl().is_ok());
+
+        // May exceed retention: compacted state is expired on its own schedule
+        stream.compacted_ttl_hours = Some(24 * 30);
+        assert!(stream.check_compacted_ttl().is_ok());
+
+        stream.compacted_ttl_hours = Some(0);
+        assert!(matches!(
+            stream.check_compacted_ttl(),
+            Err(Error::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn test_seek_all_rejects_compacted() {
+        for to in [
+            StartFrom::Earliest,
+            StartFrom::Latest,
+            StartFrom::Timestamp(Utc::now()),
+        ] {
+            assert!(SeekAllRequest { to }.validate().is_ok());
+        }
+        let err = SeekAllRequest {
+            to: StartFrom::Compacted,
+        }
+        .validate()
+        .unwrap_err();
+        assert_eq!(err.code(), "validation_error");
+    }
+
+    #[test]
+    fn test_spreading_empty_keys_requires_compaction_off() {
+        let mut stream = Stream::new("clicks".into(), 3, 48);
+        assert!(stream.compaction);
+        assert!(stream.check_empty_key_strategy().is_ok());
+
+        for strategy in [EmptyKeyStrategy::RoundRobin, EmptyKeyStrategy::Random] {
+            stream.empty_key_strategy = strategy;
+            stream.compaction = true;
+            assert!(matches!(
+                stream.check_empty_key_strategy(),
+                Err(Error::Validation(_))
+            ));
+            stream.compaction = false;
+            assert!(stream.check_empty_key_strategy().is_ok());
+        }
+
+        // Streams stored before the flag existed were compacted
+        let stored = serde_json::json!({
+            "stream_id": "old",
+            "partition_count": 1,
+            "retention_hours": 1,
+            "created_at": "2024-01-01T00:00:00Z",
+        });
+        assert!(serde_json::from_value::<Stream>(stored).unwrap().compaction);
+    }
+
+    #[test]
+    fn test_retention_must_be_within_a_year() {
+        let mut stream = Stream::new("orders".into(), 3, 0);
+        assert!(matches!(
+            stream.check_retention(),
+            Err(Error::Validation(_))
+        ));
+
+        stream.retention_hours = MAX_RETENTION_HOURS + 1;
+        assert!(matches!(
+            stream.check_retention(),
+            Err(Error::Validation(_))
+        ));
+
+        for hours in [1, 24 * 30, MAX_RETENTION_HOURS] {
+            stream.retention_hours = hours;
+            assert!(stream.check_retention().is_ok(), "{} hours", hours);
+        }
+    }
+
+    #[test]
+    fn test_publish_rate_must_be_positive() {
+        let mut stream = Stream::new("orders".into(), 3, 48);
+        assert!(stream.check_publish_rate().is_ok());
+
+        stream.max_publish_per_second = Some(0);
+        assert!(matches!(
+            stream.check_publish_rate(),
+            Err(Error::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn test_tags_are_checked_and_filtered() {
+        let mut stream = Stream::new("orders".into(), 3, 48);
+        stream.tags = HashMap::from([("team".to_string(), "payments".to_string())]);
+        assert!(stream.check_tags().is_ok());
+
+        let filter: TagFilter = "team:payments".parse().unwrap();
+        assert!(stream.has_tag(&filter));
+        assert!(!stream.has_tag(&"team:search".parse().unwrap()));
+        assert!(!stream.has_tag(&"env:payments".parse().unwrap()));
+
+        // Values may contain the separator; keys may not
+        let filter: TagFilter = "url:http://x".parse().unwrap();
+        assert_eq!(
+            (filter.key.as_str(), filter.value.as_str()),
+            ("url", "http://x")
+        );
+        for invalid in ["team", ":payments"] {
+            assert!(matches!(
+                invalid.parse::<TagFilter>(),
+                Err(Error::Validation(_))
+            ));
+        }
+        for key in ["", "a:b"] {
+            stream.tags = HashMap::from([(key.to_string(), "x".to_string())]);
+            assert!(matches!(stream.check_tags(), Err(Error::Validation(_))));
+        }
+
+        stream.tags = (0..=MAX_STREAM_TAGS)
+            .map(|i| (i.to_string(), String::new()))
+            .collect();
+        assert!(matches!(stream.check_tags(), Err(Error::Validation(_))));
+    }
+
+    #[test]
+    fn test_token_bucket_bursts_then_refills() {
+        let bucket = TokenBucket::full(10, 0);
+
+        // A full second's worth goes at once, then the next event must wait
+        let bucket = bucket.take(10, 10, 0).unwrap();
+        assert_eq!(bucket.take(10, 1, 0), Err(1));
+
+        // Half a second refills half the bucket, and never more than full
+        let refilled = bucket.take(10, 5, 500).unwrap();
+        assert_eq!(refilled.tokens, 0.0);
+        let idle = bucket.take(10, 1, 60_000).unwrap();
+        assert_eq!(idle.tokens, 9.0);
+
+        // Oversized batches take a full bucket rather than never fitting
+        assert!(TokenBucket::full(10, 0).take(10, 50, 0).is_ok());
+        assert_eq!(
+            TokenBucket::full(2, 0)
+                .take(2, 2, 0)
+                .unwrap()
+                .take(2, 50, 0),
+            Err(1)
+        );
+    }
+
+    #[test]
+    fn test_idempotency_ttl_must_fit_retention() {
+        let mut stream = Stream::new("orders".into(), 3, 48);
+        stream.idempotency_ttl_hours = Some(49);
+        assert!(stream.check_idempotency_ttl().is_err());
+
+        stream.idempotency_ttl_hours = Some(0);
+        assert!(stream.check_idempotency_ttl().is_err());
+    }
+
+    #[test]
+    fn test_partition_lag() {
+        assert_eq!(PartitionLag::new(0, 3, 10).lag, 7);
+        // A committed offset past the head (e.g. after a counter reset) is not negative lag
+        assert_eq!(PartitionLag::new(0, 12, 10).lag, 0);
     }
 
     #[test]
@@ -289,6 +2490,65 @@ mod tests {
         assert_eq!(event.event_type, "order.created");
     }
 
+    #[test]
+    fn test_publish_batch_groups_items_by_stream() {
+        let event = |key: &str| serde_json::json!({ "key": key, "type": "test.event", "data": {} });
+        let req: PublishBatchRequest = serde_json::from_value(serde_json::json!({
+            "items": [
+                { "stream_id": "orders", "events": [event("o-1")] },
+                { "stream_id": "payments", "events": [event("p-1")] },
+                { "stream_id": "orders", "events": [event("o-2")] },
+            ]
+        }))
+        .unwrap();
+
+        let grouped = req.grouped();
+        assert_eq!(grouped.len(), 2);
+        assert_eq!(grouped[0].stream_id, "orders");
+        let keys: Vec<&str> = grouped[0].events.iter().map(|e| e.key.as_str()).collect();
+        assert_eq!(keys, vec!["o-1", "o-2"]);
+        assert_eq!(grouped[1].stream_id, "payments");
+        assert_eq!(grouped[1].events.len(), 1);
+    }
+
+    #[test]
+    fn test_event_type_accepts_dotted_lowercase() {
+        for valid in ["order.created", "order", "v2.order.line.added"] {
+            assert_eq!(EventType::new(valid).unwrap().as_str(), valid);
+        }
+    }
+
+    #[test]
+    fn test_event_type_rejects_malformed() {
+        for invalid in [
+            "Order Created",
+            "",
+            "order.",
+            ".order",
+            "order..created",
+            "order_created",
+        ] {
+            let err = EventType::new(invalid).unwrap_err();
+            assert!(
+                matches!(err, Error::Validation(_)),
+                "{:?} accepted",
+                invalid
+            );
+        }
+
+        let json = r#"{"key": "order-123", "type": "Order Created", "data": {}}"#;
+        assert!(serde_json::from_str::<PublishEvent>(json).is_err());
+    }
+
+    #[test]
+    fn test_event_type_serializes_as_string() {
+        let event_type: EventType = serde_json::from_str(r#""order.created""#).unwrap();
+        assert_eq!(
+            serde_json::to_string(&event_type).unwrap(),
+            r#""order.created""#
+        );
+    }
+
     #[test]
     fn test_error_response() {
         let err = ErrorResponse::new("not_found", "Stream not found");
@@ -297,4 +2557,123 @@ mod tests {
         assert!(json.contains("Stream not found"));
         assert!(!json.contains("details"));
     }
+
+    #[test]
+    fn test_cursor_state_round_trip() {
+        let state = CursorState {
+            stream_id: "orders".to_string(),
+            subscription_id: TAIL_SUBSCRIPTION_ID.to_string(),
+            offsets: vec![PartitionOffset {
+                partition: 1,
+                offset: 7,
+            }],
+            remaining_per_partition: Vec::new(),
+            committed_at: None,
+        };
+        let cursor = state.encode().unwrap();
+        let decoded = CursorState::decode(&cursor).unwrap();
+        assert_eq!(decoded.stream_id, "orders");
+        assert_eq!(decoded.offsets[0].offset, 7);
+        assert!(decoded.remaining_per_partition.is_empty());
+
+        // Remaining counts are only carried when present
+        let with_remaining = CursorState {
+            remaining_per_partition: vec![3],
+            ..state
+        };
+        assert!(with_remaining.encode().unwrap().len() > cursor.len());
+        let decoded = CursorState::decode(&with_remaining.encode().unwrap()).unwrap();
+        assert_eq!(decoded.remaining_per_partition, vec![3]);
+
+        for garbage in ["!!!", "bm90IGpzb24"] {
+            assert_eq!(
+                CursorState::decode(garbage).unwrap_err().code(),
+                "invalid_cursor"
+            );
+        }
+
+        // Cursors issued before they named their stream and subscription
+        let legacy = URL_SAFE_NO_PAD.encode(r#"{"offsets":[{"partition":0,"offset":3}]}"#);
+        let decoded = CursorState::decode(&legacy).unwrap();
+        assert!(decoded.stream_id.is_empty());
+        assert_eq!(decoded.offsets[0].offset, 3);
+    }
+
+    #[test]
+    fn test_cursor_token_is_reused_for_the_same_state() {
+        let state = CursorState {
+            stream_id: "orders".to_string(),
+            subscription_id: "billing".to_string(),
+            offsets: vec![PartitionOffset {
+                partition: 0,
+                offset: 7,
+            }],
+            remaining_per_partition: Vec::new(),
+            committed_at: None,
+        };
+        let token = state.token().unwrap();
+        assert_eq!(token.len(), CURSOR_TOKEN_LENGTH);
+        assert_eq!(state.clone().token().unwrap(), token);
+
+        let moved = CursorState {
+            offsets: vec![PartitionOffset {
+                partition: 0,
+                offset: 8,
+            }],
+            ..state
+        };
+        assert_ne!(moved.token().unwrap(), token);
+    }
+
+    #[test]
+    fn test_cursor_decode_errors_name_the_failed_step() {
+        let details = |cursor: &str| {
+            let err = CursorState::decode(cursor).unwrap_err();
+            assert_eq!(err.code(), "invalid_cursor");
+            err.details().unwrap().clone()
+        };
+
+        let base64 = details("eyJvZmZz!XRzIjpbXX0");
+        let expected = serde_json::json!({ "step": "base64", "position": 8, "cursor_length": 19 });
+        assert_eq!(base64, expected);
+
+        let utf8 = details(&URL_SAFE_NO_PAD.encode(b"{\"a\":\xff}"));
+        assert_eq!(
+            utf8,
+            serde_json::json!({ "step": "utf8", "position": 5, "cursor_length": 10 })
+        );
+
+        let secret = URL_SAFE_NO_PAD.encode(br#"{"stream_id": "sk_live_secret"}"#);
+        let json = details(&secret);
+        assert_eq!(json["step"], "json");
+        assert!(json["column"].as_u64().unwrap() > 0);
+        assert_eq!(json["cursor_length"], secret.len());
+        assert!(!json.to_string().contains("sk_live_secret"));
+    }
+
+    #[test]
+    fn test_compacted_state_from_another_partition_is_ordered_by_time() {
+        let at = |secs: i64| DateTime::from_timestamp(1_700_000_000 + secs, 0).unwrap();
+        let state = |partition, sequence, timestamp| CompactedEvent {
+            id: String::new(),
+            stream_id: "orders".to_string(),
+            key: "order-1".to_string(),
+            event_type: "order.updated".to_string(),
+            data: serde_json::json!({}),
+            sequence,
+            partition,
+            timestamp,
+        };
+
+        // One key written to busy partition 0, then to quiet partition 1
+        let earlier = state(0, 500, at(0));
+        let later = state(1, 3, at(10));
+        assert!(later.supersedes(&earlier));
+        assert!(!earlier.supersedes(&later));
+
+        // Within a partition, sequence decides even if timestamps disagree
+        assert!(state(0, 501, at(-60)).supersedes(&earlier));
+        assert!(!state(0, 499, at(60)).supersedes(&earlier));
+        assert!(!earlier.supersedes(&earlier));
+    }
 }
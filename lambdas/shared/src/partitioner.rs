@@ -2,19 +2,50 @@
 //!
 //! Uses consistent hashing to ensure the same key always goes to the same partition.
 //! This is critical for maintaining order per key.
+//!
+//! Hot keys can be pinned to specific partitions with overrides, so a noisy
+//! key doesn't share a partition with whatever else hashes there. Events with
+//! no key at all can be spread out instead, per the stream's
+//! [`EmptyKeyStrategy`].
 
 use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::errors::{Error, Result};
+use crate::models::{EmptyKeyStrategy, PartitionDistribution};
 
 /// Partitioner maps keys to partition numbers
 pub struct Partitioner {
     partition_count: u32,
+    overrides: HashMap<String, u32>,
 }
 
 impl Partitioner {
     /// Create a new partitioner with the given partition count
     pub fn new(partition_count: u32) -> Self {
         assert!(partition_count > 0, "partition_count must be > 0");
-        Self { partition_count }
+        Self {
+            partition_count,
+            overrides: HashMap::new(),
+        }
+    }
+
+    /// Create a partitioner that routes the given keys to fixed partitions
+    ///
+    /// Keys without an override are hashed as usual. Every override must name
+    /// an existing partition.
+    pub fn with_overrides(partition_count: u32, overrides: HashMap<String, u32>) -> Result<Self> {
+        if let Some((key, partition)) = overrides.iter().find(|(_, &p)| p >= partition_count) {
+            return Err(Error::Validation(format!(
+                "Partition override for key '{}' is {}, but the stream has {} partitions",
+                key, partition, partition_count
+            )));
+        }
+
+        let mut partitioner = Self::new(partition_count);
+        partitioner.overrides = overrides;
+        Ok(partitioner)
     }
 
     /// Map a key to a partition number (0-based)
@@ -22,6 +53,10 @@ impl Partitioner {
     /// Uses SHA-256 hash for consistent distribution.
     /// The same key will always map to the same partition.
     pub fn partition(&self, key: &str) -> u32 {
+        if let Some(&partition) = self.overrides.get(key) {
+            return partition;
+        }
+
         let mut hasher = Sha256::new();
         hasher.update(key.as_bytes());
         let hash = hasher.finalize();
@@ -32,10 +67,47 @@ impl Partitioner {
         hash_value % self.partition_count
     }
 
+    /// Map an empty key to a partition under `strategy`
+    ///
+    /// `turn` is the event's place in the stream's round-robin rotation; the
+    /// other strategies ignore it.
+    pub fn partition_empty_key(&self, strategy: EmptyKeyStrategy, turn: u64) -> u32 {
+        match strategy {
+            EmptyKeyStrategy::Hash => self.partition(""),
+            EmptyKeyStrategy::RoundRobin => (turn % self.partition_count as u64) as u32,
+            EmptyKeyStrategy::Random => {
+                (Uuid::new_v4().as_u128() % self.partition_count as u128) as u32
+            }
+        }
+    }
+
     /// Get the partition count
     pub fn partition_count(&self) -> u32 {
         self.partition_count
     }
+
+    /// Count how many of `keys` land on each partition
+    pub fn distribution<S: AsRef<str>>(&self, keys: &[S]) -> PartitionDistribution {
+        let mut histogram = vec![0u64; self.partition_count as usize];
+        for key in keys {
+            histogram[self.partition(key.as_ref()) as usize] += 1;
+        }
+
+        let key_count = keys.len() as u64;
+        let busiest = histogram.iter().copied().max().unwrap_or(0);
+        let skew = if key_count == 0 {
+            0.0
+        } else {
+            busiest as f64 * self.partition_count as f64 / key_count as f64
+        };
+
+        PartitionDistribution {
+            partition_count: self.partition_count,
+            key_count,
+            histogram,
+            skew,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -112,6 +184,70 @@ mod tests {
         assert!(found_collision, "Expected to find partition collision");
     }
 
+    #[test]
+    fn test_overrides_pin_hot_keys() {
+        let hashed = Partitioner::new(4);
+        let hot_key = "tenant-big";
+        let pinned_to = (hashed.partition(hot_key) + 1) % 4;
+
+        let overrides = HashMap::from([(hot_key.to_string(), pinned_to)]);
+        let partitioner = Partitioner::with_overrides(4, overrides).unwrap();
+
+        assert_eq!(partitioner.partition(hot_key), pinned_to);
+
+        // Everything else still hashes normally
+        for i in 0..100 {
+            let key = format!("key-{}", i);
+            assert_eq!(partitioner.partition(&key), hashed.partition(&key));
+        }
+    }
+
+    #[test]
+    fn test_override_out_of_range_rejected() {
+        let overrides = HashMap::from([("tenant-big".to_string(), 4)]);
+        let err = Partitioner::with_overrides(4, overrides).err().unwrap();
+        assert_eq!(err.code(), "validation_error");
+    }
+
+    #[test]
+    fn test_distribution_histogram() {
+        let keys: Vec<String> = (0..1000).map(|i| format!("key-{}", i)).collect();
+        let distribution = Partitioner::new(4).distribution(&keys);
+
+        assert_eq!(distribution.histogram.len(), 4);
+        assert_eq!(distribution.histogram.iter().sum::<u64>(), 1000);
+        assert!(distribution.skew >= 1.0 && distribution.skew < 1.3);
+    }
+
+    #[test]
+    fn test_distribution_single_key_is_fully_skewed() {
+        let distribution = Partitioner::new(4).distribution(&["hot", "hot", "hot"]);
+        assert_eq!(distribution.key_count, 3);
+        assert_eq!(distribution.skew, 4.0);
+
+        let empty: [&str; 0] = [];
+        assert_eq!(Partitioner::new(4).distribution(&empty).skew, 0.0);
+    }
+
+    #[test]
+    fn test_empty_key_strategies() {
+        let partitioner = Partitioner::new(4);
+        let hashed = partitioner.partition("");
+
+        let turns: Vec<u32> = (10..18)
+            .map(|turn| partitioner.partition_empty_key(EmptyKeyStrategy::RoundRobin, turn))
+            .collect();
+        assert_eq!(turns, [2, 3, 0, 1, 2, 3, 0, 1]);
+
+        for turn in 0..20 {
+            assert_eq!(
+                partitioner.partition_empty_key(EmptyKeyStrategy::Hash, turn),
+                hashed
+            );
+            assert!(partitioner.partition_empty_key(EmptyKeyStrategy::Random, turn) < 4);
+        }
+    }
+
     #[test]
     #[should_panic(expected = "partition_count must be > 0")]
     fn test_zero_partitions_panics() {
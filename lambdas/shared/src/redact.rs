@@ -0,0 +1,130 @@
+//! Keeping event payloads out of logs and error messages
+//!
+//! serde and JSON Schema errors quote the offending value, which for event
+//! `data` may be a secret. Error messages built from them are redacted before
+//! they reach `tracing` output or an `ErrorResponse`. Set
+//! `EVENTLEDGER_LOG_PAYLOADS=true` to keep the values while debugging.
+
+use serde_json::Value;
+
+/// Environment variable that opts in to payload values in logs and errors
+pub const LOG_PAYLOADS_ENV: &str = "EVENTLEDGER_LOG_PAYLOADS";
+
+/// Placeholder substituted for redacted values
+pub const REDACTED: &str = "<redacted>";
+
+/// Literal kinds serde quotes in backticks, e.g. "invalid type: integer `42`"
+const BACKTICK_VALUE_PREFIXES: [&str; 4] = [
+    "integer ",
+    "floating point ",
+    "character ",
+    "unknown variant ",
+];
+
+/// Whether payload values may appear in logs and error messages
+pub fn log_payloads() -> bool {
+    matches!(
+        std::env::var(LOG_PAYLOADS_ENV).as_deref().map(str::trim),
+        Ok("true") | Ok("1")
+    )
+}
+
+/// Remove quoted values from a serde-style error message
+///
+/// Field names (e.g. "missing field `type`") are kept so the message still
+/// says what was wrong.
+pub fn redact(message: &str) -> String {
+    if log_payloads() {
+        message.to_string()
+    } else {
+        redact_values(message)
+    }
+}
+
+/// Remove a JSON Schema error's instance value from its message
+pub fn redact_instance(message: &str, instance: &Value) -> String {
+    if log_payloads() {
+        message.to_string()
+    } else {
+        message.replace(&instance.to_string(), REDACTED)
+    }
+}
+
+fn redact_values(message: &str) -> String {
+    let mut out = String::with_capacity(message.len());
+    let mut chars = message.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                // Skip to the closing quote, honoring escapes
+                let mut escaped = false;
+                for c in chars.by_ref() {
+                    match c {
+                        '\\' if !escaped => escaped = true,
+                        '"' if !escaped => break,
+                        _ => escaped = false,
+                    }
+                }
+                out.push('"');
+                out.push_str(REDACTED);
+                out.push('"');
+            }
+            '`' if BACKTICK_VALUE_PREFIXES.iter().any(|p| out.ends_with(p)) => {
+                for c in chars.by_ref() {
+                    if c == '`' {
+                        break;
+                    }
+                }
+                out.push_str(REDACTED);
+            }
+            _ => out.push(c),
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redacts_quoted_strings() {
+        let message = r#"invalid type: string "hunter2", expected u32 at line 1 column 20"#;
+        assert_eq!(
+            redact_values(message),
+            r#"invalid type: string "<redacted>", expected u32 at line 1 column 20"#
+        );
+    }
+
+    #[test]
+    fn test_redacts_escaped_quotes() {
+        let message = r#"invalid value: string "a \"b\" c", expected x"#;
+        assert_eq!(
+            redact_values(message),
+            r#"invalid value: string "<redacted>", expected x"#
+        );
+    }
+
+    #[test]
+    fn test_redacts_backtick_literals_but_keeps_field_names() {
+        assert_eq!(
+            redact_values("invalid type: integer `4111111111111111`, expected a string"),
+            "invalid type: integer <redacted>, expected a string"
+        );
+        assert_eq!(
+            redact_values("missing field `type`"),
+            "missing field `type`"
+        );
+    }
+
+    #[test]
+    fn test_redact_instance() {
+        let instance = serde_json::json!("hunter2");
+        let message = format!("{} is not of type \"number\"", instance);
+        let redacted = redact_instance(&message, &instance);
+        assert!(!redacted.contains("hunter2"));
+        assert!(redacted.contains("is not of type"));
+    }
+}
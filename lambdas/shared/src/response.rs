@@ -0,0 +1,80 @@
+//! Error responses shared by the HTTP Lambda handlers (`http` feature)
+
+use lambda_http::{Body, Error as LambdaError, Response};
+use tracing::error;
+
+use crate::errors::Error;
+use crate::models::ErrorResponse;
+
+/// Error for a route reached without a path parameter it needs
+pub fn missing_path_param(name: &str) -> Error {
+    Error::Validation(format!("Missing path parameter: {}", name))
+}
+
+/// Answer EventLedger errors raised with `?` with their API response, rather
+/// than failing the invocation (which API Gateway reports as a 500)
+pub fn recover(result: Result<Response<Body>, LambdaError>) -> Result<Response<Body>, LambdaError> {
+    match result {
+        Err(e) => match e.downcast::<Error>() {
+            Ok(e) => error_response(*e),
+            Err(e) => Err(e),
+        },
+        ok => ok,
+    }
+}
+
+/// Log a failed request and answer it with the error's API response
+pub fn error_response(e: Error) -> Result<Response<Body>, LambdaError> {
+    error!(error = %e, "Request failed");
+    error_body_response(&e, &e.to_response())
+}
+
+/// Answer with `body` and the status of `e`, telling the client when to retry
+/// (`Retry-After`) if it's worth retrying
+pub fn error_body_response(e: &Error, body: &ErrorResponse) -> Result<Response<Body>, LambdaError> {
+    let mut response = Response::builder()
+        .status(e.status_code())
+        .header("Content-Type", "application/json");
+    if let Some(secs) = e.retry_after() {
+        response = response.header("Retry-After", secs);
+    }
+    Ok(response.body(Body::from(serde_json::to_string(body)?))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::THROTTLED_RETRY_AFTER_SECS;
+
+    fn error_code(response: &Response<Body>) -> String {
+        let body: ErrorResponse = serde_json::from_slice(response.body()).unwrap();
+        body.error
+    }
+
+    #[test]
+    fn test_throttled_response_has_retry_after() {
+        let response = error_response(Error::Throttled("ThrottlingException".into())).unwrap();
+        assert_eq!(response.status(), 429);
+        assert_eq!(error_code(&response), "throttled");
+        let retry_after = response.headers().get("Retry-After").unwrap();
+        assert_eq!(
+            retry_after.to_str().unwrap(),
+            THROTTLED_RETRY_AFTER_SECS.to_string()
+        );
+
+        let response = error_response(Error::Database("boom".into())).unwrap();
+        assert!(response.headers().get("Retry-After").is_none());
+    }
+
+    #[test]
+    fn test_recover_answers_eventledger_errors() {
+        let failed: Result<Response<Body>, LambdaError> =
+            Err(missing_path_param("stream_id").into());
+        let response = recover(failed).unwrap();
+        assert_eq!(response.status(), 400);
+        assert_eq!(error_code(&response), "validation_error");
+
+        let failed: Result<Response<Body>, LambdaError> = Err("not ours".into());
+        assert!(recover(failed).is_err());
+    }
+}
@@ -0,0 +1,108 @@
+//! JSON Schema validation of event data
+//!
+//! Streams may carry a JSON Schema; every published event's `data` must
+//! conform to it. Failures are reported with the JSON pointer of the
+//! offending value so producers can find the problem.
+
+use jsonschema::Validator;
+use serde_json::Value;
+
+use crate::errors::{Error, Result};
+use crate::redact::redact_instance;
+
+/// Compile a stream's schema, rejecting schemas that are themselves invalid
+pub fn compile(schema: &Value) -> Result<Validator> {
+    jsonschema::validator_for(schema).map_err(|e| Error::ValidationDetails {
+        message: format!("Invalid JSON Schema: {}", e),
+        details: serde_json::json!({
+            "schema_path": e.schema_path.as_str(),
+            "reason": e.to_string(),
+        }),
+    })
+}
+
+/// Validate one event's data, reporting the first failing path
+///
+/// `index` is the event's position in the publish batch.
+pub fn validate_data(validator: &Validator, data: &Value, index: usize) -> Result<()> {
+    match validator.validate(data) {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            let path = e.instance_path.as_str();
+            // The root of `data` has an empty pointer
+            let path = if path.is_empty() { "/" } else { path };
+            let reason = redact_instance(&e.to_string(), &e.instance);
+            Err(Error::ValidationDetails {
+                message: format!(
+                    "Event {} data does not match stream schema at {}: {}",
+                    index, path, reason
+                ),
+                details: serde_json::json!({
+                    "index": index,
+                    "path": path,
+                    "schema_path": e.schema_path.as_str(),
+                    "reason": reason,
+                }),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn order_schema() -> Value {
+        json!({
+            "type": "object",
+            "required": ["total"],
+            "properties": {
+                "total": { "type": "number", "minimum": 0 }
+            }
+        })
+    }
+
+    #[test]
+    fn test_conforming_data() {
+        let validator = compile(&order_schema()).unwrap();
+        assert!(validate_data(&validator, &json!({ "total": 12.5 }), 0).is_ok());
+    }
+
+    #[test]
+    fn test_non_conforming_data_reports_path() {
+        let validator = compile(&order_schema()).unwrap();
+        let err = validate_data(&validator, &json!({ "total": "twelve" }), 3).unwrap_err();
+        assert_eq!(err.code(), "validation_error");
+
+        let details = err.details().unwrap();
+        assert_eq!(details["index"], 3);
+        assert_eq!(details["path"], "/total");
+    }
+
+    #[test]
+    fn test_failure_does_not_echo_data() {
+        let validator = compile(&order_schema()).unwrap();
+        let err =
+            validate_data(&validator, &json!({ "total": "card-4111111111111111" }), 0).unwrap_err();
+        assert!(!err.to_string().contains("4111111111111111"));
+        assert!(!err
+            .details()
+            .unwrap()
+            .to_string()
+            .contains("4111111111111111"));
+    }
+
+    #[test]
+    fn test_missing_required_reports_root() {
+        let validator = compile(&order_schema()).unwrap();
+        let err = validate_data(&validator, &json!({}), 0).unwrap_err();
+        assert_eq!(err.details().unwrap()["path"], "/");
+    }
+
+    #[test]
+    fn test_invalid_schema_rejected() {
+        let err = compile(&json!({ "type": "not-a-type" })).unwrap_err();
+        assert_eq!(err.code(), "validation_error");
+    }
+}
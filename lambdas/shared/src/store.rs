@@ -0,0 +1,1040 @@
+//! Storage operations the publish and poll handlers depend on
+//!
+//! Handlers are generic over [`EventStore`] so their routing and error mapping
+//! can be unit tested without DynamoDB. [`DynamoClient`] is the production
+//! implementation; with the `test-util` feature, [`MemoryStore`] is an
+//! in-memory one for tests.
+
+use crate::dynamo::DynamoClient;
+use crate::errors::Result;
+use crate::models::*;
+use chrono::{DateTime, Utc};
+use std::future::Future;
+
+#[cfg(any(test, feature = "test-util"))]
+pub use memory::MemoryStore;
+
+/// Stream, subscription and offset storage
+///
+/// Each method behaves like the [`DynamoClient`] method of the same name.
+pub trait EventStore: Send + Sync + Sized {
+    /// Store to use for one request, honoring a table override header
+    fn for_request(&self, table_override: Option<&str>) -> Self;
+
+    fn get_stream(&self, stream_id: &str) -> impl Future<Output = Result<Stream>> + Send;
+
+    fn get_stream_cached(&self, stream_id: &str) -> impl Future<Output = Result<Stream>> + Send;
+
+    fn resolve_stream_id(&self, name: &str) -> impl Future<Output = Result<String>> + Send;
+
+    fn publish_events(
+        &self,
+        stream_id: &str,
+        events: &[PublishEvent],
+    ) -> impl Future<Output = Result<Vec<PublishedEvent>>> + Send;
+
+    fn read_events(
+        &self,
+        stream_id: &str,
+        partition: u32,
+        from_offset: u64,
+        limit: u32,
+        scan_forward: bool,
+    ) -> impl Future<Output = Result<Vec<Event>>> + Send;
+
+    fn read_events_projected(
+        &self,
+        stream_id: &str,
+        partition: u32,
+        from_offset: u64,
+        limit: u32,
+        projection: &EventProjection,
+    ) -> impl Future<Output = Result<Vec<Event>>> + Send;
+
+    fn get_latest_offset(
+        &self,
+        stream_id: &str,
+        partition: u32,
+    ) -> impl Future<Output = Result<u64>> + Send;
+
+    fn get_latest_written_offset(
+        &self,
+        stream_id: &str,
+        partition: u32,
+    ) -> impl Future<Output = Result<u64>> + Send;
+
+    fn offset_at_time(
+        &self,
+        stream_id: &str,
+        partition: u32,
+        timestamp: DateTime<Utc>,
+    ) -> impl Future<Output = Result<u64>> + Send;
+
+    fn create_subscription(
+        &self,
+        stream_id: &str,
+        req: &CreateSubscriptionRequest,
+    ) -> impl Future<Output = Result<Subscription>> + Send;
+
+    fn get_subscription(
+        &self,
+        stream_id: &str,
+        subscription_id: &str,
+    ) -> impl Future<Output = Result<Subscription>> + Send;
+
+    fn record_poll(
+        &self,
+        stream_id: &str,
+        subscription_id: &str,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    fn get_offset(
+        &self,
+        stream_id: &str,
+        subscription_id: &str,
+        partition: u32,
+    ) -> impl Future<Output = Result<u64>> + Send;
+
+    fn list_offsets(
+        &self,
+        stream_id: &str,
+        subscription_id: &str,
+    ) -> impl Future<Output = Result<Vec<ConsumerOffset>>> + Send;
+
+    /// Commit `offsets`, failing with [`Error::CommitConflict`] if any of the
+    /// partitions was committed after `unmodified_since`
+    ///
+    /// [`Error::CommitConflict`]: crate::Error::CommitConflict
+    fn commit_offsets(
+        &self,
+        stream_id: &str,
+        subscription_id: &str,
+        offsets: &[PartitionOffset],
+        unmodified_since: Option<DateTime<Utc>>,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    fn get_delivered_offset(
+        &self,
+        stream_id: &str,
+        subscription_id: &str,
+        partition: u32,
+    ) -> impl Future<Output = Result<Option<u64>>> + Send;
+
+    fn set_delivered_offsets(
+        &self,
+        stream_id: &str,
+        subscription_id: &str,
+        offsets: &[PartitionOffset],
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    fn put_cursor_token(&self, state: &CursorState) -> impl Future<Output = Result<String>> + Send;
+
+    fn get_cursor_token(
+        &self,
+        stream_id: &str,
+        subscription_id: &str,
+        token: &str,
+    ) -> impl Future<Output = Result<CursorState>> + Send;
+
+    fn claim_idempotency_key(
+        &self,
+        claim: &IdempotencyRecord,
+    ) -> impl Future<Output = Result<Option<IdempotencyRecord>>> + Send;
+
+    fn put_idempotency_record(
+        &self,
+        record: &IdempotencyRecord,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    fn release_idempotency_key(
+        &self,
+        claim: &IdempotencyRecord,
+    ) -> impl Future<Output = Result<()>> + Send;
+}
+
+impl EventStore for DynamoClient {
+    fn for_request(&self, table_override: Option<&str>) -> Self {
+        DynamoClient::for_request(self, table_override)
+    }
+
+    async fn get_stream(&self, stream_id: &str) -> Result<Stream> {
+        DynamoClient::get_stream(self, stream_id).await
+    }
+
+    async fn get_stream_cached(&self, stream_id: &str) -> Result<Stream> {
+        DynamoClient::get_stream_cached(self, stream_id).await
+    }
+
+    async fn resolve_stream_id(&self, name: &str) -> Result<String> {
+        DynamoClient::resolve_stream_id(self, name).await
+    }
+
+    async fn publish_events(
+        &self,
+        stream_id: &str,
+        events: &[PublishEvent],
+    ) -> Result<Vec<PublishedEvent>> {
+        DynamoClient::publish_events(self, stream_id, events).await
+    }
+
+    async fn read_events(
+        &self,
+        stream_id: &str,
+        partition: u32,
+        from_offset: u64,
+        limit: u32,
+        scan_forward: bool,
+    ) -> Result<Vec<Event>> {
+        DynamoClient::read_events(self, stream_id, partition, from_offset, limit, scan_forward)
+            .await
+    }
+
+    async fn read_events_projected(
+        &self,
+        stream_id: &str,
+        partition: u32,
+        from_offset: u64,
+        limit: u32,
+        projection: &EventProjection,
+    ) -> Result<Vec<Event>> {
+        DynamoClient::read_events_projected(
+            self,
+            stream_id,
+            partition,
+            from_offset,
+            limit,
+            projection,
+        )
+        .await
+    }
+
+    async fn get_latest_offset(&self, stream_id: &str, partition: u32) -> Result<u64> {
+        DynamoClient::get_latest_offset(self, stream_id, partition).await
+    }
+
+    async fn get_latest_written_offset(&self, stream_id: &str, partition: u32) -> Result<u64> {
+        DynamoClient::get_latest_written_offset(self, stream_id, partition).await
+    }
+
+    async fn offset_at_time(
+        &self,
+        stream_id: &str,
+        partition: u32,
+        timestamp: DateTime<Utc>,
+    ) -> Result<u64> {
+        DynamoClient::offset_at_time(self, stream_id, partition, timestamp).await
+    }
+
+    async fn create_subscription(
+        &self,
+        stream_id: &str,
+        req: &CreateSubscriptionRequest,
+    ) -> Result<Subscription> {
+        DynamoClient::create_subscription(self, stream_id, req).await
+    }
+
+    async fn get_subscription(
+        &self,
+        stream_id: &str,
+        subscription_id: &str,
+    ) -> Result<Subscription> {
+        DynamoClient::get_subscription(self, stream_id, subscription_id).await
+    }
+
+    async fn record_poll(&self, stream_id: &str, subscription_id: &str) -> Result<()> {
+        DynamoClient::record_poll(self, stream_id, subscription_id).await
+    }
+
+    async fn get_offset(
+        &self,
+        stream_id: &str,
+        subscription_id: &str,
+        partition: u32,
+    ) -> Result<u64> {
+        DynamoClient::get_offset(self, stream_id, subscription_id, partition).await
+    }
+
+    async fn list_offsets(
+        &self,
+        stream_id: &str,
+        subscription_id: &str,
+    ) -> Result<Vec<ConsumerOffset>> {
+        DynamoClient::list_offsets(self, stream_id, subscription_id).await
+    }
+
+    async fn commit_offsets(
+        &self,
+        stream_id: &str,
+        subscription_id: &str,
+        offsets: &[PartitionOffset],
+        unmodified_since: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        DynamoClient::commit_offsets(self, stream_id, subscription_id, offsets, unmodified_since)
+            .await
+    }
+
+    async fn get_delivered_offset(
+        &self,
+        stream_id: &str,
+        subscription_id: &str,
+        partition: u32,
+    ) -> Result<Option<u64>> {
+        DynamoClient::get_delivered_offset(self, stream_id, subscription_id, partition).await
+    }
+
+    async fn set_delivered_offsets(
+        &self,
+        stream_id: &str,
+        subscription_id: &str,
+        offsets: &[PartitionOffset],
+    ) -> Result<()> {
+        DynamoClient::set_delivered_offsets(self, stream_id, subscription_id, offsets).await
+    }
+
+    async fn put_cursor_token(&self, state: &CursorState) -> Result<String> {
+        DynamoClient::put_cursor_token(self, state).await
+    }
+
+    async fn get_cursor_token(
+        &self,
+        stream_id: &str,
+        subscription_id: &str,
+        token: &str,
+    ) -> Result<CursorState> {
+        DynamoClient::get_cursor_token(self, stream_id, subscription_id, token).await
+    }
+
+    async fn claim_idempotency_key(
+        &self,
+        claim: &IdempotencyRecord,
+    ) -> Result<Option<IdempotencyRecord>> {
+        DynamoClient::claim_idempotency_key(self, claim).await
+    }
+
+    async fn put_idempotency_record(&self, record: &IdempotencyRecord) -> Result<()> {
+        DynamoClient::put_idempotency_record(self, record).await
+    }
+
+    async fn release_idempotency_key(&self, claim: &IdempotencyRecord) -> Result<()> {
+        DynamoClient::release_idempotency_key(self, claim).await
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+mod memory {
+    use super::EventStore;
+    use crate::errors::{Error, Result};
+    use crate::models::*;
+    use crate::schema;
+    use crate::stream_cache::StreamCache;
+    use chrono::{DateTime, Utc};
+    use std::collections::{HashMap, HashSet};
+    use std::sync::{Arc, Mutex, MutexGuard};
+
+    /// Table name the memory store caches streams under
+    const TABLE_NAME: &str = "memory";
+
+    /// In-memory [`EventStore`] for handler tests
+    ///
+    /// Clones (including those made by `for_request`) share the same data.
+    /// Projected reads return whole events.
+    #[derive(Clone, Default)]
+    pub struct MemoryStore {
+        state: Arc<Mutex<State>>,
+        streams: Arc<StreamCache>,
+    }
+
+    #[derive(Default)]
+    struct State {
+        streams: HashMap<String, Stream>,
+        /// Stream ID each alias points at
+        aliases: HashMap<String, String>,
+        /// Stream metadata lookups that missed the cache
+        stream_reads: u64,
+        /// Events per (stream, partition), in sequence order from 1
+        events: HashMap<(String, u32), Vec<Event>>,
+        global_positions: HashMap<String, u64>,
+        /// Turns taken in each stream's empty-key rotation
+        round_robin: HashMap<String, u64>,
+        subscriptions: HashMap<(String, String), Subscription>,
+        /// Committed offset and commit time per (stream, subscription, partition)
+        offsets: HashMap<(String, String, u32), (u64, DateTime<Utc>)>,
+        delivered: HashMap<(String, String, u32), u64>,
+        cursor_tokens: HashMap<String, CursorState>,
+        idempotency: HashMap<(String, String), IdempotencyRecord>,
+        rate_buckets: HashMap<String, TokenBucket>,
+        /// Operations that fail as if DynamoDB throttled them
+        throttled: HashSet<&'static str>,
+    }
+
+    impl MemoryStore {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Add or replace a stream
+        ///
+        /// Like a change made by another instance, this leaves the cached copy
+        /// in place until it expires.
+        pub fn put_stream(&self, stream: Stream) {
+            self.lock().streams.insert(stream.stream_id.clone(), stream);
+        }
+
+        /// Point an alias at a stream
+        pub fn put_alias(&self, alias: &str, stream_id: &str) {
+            self.lock()
+                .aliases
+                .insert(alias.to_string(), stream_id.to_string());
+        }
+
+        /// Stream metadata reads so far, not counting those served from the cache
+        pub fn stream_reads(&self) -> u64 {
+            self.lock().stream_reads
+        }
+
+        /// Every event in a partition, oldest first
+        pub fn partition_events(&self, stream_id: &str, partition: u32) -> Vec<Event> {
+            self.lock()
+                .events
+                .get(&(stream_id.to_string(), partition))
+                .cloned()
+                .unwrap_or_default()
+        }
+
+        /// Make `operation` (an [`EventStore`] method name) fail with
+        /// [`Error::Throttled`] until this is called again with `false`
+        pub fn set_throttled(&self, operation: &'static str, throttled: bool) {
+            let mut state = self.lock();
+            if throttled {
+                state.throttled.insert(operation);
+            } else {
+                state.throttled.remove(operation);
+            }
+        }
+
+        fn check_throttled(&self, operation: &str) -> Result<()> {
+            if self.lock().throttled.contains(operation) {
+                return Err(Error::Throttled("ThrottlingException".to_string()));
+            }
+            Ok(())
+        }
+
+        fn lock(&self) -> MutexGuard<'_, State> {
+            self.state.lock().unwrap_or_else(|e| e.into_inner())
+        }
+
+        fn stream(&self, stream_id: &str) -> Result<Stream> {
+            let mut state = self.lock();
+            state.stream_reads += 1;
+            state
+                .streams
+                .get(stream_id)
+                .cloned()
+                .ok_or_else(|| Error::StreamNotFound(stream_id.to_string()))
+        }
+
+        fn read(
+            &self,
+            stream_id: &str,
+            partition: u32,
+            from_offset: u64,
+            limit: u32,
+            scan_forward: bool,
+        ) -> Vec<Event> {
+            let state = self.lock();
+            let Some(events) = state.events.get(&(stream_id.to_string(), partition)) else {
+                return Vec::new();
+            };
+            if scan_forward {
+                events
+                    .iter()
+                    .filter(|e| e.sequence > from_offset)
+                    .take(limit as usize)
+                    .cloned()
+                    .collect()
+            } else {
+                events
+                    .iter()
+                    .rev()
+                    .filter(|e| from_offset == 0 || e.sequence < from_offset)
+                    .take(limit as usize)
+                    .cloned()
+                    .collect()
+            }
+        }
+    }
+
+    fn offset_key(stream_id: &str, subscription_id: &str, partition: u32) -> (String, String, u32) {
+        (
+            stream_id.to_string(),
+            subscription_id.to_string(),
+            partition,
+        )
+    }
+
+    impl EventStore for MemoryStore {
+        fn for_request(&self, _table_override: Option<&str>) -> Self {
+            self.clone()
+        }
+
+        async fn get_stream(&self, stream_id: &str) -> Result<Stream> {
+            self.stream(stream_id)
+        }
+
+        async fn get_stream_cached(&self, stream_id: &str) -> Result<Stream> {
+            if let Some(stream) = self.streams.get(TABLE_NAME, stream_id) {
+                return Ok(stream);
+            }
+            let stream = self.stream(stream_id)?;
+            self.streams.insert(TABLE_NAME, stream.clone());
+            Ok(stream)
+        }
+
+        async fn resolve_stream_id(&self, name: &str) -> Result<String> {
+            match self.get_stream_cached(name).await {
+                Err(Error::StreamNotFound(_)) => self
+                    .lock()
+                    .aliases
+                    .get(name)
+                    .cloned()
+                    .ok_or_else(|| Error::StreamNotFound(name.to_string())),
+                result => result.map(|stream| stream.stream_id),
+            }
+        }
+
+        async fn publish_events(
+            &self,
+            stream_id: &str,
+            events: &[PublishEvent],
+        ) -> Result<Vec<PublishedEvent>> {
+            let mut stream = self.get_stream_cached(stream_id).await?;
+            // Stands in for the stream creation time on DynamoDB's partition
+            // counters, which catches a stream recreated since it was cached
+            let created_at = self.lock().streams.get(stream_id).map(|s| s.created_at);
+            if created_at != Some(stream.created_at) {
+                self.streams.invalidate(TABLE_NAME, stream_id);
+                stream = self.get_stream_cached(stream_id).await?;
+            }
+
+            let validator = stream.schema.as_ref().map(schema::compile).transpose()?;
+            for (index, event) in events.iter().enumerate() {
+                stream.check_event_type(event.event_type.as_str())?;
+                if let Some(validator) = &validator {
+                    schema::validate_data(validator, &event.data, index)?;
+                }
+            }
+
+            let partitioner = stream.partitioner()?;
+            let now = timestamp_now();
+            let mut state = self.lock();
+
+            let bucket = state.rate_buckets.get(stream_id).copied();
+            let now_ms = now.timestamp_millis();
+            if let Some(next) = stream.charge_publish(bucket, events.len(), now_ms)? {
+                state.rate_buckets.insert(stream_id.to_string(), next);
+            }
+
+            let mut published = Vec::with_capacity(events.len());
+
+            for (input_index, event) in events.iter().enumerate() {
+                let partition = match event.routing_key() {
+                    "" => {
+                        let next = state.round_robin.entry(stream_id.to_string()).or_default();
+                        let turn = *next;
+                        *next += 1;
+                        partitioner.partition_empty_key(stream.empty_key_strategy, turn)
+                    }
+                    key => partitioner.partition(key),
+                };
+                let global_position = stream.global_ordering.then(|| {
+                    let position = state
+                        .global_positions
+                        .entry(stream_id.to_string())
+                        .or_default();
+                    *position += 1;
+                    *position
+                });
+                let stored = state
+                    .events
+                    .entry((stream_id.to_string(), partition))
+                    .or_default();
+                let sequence = stored.len() as u64 + 1;
+                let id = event_id(stream_id, partition, sequence);
+                let latest = stored.last().map(|e| e.timestamp);
+                let (timestamp, wall_clock_timestamp) = match event.timestamp {
+                    Some(timestamp) => (to_timestamp_precision(timestamp), Some(now)),
+                    None => partition_timestamp(now, latest),
+                };
+
+                stored.push(Event {
+                    id: id.clone(),
+                    stream_id: stream_id.to_string(),
+                    partition,
+                    sequence,
+                    global_position,
+                    key: event.key.clone(),
+                    partition_key: event.partition_key.clone(),
+                    order_key: event.order_key,
+                    event_type: event.event_type.to_string(),
+                    data: event.data.clone(),
+                    timestamp,
+                    wall_clock_timestamp,
+                });
+                published.push(PublishedEvent {
+                    input_index,
+                    id,
+                    stream_id: stream_id.to_string(),
+                    partition,
+                    sequence,
+                    global_position,
+                    key: event.key.clone(),
+                    timestamp,
+                });
+            }
+
+            Ok(published)
+        }
+
+        async fn read_events(
+            &self,
+            stream_id: &str,
+            partition: u32,
+            from_offset: u64,
+            limit: u32,
+            scan_forward: bool,
+        ) -> Result<Vec<Event>> {
+            self.check_throttled("read_events")?;
+            Ok(self.read(stream_id, partition, from_offset, limit, scan_forward))
+        }
+
+        async fn read_events_projected(
+            &self,
+            stream_id: &str,
+            partition: u32,
+            from_offset: u64,
+            limit: u32,
+            _projection: &EventProjection,
+        ) -> Result<Vec<Event>> {
+            self.check_throttled("read_events_projected")?;
+            Ok(self.read(stream_id, partition, from_offset, limit, true))
+        }
+
+        async fn get_latest_offset(&self, stream_id: &str, partition: u32) -> Result<u64> {
+            Ok(self
+                .lock()
+                .events
+                .get(&(stream_id.to_string(), partition))
+                .map_or(0, |events| events.len() as u64))
+        }
+
+        // Every reserved sequence is written here
+        async fn get_latest_written_offset(&self, stream_id: &str, partition: u32) -> Result<u64> {
+            self.get_latest_offset(stream_id, partition).await
+        }
+
+        async fn offset_at_time(
+            &self,
+            stream_id: &str,
+            partition: u32,
+            timestamp: DateTime<Utc>,
+        ) -> Result<u64> {
+            Ok(self
+                .partition_events(stream_id, partition)
+                .iter()
+                .rev()
+                .find(|e| e.timestamp <= timestamp)
+                .map_or(0, |e| e.sequence))
+        }
+
+        async fn create_subscription(
+            &self,
+            stream_id: &str,
+            req: &CreateSubscriptionRequest,
+        ) -> Result<Subscription> {
+            req.validate()?;
+            let stream = self.stream(stream_id)?;
+
+            let mut subscription =
+                Subscription::new(stream_id.to_string(), req.subscription_id.clone());
+            subscription.default_limit = req.default_limit;
+            subscription.default_wait_seconds = req.default_wait_seconds;
+            subscription.cursor_encoding = req.cursor_encoding;
+            subscription.filter = req.filter.clone();
+
+            let mut state = self.lock();
+            let key = (stream_id.to_string(), req.subscription_id.clone());
+            if state.subscriptions.contains_key(&key) {
+                return Err(Error::SubscriptionAlreadyExists(
+                    req.subscription_id.clone(),
+                ));
+            }
+            state.subscriptions.insert(key, subscription.clone());
+
+            let now = Utc::now();
+            for partition in 0..stream.partition_count {
+                let events = state.events.get(&(stream_id.to_string(), partition));
+                let offset = match req.start_from {
+                    StartFrom::Latest => events.map_or(0, |events| events.len() as u64),
+                    StartFrom::Earliest | StartFrom::Compacted => 0,
+                    StartFrom::Timestamp(at) => events
+                        .and_then(|events| events.iter().rev().find(|e| e.timestamp <= at))
+                        .map_or(0, |e| e.sequence),
+                };
+                state.offsets.insert(
+                    offset_key(stream_id, &req.subscription_id, partition),
+                    (offset, now),
+                );
+            }
+
+            Ok(subscription)
+        }
+
+        async fn get_subscription(
+            &self,
+            stream_id: &str,
+            subscription_id: &str,
+        ) -> Result<Subscription> {
+            self.lock()
+                .subscriptions
+                .get(&(stream_id.to_string(), subscription_id.to_string()))
+                .cloned()
+                .ok_or_else(|| Error::SubscriptionNotFound(subscription_id.to_string()))
+        }
+
+        async fn record_poll(&self, stream_id: &str, subscription_id: &str) -> Result<()> {
+            let mut state = self.lock();
+            let key = (stream_id.to_string(), subscription_id.to_string());
+            let subscription = state
+                .subscriptions
+                .get_mut(&key)
+                .ok_or_else(|| Error::SubscriptionNotFound(subscription_id.to_string()))?;
+            subscription.last_polled_at = Some(Utc::now());
+            Ok(())
+        }
+
+        async fn get_offset(
+            &self,
+            stream_id: &str,
+            subscription_id: &str,
+            partition: u32,
+        ) -> Result<u64> {
+            self.check_throttled("get_offset")?;
+            self.lock()
+                .offsets
+                .get(&offset_key(stream_id, subscription_id, partition))
+                .map(|&(offset, _)| offset)
+                .ok_or_else(|| Error::SubscriptionNotFound(subscription_id.to_string()))
+        }
+
+        async fn list_offsets(
+            &self,
+            stream_id: &str,
+            subscription_id: &str,
+        ) -> Result<Vec<ConsumerOffset>> {
+            let mut offsets: Vec<ConsumerOffset> = self
+                .lock()
+                .offsets
+                .iter()
+                .filter(|((s, sub, _), _)| s == stream_id && sub == subscription_id)
+                .map(
+                    |(&(_, _, partition), &(offset, committed_at))| ConsumerOffset {
+                        stream_id: stream_id.to_string(),
+                        subscription_id: subscription_id.to_string(),
+                        partition,
+                        offset,
+                        committed_at,
+                    },
+                )
+                .collect();
+            offsets.sort_by_key(|o| o.partition);
+            Ok(offsets)
+        }
+
+        async fn commit_offsets(
+            &self,
+            stream_id: &str,
+            subscription_id: &str,
+            offsets: &[PartitionOffset],
+            unmodified_since: Option<DateTime<Utc>>,
+        ) -> Result<()> {
+            let now = Utc::now();
+            let mut state = self.lock();
+            if let Some(since) = unmodified_since {
+                let modified = offsets.iter().any(|po| {
+                    let key = offset_key(stream_id, subscription_id, po.partition);
+                    state
+                        .offsets
+                        .get(&key)
+                        .is_some_and(|&(_, committed_at)| committed_at > since)
+                });
+                if modified {
+                    return Err(Error::CommitConflict(format!(
+                        "Subscription {} was committed after this cursor was issued",
+                        subscription_id
+                    )));
+                }
+            }
+            for po in offsets {
+                state.offsets.insert(
+                    offset_key(stream_id, subscription_id, po.partition),
+                    (po.offset, now),
+                );
+            }
+            Ok(())
+        }
+
+        async fn get_delivered_offset(
+            &self,
+            stream_id: &str,
+            subscription_id: &str,
+            partition: u32,
+        ) -> Result<Option<u64>> {
+            self.check_throttled("get_delivered_offset")?;
+            Ok(self
+                .lock()
+                .delivered
+                .get(&offset_key(stream_id, subscription_id, partition))
+                .copied())
+        }
+
+        async fn set_delivered_offsets(
+            &self,
+            stream_id: &str,
+            subscription_id: &str,
+            offsets: &[PartitionOffset],
+        ) -> Result<()> {
+            let mut state = self.lock();
+            for po in offsets {
+                state.delivered.insert(
+                    offset_key(stream_id, subscription_id, po.partition),
+                    po.offset,
+                );
+            }
+            Ok(())
+        }
+
+        async fn put_cursor_token(&self, state: &CursorState) -> Result<String> {
+            let token = state.token()?;
+            self.lock()
+                .cursor_tokens
+                .insert(token.clone(), state.clone());
+            Ok(token)
+        }
+
+        async fn get_cursor_token(
+            &self,
+            stream_id: &str,
+            subscription_id: &str,
+            token: &str,
+        ) -> Result<CursorState> {
+            self.lock()
+                .cursor_tokens
+                .get(token)
+                .filter(|s| s.stream_id == stream_id && s.subscription_id == subscription_id)
+                .cloned()
+                .ok_or_else(|| Error::InvalidCursor {
+                    message: "Unknown or expired cursor token".to_string(),
+                    details: None,
+                })
+        }
+
+        async fn claim_idempotency_key(
+            &self,
+            claim: &IdempotencyRecord,
+        ) -> Result<Option<IdempotencyRecord>> {
+            let mut state = self.lock();
+            let key = (claim.stream_id.clone(), claim.idempotency_key.clone());
+            match state
+                .idempotency
+                .get(&key)
+                .filter(|record| !record.is_expired())
+            {
+                Some(existing) => Ok(Some(existing.clone())),
+                None => {
+                    state.idempotency.insert(key, claim.clone());
+                    Ok(None)
+                }
+            }
+        }
+
+        async fn put_idempotency_record(&self, record: &IdempotencyRecord) -> Result<()> {
+            let mut state = self.lock();
+            let key = (record.stream_id.clone(), record.idempotency_key.clone());
+            match state.idempotency.get_mut(&key) {
+                Some(claim) if claim.created_at == record.created_at => {
+                    *claim = record.clone();
+                    Ok(())
+                }
+                _ => Err(Error::IdempotencyKeyInUse(format!(
+                    "The claim on '{}' expired before its publish finished",
+                    record.idempotency_key
+                ))),
+            }
+        }
+
+        async fn release_idempotency_key(&self, claim: &IdempotencyRecord) -> Result<()> {
+            let mut state = self.lock();
+            let key = (claim.stream_id.clone(), claim.idempotency_key.clone());
+            if state
+                .idempotency
+                .get(&key)
+                .is_some_and(|held| held.pending && held.created_at == claim.created_at)
+            {
+                state.idempotency.remove(&key);
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn publish_event(key: &str) -> PublishEvent {
+        PublishEvent {
+            key: key.to_string(),
+            partition_key: None,
+            order_key: None,
+            timestamp: None,
+            event_type: "order.created".parse().unwrap(),
+            data: serde_json::json!({}),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_reads_like_dynamo() {
+        let store = MemoryStore::new();
+        store.put_stream(Stream::new("orders".to_string(), 1, 24));
+        let events: Vec<_> = (0..5).map(|i| publish_event(&format!("k{}", i))).collect();
+        store.publish_events("orders", &events).await.unwrap();
+
+        // Forward reads start after the offset; reverse reads end before it
+        let forward = store.read_events("orders", 0, 2, 2, true).await.unwrap();
+        assert_eq!(
+            forward.iter().map(|e| e.sequence).collect::<Vec<_>>(),
+            [3, 4]
+        );
+        let reverse = store.read_events("orders", 0, 0, 2, false).await.unwrap();
+        assert_eq!(
+            reverse.iter().map(|e| e.sequence).collect::<Vec<_>>(),
+            [5, 4]
+        );
+        let reverse = store.read_events("orders", 0, 3, 10, false).await.unwrap();
+        assert_eq!(
+            reverse.iter().map(|e| e.sequence).collect::<Vec<_>>(),
+            [2, 1]
+        );
+
+        assert_eq!(store.get_latest_offset("orders", 0).await.unwrap(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_round_robin_spreads_empty_key_events() {
+        let store = MemoryStore::new();
+        let mut stream = Stream::new("clicks".to_string(), 3, 24);
+        stream.empty_key_strategy = EmptyKeyStrategy::RoundRobin;
+        stream.compaction = false;
+        store.put_stream(stream);
+
+        let events: Vec<_> = (0..6).map(|_| publish_event("")).collect();
+        let published = store.publish_events("clicks", &events).await.unwrap();
+
+        // Turns count from 0, as in DynamoDB
+        let partitions: Vec<u32> = published.iter().map(|e| e.partition).collect();
+        assert_eq!(partitions, [0, 1, 2, 0, 1, 2]);
+
+        // Under the default, they all hash to one
+        store.put_stream(Stream::new("views".to_string(), 3, 24));
+        let published = store.publish_events("views", &events).await.unwrap();
+        assert!(published
+            .iter()
+            .all(|e| e.partition == published[0].partition));
+    }
+
+    #[tokio::test]
+    async fn test_publishes_within_ttl_read_the_stream_once() {
+        let store = MemoryStore::new();
+        let mut stream = Stream::new("orders".to_string(), 2, 24);
+        store.put_stream(stream.clone());
+
+        store
+            .publish_events("orders", &[publish_event("a")])
+            .await
+            .unwrap();
+        assert_eq!(store.stream_reads(), 1);
+        store
+            .publish_events("orders", &[publish_event("b")])
+            .await
+            .unwrap();
+        assert_eq!(store.stream_reads(), 1);
+
+        // A config change made elsewhere waits for the cached copy to expire
+        stream.retention_hours = 48;
+        store.put_stream(stream.clone());
+        store
+            .publish_events("orders", &[publish_event("c")])
+            .await
+            .unwrap();
+        assert_eq!(store.stream_reads(), 1);
+        assert_eq!(
+            store
+                .get_stream_cached("orders")
+                .await
+                .unwrap()
+                .retention_hours,
+            24
+        );
+
+        // But a stream recreated with another layout is routed by at once
+        let recreated = Stream {
+            created_at: stream.created_at + chrono::Duration::seconds(1),
+            ..Stream::new("orders".to_string(), 4, 24)
+        };
+        store.put_stream(recreated);
+        store
+            .publish_events("orders", &[publish_event("d")])
+            .await
+            .unwrap();
+        assert_eq!(store.stream_reads(), 2);
+        assert_eq!(
+            store
+                .get_stream_cached("orders")
+                .await
+                .unwrap()
+                .partition_count,
+            4
+        );
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_subscription_offsets() {
+        let store = MemoryStore::new();
+        store.put_stream(Stream::new("orders".to_string(), 2, 24));
+        store
+            .publish_events("orders", &[publish_event("a")])
+            .await
+            .unwrap();
+
+        let req = CreateSubscriptionRequest {
+            subscription_id: "billing".to_string(),
+            start_from: StartFrom::Latest,
+            ..Default::default()
+        };
+        store.create_subscription("orders", &req).await.unwrap();
+        assert!(matches!(
+            store.create_subscription("orders", &req).await,
+            Err(crate::Error::SubscriptionAlreadyExists(_))
+        ));
+
+        let offsets = store.list_offsets("orders", "billing").await.unwrap();
+        let total: u64 = offsets.iter().map(|o| o.offset).sum();
+        assert_eq!(offsets.len(), 2);
+        assert_eq!(total, 1);
+
+        assert!(matches!(
+            store.get_offset("orders", "missing", 0).await,
+            Err(crate::Error::SubscriptionNotFound(_))
+        ));
+    }
+}
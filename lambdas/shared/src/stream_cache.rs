@@ -0,0 +1,157 @@
+//! In-process cache of stream metadata
+//!
+//! Publishes and polls need a stream's config (partition count, schema, rate
+//! limit) on every request. Lambda reuses an instance across invocations, so
+//! caching it for a short while saves a read per request under load. Changes
+//! made through this instance evict the entry at once; other instances see
+//! them once their entry expires, at most [`STREAM_CACHE_TTL`] later.
+//!
+//! The exception is a stream deleted and recreated with another partition
+//! layout: publishes check the cached copy against the partition counters,
+//! which record when their stream was created, and re-read it on a mismatch,
+//! so events are never routed by the old layout. Polls only read, so a stale
+//! copy there just shows the old partitions until it expires.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crate::models::Stream;
+
+/// How long a stream's metadata is served from the cache
+pub const STREAM_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// Streams cached at once; the least recently used is evicted past this
+const STREAM_CACHE_CAPACITY: usize = 1024;
+
+/// Streams by (table, stream ID), each with when it was read and last used
+#[derive(Debug)]
+pub struct StreamCache {
+    ttl: Duration,
+    entries: Mutex<Entries>,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    streams: HashMap<(String, String), CachedStream>,
+    /// Lookups so far, to order entries by last use
+    uses: u64,
+}
+
+#[derive(Debug)]
+struct CachedStream {
+    stream: Stream,
+    read_at: Instant,
+    last_used: u64,
+}
+
+impl Default for StreamCache {
+    fn default() -> Self {
+        Self::with_ttl(STREAM_CACHE_TTL)
+    }
+}
+
+impl StreamCache {
+    pub fn with_ttl(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::default(),
+        }
+    }
+
+    /// The cached stream, unless it is missing or expired
+    pub fn get(&self, table_name: &str, stream_id: &str) -> Option<Stream> {
+        let mut entries = self.lock();
+        entries.uses += 1;
+        let uses = entries.uses;
+        let key = (table_name.to_string(), stream_id.to_string());
+        match entries.streams.get_mut(&key) {
+            Some(entry) if entry.read_at.elapsed() < self.ttl => {
+                entry.last_used = uses;
+                Some(entry.stream.clone())
+            }
+            Some(_) => {
+                entries.streams.remove(&key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Cache a stream just read from the table
+    pub fn insert(&self, table_name: &str, stream: Stream) {
+        let mut entries = self.lock();
+        entries.uses += 1;
+        let last_used = entries.uses;
+        let key = (table_name.to_string(), stream.stream_id.clone());
+        if !entries.streams.contains_key(&key) && entries.streams.len() >= STREAM_CACHE_CAPACITY {
+            let oldest = entries
+                .streams
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.streams.remove(&oldest);
+            }
+        }
+        let read_at = Instant::now();
+        entries.streams.insert(
+            key,
+            CachedStream {
+                stream,
+                read_at,
+                last_used,
+            },
+        );
+    }
+
+    /// Drop a stream that was changed or deleted
+    pub fn invalidate(&self, table_name: &str, stream_id: &str) {
+        self.lock()
+            .streams
+            .remove(&(table_name.to_string(), stream_id.to_string()));
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Entries> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entries_expire_and_are_scoped_to_their_table() {
+        let cache = StreamCache::default();
+        cache.insert("eventledger", Stream::new("orders".to_string(), 3, 24));
+
+        assert_eq!(
+            cache.get("eventledger", "orders").unwrap().partition_count,
+            3
+        );
+        assert!(cache.get("isolated-table", "orders").is_none());
+
+        cache.invalidate("eventledger", "orders");
+        assert!(cache.get("eventledger", "orders").is_none());
+
+        let expired = StreamCache::with_ttl(Duration::ZERO);
+        expired.insert("eventledger", Stream::new("orders".to_string(), 3, 24));
+        assert!(expired.get("eventledger", "orders").is_none());
+    }
+
+    #[test]
+    fn test_least_recently_used_stream_is_evicted() {
+        let cache = StreamCache::default();
+        for i in 0..STREAM_CACHE_CAPACITY {
+            cache.insert("eventledger", Stream::new(format!("stream-{}", i), 1, 24));
+        }
+        // Touch the first so the second is now the least recently used
+        cache.get("eventledger", "stream-0").unwrap();
+
+        cache.insert("eventledger", Stream::new("orders".to_string(), 1, 24));
+        assert!(cache.get("eventledger", "stream-0").is_some());
+        assert!(cache.get("eventledger", "stream-1").is_none());
+        assert!(cache.get("eventledger", "orders").is_some());
+    }
+}
@@ -0,0 +1,93 @@
+//! A stand-in DynamoDB for unit tests of code that talks to [`DynamoClient`]
+//!
+//! [`DynamoClient`]: crate::DynamoClient
+
+use aws_config::retry::RetryConfig;
+use aws_config::{BehaviorVersion, Region, SdkConfig};
+use aws_sdk_dynamodb::config::{Credentials, SharedCredentialsProvider};
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::thread::JoinHandle;
+
+use crate::DynamoClient;
+
+/// Status line of a successful response
+pub const OK: &str = "200 OK";
+
+/// SDK config with static credentials and no retries, so each call sends
+/// exactly one request
+pub fn sdk_config() -> SdkConfig {
+    SdkConfig::builder()
+        .behavior_version(BehaviorVersion::latest())
+        .region(Region::new("us-west-2"))
+        .credentials_provider(SharedCredentialsProvider::new(Credentials::new(
+            "test", "test", None, None, "test",
+        )))
+        .retry_config(RetryConfig::disabled())
+        .build()
+}
+
+/// Client with no credentials, so any DynamoDB call fails fast; for code
+/// that should be answered before making one
+pub fn offline_client() -> DynamoClient {
+    let config = SdkConfig::builder()
+        .behavior_version(BehaviorVersion::latest())
+        .region(Region::new("us-east-1"))
+        .build();
+    DynamoClient::from_config(&config)
+}
+
+/// Stand-in for DynamoDB: answers one request per connection with each of
+/// `responses` (status line and JSON body) in turn
+///
+/// Returns the endpoint to point a client at, and a handle that yields the
+/// requests received once every response has been sent.
+pub fn fake_dynamo<B: Into<String>>(
+    responses: impl IntoIterator<Item = (&'static str, B)>,
+) -> (String, JoinHandle<Vec<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    let responses: Vec<(&str, String)> = responses
+        .into_iter()
+        .map(|(status, body)| (status, body.into()))
+        .collect();
+    let server = std::thread::spawn(move || {
+        let mut requests = Vec::new();
+        for (status, body) in responses {
+            let (mut stream, _) = listener.accept().unwrap();
+            let request = read_request(&mut stream);
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/x-amz-json-1.0\r\nConnection: close\r\nContent-Length: {}\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+            requests.push(String::from_utf8_lossy(&request).to_string());
+        }
+        requests
+    });
+    (endpoint, server)
+}
+
+/// Read one HTTP request: the headers, then `Content-Length` bytes of body
+fn read_request(stream: &mut impl Read) -> Vec<u8> {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 4096];
+    loop {
+        if let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+            let headers = String::from_utf8_lossy(&request[..end]);
+            let content_length = headers
+                .lines()
+                .filter_map(|line| line.split_once(':'))
+                .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+                .map_or(0, |(_, value)| value.trim().parse::<usize>().unwrap());
+            if request.len() >= end + 4 + content_length {
+                return request;
+            }
+        }
+        let n = stream.read(&mut buf).unwrap();
+        assert!(n > 0, "connection closed before the request was complete");
+        request.extend_from_slice(&buf[..n]);
+    }
+}
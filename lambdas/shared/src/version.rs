@@ -0,0 +1,66 @@
+//! Response shape versions
+//!
+//! Clients pin the shape of a response with an `Accept-Version` header, so a
+//! field added or changed in a later version doesn't surprise code written
+//! against an earlier one. Without the header they get the latest.
+
+use crate::errors::{Error, Result};
+
+/// Request header naming the API version a client was written against
+pub const ACCEPT_VERSION_HEADER: &str = "accept-version";
+
+/// A version of the response shapes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ApiVersion {
+    /// The original shapes: a poll reports only the total `remaining`
+    V1,
+    /// Polls also break `remaining` down by partition
+    #[default]
+    V2,
+}
+
+impl ApiVersion {
+    /// Version named by an `Accept-Version` header (`2` or `v2`), or the
+    /// latest when there is none
+    pub fn from_header(value: Option<&str>) -> Result<Self> {
+        let Some(value) = value.map(str::trim) else {
+            return Ok(Self::default());
+        };
+        let number = value.strip_prefix(['v', 'V']).unwrap_or(value);
+        match number {
+            "1" => Ok(ApiVersion::V1),
+            "2" => Ok(ApiVersion::V2),
+            _ => Err(Error::Validation(format!(
+                "Unsupported Accept-Version '{}': expected 1 or 2",
+                value
+            ))),
+        }
+    }
+
+    /// The version's number, as sent back in the `Content-Version` header
+    pub fn number(self) -> u32 {
+        match self {
+            ApiVersion::V1 => 1,
+            ApiVersion::V2 => 2,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accept_version_parsing() {
+        assert_eq!(ApiVersion::from_header(None).unwrap(), ApiVersion::V2);
+        assert_eq!(ApiVersion::from_header(Some("1")).unwrap(), ApiVersion::V1);
+        assert_eq!(
+            ApiVersion::from_header(Some(" v1 ")).unwrap(),
+            ApiVersion::V1
+        );
+        assert_eq!(ApiVersion::from_header(Some("V2")).unwrap(), ApiVersion::V2);
+
+        let err = ApiVersion::from_header(Some("3")).unwrap_err();
+        assert_eq!(err.code(), "validation_error");
+    }
+}
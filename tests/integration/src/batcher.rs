@@ -0,0 +1,151 @@
+//! Client-side batching for producers
+//!
+//! A [`Batcher`] buffers events for one stream and publishes them together,
+//! trading a little latency for far fewer requests.
+
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Mutex, Notify};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+use crate::client::{ApiError, ApiResult, EventLedgerClient, PublishEvent, MAX_PUBLISH_BATCH};
+
+/// Buffers events for one stream and publishes them in batches
+///
+/// A batch is published once it holds `max_batch` events (capped at the API's
+/// [`MAX_PUBLISH_BATCH`]) or its first event has waited `max_delay`,
+/// whichever comes first. A publish that fails while
+/// flushing on time is reported by the next [`send`](Self::send),
+/// [`flush`](Self::flush) or [`close`](Self::close); its events are not
+/// retried. Dropping a batcher without closing it discards what is buffered.
+pub struct Batcher {
+    client: EventLedgerClient,
+    stream_id: String,
+    max_batch: usize,
+    pending: Arc<Mutex<Pending>>,
+    /// Wakes the timer when an empty buffer gets its first event
+    started: Arc<Notify>,
+    timer: JoinHandle<()>,
+}
+
+#[derive(Default)]
+struct Pending {
+    events: Vec<PublishEvent>,
+    /// When the buffered batch got its first event, which its deadline runs from
+    first: Option<Instant>,
+    /// Error from a flush on time, for the next call to report
+    error: Option<ApiError>,
+}
+
+impl Batcher {
+    pub(crate) fn new(
+        client: EventLedgerClient,
+        stream_id: &str,
+        max_batch: usize,
+        max_delay: Duration,
+    ) -> Self {
+        let pending = Arc::new(Mutex::new(Pending::default()));
+        let started = Arc::new(Notify::new());
+        let timer = tokio::spawn(flush_on_time(
+            client.clone(),
+            stream_id.to_string(),
+            max_delay,
+            pending.clone(),
+            started.clone(),
+        ));
+        Self {
+            client,
+            stream_id: stream_id.to_string(),
+            max_batch: max_batch.clamp(1, MAX_PUBLISH_BATCH),
+            pending,
+            started,
+            timer,
+        }
+    }
+
+    /// Buffer an event, publishing the batch if this fills it
+    ///
+    /// Fails without buffering the event if an earlier flush on time failed.
+    pub async fn send(&self, event: PublishEvent) -> ApiResult<()> {
+        let mut pending = self.pending.lock().await;
+        if let Some(e) = pending.error.take() {
+            return Err(e);
+        }
+        pending.events.push(event);
+        if pending.first.is_none() {
+            pending.first = Some(Instant::now());
+            self.started.notify_one();
+        }
+        if pending.events.len() >= self.max_batch {
+            return publish(&self.client, &self.stream_id, &mut pending).await;
+        }
+        Ok(())
+    }
+
+    /// Publish whatever is buffered now
+    pub async fn flush(&self) -> ApiResult<()> {
+        let mut pending = self.pending.lock().await;
+        if let Some(e) = pending.error.take() {
+            return Err(e);
+        }
+        publish(&self.client, &self.stream_id, &mut pending).await
+    }
+
+    /// Publish whatever is buffered and stop the timer
+    pub async fn close(self) -> ApiResult<()> {
+        self.flush().await
+    }
+}
+
+impl Drop for Batcher {
+    fn drop(&mut self) {
+        self.timer.abort();
+    }
+}
+
+/// Publish each batch `max_delay` after its first event was buffered, unless
+/// it filled up or was flushed first
+async fn flush_on_time(
+    client: EventLedgerClient,
+    stream_id: String,
+    max_delay: Duration,
+    pending: Arc<Mutex<Pending>>,
+    started: Arc<Notify>,
+) {
+    loop {
+        started.notified().await;
+
+        // The wake-up may have been stored while an earlier batch was timed,
+        // or be for a batch that has since gone, so time whichever batch is
+        // buffered now from its first event
+        loop {
+            let Some(first) = pending.lock().await.first else {
+                break;
+            };
+            tokio::time::sleep_until(first + max_delay).await;
+
+            let mut pending = pending.lock().await;
+            if pending.first == Some(first) {
+                if let Err(e) = publish(&client, &stream_id, &mut pending).await {
+                    pending.error.get_or_insert(e);
+                }
+                break;
+            }
+        }
+    }
+}
+
+/// Publish the buffered events as one batch
+async fn publish(
+    client: &EventLedgerClient,
+    stream_id: &str,
+    pending: &mut Pending,
+) -> ApiResult<()> {
+    if pending.events.is_empty() {
+        return Ok(());
+    }
+    let events = std::mem::take(&mut pending.events);
+    pending.first = None;
+    client.publish_events(stream_id, events).await.map(|_| ())
+}
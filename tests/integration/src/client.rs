@@ -1,24 +1,65 @@
 //! EventLedger API Client for testing
 
-use reqwest::{Client, Response, StatusCode};
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::HashMap;
 use std::time::Duration;
 
+use crate::batcher::Batcher;
+
+/// Content type for CBOR request and response bodies
+const CBOR_CONTENT_TYPE: &str = "application/cbor";
+
+/// How many times a throttled (429) request is retried before it's returned
+const MAX_THROTTLE_RETRIES: u32 = 3;
+/// Wait used when a 429 carries no usable `Retry-After`
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(1);
+/// Upper bound on any single `Retry-After` wait, so tests stay bounded
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(10);
+
+/// Whole-request timeout unless set with [`EventLedgerClient::with_timeout`]
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Most events the API accepts in one publish
+pub use eventledger_core::MAX_PUBLISH_BATCH;
+
 /// API client for EventLedger
+#[derive(Clone)]
 pub struct EventLedgerClient {
     client: Client,
     base_url: String,
+    table_override: Option<String>,
 }
 
 // Request/Response types
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct CreateStreamRequest {
     pub stream_id: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub partition_count: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub retention_hours: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub global_ordering: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_event_types: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub partition_overrides: Option<HashMap<String, u32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub empty_key_strategy: Option<EmptyKeyStrategy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compaction: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idempotency_ttl_hours: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compacted_ttl_hours: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_publish_per_second: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<HashMap<String, String>>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -26,15 +67,243 @@ pub struct Stream {
     pub stream_id: String,
     pub partition_count: u32,
     pub retention_hours: u32,
+    #[serde(default)]
+    pub global_ordering: bool,
+    #[serde(default)]
+    pub allowed_event_types: Option<Vec<String>>,
+    #[serde(default)]
+    pub schema: Option<serde_json::Value>,
+    #[serde(default)]
+    pub partition_overrides: Option<HashMap<String, u32>>,
+    #[serde(default)]
+    pub empty_key_strategy: Option<EmptyKeyStrategy>,
+    #[serde(default)]
+    pub compaction: Option<bool>,
+    #[serde(default)]
+    pub idempotency_ttl_hours: Option<u32>,
+    #[serde(default)]
+    pub compacted_ttl_hours: Option<u32>,
+    #[serde(default)]
+    pub max_publish_per_second: Option<u32>,
+    #[serde(default)]
+    pub tags: HashMap<String, String>,
+    #[serde(default)]
+    pub version: u64,
     pub created_at: String,
 }
 
+/// Where a stream sends events with an empty key; `hash` puts them all on
+/// one partition
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmptyKeyStrategy {
+    Hash,
+    RoundRobin,
+    Random,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UpdateStreamRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retention_hours: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_event_types: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_publish_per_second: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<HashMap<String, String>>,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct ListStreamsResponse {
     pub streams: Vec<Stream>,
+    #[serde(default)]
+    pub next_start_key: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StreamStats {
+    pub stream_id: String,
+    pub partition_count: u32,
+    pub approximate_event_count: u64,
+    #[serde(default)]
+    pub event_count: u64,
+    #[serde(default)]
+    pub earliest_timestamp: Option<String>,
+    #[serde(default)]
+    pub latest_timestamp: Option<String>,
+    #[serde(default)]
+    pub hot_partitions: Vec<u32>,
+    pub partitions: Vec<PartitionStats>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PartitionStats {
+    pub partition: u32,
+    pub offset: u64,
+    #[serde(default)]
+    pub event_count: u64,
+    #[serde(default)]
+    pub earliest_timestamp: Option<String>,
+    #[serde(default)]
+    pub latest_timestamp: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CompactedEvent {
+    #[serde(default)]
+    pub id: String,
+    pub stream_id: String,
+    pub key: String,
+    pub event_type: String,
+    pub data: serde_json::Value,
+    pub sequence: u64,
+    pub partition: u32,
+    pub timestamp: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Capabilities {
+    pub version: String,
+    pub timestamp_precision: String,
+    pub limits: CapabilityLimits,
+    pub defaults: CapabilityDefaults,
+    /// Feature name to whether this deployment supports it
+    pub features: HashMap<String, bool>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CapabilityLimits {
+    pub max_publish_batch: usize,
+    pub max_decompressed_body_bytes: usize,
+    pub max_poll_limit: u32,
+    pub max_poll_wait_seconds: u32,
+    pub max_subscription_id_length: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CapabilityDefaults {
+    pub partition_count: u32,
+    pub retention_hours: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SnapshotResponse {
+    pub compacted: Vec<CompactedEvent>,
+    pub cursor: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ListCompactedResponse {
+    pub events: Vec<CompactedEvent>,
+    #[serde(default)]
+    pub next_start_key: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CompactionChange {
+    pub position: u64,
+    #[serde(flatten)]
+    pub state: CompactedEvent,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CompactionChangelogResponse {
+    pub changes: Vec<CompactionChange>,
+    pub cursor: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeadLetter {
+    pub stream_id: String,
+    pub record_id: String,
+    pub record: serde_json::Value,
+    pub error: String,
+    pub attempts: u32,
+    pub failed_at: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ListDlqResponse {
+    pub records: Vec<DeadLetter>,
+}
+
+/// Optional query parameters for the compacted listing
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CompactedQuery {
+    /// `key` (default) or `updated_at`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<String>,
+    /// `asc` (default) or `desc`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    /// Exclusive start key from a previous page's `next_start_key`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_key: Option<String>,
+    /// Only entries whose event sequence is higher than this
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since_sequence: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CompactionBackfillResult {
+    pub stream_id: String,
+    pub events_scanned: u64,
+    pub keys_compacted: u64,
+    pub partitions_completed: u32,
+    pub offsets: Vec<PartitionOffset>,
+    pub complete: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RepartitionRequest {
+    pub target_stream_id: String,
+    pub partition_count: u32,
+    /// Offsets an incomplete run returned, to carry on from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from_offsets: Option<Vec<PartitionOffset>>,
+    /// Alias to move from the source to the target once complete
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RepartitionResult {
+    pub stream_id: String,
+    pub target_stream_id: String,
+    pub partition_count: u32,
+    pub events_copied: u64,
+    pub offsets: Vec<PartitionOffset>,
+    pub complete: bool,
+    #[serde(default)]
+    pub alias: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PartitionForResponse {
+    pub key: String,
+    pub partition: u32,
 }
 
 #[derive(Debug, Clone, Serialize)]
+pub struct PartitionPreviewRequest {
+    pub partition_count: u32,
+    pub keys: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PartitionDistribution {
+    pub partition_count: u32,
+    pub key_count: u64,
+    pub histogram: Vec<u64>,
+    pub skew: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct PublishEvent {
     pub key: String,
     #[serde(rename = "type")]
@@ -42,16 +311,36 @@ pub struct PublishEvent {
     pub data: serde_json::Value,
 }
 
+/// A [`PublishEvent`] with the optional fields most publishes leave out
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DetailedPublishEvent {
+    #[serde(flatten)]
+    pub event: PublishEvent,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub partition_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order_key: Option<u64>,
+    /// Only accepted by [`EventLedgerClient::publish_events_backfill`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize)]
-pub struct PublishRequest {
-    pub events: Vec<PublishEvent>,
+pub struct PublishRequest<E = PublishEvent> {
+    pub events: Vec<E>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct PublishedEvent {
+    #[serde(default)]
+    pub input_index: usize,
+    #[serde(default)]
+    pub id: String,
     pub stream_id: String,
     pub partition: u32,
     pub sequence: u64,
+    #[serde(default)]
+    pub global_position: Option<u64>,
     pub key: String,
     pub timestamp: String,
 }
@@ -62,28 +351,198 @@ pub struct PublishResponse {
 }
 
 #[derive(Debug, Clone, Serialize)]
+pub struct StreamPublish {
+    pub stream_id: String,
+    pub events: Vec<PublishEvent>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PublishBatchRequest {
+    pub items: Vec<StreamPublish>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StreamPublishResult {
+    pub stream_id: String,
+    pub success: bool,
+    #[serde(default)]
+    pub events: Vec<PublishedEvent>,
+    pub error: Option<ErrorResponse>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PublishBatchResponse {
+    pub results: Vec<StreamPublishResult>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StartFrom {
+    Earliest,
+    Latest,
+    Compacted,
+    /// RFC 3339 time to start after
+    Timestamp(String),
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct CreateSubscriptionRequest {
     pub subscription_id: String,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub start_from: Option<String>,
+    pub start_from: Option<StartFrom>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_limit: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_wait_seconds: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor_encoding: Option<CursorEncoding>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<SubscriptionFilter>,
+}
+
+/// Events a subscription's polls return; the cursor still moves past the rest
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubscriptionFilter {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event_types: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_prefix: Option<String>,
+}
+
+/// `token` swaps the base64 cursor for a short server-side token
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CursorEncoding {
+    Inline,
+    Token,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SeekRequest {
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SeekResponse {
+    pub subscription_id: String,
+    pub offsets: Vec<PartitionOffset>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SeekAllRequest {
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SeekAllResponse {
+    pub repositioned: u32,
+    #[serde(default)]
+    pub failed: Vec<SubscriptionSeekFailure>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SubscriptionSeekFailure {
+    pub subscription_id: String,
+    pub error: ErrorResponse,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Subscription {
     pub stream_id: String,
     pub subscription_id: String,
+    #[serde(default)]
+    pub default_limit: Option<u32>,
+    #[serde(default)]
+    pub default_wait_seconds: Option<u32>,
+    #[serde(default)]
+    pub cursor_encoding: Option<CursorEncoding>,
+    #[serde(default)]
+    pub filter: Option<SubscriptionFilter>,
     pub created_at: String,
+    #[serde(default)]
+    pub last_polled_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ListSubscriptionsResponse {
+    pub subscriptions: Vec<Subscription>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SubscriptionLag {
+    pub stream_id: String,
+    pub subscription_id: String,
+    pub total_lag: u64,
+    pub partitions: Vec<PartitionLag>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PartitionLag {
+    pub partition: u32,
+    pub committed_offset: u64,
+    pub latest_offset: u64,
+    pub lag: u64,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Event {
+    #[serde(default)]
+    pub id: String,
     pub stream_id: String,
     pub partition: u32,
     pub sequence: u64,
+    #[serde(default)]
+    pub global_position: Option<u64>,
     pub key: String,
+    #[serde(default)]
+    pub partition_key: Option<String>,
+    #[serde(default)]
+    pub order_key: Option<u64>,
     pub event_type: String,
     pub data: serde_json::Value,
     pub timestamp: String,
+    #[serde(default)]
+    pub wall_clock_timestamp: Option<String>,
+}
+
+impl Event {
+    /// Deserialize `data` into `T`
+    pub fn data_as<T: DeserializeOwned>(&self) -> ApiResult<T> {
+        T::deserialize(&self.data).map_err(|e| ApiError::Data {
+            event_id: self.id.clone(),
+            message: format!("expected {}: {}", std::any::type_name::<T>(), e),
+        })
+    }
+}
+
+/// A polled event with its data deserialized
+#[derive(Debug, Clone)]
+pub struct TypedEvent<T> {
+    pub event: Event,
+    pub data: T,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PartitionEventsResponse {
+    pub events: Vec<Event>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BatchGetEventsResponse {
+    pub events: Vec<Event>,
+    pub missing: Vec<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartitionOffset {
+    pub partition: u32,
+    pub offset: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PartitionRemaining {
+    pub partition: u32,
+    pub remaining: u64,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -91,11 +550,83 @@ pub struct PollResponse {
     pub events: Vec<Event>,
     pub cursor: String,
     pub remaining: u64,
+    #[serde(default)]
+    pub remaining_per_partition: Option<Vec<PartitionRemaining>>,
+    #[serde(default)]
+    pub offsets: Option<Vec<PartitionOffset>>,
+    /// Everything published so far has been returned
+    #[serde(default)]
+    pub caught_up: bool,
+    /// Events are waiting past this poll; commit and poll again for them
+    #[serde(default)]
+    pub has_more: bool,
 }
 
-#[derive(Debug, Clone, Serialize)]
-pub struct CommitRequest {
+/// A poll whose events' data was deserialized into `T`
+#[derive(Debug, Clone)]
+pub struct TypedPollResponse<T> {
+    pub events: Vec<TypedEvent<T>>,
     pub cursor: String,
+    pub remaining: u64,
+    pub caught_up: bool,
+    pub has_more: bool,
+}
+
+/// Optional query parameters for a poll
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PollOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_partition_offsets: Option<bool>,
+    /// Count `remaining` events and carry per-partition counts in the cursor
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_remaining: Option<bool>,
+    /// `peek` (default) or `consume`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+    /// `at_least_once` (default) or `at_most_once`, which commits before responding
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub semantics: Option<String>,
+    /// Create the subscription from `earliest` or `latest` if it doesn't exist
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_create: Option<String>,
+    /// Comma-separated partitions to read, e.g. `0,2` (default: all)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub partitions: Option<String>,
+    /// `false` returns events grouped by partition instead of merged by timestamp
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub merge: Option<bool>,
+    /// Long-poll for up to this many seconds when nothing is available
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wait_seconds: Option<u32>,
+    /// Comma-separated event fields to return, e.g. `key,sequence` (default: all)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fields: Option<String>,
+    /// Continue after a previous poll's cursor instead of the committed offset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+}
+
+/// Query parameters for reading a stream without a subscription
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TailOptions {
+    /// `earliest` (default) or `latest`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    /// Cursor from the previous page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CommitRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub up_to_timestamp: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -103,10 +634,49 @@ pub struct CommitResponse {
     pub success: bool,
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct SubscriptionCommit {
+    pub subscription_id: String,
+    pub cursor: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CommitBatchRequest {
+    pub commits: Vec<SubscriptionCommit>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SubscriptionCommitResult {
+    pub subscription_id: String,
+    pub success: bool,
+    pub error: Option<ErrorResponse>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CommitBatchResponse {
+    pub results: Vec<SubscriptionCommitResult>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ConsumerOffset {
+    pub stream_id: String,
+    pub subscription_id: String,
+    pub partition: u32,
+    pub offset: u64,
+    pub committed_at: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ListOffsetsResponse {
+    pub offsets: Vec<ConsumerOffset>,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct ErrorResponse {
     pub error: String,
     pub message: String,
+    #[serde(default)]
+    pub details: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -123,6 +693,8 @@ pub enum ApiError {
     Http { status: StatusCode, body: String },
     /// Network or serialization error
     Request(String),
+    /// Event data didn't deserialize into the requested type
+    Data { event_id: String, message: String },
 }
 
 impl std::fmt::Display for ApiError {
@@ -130,26 +702,55 @@ impl std::fmt::Display for ApiError {
         match self {
             ApiError::Http { status, body } => write!(f, "HTTP {}: {}", status, body),
             ApiError::Request(msg) => write!(f, "Request error: {}", msg),
+            ApiError::Data { event_id, message } => {
+                write!(f, "Event {} data error: {}", event_id, message)
+            }
         }
     }
 }
 
 impl std::error::Error for ApiError {}
 
+/// HTTP client that gives up on a request after `timeout`
+fn http_client(timeout: Duration) -> Client {
+    Client::builder()
+        .timeout(timeout)
+        .build()
+        .expect("Failed to create HTTP client")
+}
+
 impl EventLedgerClient {
     /// Create a new client with the given base URL
     pub fn new(base_url: &str) -> Self {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(30))
-            .build()
-            .expect("Failed to create HTTP client");
-
         Self {
-            client,
+            client: http_client(DEFAULT_TIMEOUT),
             base_url: base_url.trim_end_matches('/').to_string(),
+            table_override: None,
         }
     }
 
+    /// Send requests to another deployment, keeping the other settings
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Give each request `timeout` to complete instead of [`DEFAULT_TIMEOUT`]
+    ///
+    /// Long polls need longer than their `wait_seconds`.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.client = http_client(timeout);
+        self
+    }
+
+    /// Send every request against an alternate table via the `X-EventLedger-Table` header
+    ///
+    /// Only honored by deployments with `EVENTLEDGER_ALLOW_TABLE_OVERRIDE=true`.
+    pub fn with_table_override(mut self, table_name: &str) -> Self {
+        self.table_override = Some(table_name.to_string());
+        self
+    }
+
     /// Create a client from environment variable
     pub fn from_env() -> Self {
         let base_url = std::env::var("EVENTLEDGER_API_URL")
@@ -171,16 +772,231 @@ impl EventLedgerClient {
         self.get("/streams").await
     }
 
+    /// List one page of streams starting with `prefix`, continuing after
+    /// `start_key` when given
+    pub async fn list_streams_page(
+        &self,
+        prefix: &str,
+        limit: usize,
+        start_key: Option<&str>,
+    ) -> ApiResult<ListStreamsResponse> {
+        let limit = limit.to_string();
+        let mut query = vec![("prefix", prefix), ("limit", limit.as_str())];
+        if let Some(start_key) = start_key {
+            query.push(("start_key", start_key));
+        }
+        self.get_with_query("/streams", &query).await
+    }
+
+    /// List only streams whose ID starts with `prefix`
+    pub async fn list_streams_with_prefix(&self, prefix: &str) -> ApiResult<ListStreamsResponse> {
+        self.get_with_query("/streams", &[("prefix", prefix)]).await
+    }
+
+    /// List only streams carrying every one of the `key:value` tags
+    pub async fn list_streams_tagged(&self, tags: &[&str]) -> ApiResult<ListStreamsResponse> {
+        let query: Vec<_> = tags.iter().map(|tag| ("tag", tag)).collect();
+        self.get_with_query("/streams", &query).await
+    }
+
+    /// Get the named streams in one request, skipping any that don't exist
+    pub async fn get_streams(&self, stream_ids: &[&str]) -> ApiResult<ListStreamsResponse> {
+        self.get(&format!("/streams?ids={}", stream_ids.join(",")))
+            .await
+    }
+
     /// Get a stream by ID
     pub async fn get_stream(&self, stream_id: &str) -> ApiResult<Stream> {
         self.get(&format!("/streams/{}", stream_id)).await
     }
 
     /// Delete a stream
+    /// Update stream config, sending `if_match` as the `If-Match` header when given
+    pub async fn update_stream(
+        &self,
+        stream_id: &str,
+        if_match: Option<&str>,
+        req: &UpdateStreamRequest,
+    ) -> ApiResult<Stream> {
+        let url = format!("{}/streams/{}", self.base_url, stream_id);
+        let mut builder = self.request(Method::PATCH, &url).json(req);
+        if let Some(etag) = if_match {
+            builder = builder.header("If-Match", etag);
+        }
+        let response = self.send(builder).await?;
+
+        self.handle_response(response).await
+    }
+
     pub async fn delete_stream(&self, stream_id: &str) -> ApiResult<DeleteResponse> {
         self.delete(&format!("/streams/{}", stream_id)).await
     }
 
+    /// Delete a stream even if it still has subscriptions
+    pub async fn force_delete_stream(&self, stream_id: &str) -> ApiResult<DeleteResponse> {
+        self.delete(&format!("/streams/{}?force=true", stream_id))
+            .await
+    }
+
+    /// Get size statistics for a stream
+    pub async fn stream_stats(&self, stream_id: &str) -> ApiResult<StreamStats> {
+        self.get(&format!("/streams/{}/stats", stream_id)).await
+    }
+
+    /// List compacted state (latest event per key)
+    pub async fn list_compacted(
+        &self,
+        stream_id: &str,
+        query: &CompactedQuery,
+    ) -> ApiResult<ListCompactedResponse> {
+        self.get_with_query(&format!("/streams/{}/compacted", stream_id), query)
+            .await
+    }
+
+    /// Compacted updates in the order they were applied, after `cursor`
+    pub async fn compaction_changelog(
+        &self,
+        stream_id: &str,
+        cursor: Option<&str>,
+        limit: Option<u32>,
+    ) -> ApiResult<CompactionChangelogResponse> {
+        let mut query = Vec::new();
+        if let Some(cursor) = cursor {
+            query.push(("cursor", cursor.to_string()));
+        }
+        if let Some(limit) = limit {
+            query.push(("limit", limit.to_string()));
+        }
+        self.get_with_query(
+            &format!("/streams/{}/compacted/changelog", stream_id),
+            &query,
+        )
+        .await
+    }
+
+    /// Server limits, defaults and supported features
+    pub async fn capabilities(&self) -> ApiResult<Capabilities> {
+        self.get("/capabilities").await
+    }
+
+    /// Compacted state plus a tail cursor that continues from it
+    pub async fn snapshot(&self, stream_id: &str) -> ApiResult<SnapshotResponse> {
+        self.get(&format!("/streams/{}/snapshot", stream_id)).await
+    }
+
+    /// Records the compactor dead-lettered for a stream
+    pub async fn list_dlq(&self, stream_id: &str) -> ApiResult<ListDlqResponse> {
+        self.get(&format!("/streams/{}/dlq", stream_id)).await
+    }
+
+    /// Rebuild compacted state from the stream's existing events
+    pub async fn compact_stream(&self, stream_id: &str) -> ApiResult<CompactionBackfillResult> {
+        self.post(
+            &format!("/streams/{}/compact", stream_id),
+            &serde_json::json!({}),
+        )
+        .await
+    }
+
+    /// Carry on an incomplete backfill from the offsets it returned
+    pub async fn resume_compaction(
+        &self,
+        stream_id: &str,
+        from_offsets: &[PartitionOffset],
+    ) -> ApiResult<CompactionBackfillResult> {
+        self.post(
+            &format!("/streams/{}/compact", stream_id),
+            &serde_json::json!({ "from_offsets": from_offsets }),
+        )
+        .await
+    }
+
+    /// Copy a stream's events into a new stream with another partition count
+    pub async fn repartition_stream(
+        &self,
+        stream_id: &str,
+        req: &RepartitionRequest,
+    ) -> ApiResult<RepartitionResult> {
+        self.post(&format!("/streams/{}/repartition", stream_id), req)
+            .await
+    }
+
+    /// Read a partition directly (debug endpoint); `order` is `asc` or `desc`
+    pub async fn read_partition(
+        &self,
+        stream_id: &str,
+        partition: u32,
+        order: &str,
+        limit: u32,
+    ) -> ApiResult<PartitionEventsResponse> {
+        self.get_with_query(
+            &format!("/streams/{}/partitions/{}/events", stream_id, partition),
+            &[("order", order.to_string()), ("limit", limit.to_string())],
+        )
+        .await
+    }
+
+    /// Fetch specific events from a partition, listing the sequences not found
+    pub async fn batch_get_events(
+        &self,
+        stream_id: &str,
+        partition: u32,
+        sequences: &[u64],
+    ) -> ApiResult<BatchGetEventsResponse> {
+        self.post(
+            &format!(
+                "/streams/{}/partitions/{}/events:batchGet",
+                stream_id, partition
+            ),
+            &serde_json::json!({ "sequences": sequences }),
+        )
+        .await
+    }
+
+    /// Read a partition sorted by each event's `order_key` rather than its sequence
+    pub async fn read_partition_by_order_key(
+        &self,
+        stream_id: &str,
+        partition: u32,
+        limit: u32,
+    ) -> ApiResult<PartitionEventsResponse> {
+        self.get_with_query(
+            &format!("/streams/{}/partitions/{}/events", stream_id, partition),
+            &[
+                ("order_by", "order_key".to_string()),
+                ("limit", limit.to_string()),
+            ],
+        )
+        .await
+    }
+
+    /// Replace an event's payload with a redaction tombstone, keeping its sequence
+    pub async fn redact_event(
+        &self,
+        stream_id: &str,
+        partition: u32,
+        sequence: u64,
+    ) -> ApiResult<Event> {
+        self.delete(&format!(
+            "/streams/{}/partitions/{}/events/{}",
+            stream_id, partition, sequence
+        ))
+        .await
+    }
+
+    /// Get the partition a key would be routed to
+    pub async fn partition_for(
+        &self,
+        stream_id: &str,
+        key: &str,
+    ) -> ApiResult<PartitionForResponse> {
+        self.get_with_query(
+            &format!("/streams/{}/partition-for", stream_id),
+            &[("key", key)],
+        )
+        .await
+    }
+
     // =========================================================================
     // Event Operations
     // =========================================================================
@@ -206,6 +1022,70 @@ impl EventLedgerClient {
             .await
     }
 
+    /// Publish events that set any of the optional fields
+    pub async fn publish_detailed_events(
+        &self,
+        stream_id: &str,
+        events: Vec<DetailedPublishEvent>,
+    ) -> ApiResult<PublishResponse> {
+        let req = PublishRequest { events };
+        self.post(&format!("/streams/{}/events", stream_id), &req)
+            .await
+    }
+
+    /// Buffer events for `stream_id` and publish them in batches of up to
+    /// `max_batch` (at most [`MAX_PUBLISH_BATCH`]), waiting at most
+    /// `max_delay` to fill one
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub fn batcher(&self, stream_id: &str, max_batch: usize, max_delay: Duration) -> Batcher {
+        Batcher::new(self.clone(), stream_id, max_batch, max_delay)
+    }
+
+    /// Publish events that carry their own `timestamp`, e.g. when importing
+    /// historical data
+    pub async fn publish_events_backfill(
+        &self,
+        stream_id: &str,
+        events: Vec<DetailedPublishEvent>,
+    ) -> ApiResult<PublishResponse> {
+        let req = PublishRequest { events };
+        self.post(
+            &format!(
+                "/streams/{}/events?allow_timestamp_override=true",
+                stream_id
+            ),
+            &req,
+        )
+        .await
+    }
+
+    /// Publish to several streams in one call
+    pub async fn publish_batch(
+        &self,
+        items: Vec<StreamPublish>,
+    ) -> ApiResult<PublishBatchResponse> {
+        self.post("/publish-batch", &PublishBatchRequest { items })
+            .await
+    }
+
+    /// Publish events with an `Idempotency-Key` header
+    pub async fn publish_events_idempotent(
+        &self,
+        stream_id: &str,
+        events: Vec<PublishEvent>,
+        idempotency_key: &str,
+    ) -> ApiResult<PublishResponse> {
+        let url = format!("{}/streams/{}/events", self.base_url, stream_id);
+        let request = self
+            .request(Method::POST, &url)
+            .header("Idempotency-Key", idempotency_key)
+            .json(&PublishRequest { events });
+        let response = self.send(request).await?;
+
+        self.handle_response(response).await
+    }
+
     // =========================================================================
     // Subscription Operations
     // =========================================================================
@@ -220,6 +1100,82 @@ impl EventLedgerClient {
             .await
     }
 
+    /// List subscriptions on a stream
+    pub async fn list_subscriptions(
+        &self,
+        stream_id: &str,
+    ) -> ApiResult<ListSubscriptionsResponse> {
+        self.get(&format!("/streams/{}/subscriptions", stream_id))
+            .await
+    }
+
+    /// List subscriptions on a stream that haven't polled for `hours`
+    pub async fn list_stale_subscriptions(
+        &self,
+        stream_id: &str,
+        hours: u32,
+    ) -> ApiResult<ListSubscriptionsResponse> {
+        self.get(&format!(
+            "/streams/{}/subscriptions?stale_after={}",
+            stream_id, hours
+        ))
+        .await
+    }
+
+    /// Delete a subscription and its offsets
+    pub async fn delete_subscription(
+        &self,
+        stream_id: &str,
+        subscription_id: &str,
+    ) -> ApiResult<DeleteResponse> {
+        self.delete(&format!(
+            "/streams/{}/subscriptions/{}",
+            stream_id, subscription_id
+        ))
+        .await
+    }
+
+    /// Move a subscription to `earliest` or `latest`
+    pub async fn seek(
+        &self,
+        stream_id: &str,
+        subscription_id: &str,
+        to: &str,
+    ) -> ApiResult<SeekResponse> {
+        let req = SeekRequest { to: to.to_string() };
+        self.post(
+            &format!(
+                "/streams/{}/subscriptions/{}/seek",
+                stream_id, subscription_id
+            ),
+            &req,
+        )
+        .await
+    }
+
+    /// How far a subscription's committed offsets trail each partition
+    pub async fn lag(&self, stream_id: &str, subscription_id: &str) -> ApiResult<SubscriptionLag> {
+        self.get(&format!(
+            "/streams/{}/subscriptions/{}/lag",
+            stream_id, subscription_id
+        ))
+        .await
+    }
+
+    /// Move every subscription on a stream to `earliest` or `latest`
+    pub async fn seek_all_subscriptions(
+        &self,
+        stream_id: &str,
+        to: &str,
+    ) -> ApiResult<SeekAllResponse> {
+        let req = SeekAllRequest { to: to.to_string() };
+        self.post(
+            &format!("/streams/{}/subscriptions/seek-all", stream_id),
+            &req,
+        )
+        .await
+    }
+
     /// Poll for events
     pub async fn poll(
         &self,
@@ -240,6 +1196,100 @@ impl EventLedgerClient {
         self.get(&path).await
     }
 
+    /// Poll with `If-None-Match: <cursor>`; `None` means 304 Not Modified
+    pub async fn poll_if_none_match(
+        &self,
+        stream_id: &str,
+        subscription_id: &str,
+        cursor: &str,
+    ) -> ApiResult<Option<PollResponse>> {
+        let url = format!(
+            "{}/streams/{}/subscriptions/{}/poll",
+            self.base_url, stream_id, subscription_id
+        );
+        let request = self
+            .request(Method::GET, &url)
+            .header("If-None-Match", format!("\"{}\"", cursor));
+        let response = self.send(request).await?;
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+        self.handle_response(response).await.map(Some)
+    }
+
+    /// Read a stream without a subscription, continuing from `options.cursor`
+    pub async fn tail(&self, stream_id: &str, options: &TailOptions) -> ApiResult<PollResponse> {
+        self.get_with_query(&format!("/streams/{}/tail", stream_id), options)
+            .await
+    }
+
+    /// Poll for events with additional query options
+    pub async fn poll_with_options(
+        &self,
+        stream_id: &str,
+        subscription_id: &str,
+        options: &PollOptions,
+    ) -> ApiResult<PollResponse> {
+        self.get_with_query(
+            &format!(
+                "/streams/{}/subscriptions/{}/poll",
+                stream_id, subscription_id
+            ),
+            options,
+        )
+        .await
+    }
+
+    /// Poll with each event's data deserialized into `T`
+    ///
+    /// Fails with [`ApiError::Data`] if any event's data doesn't match `T`.
+    pub async fn poll_typed<T: DeserializeOwned>(
+        &self,
+        stream_id: &str,
+        subscription_id: &str,
+        options: &PollOptions,
+    ) -> ApiResult<TypedPollResponse<T>> {
+        let response = self
+            .poll_with_options(stream_id, subscription_id, options)
+            .await?;
+        let events = response
+            .events
+            .into_iter()
+            .map(|event| {
+                Ok(TypedEvent {
+                    data: event.data_as()?,
+                    event,
+                })
+            })
+            .collect::<ApiResult<_>>()?;
+
+        Ok(TypedPollResponse {
+            events,
+            cursor: response.cursor,
+            remaining: response.remaining,
+            caught_up: response.caught_up,
+            has_more: response.has_more,
+        })
+    }
+
+    /// Poll as raw JSON, for projected polls whose events are partial
+    pub async fn poll_json(
+        &self,
+        stream_id: &str,
+        subscription_id: &str,
+        options: &PollOptions,
+    ) -> ApiResult<serde_json::Value> {
+        self.get_with_query(
+            &format!(
+                "/streams/{}/subscriptions/{}/poll",
+                stream_id, subscription_id
+            ),
+            options,
+        )
+        .await
+    }
+
     /// Commit offset
     pub async fn commit(
         &self,
@@ -248,7 +1298,8 @@ impl EventLedgerClient {
         cursor: &str,
     ) -> ApiResult<CommitResponse> {
         let req = CommitRequest {
-            cursor: cursor.to_string(),
+            cursor: Some(cursor.to_string()),
+            ..Default::default()
         };
         self.post(
             &format!(
@@ -260,47 +1311,248 @@ impl EventLedgerClient {
         .await
     }
 
+    /// Commit a cursor and poll the next batch in one request
+    pub async fn commit_poll(
+        &self,
+        stream_id: &str,
+        subscription_id: &str,
+        cursor: &str,
+        options: &PollOptions,
+    ) -> ApiResult<PollResponse> {
+        let req = CommitRequest {
+            cursor: Some(cursor.to_string()),
+            ..Default::default()
+        };
+        let url = format!(
+            "{}/streams/{}/subscriptions/{}/commit-poll",
+            self.base_url, stream_id, subscription_id
+        );
+        let request = self.request(Method::POST, &url).query(options).json(&req);
+        let response = self.send(request).await?;
+
+        self.handle_response(response).await
+    }
+
+    /// Commit every event published at or before an RFC 3339 timestamp
+    pub async fn commit_up_to(
+        &self,
+        stream_id: &str,
+        subscription_id: &str,
+        up_to_timestamp: &str,
+    ) -> ApiResult<CommitResponse> {
+        let req = CommitRequest {
+            up_to_timestamp: Some(up_to_timestamp.to_string()),
+            ..Default::default()
+        };
+        self.post(
+            &format!(
+                "/streams/{}/subscriptions/{}/commit",
+                stream_id, subscription_id
+            ),
+            &req,
+        )
+        .await
+    }
+
+    /// Commit several subscriptions of a stream in one call
+    pub async fn commit_batch(
+        &self,
+        stream_id: &str,
+        commits: Vec<SubscriptionCommit>,
+    ) -> ApiResult<CommitBatchResponse> {
+        let req = CommitBatchRequest { commits };
+        self.post(&format!("/streams/{}/commit-batch", stream_id), &req)
+            .await
+    }
+
+    /// List a subscription's committed offsets
+    pub async fn list_offsets(
+        &self,
+        stream_id: &str,
+        subscription_id: &str,
+    ) -> ApiResult<ListOffsetsResponse> {
+        self.get(&format!(
+            "/streams/{}/subscriptions/{}/offsets",
+            stream_id, subscription_id
+        ))
+        .await
+    }
+
     // =========================================================================
     // HTTP Helpers
     // =========================================================================
 
+    fn request(&self, method: Method, url: &str) -> RequestBuilder {
+        let builder = self.client.request(method, url);
+        match &self.table_override {
+            Some(table_name) => builder.header("X-EventLedger-Table", table_name),
+            None => builder,
+        }
+    }
+
+    /// Send a request, waiting out `429 Too Many Requests` responses as their
+    /// `Retry-After` header asks before giving up
+    async fn send(&self, mut request: RequestBuilder) -> ApiResult<Response> {
+        let mut attempt = 0;
+        loop {
+            let retry = request.try_clone();
+            let response = request
+                .send()
+                .await
+                .map_err(|e| ApiError::Request(e.to_string()))?;
+            if response.status() != StatusCode::TOO_MANY_REQUESTS || attempt >= MAX_THROTTLE_RETRIES
+            {
+                return Ok(response);
+            }
+            // Streaming bodies can't be replayed; hand the 429 back instead
+            let Some(retry) = retry else {
+                return Ok(response);
+            };
+            let wait = response
+                .headers()
+                .get("Retry-After")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse::<u64>().ok())
+                .map_or(DEFAULT_RETRY_AFTER, Duration::from_secs)
+                .min(MAX_RETRY_AFTER);
+            tokio::time::sleep(wait).await;
+            request = retry;
+            attempt += 1;
+        }
+    }
+
     async fn get<T: DeserializeOwned>(&self, path: &str) -> ApiResult<T> {
         let url = format!("{}{}", self.base_url, path);
-        let response = self
-            .client
-            .get(&url)
-            .send()
-            .await
-            .map_err(|e| ApiError::Request(e.to_string()))?;
+        let request = self.request(Method::GET, &url);
+        let response = self.send(request).await?;
+
+        self.handle_response(response).await
+    }
+
+    async fn get_with_query<Q: Serialize + ?Sized, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &Q,
+    ) -> ApiResult<T> {
+        let url = format!("{}{}", self.base_url, path);
+        let request = self.request(Method::GET, &url).query(query);
+        let response = self.send(request).await?;
 
         self.handle_response(response).await
     }
 
     async fn post<B: Serialize, T: DeserializeOwned>(&self, path: &str, body: &B) -> ApiResult<T> {
         let url = format!("{}{}", self.base_url, path);
-        let response = self
-            .client
-            .post(&url)
-            .json(body)
-            .send()
-            .await
-            .map_err(|e| ApiError::Request(e.to_string()))?;
+        let request = self.request(Method::POST, &url).json(body);
+        let response = self.send(request).await?;
 
         self.handle_response(response).await
     }
 
+    /// GET a raw path and query string (for malformed query parameters)
+    pub async fn partition_preview(
+        &self,
+        req: &PartitionPreviewRequest,
+    ) -> ApiResult<PartitionDistribution> {
+        self.post("/streams/partition-preview", req).await
+    }
+
+    pub async fn get_raw<T: DeserializeOwned>(&self, path_and_query: &str) -> ApiResult<T> {
+        self.get(path_and_query).await
+    }
+
+    /// POST a raw body with an explicit content type (for malformed or non-JSON payloads)
+    pub async fn post_raw<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        content_type: &str,
+        body: impl Into<reqwest::Body>,
+    ) -> ApiResult<T> {
+        let url = format!("{}{}", self.base_url, path);
+        let request = self
+            .request(Method::POST, &url)
+            .header("Content-Type", content_type)
+            .body(body);
+        let response = self.send(request).await?;
+
+        self.handle_response(response).await
+    }
+
+    /// POST an already gzip-compressed body with `Content-Encoding: gzip`
+    pub async fn post_gzip<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        content_type: &str,
+        compressed: Vec<u8>,
+    ) -> ApiResult<T> {
+        let url = format!("{}{}", self.base_url, path);
+        let request = self
+            .request(Method::POST, &url)
+            .header("Content-Type", content_type)
+            .header("Content-Encoding", "gzip")
+            .body(compressed);
+        let response = self.send(request).await?;
+
+        self.handle_response(response).await
+    }
+
+    /// POST a body as CBOR, asking for a CBOR response
+    pub async fn post_cbor<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &impl Serialize,
+    ) -> ApiResult<T> {
+        let mut encoded = Vec::new();
+        ciborium::into_writer(body, &mut encoded).map_err(|e| ApiError::Request(e.to_string()))?;
+
+        let url = format!("{}{}", self.base_url, path);
+        let request = self
+            .request(Method::POST, &url)
+            .header("Content-Type", CBOR_CONTENT_TYPE)
+            .header("Accept", CBOR_CONTENT_TYPE)
+            .body(encoded);
+        let response = self.send(request).await?;
+
+        self.handle_cbor_response(response).await
+    }
+
+    /// GET with `Accept: application/cbor`
+    pub async fn get_cbor<T: DeserializeOwned>(&self, path_and_query: &str) -> ApiResult<T> {
+        let url = format!("{}{}", self.base_url, path_and_query);
+        let request = self
+            .request(Method::GET, &url)
+            .header("Accept", CBOR_CONTENT_TYPE);
+        let response = self.send(request).await?;
+
+        self.handle_cbor_response(response).await
+    }
+
     async fn delete<T: DeserializeOwned>(&self, path: &str) -> ApiResult<T> {
         let url = format!("{}{}", self.base_url, path);
-        let response = self
-            .client
-            .delete(&url)
-            .send()
-            .await
-            .map_err(|e| ApiError::Request(e.to_string()))?;
+        let request = self.request(Method::DELETE, &url);
+        let response = self.send(request).await?;
 
         self.handle_response(response).await
     }
 
+    /// Decode a CBOR success body; errors are JSON and kept as text
+    async fn handle_cbor_response<T: DeserializeOwned>(&self, response: Response) -> ApiResult<T> {
+        let status = response.status();
+        if !status.is_success() {
+            let body = response
+                .text()
+                .await
+                .map_err(|e| ApiError::Request(e.to_string()))?;
+            return Err(ApiError::Http { status, body });
+        }
+
+        let body = response
+            .bytes()
+            .await
+            .map_err(|e| ApiError::Request(e.to_string()))?;
+        ciborium::from_reader(body.as_ref()).map_err(|e| ApiError::Request(e.to_string()))
+    }
+
     async fn handle_response<T: DeserializeOwned>(&self, response: Response) -> ApiResult<T> {
         let status = response.status();
         let body = response
@@ -1,20 +1,31 @@
 //! Test fixtures and utilities
 
+use aws_config::BehaviorVersion;
+use aws_sdk_dynamodb::config::{Credentials, Region};
+use aws_sdk_dynamodb::types::{
+    AttributeDefinition, BillingMode, GlobalSecondaryIndex, KeySchemaElement, KeyType, Projection,
+    ProjectionType, ScalarAttributeType, StreamSpecification, StreamViewType, TableStatus,
+};
+use aws_sdk_dynamodb::Client;
+use std::time::Duration;
+use tokio::time::Instant;
 use uuid::Uuid;
 
+use crate::client::{CompactedEvent, CompactedQuery, EventLedgerClient};
+
 /// Generate a unique stream ID for testing
 pub fn unique_stream_id() -> String {
-    format!("test-stream-{}", Uuid::new_v4().to_string()[..8].to_string())
+    format!("test-stream-{}", &Uuid::new_v4().to_string()[..8])
 }
 
 /// Generate a unique subscription ID for testing
 pub fn unique_subscription_id() -> String {
-    format!("test-sub-{}", Uuid::new_v4().to_string()[..8].to_string())
+    format!("test-sub-{}", &Uuid::new_v4().to_string()[..8])
 }
 
 /// Generate a unique event key for testing
 pub fn unique_key() -> String {
-    format!("key-{}", Uuid::new_v4().to_string()[..8].to_string())
+    format!("key-{}", &Uuid::new_v4().to_string()[..8])
 }
 
 /// Check if API URL is configured
@@ -32,3 +43,191 @@ macro_rules! skip_if_no_api {
         }
     };
 }
+
+// ============================================================================
+// Compaction
+// ============================================================================
+
+/// How often [`await_compacted`] re-reads compacted state
+const AWAIT_COMPACTED_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Wait until the compactor has applied `key`'s event at `expected_sequence`
+/// (or a later one) and return the compacted entry
+///
+/// Compaction runs asynchronously off the table's stream, so tests wait on
+/// this rather than sleeping and hoping it ran. Fails once `timeout` passes.
+pub async fn await_compacted(
+    client: &EventLedgerClient,
+    stream_id: &str,
+    key: &str,
+    expected_sequence: u64,
+    timeout: Duration,
+) -> Result<CompactedEvent, String> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let mut query = CompactedQuery {
+            since_sequence: expected_sequence.checked_sub(1),
+            ..Default::default()
+        };
+        loop {
+            let page = client
+                .list_compacted(stream_id, &query)
+                .await
+                .map_err(|e| format!("Failed to read compacted state of {}: {}", stream_id, e))?;
+            if let Some(entry) = page.events.into_iter().find(|e| e.key == key) {
+                return Ok(entry);
+            }
+            match page.next_start_key {
+                Some(start_key) => query.start_key = Some(start_key),
+                None => break,
+            }
+        }
+
+        let now = Instant::now();
+        if now >= deadline {
+            return Err(format!(
+                "Key '{}' on stream {} wasn't compacted at sequence {} or later within {:?}",
+                key, stream_id, expected_sequence, timeout
+            ));
+        }
+        tokio::time::sleep(AWAIT_COMPACTED_INTERVAL.min(deadline - now)).await;
+    }
+}
+
+// ============================================================================
+// Local DynamoDB
+// ============================================================================
+
+/// Local DynamoDB endpoint (e.g. `http://localhost:8000`), if configured
+pub fn local_dynamo_endpoint() -> Option<String> {
+    std::env::var("EVENTLEDGER_DYNAMO_ENDPOINT").ok()
+}
+
+/// Build a DynamoDB client pointed at a local endpoint with dummy credentials
+pub async fn local_dynamo_client(endpoint: &str) -> Client {
+    let config = aws_config::defaults(BehaviorVersion::latest())
+        .endpoint_url(endpoint)
+        .region(Region::new("us-west-2"))
+        .credentials_provider(Credentials::new("local", "local", None, None, "local"))
+        .load()
+        .await;
+    Client::new(&config)
+}
+
+/// Create the single EventLedger table if it doesn't exist and wait until it's active
+///
+/// Mirrors the deployed table: string PK/SK keys, the sparse streams and compacted-time GSIs,
+/// on-demand billing, and a NEW_IMAGE stream for the compactor. Safe to call
+/// repeatedly.
+pub async fn ensure_local_table(client: &Client, table_name: &str) -> Result<(), String> {
+    let exists = match client.describe_table().table_name(table_name).send().await {
+        Ok(_) => true,
+        Err(e)
+            if e.as_service_error()
+                .is_some_and(|e| e.is_resource_not_found_exception()) =>
+        {
+            false
+        }
+        Err(e) => return Err(format!("Failed to describe table: {}", e)),
+    };
+
+    if !exists {
+        let result = client
+            .create_table()
+            .table_name(table_name)
+            .attribute_definitions(string_attribute("PK")?)
+            .attribute_definitions(string_attribute("SK")?)
+            .key_schema(key_element("PK", KeyType::Hash)?)
+            .key_schema(key_element("SK", KeyType::Range)?)
+            .attribute_definitions(string_attribute("streams_pk")?)
+            .attribute_definitions(string_attribute("stream_id")?)
+            .attribute_definitions(number_attribute("timestamp_ns")?)
+            .global_secondary_indexes(
+                GlobalSecondaryIndex::builder()
+                    .index_name("streams-index")
+                    .key_schema(key_element("streams_pk", KeyType::Hash)?)
+                    .key_schema(key_element("stream_id", KeyType::Range)?)
+                    .projection(
+                        Projection::builder()
+                            .projection_type(ProjectionType::All)
+                            .build(),
+                    )
+                    .build()
+                    .map_err(|e| e.to_string())?,
+            )
+            .global_secondary_indexes(
+                GlobalSecondaryIndex::builder()
+                    .index_name("compacted-time-index")
+                    .key_schema(key_element("PK", KeyType::Hash)?)
+                    .key_schema(key_element("timestamp_ns", KeyType::Range)?)
+                    .projection(
+                        Projection::builder()
+                            .projection_type(ProjectionType::All)
+                            .build(),
+                    )
+                    .build()
+                    .map_err(|e| e.to_string())?,
+            )
+            .billing_mode(BillingMode::PayPerRequest)
+            .stream_specification(
+                StreamSpecification::builder()
+                    .stream_enabled(true)
+                    .stream_view_type(StreamViewType::NewImage)
+                    .build()
+                    .map_err(|e| e.to_string())?,
+            )
+            .send()
+            .await;
+
+        // Another test may have created it concurrently
+        if let Err(e) = result {
+            if !e
+                .as_service_error()
+                .is_some_and(|e| e.is_resource_in_use_exception())
+            {
+                return Err(format!("Failed to create table: {}", e));
+            }
+        }
+    }
+
+    // Wait until the table is usable
+    for _ in 0..50 {
+        let table = client
+            .describe_table()
+            .table_name(table_name)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to describe table: {}", e))?;
+
+        if table.table().and_then(|t| t.table_status()) == Some(&TableStatus::Active) {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+
+    Err(format!("Table {} did not become active", table_name))
+}
+
+fn string_attribute(name: &str) -> Result<AttributeDefinition, String> {
+    AttributeDefinition::builder()
+        .attribute_name(name)
+        .attribute_type(ScalarAttributeType::S)
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+fn number_attribute(name: &str) -> Result<AttributeDefinition, String> {
+    AttributeDefinition::builder()
+        .attribute_name(name)
+        .attribute_type(ScalarAttributeType::N)
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+fn key_element(name: &str, key_type: KeyType) -> Result<KeySchemaElement, String> {
+    KeySchemaElement::builder()
+        .attribute_name(name)
+        .key_type(key_type)
+        .build()
+        .map_err(|e| e.to_string())
+}
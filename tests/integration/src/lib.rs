@@ -6,8 +6,10 @@
 //!
 //! Run with: cargo test --package eventledger-integration-tests
 
+pub mod batcher;
 pub mod client;
 pub mod fixtures;
 
+pub use batcher::Batcher;
 pub use client::EventLedgerClient;
 pub use fixtures::*;
@@ -4,15 +4,23 @@
 //!
 //! These tests require a deployed EventLedger instance.
 
+use eventledger_core::{parse_event_id, MAX_BATCH_GET_STREAMS, MAX_PUBLISH_BATCH};
 use eventledger_integration_tests::{
     client::{
-        ApiError, CreateStreamRequest, CreateSubscriptionRequest, EventLedgerClient, PublishEvent,
+        ApiError, CompactedQuery, CreateStreamRequest, CreateSubscriptionRequest, CursorEncoding,
+        DetailedPublishEvent, EmptyKeyStrategy, ErrorResponse, EventLedgerClient,
+        ListStreamsResponse, PartitionPreviewRequest, PollOptions, PollResponse, PublishEvent,
+        PublishResponse, StartFrom, StreamPublish, SubscriptionCommit, TailOptions,
+        UpdateStreamRequest,
     },
-    fixtures::{unique_key, unique_stream_id, unique_subscription_id},
-    skip_if_no_api,
+    fixtures::{await_compacted, unique_key, unique_stream_id, unique_subscription_id},
 };
+use flate2::{write::GzEncoder, Compression};
 use pretty_assertions::assert_eq;
+use serde::Deserialize;
 use serde_json::json;
+use std::collections::HashMap;
+use std::io::Write;
 
 /// Helper to get client or skip test
 fn get_client() -> Option<EventLedgerClient> {
@@ -25,6 +33,20 @@ fn get_client() -> Option<EventLedgerClient> {
     }
 }
 
+/// Assert a request failed with a 400 validation error and return its body
+fn expect_validation_error<T: std::fmt::Debug>(result: Result<T, ApiError>) -> ErrorResponse {
+    match result {
+        Err(ApiError::Http { status, body }) => {
+            assert_eq!(status.as_u16(), 400, "unexpected status, body: {}", body);
+            let error: ErrorResponse =
+                serde_json::from_str(&body).expect("Error body should be JSON");
+            assert_eq!(error.error, "validation_error");
+            error
+        }
+        other => panic!("Expected HTTP 400, got {:?}", other),
+    }
+}
+
 // ============================================================================
 // Stream Tests
 // ============================================================================
@@ -41,6 +63,7 @@ async fn test_create_stream() {
             stream_id: stream_id.clone(),
             partition_count: Some(3),
             retention_hours: Some(24),
+            ..Default::default()
         })
         .await
         .expect("Failed to create stream");
@@ -65,6 +88,7 @@ async fn test_create_stream_defaults() {
             stream_id: stream_id.clone(),
             partition_count: None,
             retention_hours: None,
+            ..Default::default()
         })
         .await
         .expect("Failed to create stream");
@@ -89,6 +113,7 @@ async fn test_create_duplicate_stream_fails() {
             stream_id: stream_id.clone(),
             partition_count: None,
             retention_hours: None,
+            ..Default::default()
         })
         .await
         .expect("Failed to create stream");
@@ -99,6 +124,7 @@ async fn test_create_duplicate_stream_fails() {
             stream_id: stream_id.clone(),
             partition_count: None,
             retention_hours: None,
+            ..Default::default()
         })
         .await;
 
@@ -112,6 +138,61 @@ async fn test_create_duplicate_stream_fails() {
     let _ = client.delete_stream(&stream_id).await;
 }
 
+#[tokio::test]
+async fn test_create_stream_malformed_json_returns_400() {
+    let Some(client) = get_client() else { return };
+
+    let result = client
+        .post_raw::<serde_json::Value>("/streams", "application/json", r#"{"stream_id": "#)
+        .await;
+
+    let error = expect_validation_error(result);
+    let details = error.details.expect("Expected parse details");
+    assert_eq!(details["line"], 1);
+    assert_eq!(details["category"], "eof");
+}
+
+#[tokio::test]
+async fn test_table_override_header_isolates_requests() {
+    let Some(client) = get_client() else { return };
+    // Requires a deployment with EVENTLEDGER_ALLOW_TABLE_OVERRIDE=true and a second table
+    let Ok(table_name) = std::env::var("EVENTLEDGER_OVERRIDE_TABLE") else {
+        eprintln!("Skipping: EVENTLEDGER_OVERRIDE_TABLE not set");
+        return;
+    };
+    let isolated = EventLedgerClient::new(&std::env::var("EVENTLEDGER_API_URL").unwrap())
+        .with_table_override(&table_name);
+
+    let stream_id = unique_stream_id();
+
+    // Create stream in the overridden table
+    isolated
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(1),
+            retention_hours: None,
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    // Visible through the override, invisible in the default table
+    isolated
+        .get_stream(&stream_id)
+        .await
+        .expect("Stream should exist in overridden table");
+
+    let result = client.get_stream(&stream_id).await;
+    if let Err(ApiError::Http { status, .. }) = &result {
+        assert_eq!(status.as_u16(), 404);
+    } else {
+        panic!("Stream should not exist in default table");
+    }
+
+    // Cleanup
+    let _ = isolated.delete_stream(&stream_id).await;
+}
+
 #[tokio::test]
 async fn test_get_stream() {
     let Some(client) = get_client() else { return };
@@ -124,6 +205,7 @@ async fn test_get_stream() {
             stream_id: stream_id.clone(),
             partition_count: Some(5),
             retention_hours: None,
+            ..Default::default()
         })
         .await
         .expect("Failed to create stream");
@@ -165,6 +247,7 @@ async fn test_list_streams() {
             stream_id: stream_id.clone(),
             partition_count: None,
             retention_hours: None,
+            ..Default::default()
         })
         .await
         .expect("Failed to create stream");
@@ -179,6 +262,129 @@ async fn test_list_streams() {
     let _ = client.delete_stream(&stream_id).await;
 }
 
+#[tokio::test]
+async fn test_list_streams_by_prefix() {
+    let Some(client) = get_client() else { return };
+
+    // Namespaces unique to this run, so other streams can't match
+    let namespace = unique_stream_id();
+    let prod: Vec<String> = (0..2)
+        .map(|i| format!("{}-prod-{}", namespace, i))
+        .collect();
+    let staging = format!("{}-staging-0", namespace);
+    for stream_id in prod.iter().chain([&staging]) {
+        client
+            .create_stream(&CreateStreamRequest {
+                stream_id: stream_id.clone(),
+                ..Default::default()
+            })
+            .await
+            .expect("Failed to create stream");
+    }
+
+    let response = client
+        .list_streams_with_prefix(&format!("{}-prod-", namespace))
+        .await
+        .expect("Failed to list streams");
+    let mut listed: Vec<String> = response.streams.into_iter().map(|s| s.stream_id).collect();
+    listed.sort();
+    assert_eq!(listed, prod);
+
+    // One stream per page, in ID order
+    let prefix = format!("{}-prod-", namespace);
+    let first = client
+        .list_streams_page(&prefix, 1, None)
+        .await
+        .expect("Failed to list streams");
+    let next = first.next_start_key.expect("A second page should remain");
+    let second = client
+        .list_streams_page(&prefix, 1, Some(&next))
+        .await
+        .expect("Failed to list streams");
+    let paged: Vec<String> = first
+        .streams
+        .into_iter()
+        .chain(second.streams)
+        .map(|s| s.stream_id)
+        .collect();
+    assert_eq!(paged, prod);
+
+    // Cleanup
+    for stream_id in prod.iter().chain([&staging]) {
+        let _ = client.delete_stream(stream_id).await;
+    }
+}
+
+#[tokio::test]
+async fn test_delete_stream_with_subscriptions_requires_force() {
+    let Some(client) = get_client() else { return };
+
+    let (stream_id, _) = setup_poll_mode_stream(&client, 1).await;
+
+    match client.delete_stream(&stream_id).await {
+        Err(ApiError::Http { status, body }) => {
+            assert_eq!(status.as_u16(), 409, "body: {}", body);
+            let error: ErrorResponse = serde_json::from_str(&body).unwrap();
+            assert_eq!(error.error, "stream_in_use");
+        }
+        other => panic!("Expected HTTP 409, got {:?}", other),
+    }
+    client
+        .get_stream(&stream_id)
+        .await
+        .expect("Refused delete should leave the stream in place");
+
+    let response = client
+        .force_delete_stream(&stream_id)
+        .await
+        .expect("Forced delete should succeed");
+    assert!(response.success);
+    assert!(client.get_stream(&stream_id).await.is_err());
+}
+
+#[tokio::test]
+async fn test_get_streams_by_ids() {
+    let Some(client) = get_client() else { return };
+
+    let first = unique_stream_id();
+    let second = unique_stream_id();
+    for stream_id in [&first, &second] {
+        client
+            .create_stream(&CreateStreamRequest {
+                stream_id: stream_id.clone(),
+                ..Default::default()
+            })
+            .await
+            .expect("Failed to create stream");
+    }
+
+    let missing = unique_stream_id();
+    let response = client
+        .get_streams(&[&second, &missing, &first])
+        .await
+        .expect("Failed to get streams");
+
+    // Requested order, without the stream that doesn't exist
+    let ids: Vec<&str> = response
+        .streams
+        .iter()
+        .map(|s| s.stream_id.as_str())
+        .collect();
+    assert_eq!(ids, vec![second.as_str(), first.as_str()]);
+
+    // One BatchGetItem's worth of IDs at most
+    let too_many: Vec<String> = (0..=MAX_BATCH_GET_STREAMS)
+        .map(|_| unique_stream_id())
+        .collect();
+    let too_many: Vec<&str> = too_many.iter().map(String::as_str).collect();
+    let error = expect_validation_error(client.get_streams(&too_many).await);
+    assert!(error.message.contains("At most"));
+
+    // Cleanup
+    let _ = client.delete_stream(&first).await;
+    let _ = client.delete_stream(&second).await;
+}
+
 #[tokio::test]
 async fn test_delete_stream() {
     let Some(client) = get_client() else { return };
@@ -191,6 +397,7 @@ async fn test_delete_stream() {
             stream_id: stream_id.clone(),
             partition_count: None,
             retention_hours: None,
+            ..Default::default()
         })
         .await
         .expect("Failed to create stream");
@@ -208,6 +415,55 @@ async fn test_delete_stream() {
     assert!(result.is_err());
 }
 
+#[tokio::test]
+async fn test_stream_stats_counts_published_events() {
+    let Some(client) = get_client() else { return };
+
+    let stream_id = unique_stream_id();
+
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(3),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    let events = (0..5)
+        .map(|i| PublishEvent {
+            key: format!("key-{}", i),
+            event_type: "test.event".to_string(),
+            data: json!({ "i": i }),
+        })
+        .collect();
+    client
+        .publish_events(&stream_id, events)
+        .await
+        .expect("Failed to publish events");
+
+    let stats = client
+        .stream_stats(&stream_id)
+        .await
+        .expect("Failed to get stats");
+
+    assert_eq!(stats.partition_count, 3);
+    assert_eq!(stats.partitions.len(), 3);
+    assert_eq!(stats.approximate_event_count, 5);
+    assert_eq!(stats.partitions.iter().map(|p| p.offset).sum::<u64>(), 5);
+
+    // Time ranges are reported exactly for the partitions holding events
+    for partition in &stats.partitions {
+        assert_eq!(partition.earliest_timestamp.is_some(), partition.offset > 0);
+        assert_eq!(partition.latest_timestamp.is_some(), partition.offset > 0);
+    }
+    assert!(stats.earliest_timestamp.is_some());
+    assert!(stats.latest_timestamp.is_some());
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
 // ============================================================================
 // Event Tests
 // ============================================================================
@@ -225,6 +481,7 @@ async fn test_publish_single_event() {
             stream_id: stream_id.clone(),
             partition_count: Some(3),
             retention_hours: None,
+            ..Default::default()
         })
         .await
         .expect("Failed to create stream");
@@ -266,6 +523,7 @@ async fn test_publish_batch_events() {
             stream_id: stream_id.clone(),
             partition_count: Some(3),
             retention_hours: None,
+            ..Default::default()
         })
         .await
         .expect("Failed to create stream");
@@ -301,280 +559,3559 @@ async fn test_publish_batch_events() {
 }
 
 #[tokio::test]
-async fn test_publish_to_nonexistent_stream_fails() {
+async fn test_publish_malformed_json_returns_400() {
     let Some(client) = get_client() else { return };
 
-    let event = PublishEvent {
-        key: unique_key(),
+    let stream_id = unique_stream_id();
+
+    // Create stream
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(1),
+            retention_hours: None,
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    // Missing the required "type" field
+    let result = client
+        .post_raw::<serde_json::Value>(
+            &format!("/streams/{}/events", stream_id),
+            "application/json",
+            r#"{"key": "order-1", "data": {}}"#,
+        )
+        .await;
+
+    let error = expect_validation_error(result);
+    assert!(error.message.contains("missing field `type`"));
+    let details = error.details.expect("Expected parse details");
+    assert_eq!(details["category"], "data");
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_publish_rejects_malformed_event_type() {
+    let Some(client) = get_client() else { return };
+
+    let stream_id = unique_stream_id();
+
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(1),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    let result = client
+        .post_raw::<serde_json::Value>(
+            &format!("/streams/{}/events", stream_id),
+            "application/json",
+            r#"{"key": "order-1", "type": "Order Created", "data": {}}"#,
+        )
+        .await;
+
+    let error = expect_validation_error(result);
+    assert!(error.message.contains("Invalid event type"));
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_publish_batch_across_streams() {
+    let Some(client) = get_client() else { return };
+
+    let orders = unique_stream_id();
+    let payments = unique_stream_id();
+    for stream_id in [&orders, &payments] {
+        client
+            .create_stream(&CreateStreamRequest {
+                stream_id: stream_id.clone(),
+                partition_count: Some(1),
+                ..Default::default()
+            })
+            .await
+            .expect("Failed to create stream");
+    }
+
+    let event = |key: &str| PublishEvent {
+        key: key.to_string(),
         event_type: "test.event".to_string(),
         data: json!({}),
     };
+    let missing = unique_stream_id();
+    let response = client
+        .publish_batch(vec![
+            StreamPublish {
+                stream_id: orders.clone(),
+                events: vec![event("order-1"), event("order-2")],
+            },
+            StreamPublish {
+                stream_id: missing.clone(),
+                events: vec![event("lost-1")],
+            },
+            StreamPublish {
+                stream_id: payments.clone(),
+                events: vec![event("payment-1")],
+            },
+        ])
+        .await
+        .expect("Failed to publish batch");
 
-    let result = client
-        .publish_event("nonexistent-stream-12345", event)
-        .await;
+    // A missing stream fails on its own without stopping the others
+    assert_eq!(response.results.len(), 3);
+    assert!(response.results[0].success);
+    assert_eq!(response.results[0].events.len(), 2);
+    assert!(!response.results[1].success);
+    assert_eq!(
+        response.results[1].error.as_ref().unwrap().error,
+        "stream_not_found"
+    );
+    assert!(response.results[2].success);
 
-    assert!(result.is_err());
-    if let Err(ApiError::Http { status, .. }) = result {
-        assert_eq!(status.as_u16(), 404);
+    for (stream_id, expected) in [(&orders, 2), (&payments, 1)] {
+        let stats = client
+            .stream_stats(stream_id)
+            .await
+            .expect("Failed to get stats");
+        assert_eq!(stats.approximate_event_count, expected);
     }
-}
 
-// ============================================================================
-// Subscription Tests
-// ============================================================================
+    // Cleanup
+    let _ = client.delete_stream(&orders).await;
+    let _ = client.delete_stream(&payments).await;
+}
 
 #[tokio::test]
-async fn test_create_subscription() {
+async fn test_publish_gzip_body() {
     let Some(client) = get_client() else { return };
 
     let stream_id = unique_stream_id();
-    let subscription_id = unique_subscription_id();
 
-    // Create stream
     client
         .create_stream(&CreateStreamRequest {
             stream_id: stream_id.clone(),
-            partition_count: Some(3),
-            retention_hours: None,
+            partition_count: Some(1),
+            ..Default::default()
         })
         .await
         .expect("Failed to create stream");
 
-    // Create subscription
-    let subscription = client
+    let body = json!([
+        { "key": "order-1", "type": "order.created", "data": { "total": 10 } },
+        { "key": "order-2", "type": "order.created", "data": { "total": 20 } },
+    ]);
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(body.to_string().as_bytes()).unwrap();
+    let compressed = encoder.finish().unwrap();
+
+    let response: PublishResponse = client
+        .post_gzip(
+            &format!("/streams/{}/events", stream_id),
+            "application/json",
+            compressed,
+        )
+        .await
+        .expect("Failed to publish gzip body");
+
+    let keys: Vec<&str> = response.events.iter().map(|e| e.key.as_str()).collect();
+    assert_eq!(keys, vec!["order-1", "order-2"]);
+
+    // Same content as the uncompressed form
+    let subscription_id = unique_subscription_id();
+    client
         .create_subscription(
             &stream_id,
             &CreateSubscriptionRequest {
                 subscription_id: subscription_id.clone(),
-                start_from: Some("earliest".to_string()),
+                start_from: Some(StartFrom::Earliest),
+                ..Default::default()
             },
         )
         .await
         .expect("Failed to create subscription");
-
-    assert_eq!(subscription.stream_id, stream_id);
-    assert_eq!(subscription.subscription_id, subscription_id);
+    let polled = client
+        .poll(&stream_id, &subscription_id, None)
+        .await
+        .expect("Failed to poll");
+    assert_eq!(polled.events[0].data, json!({ "total": 10 }));
+    assert_eq!(polled.events[1].data, json!({ "total": 20 }));
 
     // Cleanup
     let _ = client.delete_stream(&stream_id).await;
 }
 
-// ============================================================================
-// Poll and Commit Tests
-// ============================================================================
-
 #[tokio::test]
-async fn test_poll_empty_stream() {
+async fn test_cbor_publish_and_poll_match_json() {
     let Some(client) = get_client() else { return };
 
     let stream_id = unique_stream_id();
-    let subscription_id = unique_subscription_id();
 
-    // Create stream
     client
         .create_stream(&CreateStreamRequest {
             stream_id: stream_id.clone(),
-            partition_count: Some(3),
-            retention_hours: None,
+            partition_count: Some(2),
+            ..Default::default()
         })
         .await
         .expect("Failed to create stream");
 
-    // Create subscription
+    let body = json!([
+        { "key": "order-1", "type": "order.created", "data": { "total": 10.5, "tags": ["a"] } },
+        { "key": "order-2", "type": "order.created", "data": { "total": 20 } },
+    ]);
+    let published: PublishResponse = client
+        .post_cbor(&format!("/streams/{}/events", stream_id), &body)
+        .await
+        .expect("Failed to publish CBOR body");
+    assert_eq!(published.events.len(), 2);
+
+    let subscription_id = unique_subscription_id();
     client
         .create_subscription(
             &stream_id,
             &CreateSubscriptionRequest {
                 subscription_id: subscription_id.clone(),
-                start_from: Some("earliest".to_string()),
+                start_from: Some(StartFrom::Earliest),
+                ..Default::default()
             },
         )
         .await
         .expect("Failed to create subscription");
 
-    // Poll
-    let response = client
-        .poll(&stream_id, &subscription_id, Some(10))
+    // A peek poll returns the same window either way
+    let as_json = client
+        .poll(&stream_id, &subscription_id, None)
         .await
         .expect("Failed to poll");
+    let as_cbor: PollResponse = client
+        .get_cbor(&format!(
+            "/streams/{}/subscriptions/{}/poll",
+            stream_id, subscription_id
+        ))
+        .await
+        .expect("Failed to poll as CBOR");
 
-    assert!(response.events.is_empty());
-    assert!(!response.cursor.is_empty());
+    assert_eq!(as_cbor.cursor, as_json.cursor);
+    let summary = |poll: &PollResponse| {
+        poll.events
+            .iter()
+            .map(|e| {
+                (
+                    e.id.clone(),
+                    e.key.clone(),
+                    e.data.clone(),
+                    e.timestamp.clone(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(summary(&as_cbor), summary(&as_json));
+    assert_eq!(as_json.events.len(), 2);
 
     // Cleanup
     let _ = client.delete_stream(&stream_id).await;
 }
 
 #[tokio::test]
-async fn test_full_publish_poll_commit_cycle() {
+async fn test_publish_ndjson() {
+    let Some(client) = get_client() else { return };
+
+    let stream_id = unique_stream_id();
+
+    // Create stream
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(3),
+            retention_hours: None,
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    let body = [
+        r#"{"key": "order-1", "type": "order.created", "data": {"n": 1}}"#,
+        r#"{"key": "order-2", "type": "order.created", "data": {"n": 2}}"#,
+        r#"{"key": "order-3", "type": "order.created", "data": {"n": 3}}"#,
+    ]
+    .join("\n");
+
+    let response: serde_json::Value = client
+        .post_raw(
+            &format!("/streams/{}/events", stream_id),
+            "application/x-ndjson",
+            body,
+        )
+        .await
+        .expect("Failed to publish NDJSON");
+
+    assert_eq!(response["events"].as_array().unwrap().len(), 3);
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_publish_ndjson_rejects_malformed_line() {
     let Some(client) = get_client() else { return };
 
     let stream_id = unique_stream_id();
     let subscription_id = unique_subscription_id();
-    let key = unique_key();
 
     // Create stream
     client
         .create_stream(&CreateStreamRequest {
             stream_id: stream_id.clone(),
-            partition_count: Some(1), // Single partition for ordered test
+            partition_count: Some(1),
             retention_hours: None,
+            ..Default::default()
         })
         .await
         .expect("Failed to create stream");
 
-    // Create subscription starting from earliest
     client
         .create_subscription(
             &stream_id,
             &CreateSubscriptionRequest {
                 subscription_id: subscription_id.clone(),
-                start_from: Some("earliest".to_string()),
+                start_from: Some(StartFrom::Earliest),
+                ..Default::default()
             },
         )
         .await
         .expect("Failed to create subscription");
 
-    // Publish events
-    for i in 1..=5 {
-        client
-            .publish_event(
+    // Second line is malformed
+    let body = [
+        r#"{"key": "order-1", "type": "order.created", "data": {}}"#,
+        r#"{"key": "order-2", "type": "order.created""#,
+        r#"{"key": "order-3", "type": "order.created", "data": {}}"#,
+    ]
+    .join("\n");
+
+    let result = client
+        .post_raw::<serde_json::Value>(
+            &format!("/streams/{}/events", stream_id),
+            "application/x-ndjson",
+            body,
+        )
+        .await;
+
+    let error = expect_validation_error(result);
+    assert!(error.message.contains("no events were published"));
+    let details = error.details.expect("Expected line details");
+    assert_eq!(details["line"], 2);
+    assert_eq!(details["published"], 0);
+
+    // The whole body was rejected, including the valid lines
+    let poll_response = client
+        .poll(&stream_id, &subscription_id, Some(10))
+        .await
+        .expect("Failed to poll");
+    assert!(poll_response.events.is_empty());
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_update_stream_with_matching_etag() {
+    let Some(client) = get_client() else { return };
+
+    let stream_id = unique_stream_id();
+    let stream = client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            retention_hours: Some(24),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    let etag = format!("\"{}\"", stream.version);
+    let updated = client
+        .update_stream(
+            &stream_id,
+            Some(&etag),
+            &UpdateStreamRequest {
+                retention_hours: Some(48),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Failed to update stream");
+    assert_eq!(updated.retention_hours, 48);
+    assert_eq!(updated.version, stream.version + 1);
+
+    let fetched = client
+        .get_stream(&stream_id)
+        .await
+        .expect("Failed to get stream");
+    assert_eq!(fetched.retention_hours, 48);
+    assert_eq!(fetched.version, updated.version);
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_update_stream_rejects_stale_etag() {
+    let Some(client) = get_client() else { return };
+
+    let stream_id = unique_stream_id();
+    let stream = client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+    let stale = format!("\"{}\"", stream.version);
+
+    // The first operator's change wins
+    client
+        .update_stream(
+            &stream_id,
+            Some(&stale),
+            &UpdateStreamRequest {
+                retention_hours: Some(12),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Failed to update stream");
+
+    // The second, still holding the old ETag, is rejected
+    let result = client
+        .update_stream(
+            &stream_id,
+            Some(&stale),
+            &UpdateStreamRequest {
+                retention_hours: Some(96),
+                ..Default::default()
+            },
+        )
+        .await;
+    match result {
+        Err(ApiError::Http { status, .. }) => assert_eq!(status.as_u16(), 412),
+        other => panic!("Expected 412, got {:?}", other),
+    }
+
+    // Without If-Match at all the update is refused too
+    let result = client
+        .update_stream(
+            &stream_id,
+            None,
+            &UpdateStreamRequest {
+                retention_hours: Some(96),
+                ..Default::default()
+            },
+        )
+        .await;
+    match result {
+        Err(ApiError::Http { status, .. }) => assert_eq!(status.as_u16(), 428),
+        other => panic!("Expected 428, got {:?}", other),
+    }
+
+    let fetched = client
+        .get_stream(&stream_id)
+        .await
+        .expect("Failed to get stream");
+    assert_eq!(fetched.retention_hours, 12);
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_stream_tags_filter_and_update() {
+    let Some(client) = get_client() else { return };
+
+    let tags = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    };
+    let payments_id = unique_stream_id();
+    let payments = client
+        .create_stream(&CreateStreamRequest {
+            stream_id: payments_id.clone(),
+            tags: Some(tags(&[("team", "payments"), ("env", "test")])),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+    assert_eq!(
+        payments.tags,
+        tags(&[("team", "payments"), ("env", "test")])
+    );
+
+    let search_id = unique_stream_id();
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: search_id.clone(),
+            tags: Some(tags(&[("team", "search"), ("env", "test")])),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    let listed_ids = |response: ListStreamsResponse| -> Vec<String> {
+        response.streams.into_iter().map(|s| s.stream_id).collect()
+    };
+    let listed = listed_ids(
+        client
+            .list_streams_tagged(&["team:payments"])
+            .await
+            .unwrap(),
+    );
+    assert!(listed.contains(&payments_id));
+    assert!(!listed.contains(&search_id));
+
+    // Several filters must all match
+    let both = client
+        .list_streams_tagged(&["env:test", "team:search"])
+        .await
+        .unwrap();
+    let listed = listed_ids(both);
+    assert!(listed.contains(&search_id));
+    assert!(!listed.contains(&payments_id));
+
+    // An update replaces the whole tag set
+    let updated = client
+        .update_stream(
+            &payments_id,
+            Some(&format!("\"{}\"", payments.version)),
+            &UpdateStreamRequest {
+                tags: Some(tags(&[("team", "billing")])),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Failed to update tags");
+    assert_eq!(updated.tags, tags(&[("team", "billing")]));
+
+    let listed = listed_ids(
+        client
+            .list_streams_tagged(&["team:payments"])
+            .await
+            .unwrap(),
+    );
+    assert!(!listed.contains(&payments_id));
+    let listed = listed_ids(client.list_streams_tagged(&["team:billing"]).await.unwrap());
+    assert!(listed.contains(&payments_id));
+
+    // Cleanup
+    let _ = client.delete_stream(&payments_id).await;
+    let _ = client.delete_stream(&search_id).await;
+}
+
+#[tokio::test]
+async fn test_partition_preview_histogram_covers_all_keys() {
+    let Some(client) = get_client() else { return };
+
+    let keys: Vec<String> = (0..500).map(|i| format!("tenant-{}", i)).collect();
+    let preview = client
+        .partition_preview(&PartitionPreviewRequest {
+            partition_count: 8,
+            keys: keys.clone(),
+        })
+        .await
+        .expect("Failed to preview partitions");
+
+    assert_eq!(preview.partition_count, 8);
+    assert_eq!(preview.key_count, 500);
+    assert_eq!(preview.histogram.len(), 8);
+    assert_eq!(preview.histogram.iter().sum::<u64>(), keys.len() as u64);
+    assert!(preview.skew >= 1.0);
+
+    let error = expect_validation_error(
+        client
+            .partition_preview(&PartitionPreviewRequest {
+                partition_count: 0,
+                keys,
+            })
+            .await,
+    );
+    assert!(error.message.contains("partition_count"));
+}
+
+#[tokio::test]
+async fn test_partition_key_colocates_distinct_compaction_keys() {
+    let Some(client) = get_client() else { return };
+
+    let stream_id = unique_stream_id();
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(8),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    // Line items compact per item but stay ordered with their order
+    let order_key = unique_key();
+    let events = (0..2)
+        .map(|i| DetailedPublishEvent {
+            event: PublishEvent {
+                key: format!("{}-line-{}", order_key, i),
+                event_type: "test.event".to_string(),
+                data: json!({ "line": i }),
+            },
+            partition_key: Some(order_key.clone()),
+            ..Default::default()
+        })
+        .collect();
+    let response = client
+        .publish_detailed_events(&stream_id, events)
+        .await
+        .expect("Failed to publish events");
+
+    let expected = client
+        .partition_for(&stream_id, &order_key)
+        .await
+        .expect("Failed to get partition for key")
+        .partition;
+    assert_eq!(response.events[0].partition, expected);
+    assert_eq!(response.events[1].partition, expected);
+
+    // Both compaction keys survive compaction independently
+    client
+        .compact_stream(&stream_id)
+        .await
+        .expect("Failed to compact stream");
+    let compacted = client
+        .list_compacted(&stream_id, &CompactedQuery::default())
+        .await
+        .expect("Failed to list compacted state");
+    let keys: Vec<&str> = compacted.events.iter().map(|e| e.key.as_str()).collect();
+    assert_eq!(
+        keys,
+        vec![
+            format!("{}-line-0", order_key),
+            format!("{}-line-1", order_key)
+        ]
+    );
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_partition_for_matches_publish() {
+    let Some(client) = get_client() else { return };
+
+    let stream_id = unique_stream_id();
+    let key = unique_key();
+
+    // Create stream
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(5),
+            retention_hours: None,
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    // Ask where the key will land before publishing
+    let preview = client
+        .partition_for(&stream_id, &key)
+        .await
+        .expect("Failed to get partition for key");
+
+    assert_eq!(preview.key, key);
+    assert!(preview.partition < 5);
+
+    // Publish and compare with the partition actually used
+    let response = client
+        .publish_event(
+            &stream_id,
+            PublishEvent {
+                key: key.clone(),
+                event_type: "test.event".to_string(),
+                data: json!({}),
+            },
+        )
+        .await
+        .expect("Failed to publish event");
+
+    assert_eq!(response.events[0].partition, preview.partition);
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_partition_overrides_pin_hot_keys() {
+    let Some(client) = get_client() else { return };
+
+    let stream_id = unique_stream_id();
+    let hot_key = unique_key();
+
+    let stream = client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(4),
+            partition_overrides: Some(HashMap::from([(hot_key.clone(), 3)])),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+    assert_eq!(stream.partition_overrides.unwrap()[&hot_key], 3);
+
+    let preview = client
+        .partition_for(&stream_id, &hot_key)
+        .await
+        .expect("Failed to get partition for key");
+    assert_eq!(preview.partition, 3);
+
+    let response = client
+        .publish_event(
+            &stream_id,
+            PublishEvent {
+                key: hot_key.clone(),
+                event_type: "test.event".to_string(),
+                data: json!({}),
+            },
+        )
+        .await
+        .expect("Failed to publish event");
+    assert_eq!(response.events[0].partition, 3);
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_partition_override_out_of_range_rejected() {
+    let Some(client) = get_client() else { return };
+
+    let stream_id = unique_stream_id();
+    let result = client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(2),
+            partition_overrides: Some(HashMap::from([(unique_key(), 2)])),
+            ..Default::default()
+        })
+        .await;
+    expect_validation_error(result);
+
+    // Nothing was created
+    assert!(client.get_stream(&stream_id).await.is_err());
+}
+
+#[tokio::test]
+async fn test_publish_with_idempotency_key_writes_once() {
+    let Some(client) = get_client() else { return };
+
+    let stream_id = unique_stream_id();
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(1),
+            retention_hours: Some(24),
+            idempotency_ttl_hours: Some(1),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    let event = PublishEvent {
+        key: unique_key(),
+        event_type: "test.event".to_string(),
+        data: json!({}),
+    };
+    let idempotency_key = unique_key();
+
+    let first = client
+        .publish_events_idempotent(&stream_id, vec![event.clone()], &idempotency_key)
+        .await
+        .expect("Failed to publish event");
+    let retry = client
+        .publish_events_idempotent(&stream_id, vec![event], &idempotency_key)
+        .await
+        .expect("Failed to publish event");
+    assert_eq!(retry.events[0].id, first.events[0].id);
+
+    let stats = client
+        .stream_stats(&stream_id)
+        .await
+        .expect("Failed to get stats");
+    assert_eq!(stats.approximate_event_count, 1);
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_idempotency_ttl_longer_than_retention_rejected() {
+    let Some(client) = get_client() else { return };
+
+    let result = client
+        .create_stream(&CreateStreamRequest {
+            stream_id: unique_stream_id(),
+            retention_hours: Some(24),
+            idempotency_ttl_hours: Some(48),
+            ..Default::default()
+        })
+        .await;
+    expect_validation_error(result);
+}
+
+#[tokio::test]
+async fn test_retention_hours_out_of_range_rejected() {
+    let Some(client) = get_client() else { return };
+
+    for hours in [0, 8_761] {
+        let result = client
+            .create_stream(&CreateStreamRequest {
+                stream_id: unique_stream_id(),
+                retention_hours: Some(hours),
+                ..Default::default()
+            })
+            .await;
+        expect_validation_error(result);
+    }
+
+    let stream_id = unique_stream_id();
+    let stream = client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            retention_hours: Some(720),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+    assert_eq!(stream.retention_hours, 720);
+
+    let etag = format!("\"{}\"", stream.version);
+    let result = client
+        .update_stream(
+            &stream_id,
+            Some(&etag),
+            &UpdateStreamRequest {
+                retention_hours: Some(0),
+                ..Default::default()
+            },
+        )
+        .await;
+    expect_validation_error(result);
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_publish_assigns_global_positions() {
+    let Some(client) = get_client() else { return };
+
+    let stream_id = unique_stream_id();
+
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(3),
+            global_ordering: Some(true),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    // Distinct keys spread the batch across partitions
+    let events: Vec<PublishEvent> = (0..6)
+        .map(|i| PublishEvent {
+            key: format!("key-{}", i),
+            event_type: "test.event".to_string(),
+            data: json!({ "index": i }),
+        })
+        .collect();
+
+    let response = client
+        .publish_events(&stream_id, events)
+        .await
+        .expect("Failed to publish events");
+
+    let positions: Vec<u64> = response
+        .events
+        .iter()
+        .map(|e| e.global_position.expect("Missing global_position"))
+        .collect();
+
+    assert!(positions.windows(2).all(|w| w[0] < w[1]));
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_publish_enforces_allowed_event_types() {
+    let Some(client) = get_client() else { return };
+
+    let stream_id = unique_stream_id();
+    let allowed = vec!["order.created".to_string(), "order.shipped".to_string()];
+
+    let stream = client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            allowed_event_types: Some(allowed.clone()),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+    assert_eq!(stream.allowed_event_types, Some(allowed.clone()));
+
+    // Allowed type
+    client
+        .publish_event(
+            &stream_id,
+            PublishEvent {
+                key: unique_key(),
+                event_type: "order.created".to_string(),
+                data: json!({}),
+            },
+        )
+        .await
+        .expect("Allowed event type should publish");
+
+    // Disallowed type
+    let result = client
+        .publish_event(
+            &stream_id,
+            PublishEvent {
+                key: unique_key(),
+                event_type: "order.deleted".to_string(),
+                data: json!({}),
+            },
+        )
+        .await;
+
+    let error = expect_validation_error(result);
+    let details = error.details.expect("Expected allowed types in details");
+    assert_eq!(details["event_type"], "order.deleted");
+    assert_eq!(details["allowed_event_types"], json!(allowed));
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_publish_validates_data_against_schema() {
+    let Some(client) = get_client() else { return };
+
+    let stream_id = unique_stream_id();
+
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            schema: Some(json!({
+                "type": "object",
+                "required": ["total"],
+                "properties": {
+                    "total": { "type": "number" }
+                }
+            })),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    // Conforming payload
+    client
+        .publish_event(
+            &stream_id,
+            PublishEvent {
+                key: unique_key(),
+                event_type: "order.created".to_string(),
+                data: json!({ "total": 42 }),
+            },
+        )
+        .await
+        .expect("Conforming data should publish");
+
+    // Non-conforming payload
+    let result = client
+        .publish_event(
+            &stream_id,
+            PublishEvent {
+                key: unique_key(),
+                event_type: "order.created".to_string(),
+                data: json!({ "total": "forty-two" }),
+            },
+        )
+        .await;
+
+    let error = expect_validation_error(result);
+    let details = error.details.expect("Expected failing path in details");
+    assert_eq!(details["path"], "/total");
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_event_id_is_stable_and_decodable() {
+    let Some(client) = get_client() else { return };
+
+    let (stream_id, subscription_id) = setup_poll_mode_stream(&client, 2).await;
+
+    let first = client
+        .poll(&stream_id, &subscription_id, None)
+        .await
+        .expect("Failed to poll");
+    let second = client
+        .poll(&stream_id, &subscription_id, None)
+        .await
+        .expect("Failed to poll");
+
+    assert_eq!(first.events.len(), 2);
+    for (a, b) in first.events.iter().zip(&second.events) {
+        // Same event, same id across reads
+        assert_eq!(a.id, b.id);
+        assert_eq!(
+            parse_event_id(&a.id),
+            Some((stream_id.clone(), a.partition, a.sequence))
+        );
+    }
+    assert_ne!(first.events[0].id, first.events[1].id);
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_read_partition_descending() {
+    let Some(client) = get_client() else { return };
+
+    let (stream_id, _) = setup_poll_mode_stream(&client, 5).await;
+
+    let newest = client
+        .read_partition(&stream_id, 0, "desc", 3)
+        .await
+        .expect("Failed to read partition");
+    let sequences: Vec<u64> = newest.events.iter().map(|e| e.sequence).collect();
+    assert_eq!(sequences, vec![5, 4, 3]);
+
+    let oldest = client
+        .read_partition(&stream_id, 0, "asc", 3)
+        .await
+        .expect("Failed to read partition");
+    let sequences: Vec<u64> = oldest.events.iter().map(|e| e.sequence).collect();
+    assert_eq!(sequences, vec![1, 2, 3]);
+
+    let error = expect_validation_error(client.read_partition(&stream_id, 0, "asc", 0).await);
+    assert!(error.message.contains("limit"));
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_read_partition_by_order_key() {
+    let Some(client) = get_client() else { return };
+
+    let stream_id = unique_stream_id();
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(1),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    // Source offsets arrive out of order
+    let events = [30, 10, 20]
+        .into_iter()
+        .map(|offset| DetailedPublishEvent {
+            event: PublishEvent {
+                key: unique_key(),
+                event_type: "test.event".to_string(),
+                data: json!({ "offset": offset }),
+            },
+            order_key: Some(offset),
+            ..Default::default()
+        })
+        .collect();
+    client
+        .publish_detailed_events(&stream_id, events)
+        .await
+        .expect("Failed to publish events");
+
+    let by_order_key = client
+        .read_partition_by_order_key(&stream_id, 0, 10)
+        .await
+        .expect("Failed to read partition");
+    let order_keys: Vec<Option<u64>> = by_order_key.events.iter().map(|e| e.order_key).collect();
+    assert_eq!(order_keys, vec![Some(10), Some(20), Some(30)]);
+    let sequences: Vec<u64> = by_order_key.events.iter().map(|e| e.sequence).collect();
+    assert_eq!(sequences, vec![2, 3, 1]);
+
+    // Sequence order is unchanged by default
+    let by_sequence = client
+        .read_partition(&stream_id, 0, "asc", 10)
+        .await
+        .expect("Failed to read partition");
+    let sequences: Vec<u64> = by_sequence.events.iter().map(|e| e.sequence).collect();
+    assert_eq!(sequences, vec![1, 2, 3]);
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_redact_event_keeps_neighbours_and_stream() {
+    let Some(client) = get_client() else { return };
+
+    let (stream_id, _) = setup_poll_mode_stream(&client, 5).await;
+
+    let redacted = client
+        .redact_event(&stream_id, 0, 3)
+        .await
+        .expect("Failed to redact event");
+    assert_eq!(redacted.sequence, 3);
+    assert_eq!(redacted.data["redacted"], true);
+
+    // No gap, and the DELETE didn't reach the stream itself
+    let events = client
+        .read_partition(&stream_id, 0, "asc", 10)
+        .await
+        .expect("Failed to read partition");
+    let sequences: Vec<u64> = events.events.iter().map(|e| e.sequence).collect();
+    assert_eq!(sequences, vec![1, 2, 3, 4, 5]);
+    assert_eq!(events.events[2].data["redacted"], true);
+    assert_ne!(events.events[3].data["redacted"], true);
+
+    match client.redact_event(&stream_id, 0, 99).await {
+        Err(ApiError::Http { status, .. }) => assert_eq!(status.as_u16(), 404),
+        other => panic!("Expected 404, got {:?}", other),
+    }
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_publish_to_nonexistent_stream_fails() {
+    let Some(client) = get_client() else { return };
+
+    let event = PublishEvent {
+        key: unique_key(),
+        event_type: "test.event".to_string(),
+        data: json!({}),
+    };
+
+    let result = client
+        .publish_event("nonexistent-stream-12345", event)
+        .await;
+
+    assert!(result.is_err());
+    if let Err(ApiError::Http { status, .. }) = result {
+        assert_eq!(status.as_u16(), 404);
+    }
+}
+
+// ============================================================================
+// Subscription Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_create_subscription() {
+    let Some(client) = get_client() else { return };
+
+    let stream_id = unique_stream_id();
+    let subscription_id = unique_subscription_id();
+
+    // Create stream
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(3),
+            retention_hours: None,
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    // Create subscription
+    let subscription = client
+        .create_subscription(
+            &stream_id,
+            &CreateSubscriptionRequest {
+                subscription_id: subscription_id.clone(),
+                start_from: Some(StartFrom::Earliest),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Failed to create subscription");
+
+    assert_eq!(subscription.stream_id, stream_id);
+    assert_eq!(subscription.subscription_id, subscription_id);
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_create_subscription_start_from_values() {
+    let Some(client) = get_client() else { return };
+
+    let stream_id = unique_stream_id();
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(1),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    for start_from in [StartFrom::Earliest, StartFrom::Latest, StartFrom::Compacted] {
+        client
+            .create_subscription(
+                &stream_id,
+                &CreateSubscriptionRequest {
+                    subscription_id: unique_subscription_id(),
+                    start_from: Some(start_from.clone()),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap_or_else(|e| panic!("Failed to create {:?} subscription: {:?}", start_from, e));
+    }
+
+    // A typo is reported with the accepted values
+    let body = json!({
+        "subscription_id": unique_subscription_id(),
+        "start_from": "earlist",
+    });
+    let error = expect_validation_error(
+        client
+            .post_raw::<serde_json::Value>(
+                &format!("/streams/{}/subscriptions", stream_id),
+                "application/json",
+                body.to_string(),
+            )
+            .await,
+    );
+    assert!(error.message.contains("earlist"));
+    let details = error.details.expect("Error should include details");
+    assert_eq!(
+        details["accepted"],
+        json!(["earliest", "latest", "compacted"])
+    );
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_timestamp_subscription_replays_from_that_time() {
+    let Some(client) = get_client() else { return };
+
+    let stream_id = unique_stream_id();
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(1),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    let publish = |phase: &'static str, count: usize| {
+        let events = (0..count)
+            .map(|_| PublishEvent {
+                key: unique_key(),
+                event_type: "test.event".to_string(),
+                data: json!({ "phase": phase }),
+            })
+            .collect();
+        client.publish_events(&stream_id, events)
+    };
+    let before = publish("before", 2)
+        .await
+        .expect("Failed to publish events");
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    publish("after", 3).await.expect("Failed to publish events");
+
+    // Start right after the last event of the first batch
+    let subscription_id = unique_subscription_id();
+    let cut = before.events[1].timestamp.clone();
+    client
+        .create_subscription(
+            &stream_id,
+            &CreateSubscriptionRequest {
+                subscription_id: subscription_id.clone(),
+                start_from: Some(StartFrom::Timestamp(cut)),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Failed to create subscription");
+
+    let poll_response = client
+        .poll(&stream_id, &subscription_id, Some(10))
+        .await
+        .expect("Failed to poll");
+    let phases: Vec<&serde_json::Value> = poll_response
+        .events
+        .iter()
+        .map(|e| &e.data["phase"])
+        .collect();
+    assert_eq!(phases, vec!["after", "after", "after"]);
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_create_subscription_rejects_invalid_ids() {
+    let Some(client) = get_client() else { return };
+
+    let stream_id = unique_stream_id();
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(1),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    for subscription_id in [String::new(), "a".repeat(129), "billing#1".to_string()] {
+        let result = client
+            .create_subscription(
+                &stream_id,
+                &CreateSubscriptionRequest {
+                    subscription_id: subscription_id.clone(),
+                    ..Default::default()
+                },
+            )
+            .await;
+        match result {
+            Err(ApiError::Http { status, body }) => {
+                assert_eq!(status.as_u16(), 400, "{:?}: {}", subscription_id, body);
+                let error: ErrorResponse = serde_json::from_str(&body).unwrap();
+                assert_eq!(error.error, "invalid_subscription_id");
+            }
+            other => panic!("Expected 400 for {:?}, got {:?}", subscription_id, other),
+        }
+    }
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_seek_all_subscriptions_to_earliest() {
+    let Some(client) = get_client() else { return };
+
+    let (stream_id, first_sub) = setup_poll_mode_stream(&client, 3).await;
+    let second_sub = unique_subscription_id();
+    client
+        .create_subscription(
+            &stream_id,
+            &CreateSubscriptionRequest {
+                subscription_id: second_sub.clone(),
+                start_from: Some(StartFrom::Earliest),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Failed to create subscription");
+
+    // Both consumers read and commit everything
+    for sub in [&first_sub, &second_sub] {
+        let response = client
+            .poll(&stream_id, sub, None)
+            .await
+            .expect("Failed to poll");
+        client
+            .commit(&stream_id, sub, &response.cursor)
+            .await
+            .expect("Failed to commit");
+    }
+
+    let result = client
+        .seek_all_subscriptions(&stream_id, "earliest")
+        .await
+        .expect("Failed to seek subscriptions");
+    assert_eq!(result.repositioned, 2);
+    assert!(result.failed.is_empty());
+
+    // Both replay from the start
+    for sub in [&first_sub, &second_sub] {
+        let response = client
+            .poll(&stream_id, sub, None)
+            .await
+            .expect("Failed to poll");
+        assert_eq!(sequences(&response), vec![1, 2, 3]);
+    }
+
+    // Compacted state is only read when a subscription is created
+    let error =
+        expect_validation_error(client.seek_all_subscriptions(&stream_id, "compacted").await);
+    assert!(error.message.contains("earliest"));
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_list_subscriptions() {
+    let Some(client) = get_client() else { return };
+
+    let (stream_id, first_sub) = setup_poll_mode_stream(&client, 1).await;
+    let second_sub = unique_subscription_id();
+    client
+        .create_subscription(
+            &stream_id,
+            &CreateSubscriptionRequest {
+                subscription_id: second_sub.clone(),
+                start_from: None,
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Failed to create subscription");
+
+    let response = client
+        .list_subscriptions(&stream_id)
+        .await
+        .expect("Failed to list subscriptions");
+    let mut ids: Vec<String> = response
+        .subscriptions
+        .iter()
+        .map(|s| s.subscription_id.clone())
+        .collect();
+    ids.sort();
+    let mut expected = vec![first_sub, second_sub];
+    expected.sort();
+    assert_eq!(ids, expected);
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_poll_stamps_last_polled_at() {
+    let Some(client) = get_client() else { return };
+
+    let (stream_id, subscription_id) = setup_poll_mode_stream(&client, 1).await;
+    client
+        .poll(&stream_id, &subscription_id, None)
+        .await
+        .expect("Failed to poll");
+
+    let response = client
+        .list_subscriptions(&stream_id)
+        .await
+        .expect("Failed to list subscriptions");
+    assert!(response.subscriptions[0].last_polled_at.is_some());
+
+    // Just polled, so not stale
+    let stale = client
+        .list_stale_subscriptions(&stream_id, 1)
+        .await
+        .expect("Failed to list stale subscriptions");
+    assert!(stale.subscriptions.is_empty());
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_seek_subscription_to_earliest() {
+    let Some(client) = get_client() else { return };
+
+    let (stream_id, subscription_id) = setup_poll_mode_stream(&client, 3).await;
+
+    let response = client
+        .poll(&stream_id, &subscription_id, None)
+        .await
+        .expect("Failed to poll");
+    client
+        .commit(&stream_id, &subscription_id, &response.cursor)
+        .await
+        .expect("Failed to commit");
+
+    let seek = client
+        .seek(&stream_id, &subscription_id, "earliest")
+        .await
+        .expect("Failed to seek");
+    assert_eq!(seek.subscription_id, subscription_id);
+    assert!(seek.offsets.iter().all(|po| po.offset == 0));
+
+    let response = client
+        .poll(&stream_id, &subscription_id, None)
+        .await
+        .expect("Failed to poll");
+    assert_eq!(sequences(&response), vec![1, 2, 3]);
+
+    // Seeking to latest skips the backlog again
+    client
+        .seek(&stream_id, &subscription_id, "latest")
+        .await
+        .expect("Failed to seek");
+    let response = client
+        .poll(&stream_id, &subscription_id, None)
+        .await
+        .expect("Failed to poll");
+    assert!(response.events.is_empty());
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_subscription_lag() {
+    let Some(client) = get_client() else { return };
+
+    let (stream_id, subscription_id) = setup_poll_mode_stream(&client, 5).await;
+
+    let lag = client
+        .lag(&stream_id, &subscription_id)
+        .await
+        .expect("Failed to get lag");
+    assert_eq!(lag.subscription_id, subscription_id);
+    assert_eq!(lag.total_lag, 5);
+    assert_eq!(lag.partitions.len(), 1);
+    assert_eq!(lag.partitions[0].latest_offset, 5);
+    assert_eq!(lag.partitions[0].committed_offset, 0);
+
+    // Committing a poll closes the gap
+    let response = client
+        .poll(&stream_id, &subscription_id, None)
+        .await
+        .expect("Failed to poll");
+    client
+        .commit(&stream_id, &subscription_id, &response.cursor)
+        .await
+        .expect("Failed to commit");
+
+    let lag = client
+        .lag(&stream_id, &subscription_id)
+        .await
+        .expect("Failed to get lag");
+    assert_eq!(lag.total_lag, 0);
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_delete_subscription() {
+    let Some(client) = get_client() else { return };
+
+    let (stream_id, subscription_id) = setup_poll_mode_stream(&client, 1).await;
+
+    let response = client
+        .delete_subscription(&stream_id, &subscription_id)
+        .await
+        .expect("Failed to delete subscription");
+    assert!(response.success);
+
+    let subscriptions = client
+        .list_subscriptions(&stream_id)
+        .await
+        .expect("Failed to list subscriptions");
+    assert!(subscriptions.subscriptions.is_empty());
+
+    // The subscription is gone for the poll API too, and a second delete is a 404
+    match client.list_offsets(&stream_id, &subscription_id).await {
+        Err(ApiError::Http { status, .. }) => assert_eq!(status.as_u16(), 404),
+        other => panic!("Expected 404, got {:?}", other),
+    }
+    match client
+        .delete_subscription(&stream_id, &subscription_id)
+        .await
+    {
+        Err(ApiError::Http { status, .. }) => assert_eq!(status.as_u16(), 404),
+        other => panic!("Expected 404, got {:?}", other),
+    }
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+// ============================================================================
+// Poll and Commit Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_poll_empty_stream() {
+    let Some(client) = get_client() else { return };
+
+    let stream_id = unique_stream_id();
+    let subscription_id = unique_subscription_id();
+
+    // Create stream
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(3),
+            retention_hours: None,
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    // Create subscription
+    client
+        .create_subscription(
+            &stream_id,
+            &CreateSubscriptionRequest {
+                subscription_id: subscription_id.clone(),
+                start_from: Some(StartFrom::Earliest),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Failed to create subscription");
+
+    // Poll
+    let response = client
+        .poll(&stream_id, &subscription_id, Some(10))
+        .await
+        .expect("Failed to poll");
+
+    assert!(response.events.is_empty());
+    assert!(!response.cursor.is_empty());
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_full_publish_poll_commit_cycle() {
+    let Some(client) = get_client() else { return };
+
+    let stream_id = unique_stream_id();
+    let subscription_id = unique_subscription_id();
+    let key = unique_key();
+
+    // Create stream
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(1), // Single partition for ordered test
+            retention_hours: None,
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    // Create subscription starting from earliest
+    client
+        .create_subscription(
+            &stream_id,
+            &CreateSubscriptionRequest {
+                subscription_id: subscription_id.clone(),
+                start_from: Some(StartFrom::Earliest),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Failed to create subscription");
+
+    // Publish events
+    for i in 1..=5 {
+        client
+            .publish_event(
+                &stream_id,
+                PublishEvent {
+                    key: key.clone(),
+                    event_type: "counter.incremented".to_string(),
+                    data: json!({ "value": i }),
+                },
+            )
+            .await
+            .expect("Failed to publish event");
+    }
+
+    // Poll for events
+    let poll_response = client
+        .poll(&stream_id, &subscription_id, Some(10))
+        .await
+        .expect("Failed to poll");
+
+    assert_eq!(poll_response.events.len(), 5);
+
+    // Verify event order and content
+    for (i, event) in poll_response.events.iter().enumerate() {
+        assert_eq!(event.key, key);
+        assert_eq!(event.event_type, "counter.incremented");
+        let value = event.data.get("value").unwrap().as_i64().unwrap();
+        assert_eq!(value, (i + 1) as i64);
+    }
+
+    // Commit
+    let commit_response = client
+        .commit(&stream_id, &subscription_id, &poll_response.cursor)
+        .await
+        .expect("Failed to commit");
+
+    assert!(commit_response.success);
+
+    // Poll again - should get no new events
+    let poll_response2 = client
+        .poll(&stream_id, &subscription_id, Some(10))
+        .await
+        .expect("Failed to poll again");
+
+    assert!(poll_response2.events.is_empty());
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct OrderCreated {
+    order_id: String,
+    total: f64,
+}
+
+#[tokio::test]
+async fn test_poll_typed_deserializes_event_data() {
+    let Some(client) = get_client() else { return };
+    let stream_id = unique_stream_id();
+    let subscription_id = unique_subscription_id();
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(1),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+    client
+        .create_subscription(
+            &stream_id,
+            &CreateSubscriptionRequest {
+                subscription_id: subscription_id.clone(),
+                start_from: Some(StartFrom::Earliest),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Failed to create subscription");
+
+    let order = |order_id: &str, total: serde_json::Value| PublishEvent {
+        key: order_id.to_string(),
+        event_type: "order.created".to_string(),
+        data: json!({ "order_id": order_id, "total": total }),
+    };
+    client
+        .publish_events(
+            &stream_id,
+            vec![order("order-1", json!(9.5)), order("order-2", json!(20))],
+        )
+        .await
+        .expect("Failed to publish events");
+
+    let poll_response = client
+        .poll_typed::<OrderCreated>(&stream_id, &subscription_id, &PollOptions::default())
+        .await
+        .expect("Failed to poll typed");
+    let orders: Vec<&OrderCreated> = poll_response.events.iter().map(|e| &e.data).collect();
+    assert_eq!(
+        orders,
+        vec![
+            &OrderCreated {
+                order_id: "order-1".to_string(),
+                total: 9.5
+            },
+            &OrderCreated {
+                order_id: "order-2".to_string(),
+                total: 20.0
+            },
+        ]
+    );
+    assert_eq!(poll_response.events[1].event.key, "order-2");
+    client
+        .commit(&stream_id, &subscription_id, &poll_response.cursor)
+        .await
+        .expect("Failed to commit");
+
+    // Data of the wrong shape names the event it came from
+    let published = client
+        .publish_event(&stream_id, order("order-3", json!("lots")))
+        .await
+        .expect("Failed to publish event");
+    let result = client
+        .poll_typed::<OrderCreated>(&stream_id, &subscription_id, &PollOptions::default())
+        .await;
+    match result {
+        Err(ApiError::Data { event_id, message }) => {
+            assert_eq!(event_id, published.events[0].id);
+            assert!(message.contains("OrderCreated"), "{}", message);
+        }
+        other => panic!("Expected a data error, got {:?}", other),
+    }
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_poll_includes_partition_offsets() {
+    let Some(client) = get_client() else { return };
+
+    let stream_id = unique_stream_id();
+    let subscription_id = unique_subscription_id();
+
+    // Create stream
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(3),
+            retention_hours: None,
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    // Create subscription starting from earliest
+    client
+        .create_subscription(
+            &stream_id,
+            &CreateSubscriptionRequest {
+                subscription_id: subscription_id.clone(),
+                start_from: Some(StartFrom::Earliest),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Failed to create subscription");
+
+    // Publish events spread across partitions
+    for i in 0..6 {
+        client
+            .publish_event(
+                &stream_id,
+                PublishEvent {
+                    key: format!("key-{}", i),
+                    event_type: "test.event".to_string(),
+                    data: json!({ "i": i }),
+                },
+            )
+            .await
+            .expect("Failed to publish event");
+    }
+
+    // Offsets are omitted by default
+    let response = client
+        .poll(&stream_id, &subscription_id, Some(30))
+        .await
+        .expect("Failed to poll");
+    assert!(response.offsets.is_none());
+
+    let response = client
+        .poll_with_options(
+            &stream_id,
+            &subscription_id,
+            &PollOptions {
+                limit: Some(30),
+                include_partition_offsets: Some(true),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Failed to poll");
+
+    let offsets = response.offsets.expect("Expected partition offsets");
+    assert_eq!(offsets.len(), 3);
+
+    // Each partition's offset is the highest sequence returned from it
+    for po in &offsets {
+        let highest = response
+            .events
+            .iter()
+            .filter(|e| e.partition == po.partition)
+            .map(|e| e.sequence)
+            .max()
+            .unwrap_or(0);
+        assert_eq!(po.offset, highest);
+    }
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_token_cursor_stays_short_on_wide_stream() {
+    let Some(client) = get_client() else { return };
+
+    let stream_id = unique_stream_id();
+    let subscription_id = unique_subscription_id();
+
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(200),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    let subscription = client
+        .create_subscription(
+            &stream_id,
+            &CreateSubscriptionRequest {
+                subscription_id: subscription_id.clone(),
+                start_from: Some(StartFrom::Earliest),
+                cursor_encoding: Some(CursorEncoding::Token),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Failed to create subscription");
+    assert_eq!(subscription.cursor_encoding, Some(CursorEncoding::Token));
+
+    let events = (0..3)
+        .map(|i| PublishEvent {
+            key: unique_key(),
+            event_type: "test.event".to_string(),
+            data: json!({ "i": i }),
+        })
+        .collect();
+    client
+        .publish_events(&stream_id, events)
+        .await
+        .expect("Failed to publish events");
+
+    let response = client
+        .poll(&stream_id, &subscription_id, Some(200))
+        .await
+        .expect("Failed to poll");
+    assert_eq!(response.events.len(), 3);
+    // An inline cursor for 200 partitions runs to kilobytes
+    assert!(response.cursor.starts_with("tok."));
+    assert!(
+        response.cursor.len() < 64,
+        "cursor is {} bytes",
+        response.cursor.len()
+    );
+
+    client
+        .commit(&stream_id, &subscription_id, &response.cursor)
+        .await
+        .expect("Failed to commit token cursor");
+
+    let response = client
+        .poll(&stream_id, &subscription_id, Some(200))
+        .await
+        .expect("Failed to poll");
+    assert!(response.events.is_empty());
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+/// Single-partition stream with `count` events and an earliest subscription
+async fn setup_poll_mode_stream(client: &EventLedgerClient, count: usize) -> (String, String) {
+    let stream_id = unique_stream_id();
+    let subscription_id = unique_subscription_id();
+
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(1),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    client
+        .create_subscription(
+            &stream_id,
+            &CreateSubscriptionRequest {
+                subscription_id: subscription_id.clone(),
+                start_from: Some(StartFrom::Earliest),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Failed to create subscription");
+
+    let events = (0..count)
+        .map(|i| PublishEvent {
+            key: unique_key(),
+            event_type: "test.event".to_string(),
+            data: json!({ "i": i }),
+        })
+        .collect();
+    client
+        .publish_events(&stream_id, events)
+        .await
+        .expect("Failed to publish events");
+
+    (stream_id, subscription_id)
+}
+
+fn poll_mode(mode: &str) -> PollOptions {
+    PollOptions {
+        limit: Some(2),
+        mode: Some(mode.to_string()),
+        ..Default::default()
+    }
+}
+
+fn sequences(response: &PollResponse) -> Vec<u64> {
+    response.events.iter().map(|e| e.sequence).collect()
+}
+
+#[tokio::test]
+async fn test_peek_poll_repeats_until_commit() {
+    let Some(client) = get_client() else { return };
+
+    let (stream_id, subscription_id) = setup_poll_mode_stream(&client, 4).await;
+
+    let first = client
+        .poll_with_options(&stream_id, &subscription_id, &poll_mode("peek"))
+        .await
+        .expect("Failed to poll");
+    let second = client
+        .poll_with_options(&stream_id, &subscription_id, &poll_mode("peek"))
+        .await
+        .expect("Failed to poll");
+
+    assert_eq!(sequences(&first), vec![1, 2]);
+    assert_eq!(sequences(&second), vec![1, 2]);
+
+    // Committing moves the window forward
+    client
+        .commit(&stream_id, &subscription_id, &first.cursor)
+        .await
+        .expect("Failed to commit");
+    let third = client
+        .poll_with_options(&stream_id, &subscription_id, &poll_mode("peek"))
+        .await
+        .expect("Failed to poll");
+    assert_eq!(sequences(&third), vec![3, 4]);
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_consume_poll_advances_without_commit() {
+    let Some(client) = get_client() else { return };
+
+    let (stream_id, subscription_id) = setup_poll_mode_stream(&client, 4).await;
+
+    let first = client
+        .poll_with_options(&stream_id, &subscription_id, &poll_mode("consume"))
+        .await
+        .expect("Failed to poll");
+    let second = client
+        .poll_with_options(&stream_id, &subscription_id, &poll_mode("consume"))
+        .await
+        .expect("Failed to poll");
+
+    assert_eq!(sequences(&first), vec![1, 2]);
+    assert_eq!(sequences(&second), vec![3, 4]);
+
+    // Nothing was committed, so a peek still starts from the beginning
+    let peek = client
+        .poll_with_options(&stream_id, &subscription_id, &poll_mode("peek"))
+        .await
+        .expect("Failed to poll");
+    assert_eq!(sequences(&peek), vec![1, 2]);
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_cursor_pages_before_commit() {
+    let Some(client) = get_client() else { return };
+
+    let (stream_id, subscription_id) = setup_poll_mode_stream(&client, 6).await;
+
+    let mut pages = Vec::new();
+    let mut options = poll_mode("peek");
+    for _ in 0..3 {
+        let page = client
+            .poll_with_options(&stream_id, &subscription_id, &options)
+            .await
+            .expect("Failed to poll");
+        pages.push(sequences(&page));
+        options.cursor = Some(page.cursor);
+    }
+    assert_eq!(pages, vec![vec![1, 2], vec![3, 4], vec![5, 6]]);
+
+    // Paging committed nothing; committing the last cursor covers every page
+    let peek = client
+        .poll_with_options(&stream_id, &subscription_id, &poll_mode("peek"))
+        .await
+        .expect("Failed to poll");
+    assert_eq!(sequences(&peek), vec![1, 2]);
+
+    let cursor = options.cursor.expect("Paged at least once");
+    client
+        .commit(&stream_id, &subscription_id, &cursor)
+        .await
+        .expect("Failed to commit");
+    let offsets = client
+        .list_offsets(&stream_id, &subscription_id)
+        .await
+        .expect("Failed to list offsets");
+    assert_eq!(offsets.offsets.iter().map(|o| o.offset).sum::<u64>(), 6);
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_commit_poll_returns_next_batch() {
+    let Some(client) = get_client() else { return };
+
+    let (stream_id, subscription_id) = setup_poll_mode_stream(&client, 6).await;
+
+    let first = client
+        .poll_with_options(&stream_id, &subscription_id, &poll_mode("peek"))
+        .await
+        .expect("Failed to poll");
+    assert_eq!(sequences(&first), vec![1, 2]);
+
+    // Committing batch N returns batch N+1
+    let second = client
+        .commit_poll(
+            &stream_id,
+            &subscription_id,
+            &first.cursor,
+            &poll_mode("peek"),
+        )
+        .await
+        .expect("Failed to commit-poll");
+    assert_eq!(sequences(&second), vec![3, 4]);
+
+    let third = client
+        .commit_poll(
+            &stream_id,
+            &subscription_id,
+            &second.cursor,
+            &poll_mode("peek"),
+        )
+        .await
+        .expect("Failed to commit-poll");
+    assert_eq!(sequences(&third), vec![5, 6]);
+
+    // Each call's commit is durable on its own
+    let offsets = client
+        .list_offsets(&stream_id, &subscription_id)
+        .await
+        .expect("Failed to list offsets");
+    assert_eq!(offsets.offsets[0].offset, 4);
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_poll_projects_requested_fields() {
+    let Some(client) = get_client() else { return };
+
+    let (stream_id, subscription_id) = setup_poll_mode_stream(&client, 3).await;
+
+    let full = client
+        .poll_json(&stream_id, &subscription_id, &PollOptions::default())
+        .await
+        .expect("Failed to poll");
+    let projected = client
+        .poll_json(
+            &stream_id,
+            &subscription_id,
+            &PollOptions {
+                fields: Some("key,event_type,sequence".to_string()),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Failed to poll with fields");
+
+    let events = projected["events"].as_array().unwrap();
+    assert_eq!(events.len(), 3);
+    for (i, event) in events.iter().enumerate() {
+        let mut names: Vec<&str> = event
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["event_type", "key", "sequence"]);
+        assert_eq!(event["sequence"], full["events"][i]["sequence"]);
+    }
+    assert!(projected.to_string().len() < full.to_string().len());
+
+    // The cursor still covers the projected events
+    client
+        .commit(
+            &stream_id,
+            &subscription_id,
+            projected["cursor"].as_str().unwrap(),
+        )
+        .await
+        .expect("Failed to commit");
+    let after = client
+        .poll(&stream_id, &subscription_id, None)
+        .await
+        .expect("Failed to poll");
+    assert!(after.events.is_empty());
+
+    let error = expect_validation_error(
+        client
+            .poll_json(
+                &stream_id,
+                &subscription_id,
+                &PollOptions {
+                    fields: Some("key,payload".to_string()),
+                    ..Default::default()
+                },
+            )
+            .await,
+    );
+    assert!(error.message.contains("payload"));
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_poll_rejects_unknown_mode() {
+    let Some(client) = get_client() else { return };
+
+    let (stream_id, subscription_id) = setup_poll_mode_stream(&client, 1).await;
+
+    let result = client
+        .poll_with_options(&stream_id, &subscription_id, &poll_mode("skim"))
+        .await;
+    expect_validation_error(result);
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_poll_rejects_invalid_limit() {
+    let Some(client) = get_client() else { return };
+
+    let (stream_id, subscription_id) = setup_poll_mode_stream(&client, 1).await;
+
+    for limit in ["0", "abc", "100000"] {
+        let result = client
+            .get_raw::<PollResponse>(&format!(
+                "/streams/{}/subscriptions/{}/poll?limit={}",
+                stream_id, subscription_id, limit
+            ))
+            .await;
+        let error = expect_validation_error(result);
+        assert!(
+            error.message.contains("limit"),
+            "limit={} should explain the problem, got: {}",
+            limit,
+            error.message
+        );
+    }
+
+    // The maximum itself is accepted
+    client
+        .poll(&stream_id, &subscription_id, Some(10_000))
+        .await
+        .expect("Poll at the maximum limit should succeed");
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_poll_partitions_split_between_workers() {
+    let Some(client) = get_client() else { return };
+
+    let stream_id = unique_stream_id();
+    let subscription_id = unique_subscription_id();
+
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(4),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+    client
+        .create_subscription(
+            &stream_id,
+            &CreateSubscriptionRequest {
+                subscription_id: subscription_id.clone(),
+                start_from: Some(StartFrom::Earliest),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Failed to create subscription");
+
+    let events = (0..40)
+        .map(|i| PublishEvent {
+            key: unique_key(),
+            event_type: "test.event".to_string(),
+            data: json!({ "i": i }),
+        })
+        .collect();
+    let published = client
+        .publish_events(&stream_id, events)
+        .await
+        .expect("Failed to publish events");
+    let mut expected: Vec<String> = published.events.iter().map(|e| e.id.clone()).collect();
+    expected.sort();
+
+    // Two workers share the subscription, each owning half the partitions
+    let mut consumed: Vec<String> = Vec::new();
+    for (worker_partitions, owned) in [("0,1", [0, 1]), ("2,3", [2, 3])] {
+        let options = PollOptions {
+            limit: Some(4),
+            partitions: Some(worker_partitions.to_string()),
+            ..Default::default()
+        };
+        loop {
+            let response = client
+                .poll_with_options(&stream_id, &subscription_id, &options)
+                .await
+                .expect("Failed to poll");
+            if response.events.is_empty() {
+                break;
+            }
+            assert!(response.events.iter().all(|e| owned.contains(&e.partition)));
+            consumed.extend(response.events.iter().map(|e| e.id.clone()));
+            client
+                .commit(&stream_id, &subscription_id, &response.cursor)
+                .await
+                .expect("Failed to commit");
+        }
+    }
+
+    // Together they saw every event exactly once
+    consumed.sort();
+    assert_eq!(consumed, expected);
+
+    // An unknown partition is rejected
+    let result = client
+        .poll_with_options(
+            &stream_id,
+            &subscription_id,
+            &PollOptions {
+                partitions: Some("0,4".to_string()),
+                ..Default::default()
+            },
+        )
+        .await;
+    expect_validation_error(result);
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_poll_uses_subscription_default_limit() {
+    let Some(client) = get_client() else { return };
+
+    let (stream_id, _) = setup_poll_mode_stream(&client, 10).await;
+    let subscription_id = unique_subscription_id();
+    let subscription = client
+        .create_subscription(
+            &stream_id,
+            &CreateSubscriptionRequest {
+                subscription_id: subscription_id.clone(),
+                start_from: Some(StartFrom::Earliest),
+                default_limit: Some(5),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Failed to create subscription");
+    assert_eq!(subscription.default_limit, Some(5));
+
+    let response = client
+        .poll(&stream_id, &subscription_id, None)
+        .await
+        .expect("Failed to poll");
+    assert_eq!(response.events.len(), 5);
+
+    // An explicit limit still wins
+    let response = client
+        .poll(&stream_id, &subscription_id, Some(8))
+        .await
+        .expect("Failed to poll");
+    assert_eq!(response.events.len(), 8);
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_subscription_poll_defaults_validated() {
+    let Some(client) = get_client() else { return };
+
+    let (stream_id, _) = setup_poll_mode_stream(&client, 1).await;
+
+    for (default_limit, default_wait_seconds) in [(Some(0), None), (None, Some(600))] {
+        let result = client
+            .create_subscription(
+                &stream_id,
+                &CreateSubscriptionRequest {
+                    subscription_id: unique_subscription_id(),
+                    default_limit,
+                    default_wait_seconds,
+                    ..Default::default()
+                },
+            )
+            .await;
+        expect_validation_error(result);
+    }
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_poll_waits_for_subscription_default_wait() {
+    let Some(client) = get_client() else { return };
+
+    let (stream_id, _) = setup_poll_mode_stream(&client, 1).await;
+    let subscription_id = unique_subscription_id();
+    client
+        .create_subscription(
+            &stream_id,
+            &CreateSubscriptionRequest {
+                subscription_id: subscription_id.clone(),
+                start_from: Some(StartFrom::Latest),
+                default_wait_seconds: Some(2),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Failed to create subscription");
+
+    // Nothing new arrives, so the poll returns empty after waiting
+    let started = std::time::Instant::now();
+    let response = client
+        .poll(&stream_id, &subscription_id, None)
+        .await
+        .expect("Failed to poll");
+    assert!(response.events.is_empty());
+    assert!(started.elapsed() >= std::time::Duration::from_millis(1500));
+
+    // An explicit wait_seconds=0 returns immediately
+    let started = std::time::Instant::now();
+    client
+        .poll_with_options(
+            &stream_id,
+            &subscription_id,
+            &PollOptions {
+                wait_seconds: Some(0),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Failed to poll");
+    assert!(started.elapsed() < std::time::Duration::from_millis(1500));
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_poll_not_modified_after_commit() {
+    let Some(client) = get_client() else { return };
+
+    let (stream_id, subscription_id) = setup_poll_mode_stream(&client, 3).await;
+
+    let response = client
+        .poll(&stream_id, &subscription_id, None)
+        .await
+        .expect("Failed to poll");
+    assert_eq!(response.events.len(), 3);
+    client
+        .commit(&stream_id, &subscription_id, &response.cursor)
+        .await
+        .expect("Failed to commit");
+
+    // Nothing published since the cursor: 304 with no body
+    let result = client
+        .poll_if_none_match(&stream_id, &subscription_id, &response.cursor)
+        .await
+        .expect("Failed to poll");
+    assert!(result.is_none(), "Expected 304, got {:?}", result);
+
+    // A new event means a full response again
+    client
+        .publish_event(
+            &stream_id,
+            PublishEvent {
+                key: unique_key(),
+                event_type: "test.event".to_string(),
+                data: json!({}),
+            },
+        )
+        .await
+        .expect("Failed to publish event");
+    let result = client
+        .poll_if_none_match(&stream_id, &subscription_id, &response.cursor)
+        .await
+        .expect("Failed to poll")
+        .expect("Expected new events");
+    assert_eq!(sequences(&result), vec![4]);
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_poll_auto_create_earliest_returns_backlog() {
+    let Some(client) = get_client() else { return };
+
+    let stream_id = unique_stream_id();
+    let subscription_id = unique_subscription_id();
+
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(1),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    let events = (0..3)
+        .map(|i| PublishEvent {
+            key: unique_key(),
+            event_type: "test.event".to_string(),
+            data: json!({ "i": i }),
+        })
+        .collect();
+    client
+        .publish_events(&stream_id, events)
+        .await
+        .expect("Failed to publish events");
+
+    // No explicit create step
+    let response = client
+        .poll_with_options(
+            &stream_id,
+            &subscription_id,
+            &PollOptions {
+                auto_create: Some("earliest".to_string()),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Failed to poll with auto_create");
+
+    assert_eq!(sequences(&response), vec![1, 2, 3]);
+
+    // The subscription now exists for plain polls
+    client
+        .poll(&stream_id, &subscription_id, None)
+        .await
+        .expect("Auto-created subscription should exist");
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_poll_missing_subscription_returns_404() {
+    let Some(client) = get_client() else { return };
+
+    let stream_id = unique_stream_id();
+
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    let result = client
+        .poll(&stream_id, &unique_subscription_id(), None)
+        .await;
+
+    match result {
+        Err(ApiError::Http { status, .. }) => assert_eq!(status.as_u16(), 404),
+        other => panic!("Expected 404, got {:?}", other),
+    }
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_poll_reads_many_partitions() {
+    let Some(client) = get_client() else { return };
+
+    let stream_id = unique_stream_id();
+    let subscription_id = unique_subscription_id();
+
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(10),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    client
+        .create_subscription(
+            &stream_id,
+            &CreateSubscriptionRequest {
+                subscription_id: subscription_id.clone(),
+                start_from: Some(StartFrom::Earliest),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Failed to create subscription");
+
+    let events = (0..30)
+        .map(|i| PublishEvent {
+            key: format!("key-{}", i),
+            event_type: "test.event".to_string(),
+            data: json!({ "i": i }),
+        })
+        .collect();
+    let published = client
+        .publish_events(&stream_id, events)
+        .await
+        .expect("Failed to publish events");
+
+    let response = client
+        .poll(&stream_id, &subscription_id, Some(100))
+        .await
+        .expect("Failed to poll");
+
+    // Every event exactly once
+    let mut polled: Vec<(u32, u64)> = response
+        .events
+        .iter()
+        .map(|e| (e.partition, e.sequence))
+        .collect();
+    let mut expected: Vec<(u32, u64)> = published
+        .events
+        .iter()
+        .map(|e| (e.partition, e.sequence))
+        .collect();
+    polled.sort();
+    expected.sort();
+    assert_eq!(polled, expected);
+
+    // One batch shares a timestamp, so the merge falls back to (partition, sequence)
+    let order: Vec<(u32, u64)> = response
+        .events
+        .iter()
+        .map(|e| (e.partition, e.sequence))
+        .collect();
+    assert_eq!(order, expected);
+
+    // Committing the cursor consumes everything
+    client
+        .commit(&stream_id, &subscription_id, &response.cursor)
+        .await
+        .expect("Failed to commit");
+    let response = client
+        .poll(&stream_id, &subscription_id, Some(100))
+        .await
+        .expect("Failed to poll");
+    assert!(response.events.is_empty());
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_commit_up_to_timestamp() {
+    let Some(client) = get_client() else { return };
+
+    let (stream_id, subscription_id) = setup_poll_mode_stream(&client, 2).await;
+
+    // The first batch is stamped before the watermark, the second after it
+    let first = client
+        .read_partition(&stream_id, 0, "desc", 1)
+        .await
+        .expect("Failed to read partition");
+    let watermark = first.events[0].timestamp.clone();
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(1100)).await;
+    client
+        .publish_event(
+            &stream_id,
+            PublishEvent {
+                key: unique_key(),
+                event_type: "test.event".to_string(),
+                data: json!({ "late": true }),
+            },
+        )
+        .await
+        .expect("Failed to publish event");
+
+    client
+        .commit_up_to(&stream_id, &subscription_id, &watermark)
+        .await
+        .expect("Failed to commit up to timestamp");
+
+    // The next poll starts after the watermark
+    let response = client
+        .poll(&stream_id, &subscription_id, None)
+        .await
+        .expect("Failed to poll");
+    assert_eq!(sequences(&response), vec![3]);
+    assert_eq!(response.events[0].data, json!({ "late": true }));
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_commit_malformed_json_returns_400() {
+    let Some(client) = get_client() else { return };
+
+    let stream_id = unique_stream_id();
+    let subscription_id = unique_subscription_id();
+
+    // Create stream
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(1),
+            retention_hours: None,
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    client
+        .create_subscription(
+            &stream_id,
+            &CreateSubscriptionRequest {
+                subscription_id: subscription_id.clone(),
+                start_from: None,
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Failed to create subscription");
+
+    let result = client
+        .post_raw::<serde_json::Value>(
+            &format!(
+                "/streams/{}/subscriptions/{}/commit",
+                stream_id, subscription_id
+            ),
+            "application/json",
+            "{\n  \"cursor\": 42\n}",
+        )
+        .await;
+
+    let error = expect_validation_error(result);
+    let details = error.details.expect("Expected parse details");
+    assert_eq!(details["line"], 2);
+    assert!(details["reason"].as_str().unwrap().contains("invalid type"));
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_commit_rejects_cursor_from_other_subscription() {
+    let Some(client) = get_client() else { return };
+
+    let (stream_id, sub_a) = setup_poll_mode_stream(&client, 2).await;
+    let sub_b = unique_subscription_id();
+
+    client
+        .create_subscription(
+            &stream_id,
+            &CreateSubscriptionRequest {
+                subscription_id: sub_b.clone(),
+                start_from: Some(StartFrom::Earliest),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Failed to create subscription");
+
+    let response = client
+        .poll(&stream_id, &sub_a, None)
+        .await
+        .expect("Failed to poll");
+
+    // A's cursor must not move B's offsets
+    let result = client.commit(&stream_id, &sub_b, &response.cursor).await;
+    match result {
+        Err(ApiError::Http { status, body }) => {
+            assert_eq!(status.as_u16(), 400);
+            let error: ErrorResponse = serde_json::from_str(&body).unwrap();
+            assert_eq!(error.error, "invalid_cursor");
+        }
+        other => panic!("Expected invalid_cursor, got {:?}", other),
+    }
+
+    let b_poll = client
+        .poll(&stream_id, &sub_b, None)
+        .await
+        .expect("Failed to poll");
+    assert_eq!(sequences(&b_poll), vec![1, 2]);
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_list_offsets_returns_committed_offsets() {
+    let Some(client) = get_client() else { return };
+
+    let (stream_id, subscription_id) = setup_poll_mode_stream(&client, 3).await;
+
+    let response = client
+        .poll(&stream_id, &subscription_id, None)
+        .await
+        .expect("Failed to poll");
+    client
+        .commit(&stream_id, &subscription_id, &response.cursor)
+        .await
+        .expect("Failed to commit");
+
+    let offsets = client
+        .list_offsets(&stream_id, &subscription_id)
+        .await
+        .expect("Failed to list offsets")
+        .offsets;
+
+    assert_eq!(offsets.len(), 1);
+    assert_eq!(offsets[0].partition, 0);
+    assert_eq!(offsets[0].offset, 3);
+    assert_eq!(offsets[0].subscription_id, subscription_id);
+    assert!(!offsets[0].committed_at.is_empty());
+
+    // Unknown subscriptions are a 404
+    let result = client
+        .list_offsets(&stream_id, &unique_subscription_id())
+        .await;
+    match result {
+        Err(ApiError::Http { status, .. }) => assert_eq!(status.as_u16(), 404),
+        other => panic!("Expected 404, got {:?}", other),
+    }
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_commit_batch_reports_each_subscription() {
+    let Some(client) = get_client() else { return };
+
+    let stream_id = unique_stream_id();
+    let good_sub = unique_subscription_id();
+    let bad_sub = unique_subscription_id();
+
+    // Create stream
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(1),
+            retention_hours: None,
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    // Create two subscriptions starting from earliest
+    for subscription_id in [&good_sub, &bad_sub] {
+        client
+            .create_subscription(
+                &stream_id,
+                &CreateSubscriptionRequest {
+                    subscription_id: subscription_id.clone(),
+                    start_from: Some(StartFrom::Earliest),
+                    ..Default::default()
+                },
+            )
+            .await
+            .expect("Failed to create subscription");
+    }
+
+    client
+        .publish_event(
+            &stream_id,
+            PublishEvent {
+                key: unique_key(),
+                event_type: "test.event".to_string(),
+                data: json!({}),
+            },
+        )
+        .await
+        .expect("Failed to publish event");
+
+    let poll_response = client
+        .poll(&stream_id, &good_sub, Some(10))
+        .await
+        .expect("Failed to poll");
+    assert_eq!(poll_response.events.len(), 1);
+
+    // Commit a valid cursor and an invalid one together
+    let response = client
+        .commit_batch(
+            &stream_id,
+            vec![
+                SubscriptionCommit {
+                    subscription_id: good_sub.clone(),
+                    cursor: poll_response.cursor.clone(),
+                },
+                SubscriptionCommit {
+                    subscription_id: bad_sub.clone(),
+                    cursor: "not-a-cursor!".to_string(),
+                },
+            ],
+        )
+        .await
+        .expect("Failed to commit batch");
+
+    assert_eq!(response.results.len(), 2);
+    assert_eq!(response.results[0].subscription_id, good_sub);
+    assert!(response.results[0].success);
+    assert_eq!(response.results[1].subscription_id, bad_sub);
+    assert!(!response.results[1].success);
+    assert_eq!(
+        response.results[1].error.as_ref().map(|e| e.error.as_str()),
+        Some("invalid_cursor")
+    );
+
+    // The valid commit was applied, the invalid one left its subscription untouched
+    let good_poll = client
+        .poll(&stream_id, &good_sub, Some(10))
+        .await
+        .expect("Failed to poll");
+    assert!(good_poll.events.is_empty());
+
+    let bad_poll = client
+        .poll(&stream_id, &bad_sub, Some(10))
+        .await
+        .expect("Failed to poll");
+    assert_eq!(bad_poll.events.len(), 1);
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_same_key_goes_to_same_partition() {
+    let Some(client) = get_client() else { return };
+
+    let stream_id = unique_stream_id();
+    let key = unique_key();
+
+    // Create stream with multiple partitions
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(10),
+            retention_hours: None,
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    // Publish multiple events with same key
+    let mut partitions = Vec::new();
+    for i in 1..=10 {
+        let response = client
+            .publish_event(
                 &stream_id,
                 PublishEvent {
                     key: key.clone(),
-                    event_type: "counter.incremented".to_string(),
-                    data: json!({ "value": i }),
+                    event_type: "test.event".to_string(),
+                    data: json!({ "seq": i }),
                 },
             )
             .await
-            .expect("Failed to publish event");
+            .expect("Failed to publish event");
+
+        partitions.push(response.events[0].partition);
+    }
+
+    // All events should be in the same partition
+    let first_partition = partitions[0];
+    for p in &partitions {
+        assert_eq!(*p, first_partition, "Events with same key should go to same partition");
+    }
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_tail_pages_without_a_subscription() {
+    let Some(client) = get_client() else { return };
+
+    let stream_id = unique_stream_id();
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(3),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    let events = (0..7)
+        .map(|i| PublishEvent {
+            key: format!("key-{}", i),
+            event_type: "test.event".to_string(),
+            data: json!({ "i": i }),
+        })
+        .collect();
+    client
+        .publish_events(&stream_id, events)
+        .await
+        .expect("Failed to publish events");
+
+    // Page from the start with the returned cursor until nothing is left
+    let mut seen = Vec::new();
+    let mut options = TailOptions {
+        from: Some("earliest".to_string()),
+        limit: Some(3),
+        ..Default::default()
+    };
+    for _ in 0..10 {
+        let page = client
+            .tail(&stream_id, &options)
+            .await
+            .expect("Failed to tail");
+        if page.events.is_empty() {
+            break;
+        }
+        assert!(page.events.len() <= 3);
+        seen.extend(page.events.into_iter().map(|e| e.id));
+        options.cursor = Some(page.cursor);
     }
 
-    // Poll for events
-    let poll_response = client
-        .poll(&stream_id, &subscription_id, Some(10))
+    let mut unique = seen.clone();
+    unique.sort();
+    unique.dedup();
+    assert_eq!(seen.len(), 7);
+    assert_eq!(unique.len(), 7);
+
+    // Starting from latest skips what is already there
+    let latest = client
+        .tail(
+            &stream_id,
+            &TailOptions {
+                from: Some("latest".to_string()),
+                ..Default::default()
+            },
+        )
         .await
-        .expect("Failed to poll");
+        .expect("Failed to tail from latest");
+    assert!(latest.events.is_empty());
 
-    assert_eq!(poll_response.events.len(), 5);
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
 
-    // Verify event order and content
-    for (i, event) in poll_response.events.iter().enumerate() {
-        assert_eq!(event.key, key);
-        assert_eq!(event.event_type, "counter.incremented");
-        let value = event.data.get("value").unwrap().as_i64().unwrap();
-        assert_eq!(value, (i + 1) as i64);
-    }
+#[tokio::test]
+async fn test_snapshot_then_tail_misses_nothing() {
+    let Some(client) = get_client() else { return };
 
-    // Commit
-    let commit_response = client
-        .commit(&stream_id, &subscription_id, &poll_response.cursor)
+    let stream_id = unique_stream_id();
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(3),
+            ..Default::default()
+        })
         .await
-        .expect("Failed to commit");
+        .expect("Failed to create stream");
 
-    assert!(commit_response.success);
+    let round = |status: &str| -> Vec<PublishEvent> {
+        (0..5)
+            .map(|i| PublishEvent {
+                key: format!("key-{}", i),
+                event_type: format!("item.{}", status),
+                data: json!({ "status": status }),
+            })
+            .collect()
+    };
 
-    // Poll again - should get no new events
-    let poll_response2 = client
-        .poll(&stream_id, &subscription_id, Some(10))
+    client
+        .publish_events(&stream_id, round("created"))
         .await
-        .expect("Failed to poll again");
+        .expect("Failed to publish events");
+    client
+        .compact_stream(&stream_id)
+        .await
+        .expect("Failed to compact stream");
 
-    assert!(poll_response2.events.is_empty());
+    let snapshot = client
+        .snapshot(&stream_id)
+        .await
+        .expect("Failed to snapshot");
+    let mut keys: Vec<String> = snapshot.compacted.iter().map(|e| e.key.clone()).collect();
+    keys.sort();
+    assert_eq!(
+        keys,
+        (0..5).map(|i| format!("key-{}", i)).collect::<Vec<_>>()
+    );
+
+    let later = client
+        .publish_events(&stream_id, round("updated"))
+        .await
+        .expect("Failed to publish events");
+
+    // Tailing from the snapshot cursor picks up exactly what came after it
+    let tailed = client
+        .tail(
+            &stream_id,
+            &TailOptions {
+                cursor: Some(snapshot.cursor),
+                limit: Some(100),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Failed to tail from snapshot");
+    let mut tailed: Vec<String> = tailed.events.into_iter().map(|e| e.id).collect();
+    let mut expected: Vec<String> = later.events.into_iter().map(|e| e.id).collect();
+    tailed.sort();
+    expected.sort();
+    assert_eq!(tailed, expected);
 
     // Cleanup
     let _ = client.delete_stream(&stream_id).await;
 }
 
+// ============================================================================
+// Compaction Tests (requires waiting for compactor)
+// ============================================================================
+
 #[tokio::test]
-async fn test_same_key_goes_to_same_partition() {
+#[ignore] // Run manually: cargo test test_compaction -- --ignored
+async fn test_compaction_updates_latest_value() {
     let Some(client) = get_client() else { return };
 
     let stream_id = unique_stream_id();
     let key = unique_key();
 
-    // Create stream with multiple partitions
+    // Create stream
     client
         .create_stream(&CreateStreamRequest {
             stream_id: stream_id.clone(),
-            partition_count: Some(10),
+            partition_count: Some(1),
             retention_hours: None,
+            ..Default::default()
         })
         .await
         .expect("Failed to create stream");
 
-    // Publish multiple events with same key
-    let mut partitions = Vec::new();
-    for i in 1..=10 {
+    // Publish multiple updates for same key
+    let mut last_sequence = 0;
+    for status in ["created", "processing", "shipped", "delivered"] {
         let response = client
             .publish_event(
                 &stream_id,
                 PublishEvent {
                     key: key.clone(),
-                    event_type: "test.event".to_string(),
-                    data: json!({ "seq": i }),
+                    event_type: format!("order.{}", status),
+                    data: json!({ "status": status }),
                 },
             )
             .await
             .expect("Failed to publish event");
-
-        partitions.push(response.events[0].partition);
+        last_sequence = response.events[0].sequence;
     }
 
-    // All events should be in the same partition
-    let first_partition = partitions[0];
-    for p in &partitions {
-        assert_eq!(*p, first_partition, "Events with same key should go to same partition");
-    }
+    let timeout = std::time::Duration::from_secs(30);
+    let latest = await_compacted(&client, &stream_id, &key, last_sequence, timeout)
+        .await
+        .expect("Compactor should apply the last event");
+    assert_eq!(latest.sequence, last_sequence);
+
+    // The compacted state shows only the last event
+    let compacted = client
+        .list_compacted(&stream_id, &CompactedQuery::default())
+        .await
+        .expect("Failed to list compacted state");
+    assert_eq!(compacted.events.len(), 1);
+    assert_eq!(compacted.events[0].key, key);
+    assert_eq!(compacted.events[0].event_type, "order.delivered");
 
     // Cleanup
     let _ = client.delete_stream(&stream_id).await;
 }
 
-// ============================================================================
-// Compaction Tests (requires waiting for compactor)
-// ============================================================================
+#[tokio::test]
+async fn test_list_compacted_most_recently_updated_first() {
+    let Some(client) = get_client() else { return };
+
+    let stream_id = unique_stream_id();
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(2),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    // Publish one at a time so each key gets a distinct timestamp, then touch
+    // the first key again so it becomes the most recent
+    let keys: Vec<String> = (0..3).map(|_| unique_key()).collect();
+    for key in keys.iter().chain(std::iter::once(&keys[0])) {
+        client
+            .publish_event(
+                &stream_id,
+                PublishEvent {
+                    key: key.clone(),
+                    event_type: "test.event".to_string(),
+                    data: json!({}),
+                },
+            )
+            .await
+            .expect("Failed to publish event");
+        tokio::time::sleep(tokio::time::Duration::from_millis(20)).await;
+    }
+
+    let result = client
+        .compact_stream(&stream_id)
+        .await
+        .expect("Failed to compact stream");
+    assert!(result.complete);
+
+    let compacted = client
+        .list_compacted(
+            &stream_id,
+            &CompactedQuery {
+                sort: Some("updated_at".to_string()),
+                order: Some("desc".to_string()),
+                limit: Some(2),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Failed to list compacted state");
+    let listed: Vec<&str> = compacted.events.iter().map(|e| e.key.as_str()).collect();
+    assert_eq!(listed, vec![keys[0].as_str(), keys[2].as_str()]);
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
 
 #[tokio::test]
-#[ignore] // Run manually: cargo test test_compaction -- --ignored
-async fn test_compaction_updates_latest_value() {
+async fn test_compact_stream_resumes_from_returned_offsets() {
     let Some(client) = get_client() else { return };
 
     let stream_id = unique_stream_id();
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(1),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
     let key = unique_key();
+    let publish = |status: &'static str| PublishEvent {
+        key: key.clone(),
+        event_type: "test.event".to_string(),
+        data: json!({ "status": status }),
+    };
+    client
+        .publish_event(&stream_id, publish("created"))
+        .await
+        .expect("Failed to publish event");
+    let first = client
+        .compact_stream(&stream_id)
+        .await
+        .expect("Failed to compact stream");
+    assert!(first.complete);
 
-    // Create stream
+    // Resuming scans only what was published since
+    client
+        .publish_event(&stream_id, publish("shipped"))
+        .await
+        .expect("Failed to publish event");
+    let resumed = client
+        .resume_compaction(&stream_id, &first.offsets)
+        .await
+        .expect("Failed to compact stream");
+    assert!(resumed.complete);
+    assert_eq!(resumed.events_scanned, 1);
+
+    let compacted = client
+        .list_compacted(&stream_id, &CompactedQuery::default())
+        .await
+        .expect("Failed to list compacted state");
+    assert_eq!(compacted.events[0].data, json!({ "status": "shipped" }));
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_list_compacted_since_sequence() {
+    let Some(client) = get_client() else { return };
+
+    let stream_id = unique_stream_id();
     client
         .create_stream(&CreateStreamRequest {
             stream_id: stream_id.clone(),
             partition_count: Some(1),
-            retention_hours: None,
+            ..Default::default()
         })
         .await
         .expect("Failed to create stream");
 
-    // Publish multiple updates for same key
-    for status in ["created", "processing", "shipped", "delivered"] {
-        client
+    let keys: Vec<String> = (0..3).map(|_| unique_key()).collect();
+    let mut sequences = Vec::new();
+    for key in keys.iter().chain(std::iter::once(&keys[1])) {
+        let published = client
             .publish_event(
                 &stream_id,
                 PublishEvent {
                     key: key.clone(),
-                    event_type: format!("order.{}", status),
-                    data: json!({ "status": status }),
+                    event_type: "test.event".to_string(),
+                    data: json!({}),
                 },
             )
             .await
             .expect("Failed to publish event");
+        sequences.push(published.events[0].sequence);
+    }
+    // Everything up to the first round, so only the re-published key is newer
+    let threshold = sequences[2];
+
+    let result = client
+        .compact_stream(&stream_id)
+        .await
+        .expect("Failed to compact stream");
+    assert!(result.complete);
+
+    // Only the key touched after the threshold, in either sort order
+    for sort in ["key", "updated_at"] {
+        let compacted = client
+            .list_compacted(
+                &stream_id,
+                &CompactedQuery {
+                    sort: Some(sort.to_string()),
+                    since_sequence: Some(threshold),
+                    ..Default::default()
+                },
+            )
+            .await
+            .expect("Failed to list compacted state");
+        let listed: Vec<&str> = compacted.events.iter().map(|e| e.key.as_str()).collect();
+        assert_eq!(listed, vec![keys[1].as_str()], "sort={}", sort);
+    }
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_list_compacted_pages_through_all_keys() {
+    let Some(client) = get_client() else { return };
+
+    let stream_id = unique_stream_id();
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(4),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    let mut keys: Vec<String> = (0..25).map(|i| format!("key-{:02}", i)).collect();
+    let events = keys
+        .iter()
+        .map(|key| PublishEvent {
+            key: key.clone(),
+            event_type: "test.event".to_string(),
+            data: json!({}),
+        })
+        .collect();
+    client
+        .publish_events(&stream_id, events)
+        .await
+        .expect("Failed to publish events");
+    let result = client
+        .compact_stream(&stream_id)
+        .await
+        .expect("Failed to compact stream");
+    assert!(result.complete);
+
+    // Follow next_start_key until the listing runs out
+    let mut listed = Vec::new();
+    let mut start_key = None;
+    loop {
+        let page = client
+            .list_compacted(
+                &stream_id,
+                &CompactedQuery {
+                    limit: Some(10),
+                    start_key: start_key.clone(),
+                    ..Default::default()
+                },
+            )
+            .await
+            .expect("Failed to list compacted state");
+        assert!(page.events.len() <= 10);
+        listed.extend(page.events.into_iter().map(|e| e.key));
+        match page.next_start_key {
+            Some(key) => start_key = Some(key),
+            None => break,
+        }
     }
+    keys.sort();
+    assert_eq!(listed, keys);
+
+    // Newest first pages the same way, visiting every key once
+    let mut listed = Vec::new();
+    let mut start_key = None;
+    loop {
+        let page = client
+            .list_compacted(
+                &stream_id,
+                &CompactedQuery {
+                    sort: Some("updated_at".to_string()),
+                    order: Some("desc".to_string()),
+                    limit: Some(10),
+                    start_key: start_key.clone(),
+                    ..Default::default()
+                },
+            )
+            .await
+            .expect("Failed to list compacted state");
+        assert!(page.events.len() <= 10);
+        listed.extend(page.events.into_iter().map(|e| e.key));
+        match page.next_start_key {
+            Some(key) => start_key = Some(key),
+            None => break,
+        }
+    }
+    listed.sort();
+    assert_eq!(listed, keys);
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_list_dlq() {
+    let Some(client) = get_client() else { return };
+
+    // Well-formed events never reach the dead-letter queue
+    let (stream_id, _) = setup_poll_mode_stream(&client, 2).await;
+    let dlq = client
+        .list_dlq(&stream_id)
+        .await
+        .expect("Failed to list DLQ");
+    assert!(dlq.records.is_empty());
+
+    let result = client.list_dlq(&unique_stream_id()).await;
+    assert!(matches!(result, Err(ApiError::Http { status, .. }) if status.as_u16() == 404));
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_create_stream_with_compacted_ttl() {
+    let Some(client) = get_client() else { return };
 
-    // Wait for compactor (in real test, check compacted endpoint)
-    tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+    let stream_id = unique_stream_id();
+    let stream = client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            retention_hours: Some(24),
+            compacted_ttl_hours: Some(24 * 30),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+    assert_eq!(stream.compacted_ttl_hours, Some(24 * 30));
+
+    let error = expect_validation_error(
+        client
+            .create_stream(&CreateStreamRequest {
+                stream_id: unique_stream_id(),
+                compacted_ttl_hours: Some(0),
+                ..Default::default()
+            })
+            .await,
+    );
+    assert!(error.message.contains("compacted_ttl_hours"));
+
+    // Cleanup
+    let _ = client.delete_stream(&stream_id).await;
+}
+
+#[tokio::test]
+async fn test_empty_key_strategy_requires_compaction_off() {
+    let Some(client) = get_client() else { return };
+
+    let error = expect_validation_error(
+        client
+            .create_stream(&CreateStreamRequest {
+                stream_id: unique_stream_id(),
+                empty_key_strategy: Some(EmptyKeyStrategy::RoundRobin),
+                ..Default::default()
+            })
+            .await,
+    );
+    assert!(error.message.contains("compaction"));
 
-    // TODO: Add endpoint to get compacted state and verify
-    // The compacted state should show only the last event (delivered)
+    let stream_id = unique_stream_id();
+    let stream = client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            empty_key_strategy: Some(EmptyKeyStrategy::RoundRobin),
+            compaction: Some(false),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+    assert_eq!(stream.compaction, Some(false));
 
     // Cleanup
     let _ = client.delete_stream(&stream_id).await;
 }
+
+#[tokio::test]
+async fn test_capabilities_advertise_limits() {
+    let Some(client) = get_client() else { return };
+
+    let capabilities = client
+        .capabilities()
+        .await
+        .expect("Failed to get capabilities");
+    assert_eq!(capabilities.limits.max_publish_batch, MAX_PUBLISH_BATCH);
+    assert_eq!(capabilities.features.get("compaction"), Some(&true));
+}
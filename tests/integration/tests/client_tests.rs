@@ -0,0 +1,306 @@
+//! Tests of the API client and its fixtures, against a stub server on localhost
+//!
+//! Run with: cargo test --test client_tests
+//!
+//! These need no deployment, so they always run.
+
+use eventledger_integration_tests::client::{
+    ApiError, EventLedgerClient, PublishEvent, DEFAULT_TIMEOUT, MAX_PUBLISH_BATCH,
+};
+use eventledger_integration_tests::fixtures::await_compacted;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Answer one request with an empty stream list after `delay`, returning the
+/// server's base URL
+fn slow_server(delay: Duration) -> (String, JoinHandle<()>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        read_request(&mut stream);
+        std::thread::sleep(delay);
+        // The client may already have given up
+        let _ = respond(&mut stream, r#"{"streams": []}"#);
+    });
+    (url, server)
+}
+
+/// Answer every request with `body`, returning the server's base URL and
+/// each request it received
+fn stub_server(body: &'static str) -> (String, Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let (sender, requests) = mpsc::channel();
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().map_while(Result::ok) {
+            let request = read_request(&mut stream);
+            if sender.send(request).is_err() || respond(&mut stream, body).is_err() {
+                break;
+            }
+        }
+    });
+    (url, requests)
+}
+
+/// Read a request, including any body its `Content-Length` announces
+fn read_request(stream: &mut TcpStream) -> String {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 4096];
+    let header_end = loop {
+        if let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+            break end + 4;
+        }
+        let n = stream.read(&mut buf).unwrap();
+        request.extend_from_slice(&buf[..n]);
+    };
+    let headers = String::from_utf8_lossy(&request[..header_end]).to_lowercase();
+    let content_length: usize = headers
+        .lines()
+        .find_map(|line| line.strip_prefix("content-length:"))
+        .map_or(0, |len| len.trim().parse().unwrap());
+    while request.len() < header_end + content_length {
+        let n = stream.read(&mut buf).unwrap();
+        request.extend_from_slice(&buf[..n]);
+    }
+    String::from_utf8_lossy(&request).into_owned()
+}
+
+/// Events in each publish request received so far
+fn published_batches(requests: &Receiver<String>) -> Vec<usize> {
+    requests
+        .try_iter()
+        .map(|request| batch_size(&request))
+        .collect()
+}
+
+/// Size of the next batch published within `timeout`, if one is
+async fn next_published_batch(requests: &Receiver<String>, timeout: Duration) -> Option<usize> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Ok(request) = requests.try_recv() {
+            return Some(batch_size(&request));
+        }
+        if Instant::now() >= deadline {
+            return None;
+        }
+        // The batcher's timer runs on this runtime, so wait without blocking it
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
+
+/// Number of events a publish request carries
+fn batch_size(request: &str) -> usize {
+    let (_, body) = request.split_once("\r\n\r\n").unwrap();
+    let body: serde_json::Value = serde_json::from_str(body).unwrap();
+    body["events"].as_array().unwrap().len()
+}
+
+fn respond(stream: &mut TcpStream, body: &str) -> std::io::Result<()> {
+    let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nConnection: close\r\nContent-Length: {}\r\n\r\n{}",
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes())
+}
+
+#[tokio::test]
+async fn test_configured_timeout_is_honored() {
+    let (url, server) = slow_server(Duration::from_millis(500));
+    let client = EventLedgerClient::new(&url).with_timeout(Duration::from_millis(100));
+    let err = client
+        .list_streams()
+        .await
+        .expect_err("Request should time out");
+    assert!(matches!(err, ApiError::Request(_)), "{}", err);
+    server.join().unwrap();
+
+    let (url, server) = slow_server(Duration::from_millis(500));
+    let client = EventLedgerClient::new(&url).with_timeout(Duration::from_secs(45));
+    let streams = client
+        .list_streams()
+        .await
+        .expect("Request should outlast the delay");
+    assert!(streams.streams.is_empty());
+    server.join().unwrap();
+}
+
+#[tokio::test]
+async fn test_with_base_url_switches_deployment() {
+    let (url, server) = slow_server(Duration::ZERO);
+    let client = EventLedgerClient::new("http://127.0.0.1:9/").with_base_url(&format!("{}/", url));
+    client
+        .list_streams()
+        .await
+        .expect("Request should reach the new base URL");
+    server.join().unwrap();
+}
+
+/// A 45s timeout outlasts an endpoint slower than the 30s default
+#[tokio::test]
+#[ignore] // Slow: cargo test --test client_tests -- --ignored
+async fn test_long_timeout_outlasts_default() {
+    let (url, server) = slow_server(DEFAULT_TIMEOUT + Duration::from_secs(5));
+    let client = EventLedgerClient::new(&url).with_timeout(Duration::from_secs(45));
+    client
+        .list_streams()
+        .await
+        .expect("Request should outlast the default timeout");
+    server.join().unwrap();
+}
+
+#[tokio::test]
+async fn test_await_compacted_returns_seeded_entry() {
+    let (url, requests) = stub_server(
+        r#"{"events": [{"stream_id": "orders", "key": "order-1", "event_type": "order.shipped",
+            "data": {}, "sequence": 7, "partition": 0, "timestamp": "2025-01-01T00:00:00Z"}]}"#,
+    );
+    let client = EventLedgerClient::new(&url);
+
+    let entry = await_compacted(&client, "orders", "order-1", 7, Duration::from_secs(5))
+        .await
+        .expect("Seeded entry should be found");
+    assert_eq!(entry.event_type, "order.shipped");
+    assert_eq!(entry.sequence, 7);
+
+    // Only entries at or past the expected sequence are asked for
+    let request = requests.recv().unwrap();
+    let request_line = request.lines().next().unwrap();
+    assert!(
+        request_line.contains("since_sequence=6"),
+        "{}",
+        request_line
+    );
+}
+
+#[tokio::test]
+async fn test_await_compacted_times_out_with_a_clear_error() {
+    let (url, requests) = stub_server(r#"{"events": []}"#);
+    let client = EventLedgerClient::new(&url);
+
+    let err = await_compacted(&client, "orders", "order-1", 7, Duration::from_millis(600))
+        .await
+        .expect_err("Nothing was compacted");
+    assert!(
+        err.contains("'order-1'") && err.contains("sequence 7"),
+        "{}",
+        err
+    );
+
+    // It kept re-reading until the timeout rather than giving up at once
+    assert!(requests.try_iter().count() > 1);
+}
+
+#[tokio::test]
+async fn test_batcher_publishes_in_few_requests() {
+    let (url, requests) = stub_server(r#"{"events": []}"#);
+    let client = EventLedgerClient::new(&url);
+    let batcher = client.batcher("orders", 20, Duration::from_secs(60));
+
+    for i in 0..50 {
+        let event = PublishEvent {
+            key: format!("order-{}", i),
+            event_type: "order.created".to_string(),
+            data: serde_json::json!({ "n": i }),
+        };
+        batcher.send(event).await.expect("Failed to buffer event");
+    }
+    batcher
+        .close()
+        .await
+        .expect("Failed to flush the last batch");
+
+    // Two full batches, then the remainder on close
+    assert_eq!(published_batches(&requests), vec![20, 20, 10]);
+}
+
+#[tokio::test]
+async fn test_batcher_flushes_after_max_delay() {
+    let (url, requests) = stub_server(r#"{"events": []}"#);
+    let client = EventLedgerClient::new(&url);
+    let batcher = client.batcher("orders", 100, Duration::from_millis(50));
+
+    batcher.send(PublishEvent::default()).await.unwrap();
+    batcher.send(PublishEvent::default()).await.unwrap();
+
+    assert_eq!(
+        next_published_batch(&requests, Duration::from_secs(5)).await,
+        Some(2)
+    );
+    batcher.close().await.unwrap();
+    assert!(published_batches(&requests).is_empty());
+}
+
+#[tokio::test]
+async fn test_batcher_times_each_batch_from_its_first_event() {
+    let (url, requests) = stub_server(r#"{"events": []}"#);
+    let client = EventLedgerClient::new(&url);
+    let max_delay = Duration::from_millis(400);
+    let batcher = client.batcher("orders", 2, max_delay);
+
+    // The first batch fills while the timer waits on it, and the next starts
+    // before that wait is over
+    batcher.send(PublishEvent::default()).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    batcher.send(PublishEvent::default()).await.unwrap();
+    assert_eq!(published_batches(&requests), vec![2]);
+    let started = Instant::now();
+    batcher.send(PublishEvent::default()).await.unwrap();
+
+    // It still goes after about max_delay, not after the first batch's wait
+    // and then a whole max_delay more
+    assert_eq!(
+        next_published_batch(&requests, Duration::from_secs(5)).await,
+        Some(1)
+    );
+    let waited = started.elapsed();
+    assert!(
+        waited >= max_delay - Duration::from_millis(50),
+        "{:?}",
+        waited
+    );
+    assert!(
+        waited < max_delay + Duration::from_millis(250),
+        "{:?}",
+        waited
+    );
+    batcher.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_batcher_caps_batches_at_the_publish_limit() {
+    let (url, requests) = stub_server(r#"{"events": []}"#);
+    let client = EventLedgerClient::new(&url);
+    let batcher = client.batcher("orders", MAX_PUBLISH_BATCH * 2, Duration::from_secs(60));
+
+    for _ in 0..MAX_PUBLISH_BATCH {
+        batcher.send(PublishEvent::default()).await.unwrap();
+    }
+
+    // Full at the API's limit, rather than waiting for a batch it would reject
+    assert_eq!(published_batches(&requests), vec![MAX_PUBLISH_BATCH]);
+    batcher.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_batcher_reports_publish_errors() {
+    // Nothing listens here, so every publish fails
+    let client = EventLedgerClient::new("http://127.0.0.1:9");
+    let batcher = client.batcher("orders", 2, Duration::from_millis(50));
+
+    batcher.send(PublishEvent::default()).await.unwrap();
+    let err = batcher
+        .send(PublishEvent::default())
+        .await
+        .expect_err("Full batch should fail");
+    assert!(matches!(err, ApiError::Request(_)), "{}", err);
+
+    // A failed flush on time surfaces on the next call
+    batcher.send(PublishEvent::default()).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert!(batcher.close().await.is_err());
+}
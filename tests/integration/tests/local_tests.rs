@@ -0,0 +1,2400 @@
+//! Tests against local DynamoDB
+//!
+//! Run with: EVENTLEDGER_DYNAMO_ENDPOINT=http://localhost:8000 cargo test --test local_tests
+//!
+//! Start a local instance with `just dynamodb-local`.
+
+use aws_sdk_dynamodb::types::AttributeValue;
+use eventledger_core::{
+    CompactedEvent, CompactionBackfillRequest, CreateStreamRequest, CreateSubscriptionRequest,
+    CursorState, DynamoClient, EmptyKeyStrategy, Error, IdempotencyRecord, PartitionOffset,
+    Partitioner, PublishEvent, RepartitionRequest, RepartitionResult, UpdateStreamRequest,
+};
+use eventledger_integration_tests::fixtures::{
+    ensure_local_table, local_dynamo_client, local_dynamo_endpoint, unique_stream_id,
+};
+use pretty_assertions::assert_eq;
+use serde_json::json;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Bootstrap a fresh table and return a core client bound to it
+async fn setup_table(sdk_client: &aws_sdk_dynamodb::Client) -> (DynamoClient, String) {
+    let table_name = format!("eventledger-{}", unique_stream_id());
+    ensure_local_table(sdk_client, &table_name)
+        .await
+        .expect("Failed to bootstrap table");
+    let client = DynamoClient::with_table_name(sdk_client.clone(), table_name.clone());
+    (client, table_name)
+}
+
+/// Helper to get a local DynamoDB client or skip test
+async fn get_local_client() -> Option<aws_sdk_dynamodb::Client> {
+    match local_dynamo_endpoint() {
+        Some(endpoint) => Some(local_dynamo_client(&endpoint).await),
+        None => {
+            eprintln!("Skipping: EVENTLEDGER_DYNAMO_ENDPOINT not set");
+            None
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_bootstrap_local_table_and_create_stream() {
+    let Some(sdk_client) = get_local_client().await else {
+        return;
+    };
+
+    let table_name = format!("eventledger-{}", unique_stream_id());
+
+    // Bootstrapping twice is a no-op the second time
+    ensure_local_table(&sdk_client, &table_name)
+        .await
+        .expect("Failed to bootstrap table");
+    ensure_local_table(&sdk_client, &table_name)
+        .await
+        .expect("Bootstrap should be idempotent");
+
+    // Round trip a stream through the bootstrapped table
+    let client = DynamoClient::with_table_name(sdk_client.clone(), table_name.clone());
+    let stream_id = unique_stream_id();
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(2),
+            retention_hours: Some(24),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    let stream = client
+        .get_stream(&stream_id)
+        .await
+        .expect("Failed to get stream");
+    assert_eq!(stream.stream_id, stream_id);
+    assert_eq!(stream.partition_count, 2);
+
+    // Cleanup
+    let _ = sdk_client
+        .delete_table()
+        .table_name(&table_name)
+        .send()
+        .await;
+}
+
+#[tokio::test]
+async fn test_backfill_compacted_keeps_latest_per_key() {
+    let Some(sdk_client) = get_local_client().await else {
+        return;
+    };
+    let (client, table_name) = setup_table(&sdk_client).await;
+
+    let stream_id = unique_stream_id();
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(2),
+            retention_hours: None,
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    // Several updates per key, written without a compactor running
+    let mut events = Vec::new();
+    for status in ["created", "processing", "shipped"] {
+        for key in ["order-1", "order-2", "order-3"] {
+            events.push(PublishEvent {
+                key: key.to_string(),
+                partition_key: None,
+                order_key: None,
+                timestamp: None,
+                event_type: format!("order.{}", status).parse().unwrap(),
+                data: json!({ "status": status }),
+            });
+        }
+    }
+    client
+        .publish_events(&stream_id, &events)
+        .await
+        .expect("Failed to publish events");
+
+    let result = client
+        .backfill_compacted(
+            &stream_id,
+            &CompactionBackfillRequest::default(),
+            Duration::from_secs(30),
+        )
+        .await
+        .expect("Failed to backfill");
+
+    assert!(result.complete);
+    assert_eq!(result.events_scanned, 9);
+    assert_eq!(result.keys_compacted, 3);
+    assert_eq!(result.partitions_completed, 2);
+
+    for key in ["order-1", "order-2", "order-3"] {
+        let compacted = client
+            .get_compacted(&stream_id, key)
+            .await
+            .expect("Failed to get compacted")
+            .expect("Missing compacted state");
+        assert_eq!(compacted.event_type, "order.shipped");
+        assert_eq!(compacted.data, json!({ "status": "shipped" }));
+    }
+
+    // Re-running finds nothing newer to write
+    let rerun = client
+        .backfill_compacted(
+            &stream_id,
+            &CompactionBackfillRequest::default(),
+            Duration::from_secs(30),
+        )
+        .await
+        .expect("Failed to backfill");
+    assert_eq!(rerun.keys_compacted, 0);
+
+    // Resuming from where it stopped has nothing left to scan
+    let resumed = client
+        .backfill_compacted(
+            &stream_id,
+            &CompactionBackfillRequest {
+                from_offsets: Some(result.offsets.clone()),
+            },
+            Duration::from_secs(30),
+        )
+        .await
+        .expect("Failed to backfill");
+    assert!(resumed.complete);
+    assert_eq!(resumed.events_scanned, 0);
+    assert_eq!(
+        resumed
+            .offsets
+            .iter()
+            .map(|p| (p.partition, p.offset))
+            .collect::<Vec<_>>(),
+        result
+            .offsets
+            .iter()
+            .map(|p| (p.partition, p.offset))
+            .collect::<Vec<_>>()
+    );
+
+    // Cleanup
+    let _ = sdk_client
+        .delete_table()
+        .table_name(&table_name)
+        .send()
+        .await;
+}
+
+#[tokio::test]
+async fn test_backfill_orders_a_key_across_partitions_by_time() {
+    let Some(sdk_client) = get_local_client().await else {
+        return;
+    };
+    let (client, table_name) = setup_table(&sdk_client).await;
+    let stream_id = unique_stream_id();
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(2),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    // Routing keys for each partition
+    let partitioner = Partitioner::new(2);
+    let route = |partition| {
+        (0..)
+            .map(|i| format!("customer-{}", i))
+            .find(|key| partitioner.partition(key) == partition)
+            .unwrap()
+    };
+
+    // order-1 is updated three times through partition 0, then moves to
+    // partition 1, where its latest event gets a lower sequence
+    let update = |status: &str, partition_key: String, timestamp: &str| PublishEvent {
+        key: "order-1".to_string(),
+        partition_key: Some(partition_key),
+        order_key: None,
+        timestamp: timestamp.parse().ok(),
+        event_type: format!("order.{}", status).parse().unwrap(),
+        data: json!({ "status": status }),
+    };
+    let events = vec![
+        update("created", route(0), "2025-01-01T00:00:00Z"),
+        update("paid", route(0), "2025-01-01T00:01:00Z"),
+        update("packed", route(0), "2025-01-01T00:02:00Z"),
+        update("shipped", route(1), "2025-01-01T00:03:00Z"),
+    ];
+    client
+        .publish_events(&stream_id, &events)
+        .await
+        .expect("Failed to publish events");
+
+    // Whichever partition is scanned first, the latest event wins
+    for _ in 0..2 {
+        client
+            .backfill_compacted(
+                &stream_id,
+                &CompactionBackfillRequest::default(),
+                Duration::from_secs(30),
+            )
+            .await
+            .expect("Failed to backfill");
+        let compacted = client
+            .get_compacted(&stream_id, "order-1")
+            .await
+            .expect("Failed to get compacted")
+            .expect("Missing compacted state");
+        assert_eq!(compacted.event_type, "order.shipped");
+        assert_eq!((compacted.partition, compacted.sequence), (1, 1));
+    }
+
+    // Cleanup
+    let _ = sdk_client
+        .delete_table()
+        .table_name(&table_name)
+        .send()
+        .await;
+}
+
+#[tokio::test]
+async fn test_compacted_snapshot_continues_without_gaps() {
+    let Some(sdk_client) = get_local_client().await else {
+        return;
+    };
+    let (client, table_name) = setup_table(&sdk_client).await;
+
+    let stream_id = unique_stream_id();
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(3),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    let round = |status: &str| -> Vec<PublishEvent> {
+        ["order-1", "order-2", "order-3", "order-4"]
+            .iter()
+            .map(|key| PublishEvent {
+                key: key.to_string(),
+                partition_key: None,
+                order_key: None,
+                timestamp: None,
+                event_type: format!("order.{}", status).parse().unwrap(),
+                data: json!({ "status": status }),
+            })
+            .collect()
+    };
+
+    client
+        .publish_events(&stream_id, &round("created"))
+        .await
+        .expect("Failed to publish events");
+    client
+        .backfill_compacted(
+            &stream_id,
+            &CompactionBackfillRequest::default(),
+            Duration::from_secs(30),
+        )
+        .await
+        .expect("Failed to backfill");
+
+    let (compacted, offsets) = client
+        .compacted_snapshot(&stream_id)
+        .await
+        .expect("Failed to snapshot");
+    assert_eq!(compacted.len(), 4);
+    assert_eq!(offsets.iter().map(|o| o.offset).sum::<u64>(), 4);
+
+    let later = client
+        .publish_events(&stream_id, &round("shipped"))
+        .await
+        .expect("Failed to publish events");
+
+    // Reading on from the snapshot offsets returns exactly the later events
+    let mut tailed = Vec::new();
+    for po in &offsets {
+        let events = client
+            .read_events(&stream_id, po.partition, po.offset, 100, true)
+            .await
+            .expect("Failed to read events");
+        tailed.extend(events.into_iter().map(|e| e.id));
+    }
+    let mut expected: Vec<String> = later.into_iter().map(|e| e.id).collect();
+    tailed.sort();
+    expected.sort();
+    assert_eq!(tailed, expected);
+
+    // Nothing compacts the later events here, as when the compactor lags;
+    // the snapshot still reflects them
+    let (compacted, offsets) = client
+        .compacted_snapshot(&stream_id)
+        .await
+        .expect("Failed to snapshot");
+    assert_eq!(offsets.iter().map(|o| o.offset).sum::<u64>(), 8);
+    let types: Vec<&str> = compacted.iter().map(|e| e.event_type.as_str()).collect();
+    assert_eq!(types, vec!["order.shipped"; 4]);
+
+    // Cleanup
+    let _ = sdk_client
+        .delete_table()
+        .table_name(&table_name)
+        .send()
+        .await;
+}
+
+#[tokio::test]
+async fn test_compaction_changelog_records_each_update_in_order() {
+    let Some(sdk_client) = get_local_client().await else {
+        return;
+    };
+    let (client, table_name) = setup_table(&sdk_client).await;
+
+    let stream_id = unique_stream_id();
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(1),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    // One compaction per status, so each becomes its own changelog entry
+    for status in ["created", "processing", "shipped"] {
+        let event = PublishEvent {
+            key: "order-1".to_string(),
+            partition_key: None,
+            order_key: None,
+            timestamp: None,
+            event_type: format!("order.{}", status).parse().unwrap(),
+            data: json!({ "status": status }),
+        };
+        client
+            .publish_events(&stream_id, &[event])
+            .await
+            .expect("Failed to publish event");
+        client
+            .backfill_compacted(
+                &stream_id,
+                &CompactionBackfillRequest::default(),
+                Duration::from_secs(30),
+            )
+            .await
+            .expect("Failed to backfill");
+    }
+
+    let changes = client
+        .read_compaction_changelog(&stream_id, 0, 100)
+        .await
+        .expect("Failed to read changelog");
+    let types: Vec<_> = changes
+        .iter()
+        .map(|c| c.state.event_type.as_str())
+        .collect();
+    assert_eq!(
+        types,
+        vec!["order.created", "order.processing", "order.shipped"]
+    );
+    assert!(changes.windows(2).all(|w| w[0].position < w[1].position));
+
+    // Continuing from a position returns only what came after it
+    let rest = client
+        .read_compaction_changelog(&stream_id, changes[0].position, 100)
+        .await
+        .expect("Failed to read changelog");
+    assert_eq!(rest.len(), 2);
+    assert_eq!(rest[0].position, changes[1].position);
+
+    let page = client
+        .read_compaction_changelog(&stream_id, 0, 1)
+        .await
+        .expect("Failed to read changelog");
+    assert_eq!(page.len(), 1);
+    assert_eq!(page[0].state.event_type, "order.created");
+
+    // Cleanup
+    let _ = sdk_client
+        .delete_table()
+        .table_name(&table_name)
+        .send()
+        .await;
+}
+
+#[tokio::test]
+async fn test_publish_rejects_duplicate_sequence() {
+    let Some(sdk_client) = get_local_client().await else {
+        return;
+    };
+    let (client, table_name) = setup_table(&sdk_client).await;
+
+    let stream_id = unique_stream_id();
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(1),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    let original = PublishEvent {
+        key: "order-1".to_string(),
+        partition_key: None,
+        order_key: None,
+        timestamp: None,
+        event_type: "order.created".parse().unwrap(),
+        data: json!({ "original": true }),
+    };
+    client
+        .publish_events(&stream_id, &[original])
+        .await
+        .expect("Failed to publish event");
+
+    // Corrupt the counter so the next publish reuses sequence 1
+    sdk_client
+        .put_item()
+        .table_name(&table_name)
+        .item("PK", AttributeValue::S(format!("STREAM#{}#P0", stream_id)))
+        .item("SK", AttributeValue::S("COUNTER".to_string()))
+        .item("sequence", AttributeValue::N("0".to_string()))
+        .send()
+        .await
+        .expect("Failed to reset counter");
+
+    let duplicate = PublishEvent {
+        key: "order-1".to_string(),
+        partition_key: None,
+        order_key: None,
+        timestamp: None,
+        event_type: "order.created".parse().unwrap(),
+        data: json!({ "original": false }),
+    };
+    let err = client
+        .publish_events(&stream_id, &[duplicate])
+        .await
+        .expect_err("Duplicate sequence should be rejected");
+    assert_eq!(err.code(), "internal_error");
+
+    // The original event is untouched
+    let events = client
+        .read_events(&stream_id, 0, 0, 10, true)
+        .await
+        .expect("Failed to read events");
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].data, json!({ "original": true }));
+
+    // Cleanup
+    let _ = sdk_client
+        .delete_table()
+        .table_name(&table_name)
+        .send()
+        .await;
+}
+
+#[tokio::test]
+async fn test_update_stream_checks_version() {
+    let Some(sdk_client) = get_local_client().await else {
+        return;
+    };
+    let (client, table_name) = setup_table(&sdk_client).await;
+
+    let stream_id = unique_stream_id();
+    let stream = client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            schema: Some(json!({ "type": "object" })),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    let update = UpdateStreamRequest {
+        retention_hours: Some(12),
+        allowed_event_types: Some(vec!["order.created".to_string()]),
+        schema: Some(json!({ "type": "object", "required": ["total"] })),
+        max_publish_per_second: Some(100),
+        ..Default::default()
+    };
+    let updated = client
+        .update_stream(&stream_id, stream.version, &update)
+        .await
+        .expect("Failed to update stream");
+    assert_eq!(updated.version, stream.version + 1);
+    assert_eq!(updated.retention_hours, 12);
+    assert_eq!(updated.schema, update.schema);
+    assert_eq!(updated.max_publish_per_second, Some(100));
+
+    // Reusing the old version is a conflict and leaves the stream alone
+    let err = client
+        .update_stream(
+            &stream_id,
+            stream.version,
+            &UpdateStreamRequest {
+                retention_hours: Some(1),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect_err("Stale version should be rejected");
+    assert_eq!(err.status_code(), 412);
+
+    let fetched = client
+        .get_stream(&stream_id)
+        .await
+        .expect("Failed to get stream");
+    assert_eq!(fetched.retention_hours, 12);
+    assert_eq!(fetched.allowed_event_types, update.allowed_event_types);
+
+    // Cleanup
+    let _ = sdk_client
+        .delete_table()
+        .table_name(&table_name)
+        .send()
+        .await;
+}
+
+#[tokio::test]
+async fn test_list_streams_filters_by_prefix() {
+    let Some(sdk_client) = get_local_client().await else {
+        return;
+    };
+    let (client, table_name) = setup_table(&sdk_client).await;
+
+    for stream_id in ["prod-orders", "prod-payments", "staging-orders", "products"] {
+        client
+            .create_stream(&CreateStreamRequest {
+                stream_id: stream_id.to_string(),
+                ..Default::default()
+            })
+            .await
+            .expect("Failed to create stream");
+    }
+
+    let (listed, next) = client
+        .list_streams_page(Some("prod-"), None, 10)
+        .await
+        .expect("Failed to list streams");
+    let listed: Vec<String> = listed.into_iter().map(|s| s.stream_id).collect();
+    assert_eq!(listed, vec!["prod-orders", "prod-payments"]);
+    assert!(next.is_none());
+
+    // Pages follow on in ID order
+    let (first, next) = client
+        .list_streams_page(None, None, 3)
+        .await
+        .expect("Failed to list streams");
+    let next = next.expect("More streams should remain");
+    let (rest, _) = client
+        .list_streams_page(None, Some(&next), 3)
+        .await
+        .expect("Failed to list streams");
+    let all: Vec<String> = first.into_iter().chain(rest).map(|s| s.stream_id).collect();
+    assert_eq!(
+        all,
+        vec!["prod-orders", "prod-payments", "products", "staging-orders"]
+    );
+
+    // Cleanup
+    let _ = sdk_client
+        .delete_table()
+        .table_name(&table_name)
+        .send()
+        .await;
+}
+
+#[tokio::test]
+async fn test_stream_tags_are_stored_and_replaced() {
+    let Some(sdk_client) = get_local_client().await else {
+        return;
+    };
+    let (client, table_name) = setup_table(&sdk_client).await;
+
+    let stream_id = unique_stream_id();
+    let tags = HashMap::from([("team".to_string(), "payments".to_string())]);
+    let stream = client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            tags: tags.clone(),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+    let fetched = client
+        .get_stream(&stream_id)
+        .await
+        .expect("Failed to get stream");
+    assert_eq!(fetched.tags, tags);
+
+    let update = UpdateStreamRequest {
+        tags: Some(HashMap::from([("env".to_string(), "prod".to_string())])),
+        ..Default::default()
+    };
+    client
+        .update_stream(&stream_id, stream.version, &update)
+        .await
+        .expect("Failed to update tags");
+    let fetched = client
+        .get_stream(&stream_id)
+        .await
+        .expect("Failed to get stream");
+    assert_eq!(Some(fetched.tags), update.tags);
+
+    // Keys that couldn't be filtered on are refused
+    let err = client
+        .update_stream(
+            &stream_id,
+            fetched.version,
+            &UpdateStreamRequest {
+                tags: Some(HashMap::from([("a:b".to_string(), "c".to_string())])),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect_err("Tag key with ':' should be rejected");
+    assert_eq!(err.code(), "validation_error");
+
+    // Cleanup
+    let _ = sdk_client
+        .delete_table()
+        .table_name(&table_name)
+        .send()
+        .await;
+}
+
+#[tokio::test]
+async fn test_idempotency_record_expires_after_configured_window() {
+    let Some(sdk_client) = get_local_client().await else {
+        return;
+    };
+    let (client, table_name) = setup_table(&sdk_client).await;
+
+    let stream_id = unique_stream_id();
+    let stream = client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            retention_hours: Some(48),
+            idempotency_ttl_hours: Some(6),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    let published = client
+        .publish_events(
+            &stream_id,
+            &[PublishEvent {
+                key: "order-1".to_string(),
+                partition_key: None,
+                order_key: None,
+                timestamp: None,
+                event_type: "order.created".parse().unwrap(),
+                data: json!({}),
+            }],
+        )
+        .await
+        .expect("Failed to publish event");
+    let claim = IdempotencyRecord::claim(&stream_id, "req-1".to_string(), "abc".to_string());
+    let existing = client
+        .claim_idempotency_key(&claim)
+        .await
+        .expect("Failed to claim idempotency key");
+    assert!(existing.is_none());
+    let record = claim.complete(&stream, published);
+    client
+        .put_idempotency_record(&record)
+        .await
+        .expect("Failed to record idempotency key");
+
+    // The stored TTL attribute is six hours out, not the 48 hour retention
+    let item = sdk_client
+        .get_item()
+        .table_name(&table_name)
+        .key(
+            "PK",
+            AttributeValue::S(format!("STREAM#{}#IDEMPOTENCY", stream_id)),
+        )
+        .key("SK", AttributeValue::S("KEY#req-1".to_string()))
+        .send()
+        .await
+        .expect("Failed to read idempotency item")
+        .item
+        .expect("Idempotency item should exist");
+    let expires_at: i64 = match item.get("expires_at") {
+        Some(AttributeValue::N(n)) => n.parse().expect("numeric expires_at"),
+        other => panic!("Unexpected expires_at: {:?}", other),
+    };
+    assert_eq!(expires_at - record.created_at.timestamp(), 6 * 3600);
+
+    let found = client
+        .get_idempotency_record(&stream_id, "req-1")
+        .await
+        .expect("Failed to read idempotency record")
+        .expect("Record should be found");
+    assert_eq!(found.events.len(), 1);
+
+    // A second claim finds the finished publish instead
+    let again = IdempotencyRecord::claim(&stream_id, "req-1".to_string(), "abc".to_string());
+    let existing = client
+        .claim_idempotency_key(&again)
+        .await
+        .expect("Failed to claim idempotency key")
+        .expect("The key should already be held");
+    assert!(!existing.pending);
+    assert!(existing.check_replay("abc").is_ok());
+
+    // Cleanup
+    let _ = sdk_client
+        .delete_table()
+        .table_name(&table_name)
+        .send()
+        .await;
+}
+
+#[tokio::test]
+async fn test_list_compacted_returns_keys_beyond_one_page() {
+    let Some(sdk_client) = get_local_client().await else {
+        return;
+    };
+    let (client, table_name) = setup_table(&sdk_client).await;
+
+    let stream_id = unique_stream_id();
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    // ~1.2MB of compacted state, more than a single 1MB query page holds
+    let padding = "x".repeat(4 * 1024);
+    let key_count = 300;
+    for i in 0..key_count {
+        let compacted = CompactedEvent {
+            id: String::new(),
+            stream_id: stream_id.clone(),
+            key: format!("key-{:04}", i),
+            event_type: "test.event".to_string(),
+            data: json!({ "padding": padding }),
+            sequence: i + 1,
+            partition: 0,
+            timestamp: "2025-01-01T00:00:00Z".parse().unwrap(),
+        };
+        client
+            .put_compacted(&compacted, compacted.timestamp, None)
+            .await
+            .expect("Failed to seed compacted state");
+    }
+
+    let all = client
+        .list_compacted(&stream_id)
+        .await
+        .expect("Failed to list compacted state");
+    assert_eq!(all.len(), key_count as usize);
+
+    // Paging by key visits every key exactly once, in order
+    let mut paged = Vec::new();
+    let mut start_key = None;
+    loop {
+        let (events, next_key) = client
+            .list_compacted_page(&stream_id, start_key.as_deref(), 128, false, None)
+            .await
+            .expect("Failed to list compacted page");
+        assert!(events.len() <= 128);
+        paged.extend(events.into_iter().map(|e| e.key));
+        match next_key {
+            Some(key) => start_key = Some(key),
+            None => break,
+        }
+    }
+    let expected: Vec<String> = (0..key_count).map(|i| format!("key-{:04}", i)).collect();
+    assert_eq!(paged, expected);
+
+    // Cleanup
+    let _ = sdk_client
+        .delete_table()
+        .table_name(&table_name)
+        .send()
+        .await;
+}
+
+#[tokio::test]
+async fn test_list_compacted_pages_by_timestamp() {
+    let Some(sdk_client) = get_local_client().await else {
+        return;
+    };
+    let (client, table_name) = setup_table(&sdk_client).await;
+
+    let stream_id = unique_stream_id();
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    // Key order is the reverse of timestamp order
+    let key_count = 7;
+    for i in 0..key_count {
+        let mut compacted = CompactedEvent {
+            id: String::new(),
+            stream_id: stream_id.clone(),
+            key: format!("key-{}", key_count - i),
+            event_type: "test.event".to_string(),
+            data: json!({}),
+            sequence: i + 1,
+            partition: 0,
+            timestamp: "2025-01-01T00:00:00Z".parse().unwrap(),
+        };
+        compacted.timestamp += Duration::from_secs(i);
+        client
+            .put_compacted(&compacted, compacted.timestamp, None)
+            .await
+            .expect("Failed to seed compacted state");
+    }
+
+    for descending in [false, true] {
+        let mut paged = Vec::new();
+        let mut start_key = None;
+        loop {
+            let (events, next_key) = client
+                .list_compacted_page_by_timestamp(
+                    &stream_id,
+                    start_key.as_deref(),
+                    3,
+                    descending,
+                    None,
+                )
+                .await
+                .expect("Failed to list compacted page");
+            assert!(events.len() <= 3);
+            paged.extend(events.into_iter().map(|e| e.sequence));
+            match next_key {
+                Some(key) => start_key = Some(key),
+                None => break,
+            }
+        }
+        let mut expected: Vec<u64> = (1..=key_count).collect();
+        if descending {
+            expected.reverse();
+        }
+        assert_eq!(paged, expected);
+    }
+
+    // Cleanup
+    let _ = sdk_client
+        .delete_table()
+        .table_name(&table_name)
+        .send()
+        .await;
+}
+
+#[tokio::test]
+async fn test_list_compacted_since_sequence_skips_older_updates() {
+    let Some(sdk_client) = get_local_client().await else {
+        return;
+    };
+    let (client, table_name) = setup_table(&sdk_client).await;
+
+    let stream_id = unique_stream_id();
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(1),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    let event = |key: &str| PublishEvent {
+        key: key.to_string(),
+        partition_key: None,
+        order_key: None,
+        timestamp: None,
+        event_type: "order.updated".parse().unwrap(),
+        data: json!({ "key": key }),
+    };
+    let first = client
+        .publish_events(
+            &stream_id,
+            &[event("order-1"), event("order-2"), event("order-3")],
+        )
+        .await
+        .expect("Failed to publish events");
+    let threshold = first.iter().map(|e| e.sequence).max().unwrap();
+
+    client
+        .publish_events(&stream_id, &[event("order-2")])
+        .await
+        .expect("Failed to publish event");
+    client
+        .backfill_compacted(
+            &stream_id,
+            &CompactionBackfillRequest::default(),
+            Duration::from_secs(30),
+        )
+        .await
+        .expect("Failed to backfill");
+
+    let (events, _) = client
+        .list_compacted_page(&stream_id, None, 100, false, Some(threshold))
+        .await
+        .expect("Failed to list compacted page");
+    let keys: Vec<_> = events.iter().map(|e| e.key.as_str()).collect();
+    assert_eq!(keys, vec!["order-2"]);
+
+    // A threshold below every sequence filters nothing out
+    let (events, _) = client
+        .list_compacted_page(&stream_id, None, 100, false, Some(0))
+        .await
+        .expect("Failed to list compacted page");
+    assert_eq!(events.len(), 3);
+
+    // Cleanup
+    let _ = sdk_client
+        .delete_table()
+        .table_name(&table_name)
+        .send()
+        .await;
+}
+
+#[tokio::test]
+async fn test_unparseable_record_is_dead_lettered_after_max_attempts() {
+    let Some(sdk_client) = get_local_client().await else {
+        return;
+    };
+    let (client, table_name) = setup_table(&sdk_client).await;
+
+    let stream_id = unique_stream_id();
+    // `sequence` is not a number, so the compactor can never parse this record
+    let record = json!({
+        "PK": { "S": format!("STREAM#{}#P0", stream_id) },
+        "SK": { "S": "SEQ#00000000000000000001" },
+        "sequence": { "S": "not-a-number" }
+    });
+    let error = "Missing or invalid sequence";
+
+    for attempt in 1..=3 {
+        let dead_lettered = client
+            .dead_letter_on_failure(&stream_id, "4900000000000000000001", &record, error, 3)
+            .await
+            .expect("Failed to count failure");
+        assert_eq!(dead_lettered, attempt == 3, "attempt {}", attempt);
+    }
+
+    let dlq = client
+        .list_dlq(&stream_id)
+        .await
+        .expect("Failed to list DLQ");
+    assert_eq!(dlq.len(), 1);
+    assert_eq!(dlq[0].record_id, "4900000000000000000001");
+    assert_eq!(dlq[0].record, record);
+    assert_eq!(dlq[0].error, error);
+    assert_eq!(dlq[0].attempts, 3);
+
+    // Cleanup
+    let _ = sdk_client
+        .delete_table()
+        .table_name(&table_name)
+        .send()
+        .await;
+}
+
+#[tokio::test]
+async fn test_compacted_state_carries_stream_ttl() {
+    let Some(sdk_client) = get_local_client().await else {
+        return;
+    };
+    let (client, table_name) = setup_table(&sdk_client).await;
+
+    // Shorter than retention: the two are configured independently
+    let stream_id = unique_stream_id();
+    let stream = client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            retention_hours: Some(168),
+            compacted_ttl_hours: Some(24),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+    assert_eq!(stream.compacted_ttl_hours, Some(24));
+
+    let compacted = CompactedEvent {
+        id: String::new(),
+        stream_id: stream_id.clone(),
+        key: "order-1".to_string(),
+        event_type: "order.created".to_string(),
+        data: json!({}),
+        sequence: 1,
+        partition: 0,
+        timestamp: "2025-01-01T00:00:00Z".parse().unwrap(),
+    };
+    // Backfilled long after it happened: the TTL runs from when it was written
+    let ingested_at = compacted.timestamp + Duration::from_secs(90 * 24 * 3600);
+    client
+        .put_compacted(&compacted, ingested_at, stream.compacted_ttl_hours)
+        .await
+        .expect("Failed to put compacted state");
+
+    let item = sdk_client
+        .get_item()
+        .table_name(&table_name)
+        .key(
+            "PK",
+            AttributeValue::S(format!("STREAM#{}#COMPACT", stream_id)),
+        )
+        .key("SK", AttributeValue::S("KEY#order-1".to_string()))
+        .send()
+        .await
+        .expect("Failed to read compacted item")
+        .item
+        .expect("Compacted item should exist");
+    let expires_at: i64 = match item.get("expires_at") {
+        Some(AttributeValue::N(n)) => n.parse().expect("numeric expires_at"),
+        other => panic!("Unexpected expires_at: {:?}", other),
+    };
+    assert_eq!(expires_at - ingested_at.timestamp(), 24 * 3600);
+
+    // Without a TTL the compacted state never expires
+    let compacted = CompactedEvent {
+        sequence: 2,
+        ..compacted
+    };
+    assert!(client
+        .put_compacted(&compacted, ingested_at, None)
+        .await
+        .expect("Failed to put compacted state"));
+
+    // Older state is never written over newer
+    let stale = CompactedEvent {
+        sequence: 1,
+        ..compacted.clone()
+    };
+    assert!(!client
+        .put_compacted(&stale, ingested_at, stream.compacted_ttl_hours)
+        .await
+        .expect("Failed to put compacted state"));
+    let item = sdk_client
+        .get_item()
+        .table_name(&table_name)
+        .key(
+            "PK",
+            AttributeValue::S(format!("STREAM#{}#COMPACT", stream_id)),
+        )
+        .key("SK", AttributeValue::S("KEY#order-1".to_string()))
+        .send()
+        .await
+        .expect("Failed to read compacted item")
+        .item
+        .expect("Compacted item should exist");
+    assert!(!item.contains_key("expires_at"));
+
+    // Cleanup
+    let _ = sdk_client
+        .delete_table()
+        .table_name(&table_name)
+        .send()
+        .await;
+}
+
+#[tokio::test]
+async fn test_head_stream_reports_existence() {
+    let Some(sdk_client) = get_local_client().await else {
+        return;
+    };
+    let (client, table_name) = setup_table(&sdk_client).await;
+
+    let stream_id = unique_stream_id();
+    let exists = client
+        .head_stream(&stream_id)
+        .await
+        .expect("A missing stream should not be an error");
+    assert!(!exists);
+
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+    assert!(client
+        .head_stream(&stream_id)
+        .await
+        .expect("Failed to check stream"));
+
+    // Cleanup
+    let _ = sdk_client
+        .delete_table()
+        .table_name(&table_name)
+        .send()
+        .await;
+}
+
+#[tokio::test]
+async fn test_batch_get_streams_skips_missing() {
+    let Some(sdk_client) = get_local_client().await else {
+        return;
+    };
+    let (client, table_name) = setup_table(&sdk_client).await;
+
+    // More than one BatchGetItem's worth of keys
+    let mut stream_ids = Vec::new();
+    for _ in 0..105 {
+        let stream_id = unique_stream_id();
+        client
+            .create_stream(&CreateStreamRequest {
+                stream_id: stream_id.clone(),
+                partition_count: Some(2),
+                ..Default::default()
+            })
+            .await
+            .expect("Failed to create stream");
+        stream_ids.push(stream_id);
+    }
+
+    let mut requested = stream_ids.clone();
+    requested.insert(3, unique_stream_id());
+    let streams = client
+        .batch_get_streams(&requested)
+        .await
+        .expect("Failed to batch get streams");
+
+    let returned: Vec<String> = streams.iter().map(|s| s.stream_id.clone()).collect();
+    assert_eq!(returned, stream_ids);
+    assert!(streams.iter().all(|s| s.partition_count == 2));
+
+    // Cleanup
+    let _ = sdk_client
+        .delete_table()
+        .table_name(&table_name)
+        .send()
+        .await;
+}
+
+#[tokio::test]
+async fn test_batch_get_events_reports_missing_sequences() {
+    let Some(sdk_client) = get_local_client().await else {
+        return;
+    };
+    let (client, table_name) = setup_table(&sdk_client).await;
+
+    let stream_id = unique_stream_id();
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(1),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    let events: Vec<PublishEvent> = (0..3)
+        .map(|i| PublishEvent {
+            key: format!("order-{}", i),
+            partition_key: None,
+            order_key: None,
+            timestamp: None,
+            event_type: "order.created".parse().unwrap(),
+            data: json!({ "n": i }),
+        })
+        .collect();
+    client
+        .publish_events(&stream_id, &events)
+        .await
+        .expect("Failed to publish events");
+
+    let fetched = client
+        .batch_get_events(&stream_id, 0, &[3, 42, 1, 3, 7])
+        .await
+        .expect("Failed to batch get events");
+    let sequences: Vec<u64> = fetched.events.iter().map(|e| e.sequence).collect();
+    assert_eq!(sequences, vec![3, 1]);
+    assert_eq!(fetched.events[1].data, json!({ "n": 0 }));
+    assert_eq!(fetched.missing, vec![42, 7]);
+
+    // Partitions beyond the stream's are rejected rather than reported missing
+    let err = client
+        .batch_get_events(&stream_id, 1, &[1])
+        .await
+        .expect_err("Partition should be out of range");
+    assert!(matches!(err, Error::Validation(_)), "{:?}", err);
+
+    // Cleanup
+    let _ = sdk_client
+        .delete_table()
+        .table_name(&table_name)
+        .send()
+        .await;
+}
+
+#[tokio::test]
+async fn test_cursor_token_round_trip() {
+    let Some(sdk_client) = get_local_client().await else {
+        return;
+    };
+    let (client, table_name) = setup_table(&sdk_client).await;
+
+    let stream_id = unique_stream_id();
+    let state = CursorState {
+        stream_id: stream_id.clone(),
+        subscription_id: "wide-consumer".to_string(),
+        offsets: (0..200)
+            .map(|partition| PartitionOffset {
+                partition,
+                offset: 1_000_000 + partition as u64,
+            })
+            .collect(),
+        remaining_per_partition: Vec::new(),
+        committed_at: None,
+    };
+
+    let token = client
+        .put_cursor_token(&state)
+        .await
+        .expect("Failed to store cursor");
+    assert!(token.len() <= 32);
+    let again = client
+        .put_cursor_token(&state)
+        .await
+        .expect("Failed to store cursor");
+    assert_eq!(again, token);
+
+    let resolved = client
+        .get_cursor_token(&stream_id, "wide-consumer", &token)
+        .await
+        .expect("Failed to resolve cursor token");
+    assert_eq!(resolved.offsets.len(), 200);
+    assert!(resolved
+        .offsets
+        .iter()
+        .all(|po| po.offset == 1_000_000 + po.partition as u64));
+
+    // Tokens only resolve for the subscription they were issued to
+    let err = client
+        .get_cursor_token(&stream_id, "other-consumer", &token)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::InvalidCursor { .. }));
+
+    // Cleanup
+    let _ = sdk_client
+        .delete_table()
+        .table_name(&table_name)
+        .send()
+        .await;
+}
+
+#[tokio::test]
+async fn test_stream_stats_time_range_brackets_events() {
+    let Some(sdk_client) = get_local_client().await else {
+        return;
+    };
+    let (client, table_name) = setup_table(&sdk_client).await;
+
+    let stream_id = unique_stream_id();
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(4),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    let stats = client
+        .stream_stats(&stream_id)
+        .await
+        .expect("Failed to get stats");
+    assert!(stats.earliest_timestamp.is_none());
+    assert!(stats
+        .partitions
+        .iter()
+        .all(|p| p.latest_timestamp.is_none()));
+
+    // Two batches a little apart, so the first and last events differ in time
+    let mut published = Vec::new();
+    for batch in ["order.created", "order.shipped"] {
+        let events: Vec<PublishEvent> = ["order-1", "order-2"]
+            .into_iter()
+            .map(|key| PublishEvent {
+                key: key.to_string(),
+                partition_key: None,
+                order_key: None,
+                timestamp: None,
+                event_type: batch.parse().unwrap(),
+                data: json!({}),
+            })
+            .collect();
+        published.extend(
+            client
+                .publish_events(&stream_id, &events)
+                .await
+                .expect("Failed to publish events"),
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    let mut timestamps = Vec::new();
+    for partition in 0..4 {
+        let events = client
+            .read_events(&stream_id, partition, 0, 100, true)
+            .await
+            .expect("Failed to read events");
+        timestamps.extend(events.iter().map(|e| e.timestamp));
+    }
+    assert_eq!(timestamps.len(), published.len());
+
+    let stats = client
+        .stream_stats(&stream_id)
+        .await
+        .expect("Failed to get stats");
+    assert_eq!(stats.earliest_timestamp, timestamps.iter().min().copied());
+    assert_eq!(stats.latest_timestamp, timestamps.iter().max().copied());
+    assert!(stats.earliest_timestamp < stats.latest_timestamp);
+    for partition in &stats.partitions {
+        // Empty partitions have no time range
+        assert_eq!(partition.earliest_timestamp.is_some(), partition.offset > 0);
+        assert!(partition.earliest_timestamp <= partition.latest_timestamp);
+    }
+
+    // Cleanup
+    let _ = sdk_client
+        .delete_table()
+        .table_name(&table_name)
+        .send()
+        .await;
+}
+
+#[tokio::test]
+async fn test_live_event_count_leaves_out_removed_events() {
+    let Some(sdk_client) = get_local_client().await else {
+        return;
+    };
+    let (client, table_name) = setup_table(&sdk_client).await;
+    let stream_id = single_partition_stream(&client).await;
+
+    let events: Vec<PublishEvent> = (0..5)
+        .map(|i| PublishEvent {
+            key: format!("order-{}", i),
+            partition_key: None,
+            order_key: None,
+            timestamp: None,
+            event_type: "order.created".parse().unwrap(),
+            data: json!({}),
+        })
+        .collect();
+    client
+        .publish_events(&stream_id, &events)
+        .await
+        .expect("Failed to publish events");
+
+    // Remove two events the way TTL would
+    for sequence in [2, 4] {
+        sdk_client
+            .delete_item()
+            .table_name(&table_name)
+            .key("PK", AttributeValue::S(format!("STREAM#{}#P0", stream_id)))
+            .key("SK", AttributeValue::S(format!("SEQ#{:020}", sequence)))
+            .send()
+            .await
+            .expect("Failed to delete event");
+    }
+
+    let count = |from_offset| client.count_events_in_partition(&stream_id, 0, from_offset);
+    assert_eq!(count(0).await.expect("Failed to count events"), 3);
+    assert_eq!(count(2).await.expect("Failed to count events"), 2);
+    assert_eq!(count(5).await.expect("Failed to count events"), 0);
+
+    // The counter still reports every event ever published
+    let stats = client
+        .stream_stats(&stream_id)
+        .await
+        .expect("Failed to get stats");
+    assert_eq!(stats.approximate_event_count, 5);
+    assert_eq!(stats.event_count, 3);
+    assert_eq!(stats.partitions[0].event_count, 3);
+
+    // Cleanup
+    let _ = sdk_client
+        .delete_table()
+        .table_name(&table_name)
+        .send()
+        .await;
+}
+
+#[tokio::test]
+async fn test_delete_stream_guarded_by_subscriptions() {
+    let Some(sdk_client) = get_local_client().await else {
+        return;
+    };
+    let (client, table_name) = setup_table(&sdk_client).await;
+
+    let stream_id = unique_stream_id();
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+    client
+        .create_subscription(
+            &stream_id,
+            &CreateSubscriptionRequest {
+                subscription_id: "billing".to_string(),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Failed to create subscription");
+    let offsets = [PartitionOffset {
+        partition: 0,
+        offset: 3,
+    }];
+    client
+        .commit_offsets(&stream_id, "billing", &offsets, None)
+        .await
+        .expect("Failed to commit offsets");
+    client
+        .put_cursor_token(&CursorState {
+            stream_id: stream_id.clone(),
+            subscription_id: "billing".to_string(),
+            offsets: offsets.to_vec(),
+            remaining_per_partition: Vec::new(),
+            committed_at: None,
+        })
+        .await
+        .expect("Failed to store cursor");
+
+    let err = client.delete_stream(&stream_id, false).await.unwrap_err();
+    assert!(matches!(err, Error::StreamInUse(_)));
+    assert!(client
+        .head_stream(&stream_id)
+        .await
+        .expect("Failed to check stream"));
+
+    client
+        .delete_stream(&stream_id, true)
+        .await
+        .expect("Forced delete should succeed");
+    assert!(!client
+        .head_stream(&stream_id)
+        .await
+        .expect("Failed to check stream"));
+
+    // The subscription, its offsets and its cursor tokens go with the stream
+    let leftover = sdk_client
+        .scan()
+        .table_name(&table_name)
+        .filter_expression("begins_with(PK, :sub) OR SK = :sk")
+        .expression_attribute_values(
+            ":sub",
+            AttributeValue::S(format!("STREAM#{}#SUB#", stream_id)),
+        )
+        .expression_attribute_values(":sk", AttributeValue::S("SUB#billing".to_string()))
+        .send()
+        .await
+        .expect("Failed to scan table");
+    assert_eq!(leftover.count(), 0);
+
+    // Cleanup
+    let _ = sdk_client
+        .delete_table()
+        .table_name(&table_name)
+        .send()
+        .await;
+}
+
+#[tokio::test]
+async fn test_latest_written_offset_skips_unwritten_sequences() {
+    let Some(sdk_client) = get_local_client().await else {
+        return;
+    };
+    let (client, table_name) = setup_table(&sdk_client).await;
+
+    let stream_id = unique_stream_id();
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(1),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+    let event = PublishEvent {
+        key: "order-1".to_string(),
+        partition_key: None,
+        order_key: None,
+        timestamp: None,
+        event_type: "order.created".parse().unwrap(),
+        data: json!({}),
+    };
+    client
+        .publish_events(&stream_id, &[event.clone(), event])
+        .await
+        .expect("Failed to publish events");
+
+    // Reserve three more sequences without writing them, as a batch that
+    // failed partway would
+    sdk_client
+        .update_item()
+        .table_name(&table_name)
+        .key("PK", AttributeValue::S(format!("STREAM#{}#P0", stream_id)))
+        .key("SK", AttributeValue::S("COUNTER".to_string()))
+        .update_expression("SET #seq = #seq + :inc")
+        .expression_attribute_names("#seq", "sequence")
+        .expression_attribute_values(":inc", AttributeValue::N("3".to_string()))
+        .send()
+        .await
+        .expect("Failed to advance counter");
+
+    assert_eq!(client.get_latest_offset(&stream_id, 0).await.unwrap(), 5);
+    assert_eq!(
+        client
+            .get_latest_written_offset(&stream_id, 0)
+            .await
+            .unwrap(),
+        2
+    );
+
+    // Cleanup
+    let _ = sdk_client
+        .delete_table()
+        .table_name(&table_name)
+        .send()
+        .await;
+}
+
+#[tokio::test]
+async fn test_round_robin_starts_at_partition_zero() {
+    let Some(sdk_client) = get_local_client().await else {
+        return;
+    };
+    let (client, table_name) = setup_table(&sdk_client).await;
+
+    let stream_id = unique_stream_id();
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(3),
+            empty_key_strategy: EmptyKeyStrategy::RoundRobin,
+            compaction: Some(false),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    let event = PublishEvent {
+        key: String::new(),
+        partition_key: None,
+        order_key: None,
+        timestamp: None,
+        event_type: "page.viewed".parse().unwrap(),
+        data: json!({}),
+    };
+    let mut partitions = Vec::new();
+    for batch in [2, 3] {
+        let published = client
+            .publish_events(&stream_id, &vec![event.clone(); batch])
+            .await
+            .expect("Failed to publish events");
+        partitions.extend(published.iter().map(|e| e.partition));
+    }
+    // Same turns as the memory store, across batches
+    assert_eq!(partitions, [0, 1, 2, 0, 1]);
+
+    // Cleanup
+    let _ = sdk_client
+        .delete_table()
+        .table_name(&table_name)
+        .send()
+        .await;
+}
+
+#[tokio::test]
+async fn test_publish_rate_limit_bursts_then_refills() {
+    let Some(sdk_client) = get_local_client().await else {
+        return;
+    };
+    let (client, table_name) = setup_table(&sdk_client).await;
+
+    let stream_id = unique_stream_id();
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            max_publish_per_second: Some(5),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+    let event = |i: u32| PublishEvent {
+        key: format!("order-{}", i),
+        partition_key: None,
+        order_key: None,
+        timestamp: None,
+        event_type: "order.created".parse().unwrap(),
+        data: json!({}),
+    };
+
+    // A full second's worth goes through at once
+    for i in 0..5 {
+        client
+            .publish_events(&stream_id, &[event(i)])
+            .await
+            .expect("Publish within the limit should succeed");
+    }
+
+    let err = client
+        .publish_events(&stream_id, &[event(5)])
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::RateLimited { .. }));
+    assert_eq!(err.status_code(), 429);
+    let retry_after = err
+        .retry_after()
+        .expect("Rate limiting should say when to retry");
+
+    tokio::time::sleep(Duration::from_secs(retry_after)).await;
+    client
+        .publish_events(&stream_id, &[event(5)])
+        .await
+        .expect("Publish should succeed once the bucket refills");
+
+    // Streams without a limit are never throttled
+    let unlimited = unique_stream_id();
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: unlimited.clone(),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+    let burst: Vec<_> = (0..20).map(event).collect();
+    client
+        .publish_events(&unlimited, &burst)
+        .await
+        .expect("Unlimited streams accept any burst");
+
+    // Cleanup
+    let _ = sdk_client
+        .delete_table()
+        .table_name(&table_name)
+        .send()
+        .await;
+}
+
+#[tokio::test]
+async fn test_redact_event_keeps_sequence_continuity() {
+    let Some(sdk_client) = get_local_client().await else {
+        return;
+    };
+    let (client, table_name) = setup_table(&sdk_client).await;
+
+    let stream_id = unique_stream_id();
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(1),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+    let events: Vec<PublishEvent> = (1..=3)
+        .map(|n| PublishEvent {
+            key: "user-1".to_string(),
+            partition_key: None,
+            order_key: None,
+            timestamp: None,
+            event_type: "user.updated".parse().unwrap(),
+            data: json!({ "email": format!("user{}@example.com", n) }),
+        })
+        .collect();
+    let published = client
+        .publish_events(&stream_id, &events)
+        .await
+        .expect("Failed to publish");
+
+    // Stand in for the compactor, which isn't running against local DynamoDB
+    let latest = &published[2];
+    client
+        .put_compacted(
+            &CompactedEvent {
+                id: latest.id.clone(),
+                stream_id: stream_id.clone(),
+                key: "user-1".to_string(),
+                event_type: "user.updated".to_string(),
+                data: events[2].data.clone(),
+                sequence: latest.sequence,
+                partition: latest.partition,
+                timestamp: latest.timestamp,
+            },
+            latest.timestamp,
+            None,
+        )
+        .await
+        .expect("Failed to put compacted state");
+
+    let redacted = client
+        .redact_event(&stream_id, 0, 2)
+        .await
+        .expect("Failed to redact event");
+    assert_eq!(redacted.sequence, 2);
+    assert_eq!(redacted.data["redacted"], true);
+
+    // Every sequence is still there; only the redacted payload changed
+    let stored = client
+        .read_events(&stream_id, 0, 0, 10, true)
+        .await
+        .expect("Failed to read events");
+    assert_eq!(
+        stored.iter().map(|e| e.sequence).collect::<Vec<_>>(),
+        vec![1, 2, 3]
+    );
+    assert_eq!(stored[0].data, events[0].data);
+    assert_eq!(stored[1].data["redacted"], true);
+    assert_eq!(stored[1].key, "user-1");
+    assert_eq!(stored[2].data, events[2].data);
+
+    // Older than the compacted state, so that is left alone
+    let compacted = client
+        .get_compacted(&stream_id, "user-1")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(compacted.data, events[2].data);
+
+    // Redacting the latest event redacts the compacted state with it
+    client
+        .redact_event(&stream_id, 0, 3)
+        .await
+        .expect("Failed to redact event");
+    let compacted = client
+        .get_compacted(&stream_id, "user-1")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(compacted.sequence, 3);
+    assert_eq!(compacted.data["redacted"], true);
+
+    // The changelog keeps no copy of the payload, and logs the redaction
+    let changes = client
+        .read_compaction_changelog(&stream_id, 0, 100)
+        .await
+        .expect("Failed to read changelog");
+    assert_eq!(changes.len(), 2);
+    assert!(changes
+        .iter()
+        .all(|change| change.state.data["redacted"] == true));
+
+    // Deleting the stream takes its compacted state and changelog with it
+    client
+        .delete_stream(&stream_id, true)
+        .await
+        .expect("Failed to delete stream");
+    assert!(client
+        .get_compacted(&stream_id, "user-1")
+        .await
+        .unwrap()
+        .is_none());
+    let changes = client
+        .read_compaction_changelog(&stream_id, 0, 100)
+        .await
+        .expect("Failed to read changelog");
+    assert!(changes.is_empty());
+
+    let err = client.redact_event(&stream_id, 0, 99).await.unwrap_err();
+    assert!(matches!(err, Error::EventNotFound(_)));
+
+    // Cleanup
+    let _ = sdk_client
+        .delete_table()
+        .table_name(&table_name)
+        .send()
+        .await;
+}
+
+#[tokio::test]
+async fn test_timestamps_stay_monotonic_when_clock_goes_backward() {
+    let Some(sdk_client) = get_local_client().await else {
+        return;
+    };
+    let (client, table_name) = setup_table(&sdk_client).await;
+    let stream_id = single_partition_stream(&client).await;
+
+    let publish = |key: &str| PublishEvent {
+        key: key.to_string(),
+        partition_key: None,
+        order_key: None,
+        timestamp: None,
+        event_type: "order.created".parse().unwrap(),
+        data: json!({}),
+    };
+    let before = client
+        .publish_events(&stream_id, &[publish("order-1")])
+        .await
+        .expect("Failed to publish event");
+
+    // Move the partition's latest timestamp an hour ahead, as if the last
+    // publisher's clock were that far in front of ours
+    let ahead = before[0].timestamp + Duration::from_secs(3600);
+    sdk_client
+        .update_item()
+        .table_name(&table_name)
+        .key("PK", AttributeValue::S(format!("STREAM#{}#P0", stream_id)))
+        .key("SK", AttributeValue::S("COUNTER".to_string()))
+        .update_expression("SET latest_timestamp_us = :ahead")
+        .expression_attribute_values(
+            ":ahead",
+            AttributeValue::N(ahead.timestamp_micros().to_string()),
+        )
+        .send()
+        .await
+        .expect("Failed to move partition clock");
+
+    client
+        .publish_events(&stream_id, &[publish("order-2"), publish("order-3")])
+        .await
+        .expect("Failed to publish events");
+
+    let events = client
+        .read_events(&stream_id, 0, 0, 10, true)
+        .await
+        .expect("Failed to read events");
+    assert_eq!(events.len(), 3);
+    assert!(events.windows(2).all(|w| w[0].timestamp <= w[1].timestamp));
+
+    // The held-back events keep the time they were really published at
+    assert_eq!(events[0].wall_clock_timestamp, None);
+    for event in &events[1..] {
+        assert_eq!(event.timestamp, ahead);
+        assert!(
+            event
+                .wall_clock_timestamp
+                .expect("Wall clock should be kept")
+                < ahead
+        );
+    }
+
+    // Cleanup
+    let _ = sdk_client
+        .delete_table()
+        .table_name(&table_name)
+        .send()
+        .await;
+}
+
+#[tokio::test]
+async fn test_skewed_keys_flag_hot_partition() {
+    let Some(sdk_client) = get_local_client().await else {
+        return;
+    };
+    let (client, table_name) = setup_table(&sdk_client).await;
+
+    let stream_id = unique_stream_id();
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(4),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    // One key carries most of the traffic
+    let keys = (0..30)
+        .map(|_| "tenant-hot".to_string())
+        .chain((0..12).map(|i| format!("tenant-{}", i)));
+    let events: Vec<PublishEvent> = keys
+        .map(|key| PublishEvent {
+            key,
+            partition_key: None,
+            order_key: None,
+            timestamp: None,
+            event_type: "order.created".parse().unwrap(),
+            data: json!({}),
+        })
+        .collect();
+    let published = client
+        .publish_events(&stream_id, &events)
+        .await
+        .expect("Failed to publish events");
+    let hot = published[0].partition;
+
+    let load = client
+        .partition_load(&stream_id)
+        .await
+        .expect("Failed to get partition load");
+    assert_eq!(load.hot_partitions, vec![hot]);
+    assert_eq!(load.partitions.iter().map(|p| p.offset).sum::<u64>(), 42);
+
+    let stats = client
+        .stream_stats(&stream_id)
+        .await
+        .expect("Failed to get stats");
+    assert_eq!(stats.hot_partitions, vec![hot]);
+
+    // Cleanup
+    let _ = sdk_client
+        .delete_table()
+        .table_name(&table_name)
+        .send()
+        .await;
+}
+
+#[tokio::test]
+async fn test_commit_rejected_after_newer_commit() {
+    let Some(sdk_client) = get_local_client().await else {
+        return;
+    };
+    let (client, table_name) = setup_table(&sdk_client).await;
+    let stream_id = single_partition_stream(&client).await;
+    client
+        .create_subscription(
+            &stream_id,
+            &CreateSubscriptionRequest {
+                subscription_id: "billing".to_string(),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Failed to create subscription");
+
+    // What a poll would have seen before another consumer committed
+    let seen = client
+        .list_offsets(&stream_id, "billing")
+        .await
+        .expect("Failed to list offsets")[0]
+        .committed_at;
+    let other = [PartitionOffset {
+        partition: 0,
+        offset: 5,
+    }];
+    client
+        .commit_offsets(&stream_id, "billing", &other, Some(seen))
+        .await
+        .expect("First commit since the poll should succeed");
+
+    let stale = [PartitionOffset {
+        partition: 0,
+        offset: 3,
+    }];
+    let err = client
+        .commit_offsets(&stream_id, "billing", &stale, Some(seen))
+        .await
+        .expect_err("Stale commit should be rejected");
+    assert!(matches!(err, Error::CommitConflict(_)));
+    let offset = client
+        .get_offset(&stream_id, "billing", 0)
+        .await
+        .expect("Failed to get offset");
+    assert_eq!(offset, 5);
+
+    // Without a cursor timestamp the commit is unconditional
+    client
+        .commit_offsets(&stream_id, "billing", &stale, None)
+        .await
+        .expect("Unconditional commit should succeed");
+
+    // Cleanup
+    let _ = sdk_client
+        .delete_table()
+        .table_name(&table_name)
+        .send()
+        .await;
+}
+
+#[tokio::test]
+async fn test_record_poll_stamps_last_polled_at() {
+    let Some(sdk_client) = get_local_client().await else {
+        return;
+    };
+    let (client, table_name) = setup_table(&sdk_client).await;
+    let stream_id = single_partition_stream(&client).await;
+    let req = CreateSubscriptionRequest {
+        subscription_id: "billing".to_string(),
+        ..Default::default()
+    };
+    let created = client
+        .create_subscription(&stream_id, &req)
+        .await
+        .expect("Failed to create subscription");
+
+    client
+        .record_poll(&stream_id, "billing")
+        .await
+        .expect("Failed to record poll");
+    let subscription = client
+        .get_subscription(&stream_id, "billing")
+        .await
+        .expect("Failed to get subscription");
+    let polled_at = subscription
+        .last_polled_at
+        .expect("last_polled_at should be set");
+    assert!(polled_at >= created.created_at);
+    assert!(!subscription.is_stale(created.created_at));
+
+    // A deleted subscription isn't brought back
+    let err = client
+        .record_poll(&stream_id, "deleted")
+        .await
+        .expect_err("Unknown subscription should not be stamped");
+    assert!(matches!(err, Error::SubscriptionNotFound(_)));
+    let subscriptions = client
+        .list_subscriptions(&stream_id)
+        .await
+        .expect("Failed to list subscriptions");
+    assert_eq!(subscriptions.len(), 1);
+
+    // Cleanup
+    let _ = sdk_client
+        .delete_table()
+        .table_name(&table_name)
+        .send()
+        .await;
+}
+
+#[tokio::test]
+async fn test_failed_transactional_publish_writes_nothing() {
+    let Some(sdk_client) = get_local_client().await else {
+        return;
+    };
+    let (client, table_name) = setup_table(&sdk_client).await;
+    let source_id = single_partition_stream(&client).await;
+    let target_id = single_partition_stream(&client).await;
+    client
+        .create_subscription(
+            &source_id,
+            &CreateSubscriptionRequest {
+                subscription_id: "enricher".to_string(),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Failed to create subscription");
+
+    let derived: Vec<PublishEvent> = (0..3)
+        .map(|i| PublishEvent {
+            key: format!("order-{}", i),
+            partition_key: None,
+            order_key: None,
+            timestamp: None,
+            event_type: "order.enriched".parse().unwrap(),
+            data: json!({ "index": i }),
+        })
+        .collect();
+    let consumed = [PartitionOffset {
+        partition: 0,
+        offset: 3,
+    }];
+
+    // Another consumer commits after this one polled, so the offset update's
+    // condition fails and the whole transaction is cancelled
+    let seen = client
+        .list_offsets(&source_id, "enricher")
+        .await
+        .expect("Failed to list offsets")[0]
+        .committed_at;
+    let other = [PartitionOffset {
+        partition: 0,
+        offset: 1,
+    }];
+    client
+        .commit_offsets(&source_id, "enricher", &other, None)
+        .await
+        .expect("Failed to commit");
+    let err = client
+        .transact_publish_and_commit(
+            &target_id, &derived, &source_id, "enricher", &consumed, seen,
+        )
+        .await
+        .expect_err("Stale transaction should be cancelled");
+    assert!(matches!(err, Error::CommitConflict(_)), "{:?}", err);
+
+    let stored = client
+        .read_events(&target_id, 0, 0, 100, true)
+        .await
+        .expect("Failed to read events");
+    assert!(stored.is_empty(), "{:?}", stored);
+    let offset = client
+        .get_offset(&source_id, "enricher", 0)
+        .await
+        .expect("Failed to get offset");
+    assert_eq!(offset, 1);
+
+    // With a current cursor both sides land together
+    let seen = client
+        .list_offsets(&source_id, "enricher")
+        .await
+        .expect("Failed to list offsets")[0]
+        .committed_at;
+    let published = client
+        .transact_publish_and_commit(
+            &target_id, &derived, &source_id, "enricher", &consumed, seen,
+        )
+        .await
+        .expect("Failed to publish and commit");
+    assert_eq!(published.len(), 3);
+    let stored = client
+        .read_events(&target_id, 0, 0, 100, true)
+        .await
+        .expect("Failed to read events");
+    assert_eq!(stored.len(), 3);
+    let offset = client
+        .get_offset(&source_id, "enricher", 0)
+        .await
+        .expect("Failed to get offset");
+    assert_eq!(offset, 3);
+
+    // Cleanup
+    let _ = sdk_client
+        .delete_table()
+        .table_name(&table_name)
+        .send()
+        .await;
+}
+
+/// Publish `batches` batches of `batch_size` events to one partition at once,
+/// returning every sequence handed out
+async fn publish_concurrently(
+    client: &DynamoClient,
+    stream_id: &str,
+    batches: usize,
+    batch_size: usize,
+) -> Vec<u64> {
+    let tasks: Vec<_> = (0..batches)
+        .map(|batch| {
+            let client = client.clone();
+            let stream_id = stream_id.to_string();
+            tokio::spawn(async move {
+                let events: Vec<PublishEvent> = (0..batch_size)
+                    .map(|i| PublishEvent {
+                        key: format!("batch-{}-{}", batch, i),
+                        partition_key: None,
+                        order_key: None,
+                        timestamp: None,
+                        event_type: "order.created".parse().unwrap(),
+                        data: json!({ "batch": batch, "index": i }),
+                    })
+                    .collect();
+                client.publish_events(&stream_id, &events).await
+            })
+        })
+        .collect();
+
+    let mut sequences = Vec::new();
+    for task in tasks {
+        let published = task
+            .await
+            .expect("Publish task panicked")
+            .expect("Failed to publish");
+        // Each batch gets one contiguous block, in publish order
+        let block: Vec<u64> = published.iter().map(|e| e.sequence).collect();
+        assert!(block.windows(2).all(|w| w[1] == w[0] + 1), "{:?}", block);
+        sequences.extend(block);
+    }
+    sequences
+}
+
+async fn single_partition_stream(client: &DynamoClient) -> String {
+    let stream_id = unique_stream_id();
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(1),
+            global_ordering: true,
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+    stream_id
+}
+
+#[tokio::test]
+async fn test_concurrent_batches_never_share_sequences() {
+    let Some(sdk_client) = get_local_client().await else {
+        return;
+    };
+    let (client, table_name) = setup_table(&sdk_client).await;
+    let stream_id = single_partition_stream(&client).await;
+
+    let mut sequences = publish_concurrently(&client, &stream_id, 16, 10).await;
+    sequences.sort_unstable();
+
+    // No duplicates and, with every write succeeding, no gaps either
+    assert_eq!(sequences, (1..=160).collect::<Vec<u64>>());
+    let stored = client
+        .read_events(&stream_id, 0, 0, 1000, true)
+        .await
+        .expect("Failed to read events");
+    assert_eq!(stored.len(), 160);
+
+    // Global positions are reserved the same way
+    let mut positions: Vec<u64> = stored.iter().filter_map(|e| e.global_position).collect();
+    positions.sort_unstable();
+    assert_eq!(positions, (1..=160).collect::<Vec<u64>>());
+
+    // Cleanup
+    let _ = sdk_client
+        .delete_table()
+        .table_name(&table_name)
+        .send()
+        .await;
+}
+
+#[tokio::test]
+async fn test_repartition_keeps_each_key_in_order() {
+    let Some(sdk_client) = get_local_client().await else {
+        return;
+    };
+    let (client, table_name) = setup_table(&sdk_client).await;
+    let stream_id = unique_stream_id();
+    client
+        .create_stream(&CreateStreamRequest {
+            stream_id: stream_id.clone(),
+            partition_count: Some(2),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to create stream");
+
+    // 25 keys, each with 10 updates numbered in publish order
+    let keys: Vec<String> = (0..25).map(|i| format!("order-{}", i)).collect();
+    for round in 0..10 {
+        let events: Vec<PublishEvent> = keys
+            .iter()
+            .map(|key| PublishEvent {
+                key: key.clone(),
+                partition_key: None,
+                order_key: None,
+                timestamp: None,
+                event_type: "order.updated".parse().unwrap(),
+                data: json!({ "round": round }),
+            })
+            .collect();
+        client
+            .publish_events(&stream_id, &events)
+            .await
+            .expect("Failed to publish");
+    }
+
+    // Clients name the stream by an alias, which moves once the copy is done
+    let alias = format!("{}-live", stream_id);
+    client
+        .put_alias(&alias, &stream_id, None)
+        .await
+        .expect("Failed to create alias");
+
+    // With no time budget the first run stops after one batch
+    let target_id = format!("{}-wide", stream_id);
+    let mut req = RepartitionRequest {
+        target_stream_id: target_id.clone(),
+        partition_count: 4,
+        from_offsets: None,
+        alias: Some(alias.clone()),
+    };
+    let first = client
+        .repartition_stream(&stream_id, &req, Duration::ZERO)
+        .await
+        .expect("Failed to repartition");
+    assert!(!first.complete);
+    assert_eq!(first.events_copied, 100);
+    assert_eq!(first.alias, None);
+    assert_eq!(client.resolve_stream_id(&alias).await.unwrap(), stream_id);
+
+    req.from_offsets = Some(first.offsets.clone());
+    let rest = client
+        .repartition_stream(&stream_id, &req, Duration::from_secs(60))
+        .await
+        .expect("Failed to resume repartition");
+    assert!(rest.complete);
+    assert_eq!(rest.events_copied, 150);
+    assert_eq!(rest.alias.as_deref(), Some(alias.as_str()));
+    assert_eq!(client.resolve_stream_id(&alias).await.unwrap(), target_id);
+
+    // Resuming from the same offsets again, as if the response were lost,
+    // copies nothing twice
+    let again = client
+        .repartition_stream(&stream_id, &req, Duration::from_secs(60))
+        .await
+        .expect("Failed to resume repartition again");
+    assert!(again.complete);
+    assert_eq!(again.events_copied, 0);
+    let offsets = |result: &RepartitionResult| -> Vec<u64> {
+        result.offsets.iter().map(|o| o.offset).collect()
+    };
+    assert_eq!(offsets(&again), offsets(&rest));
+
+    let target = client
+        .get_stream(&target_id)
+        .await
+        .expect("Target should exist");
+    assert_eq!(target.partition_count, 4);
+
+    // Every key landed on its new partition, with its updates still in order
+    let partitioner = Partitioner::new(4);
+    let mut rounds: HashMap<String, Vec<u64>> = HashMap::new();
+    for partition in 0..4 {
+        let events = client
+            .read_events(&target_id, partition, 0, 1000, true)
+            .await
+            .expect("Failed to read events");
+        for event in events {
+            assert_eq!(event.partition, partitioner.partition(&event.key));
+            let round = event.data["round"].as_u64().unwrap();
+            rounds.entry(event.key).or_default().push(round);
+        }
+    }
+    assert_eq!(rounds.len(), keys.len());
+    for (key, rounds) in rounds {
+        assert_eq!(rounds, (0..10).collect::<Vec<u64>>(), "{}", key);
+    }
+
+    // Cleanup
+    let _ = sdk_client
+        .delete_table()
+        .table_name(&table_name)
+        .send()
+        .await;
+}
+
+/// Throughput of concurrent batch publishes to one partition
+#[tokio::test]
+#[ignore] // Run manually: cargo test --test local_tests bench_ -- --ignored --nocapture
+async fn bench_concurrent_publish_to_one_partition() {
+    let Some(sdk_client) = get_local_client().await else {
+        return;
+    };
+    let (client, table_name) = setup_table(&sdk_client).await;
+
+    for batch_size in [1, 10, 100] {
+        let stream_id = single_partition_stream(&client).await;
+        let started = std::time::Instant::now();
+        let sequences = publish_concurrently(&client, &stream_id, 32, batch_size).await;
+        let elapsed = started.elapsed();
+        eprintln!(
+            "batch_size={:>3}: {} events in {:?} ({:.0} events/s)",
+            batch_size,
+            sequences.len(),
+            elapsed,
+            sequences.len() as f64 / elapsed.as_secs_f64()
+        );
+    }
+
+    // Cleanup
+    let _ = sdk_client
+        .delete_table()
+        .table_name(&table_name)
+        .send()
+        .await;
+}